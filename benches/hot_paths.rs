@@ -0,0 +1,132 @@
+//! Benchmarks of the drawing and flushing hot paths, see `benches/README.md`.
+//!
+//! The geometry is selected via the model feature as usual, e.g.
+//!
+//! ```text
+//! cargo bench --bench hot_paths
+//! cargo bench --bench hot_paths --no-default-features --features ls013b7dh05,graphics
+//! cargo bench --bench hot_paths --features column-major
+//! ```
+use core::convert::Infallible;
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use embedded_graphics::mono_font::{ascii::FONT_6X10, MonoTextStyle};
+use embedded_graphics::pixelcolor::BinaryColor;
+use embedded_graphics::prelude::*;
+use embedded_graphics::primitives::{PrimitiveStyle, Rectangle};
+use embedded_graphics::text::Text;
+use embedded_hal::blocking::spi::Write;
+use embedded_hal::digital::v2::OutputPin;
+use sharp_memory_display::MemoryDisplay;
+
+/// An SPI bus discarding everything, so only the encoding is measured.
+struct Sink;
+
+impl Write<u8> for Sink {
+    type Error = Infallible;
+
+    fn write(&mut self, words: &[u8]) -> Result<(), Self::Error> {
+        black_box(words);
+        Ok(())
+    }
+}
+
+struct Pin;
+
+impl OutputPin for Pin {
+    type Error = Infallible;
+
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+fn display() -> MemoryDisplay<Sink, Pin, Pin> {
+    MemoryDisplay::new(Sink, Pin, Pin)
+}
+
+fn benches(c: &mut Criterion) {
+    let size = display().size();
+    let name = |bench: &str| format!("{bench} {}x{}", size.width, size.height);
+
+    let mut disp = display();
+    let mut color = BinaryColor::On;
+    c.bench_function(&name("fill_solid full frame"), |b| {
+        b.iter(|| {
+            color = color.invert();
+            let _ = disp.fill_solid(&disp.bounding_box(), color);
+        })
+    });
+
+    let mut disp = display();
+    let mut phase = 0;
+    c.bench_function(&name("fill_contiguous full frame"), |b| {
+        b.iter(|| {
+            phase ^= 1;
+            let pixels = (size.width * size.height) as usize;
+            let colors = (0..pixels).map(|i| BinaryColor::from((i + phase) % 3 == 0));
+            let _ = disp.fill_contiguous(&disp.bounding_box(), colors);
+        })
+    });
+
+    let mut disp = display();
+    let style = MonoTextStyle::new(&FONT_6X10, BinaryColor::Off);
+    let text = "The quick brown fox jumps over the lazy dog";
+    c.bench_function(&name("text draw_iter"), |b| {
+        b.iter(|| {
+            let _ = Text::new(black_box(text), Point::new(0, 10), style).draw(&mut disp);
+        })
+    });
+
+    let mut disp = display();
+    c.bench_function(&name("flush single line"), |b| {
+        b.iter(|| {
+            disp.mark_lines_dirty(black_box(7..8));
+            disp.flush_buffer().unwrap();
+        })
+    });
+
+    let mut disp = display();
+    let _ = Rectangle::new(Point::new(10, 10), Size::new(60, 60))
+        .into_styled(PrimitiveStyle::with_fill(BinaryColor::On))
+        .draw(&mut disp);
+    c.bench_function(&name("flush full frame"), |b| {
+        b.iter(|| {
+            disp.mark_all_dirty();
+            disp.flush_buffer().unwrap();
+        })
+    });
+
+    #[cfg(feature = "transaction-buffer")]
+    {
+        let mut disp = display();
+        disp.set_cs_strategy(sharp_memory_display::CsStrategy::Hardware);
+        let _ = Rectangle::new(Point::new(10, 10), Size::new(60, 60))
+            .into_styled(PrimitiveStyle::with_fill(BinaryColor::On))
+            .draw(&mut disp);
+        c.bench_function(&name("flush full frame single write"), |b| {
+            b.iter(|| {
+                disp.mark_all_dirty();
+                disp.flush_buffer().unwrap();
+            })
+        });
+    }
+
+    let mut disp = display();
+    let _ = Text::new(text, Point::new(0, 10), style).draw(&mut disp);
+    c.bench_function(&name("scroll_region_up full frame"), |b| {
+        b.iter(|| disp.scroll_region_up(disp.bounding_box(), black_box(1), BinaryColor::On))
+    });
+
+    let mut disp = display();
+    let _ = Text::new(text, Point::new(0, 10), style).draw(&mut disp);
+    c.bench_function(&name("scroll_region_left full frame"), |b| {
+        b.iter(|| disp.scroll_region_left(disp.bounding_box(), black_box(1), BinaryColor::On))
+    });
+}
+
+criterion_group!(hot_paths, benches);
+criterion_main!(hot_paths);
@@ -0,0 +1,85 @@
+//! Three tasks sharing one display: a UI task redrawing a counter, a notification task which
+//! must never block, and a VCOM ticker. Runs on the host using the simulator.
+//!
+//! ```text
+//! cargo run --example embassy_tasks --features embassy,simulator
+//! ```
+use embassy_executor::Spawner;
+use embassy_sync::blocking_mutex::raw::NoopRawMutex;
+use embassy_time::Timer;
+use embedded_graphics::mono_font::{ascii::FONT_6X10, MonoTextStyle};
+use embedded_graphics::pixelcolor::BinaryColor;
+use embedded_graphics::prelude::*;
+use embedded_graphics::primitives::{PrimitiveStyle, Rectangle};
+use embedded_graphics::text::Text;
+use sharp_memory_display::shared::SharedDisplay;
+use sharp_memory_display::simulator::{Simulator, SimulatorPin, SimulatorSpi};
+use sharp_memory_display::{MemoryDisplay, RECOMMENDED_VCOM_HZ};
+
+type Display = SharedDisplay<NoopRawMutex, SimulatorSpi, SimulatorPin, SimulatorPin>;
+
+#[embassy_executor::task]
+async fn ui(display: &'static Display) {
+    for frame in 0.. {
+        display
+            .draw(|d| {
+                let area = Rectangle::new(Point::new(0, 0), Size::new(200, 20));
+                area.into_styled(PrimitiveStyle::with_fill(BinaryColor::On))
+                    .draw(d)
+                    .ok();
+                let style = MonoTextStyle::new(&FONT_6X10, BinaryColor::Off);
+                let text = format!("frame {frame}");
+                Text::new(&text, Point::new(4, 14), style).draw(d).ok();
+            })
+            .await
+            .unwrap();
+        Timer::after_millis(100).await;
+    }
+}
+
+#[embassy_executor::task]
+async fn notifications(display: &'static Display) {
+    let (mut shown, mut skipped) = (0, 0);
+    loop {
+        // Notifications are dropped rather than delaying the caller if the display is busy
+        let result = display.try_draw(|d| {
+            let area = Rectangle::new(Point::new(0, 30), Size::new(40, 10));
+            area.into_styled(PrimitiveStyle::with_fill(BinaryColor::Off))
+                .draw(d)
+                .ok();
+        });
+        match result {
+            Ok(flushed) => {
+                flushed.unwrap();
+                shown += 1;
+            }
+            Err(_) => skipped += 1,
+        }
+        if shown + skipped == 20 {
+            println!("notifications: {shown} shown, {skipped} skipped");
+            std::process::exit(0);
+        }
+        Timer::after_millis(150).await;
+    }
+}
+
+#[embassy_executor::task]
+async fn vcom(display: &'static Display) {
+    loop {
+        display.maintain_vcom().await.unwrap();
+        Timer::after_millis(1000 / RECOMMENDED_VCOM_HZ as u64).await;
+    }
+}
+
+#[embassy_executor::main]
+async fn main(spawner: Spawner) {
+    let simulator = Simulator::new();
+    let mut display = MemoryDisplay::new(simulator.spi(), simulator.cs(), simulator.disp());
+    display.enable().unwrap();
+    display.clear().unwrap();
+
+    let display: &'static Display = Box::leak(Box::new(SharedDisplay::new(display)));
+    spawner.spawn(ui(display).unwrap());
+    spawner.spawn(notifications(display).unwrap());
+    spawner.spawn(vcom(display).unwrap());
+}
@@ -0,0 +1,87 @@
+//! Draw a 1-bit BMP, loaded with `tinybmp`, both through `embedded-graphics`, pixel by pixel,
+//! and with `draw_packed_image`, then flow text over both with `embedded-text`. Checks that
+//! both framebuffers come out the same and compares how long each image took. Runs on the host
+//! using the simulator.
+//!
+//! ```text
+//! cargo run --manifest-path examples/packed_image/Cargo.toml
+//! ```
+//!
+//! The pixel data of a 1-bit BMP is what `draw_packed_image` takes with `msb_first`, once its
+//! rows are in top to bottom order and without the padding to 4 bytes.
+//!
+//! It is a package of its own as `tinybmp` 0.3, the version for `embedded-graphics` 0.7, can't
+//! be built together with the benchmarks of the driver.
+use embedded_graphics::image::Image;
+use embedded_graphics::mono_font::{ascii::FONT_6X10, MonoTextStyle};
+use embedded_graphics::pixelcolor::BinaryColor;
+use embedded_graphics::prelude::*;
+use embedded_graphics::primitives::Rectangle;
+use embedded_text::TextBox;
+use sharp_memory_display::simulator::Simulator;
+use sharp_memory_display::MemoryDisplay;
+use std::time::Instant;
+use tinybmp::{Bmp, RowOrder};
+
+const TEXT: &str = "Both displays are drawn the same, whether the image goes through \
+    colors or is copied a row at a time.";
+
+fn main() {
+    // Black and white in its color table, so a set bit is `BinaryColor::On`
+    let bmp = Bmp::<BinaryColor>::from_slice(include_bytes!("../rings.bmp")).unwrap();
+    let Size { width, height } = bmp.size();
+
+    // Rows of a BMP are padded to 4 bytes and usually stored bottom up
+    let stride = width.div_ceil(8) as usize;
+    let padded = stride.next_multiple_of(4);
+    let mut rows: Vec<_> = bmp.as_raw().image_data().chunks(padded).collect();
+    if bmp.as_raw().header().row_order == RowOrder::BottomUp {
+        rows.reverse();
+    }
+    let data: Vec<u8> = rows
+        .iter()
+        .flat_map(|row| &row[..stride])
+        .copied()
+        .collect();
+
+    let (left, right) = (Simulator::new(), Simulator::new());
+    let mut generic = MemoryDisplay::new(left.spi(), left.cs(), left.disp());
+    let mut packed = MemoryDisplay::new(right.spi(), right.cs(), right.disp());
+
+    // Partly off the left edge, so both paths clip
+    let positions = [Point::new(13, 7), Point::new(-21, 70)];
+    let start = Instant::now();
+    for position in positions {
+        let _ = Image::new(&bmp, position).draw(&mut generic);
+    }
+    let generic_time = start.elapsed();
+    let start = Instant::now();
+    for position in positions {
+        packed.draw_packed_image(position, width, height, &data, true);
+    }
+    let packed_time = start.elapsed();
+
+    let style = MonoTextStyle::new(&FONT_6X10, BinaryColor::On);
+    let bounds = Rectangle::new(Point::new(130, 10), Size::new(180, 60));
+    for display in [&mut generic, &mut packed] {
+        let _ = TextBox::new(TEXT, bounds, style).draw(display);
+    }
+
+    let size = generic.size();
+    for y in 0..size.height {
+        for x in 0..size.width {
+            assert_eq!(
+                generic.get_pixel(x, y),
+                packed.get_pixel(x, y),
+                "pixel {x}, {y}"
+            );
+        }
+    }
+    assert_eq!(generic.dirty_line_count(), packed.dirty_line_count());
+    println!("framebuffers match, Image: {generic_time:?}, draw_packed_image: {packed_time:?}");
+
+    for display in [&mut generic, &mut packed] {
+        display.enable().unwrap();
+        display.flush_buffer().unwrap();
+    }
+}
@@ -0,0 +1,60 @@
+//! A dashboard refreshed at 10 fps on a 400x240 panel connected to a Raspberry Pi, see the
+//! wiring section of the README.
+//!
+//! ```text
+//! cargo run --release --example rpi_rppal --features rppal
+//! ```
+use embedded_graphics::mono_font::{ascii::FONT_10X20, ascii::FONT_6X10, MonoTextStyle};
+use embedded_graphics::pixelcolor::BinaryColor;
+use embedded_graphics::prelude::*;
+use embedded_graphics::primitives::{PrimitiveStyle, Rectangle};
+use embedded_graphics::text::Text;
+use rppal::spi::{Bus, SlaveSelect};
+use sharp_memory_display::rpi;
+use std::time::{Duration, Instant};
+
+// BCM number of the GPIO connected to DISP, physical pin 18
+const DISP_PIN: u8 = 24;
+const FRAME_TIME: Duration = Duration::from_millis(100);
+
+fn main() -> Result<(), rpi::RpiError> {
+    let mut display = rpi::open(Bus::Spi0, SlaveSelect::Ss0, DISP_PIN)?;
+    display.enable().unwrap();
+    display.clear().unwrap();
+
+    let large = MonoTextStyle::new(&FONT_10X20, BinaryColor::Off);
+    let small = MonoTextStyle::new(&FONT_6X10, BinaryColor::Off);
+    let white = PrimitiveStyle::with_fill(BinaryColor::On);
+    let black = PrimitiveStyle::with_fill(BinaryColor::Off);
+    let _ = Text::new("Uptime", Point::new(10, 20), small).draw(&mut display);
+    let _ = Text::new("Load", Point::new(10, 80), small).draw(&mut display);
+
+    let start = Instant::now();
+    let mut next_frame = start;
+    loop {
+        let uptime = start.elapsed();
+        let load = std::fs::read_to_string("/proc/loadavg")
+            .ok()
+            .and_then(|s| s.split_whitespace().next()?.parse::<f32>().ok())
+            .unwrap_or(0.0);
+
+        let _ = Rectangle::new(Point::new(10, 26), Size::new(200, 24))
+            .into_styled(white)
+            .draw(&mut display);
+        let text = format!("{:.1} s", uptime.as_secs_f32());
+        let _ = Text::new(&text, Point::new(10, 44), large).draw(&mut display);
+
+        let bar = Rectangle::new(Point::new(10, 90), Size::new(380, 20));
+        let _ = bar.into_styled(white).draw(&mut display);
+        let width = (load.min(4.0) / 4.0 * bar.size.width as f32) as u32;
+        let _ = Rectangle::new(bar.top_left, Size::new(width, bar.size.height))
+            .into_styled(black)
+            .draw(&mut display);
+
+        // Only the lines changed above are sent, which also toggles VCOM
+        display.flush_buffer().unwrap();
+
+        next_frame += FRAME_TIME;
+        std::thread::sleep(next_frame.saturating_duration_since(Instant::now()));
+    }
+}
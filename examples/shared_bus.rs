@@ -0,0 +1,55 @@
+//! Drive two displays from one SPI bus with separate chip select pins, each showing its own
+//! content. Runs on the host using the simulator, with a bus wired to both simulated panels.
+//!
+//! ```text
+//! cargo run --example shared_bus --features simulator
+//! ```
+use core::cell::RefCell;
+use core::convert::Infallible;
+use embedded_graphics::mono_font::{ascii::FONT_10X20, MonoTextStyle};
+use embedded_graphics::pixelcolor::BinaryColor;
+use embedded_graphics::prelude::*;
+use embedded_graphics::text::Text;
+use embedded_hal::blocking::spi::Write;
+use sharp_memory_display::simulator::{Simulator, SimulatorSpi};
+use sharp_memory_display::{MemoryDisplay, SharedSpi};
+
+/// One bus connected to both panels, each of which only listens while it is selected.
+struct Bus([SimulatorSpi; 2]);
+
+impl Write<u8> for Bus {
+    type Error = Infallible;
+
+    fn write(&mut self, words: &[u8]) -> Result<(), Self::Error> {
+        self.0.iter_mut().try_for_each(|panel| panel.write(words))
+    }
+}
+
+fn main() {
+    let (left, right) = (Simulator::new(), Simulator::new());
+    let bus = RefCell::new(Bus([left.spi(), right.spi()]));
+
+    let mut displays = [
+        MemoryDisplay::new(SharedSpi::new(&bus), left.cs(), left.disp()),
+        MemoryDisplay::new(SharedSpi::new(&bus), right.cs(), right.disp()),
+    ];
+    let style = MonoTextStyle::new(&FONT_10X20, BinaryColor::Off);
+    for (display, text) in displays.iter_mut().zip(["Left", "Right"]) {
+        display.enable().unwrap();
+        display.clear().unwrap();
+        let _ = Text::new(text, Point::new(10, 30), style).draw(display);
+    }
+    // Flushing one display doesn't touch the other
+    for display in &mut displays {
+        display.flush_buffer().unwrap();
+    }
+
+    for (name, simulator) in [("left", &left), ("right", &right)] {
+        let panel = simulator.panel();
+        let black = (0..panel.height())
+            .flat_map(|y| (0..panel.width()).map(move |x| (x, y)))
+            .filter(|&(x, y)| !panel.pixel(x, y))
+            .count();
+        println!("{name} panel: {black} black pixels");
+    }
+}
@@ -0,0 +1,49 @@
+//! Plot a simulated temperature sampled every second for an hour, one column per 10 samples,
+//! and record the plot to `strip_chart.gif`. Runs on the host using the simulator.
+//!
+//! ```text
+//! cargo run --example strip_chart --features widgets,simulator
+//! ```
+use embedded_graphics::mono_font::{ascii::FONT_6X10, MonoTextStyle};
+use embedded_graphics::pixelcolor::BinaryColor;
+use embedded_graphics::prelude::*;
+use embedded_graphics::primitives::Rectangle;
+use embedded_graphics::text::Text;
+use sharp_memory_display::simulator::Simulator;
+use sharp_memory_display::widgets::{Aggregate, StripChart};
+use sharp_memory_display::MemoryDisplay;
+
+fn main() -> std::io::Result<()> {
+    let simulator = Simulator::new();
+    let mut display = MemoryDisplay::new(simulator.spi(), simulator.cs(), simulator.disp());
+    display.enable().unwrap();
+    display.clear().unwrap();
+
+    let size = display.size();
+    let style = MonoTextStyle::new(&FONT_6X10, BinaryColor::Off);
+    let _ = Text::new("Temperature, last hour", Point::new(4, 10), style).draw(&mut display);
+
+    let region = Rectangle::new(Point::new(0, 16), Size::new(size.width, size.height - 16));
+    let mut chart = StripChart::<400>::new(region, 18.0, 24.0);
+    chart.set_samples_per_column(10, Aggregate::MinMax);
+    chart.set_grid(Some(10));
+    chart.set_autoscale(true);
+
+    simulator.start_recording("strip_chart.gif", 2, 100, Some(10))?;
+    for second in 0..3600 {
+        // A slow drift plus some sensor noise
+        let t = second as f32;
+        let noise = ((second * 7919) % 97) as f32 / 97.0 - 0.5;
+        let temperature = 21.0 + 2.0 * (t / 900.0).sin() + 0.3 * noise;
+        let _ = chart.push(&mut display, temperature);
+
+        if second % 60 == 59 {
+            display.flush_buffer().unwrap();
+        }
+    }
+    let frames = simulator.stop_recording()?;
+
+    let (min, max) = chart.range();
+    println!("recorded {frames} frames to strip_chart.gif, range {min:.1}..{max:.1} °C");
+    Ok(())
+}
@@ -0,0 +1,75 @@
+//! Cycle between two screens with every transition, ten steps each, and record the result to
+//! `transitions.gif`. Runs on the host using the simulator.
+//!
+//! ```text
+//! cargo run --example transitions --features simulator
+//! ```
+use embedded_graphics::mono_font::{ascii::FONT_10X20, MonoTextStyle};
+use embedded_graphics::pixelcolor::BinaryColor;
+use embedded_graphics::prelude::*;
+use embedded_graphics::primitives::{Circle, PrimitiveStyle, Rectangle};
+use embedded_graphics::text::Text;
+use sharp_memory_display::simulator::Simulator;
+use sharp_memory_display::{MemoryDisplay, SharpFrame, Transition};
+
+const STEPS: usize = 10;
+
+fn main() -> std::io::Result<()> {
+    let simulator = Simulator::new();
+    let mut display = MemoryDisplay::new(simulator.spi(), simulator.cs(), simulator.disp());
+    display.enable().unwrap();
+    display.clear().unwrap();
+
+    let size = display.size();
+    let mut screens = [SharpFrame::new(), SharpFrame::new()];
+
+    let (home, settings) = screens.split_at_mut(1);
+    let home = &mut home[0];
+    home.fill(BinaryColor::On);
+    let _ = Text::new(
+        "Home",
+        Point::new(8, 24),
+        MonoTextStyle::new(&FONT_10X20, BinaryColor::Off),
+    )
+    .draw(home);
+    let _ = Circle::with_center(
+        Point::new(size.width as i32 / 2, size.height as i32 / 2),
+        60,
+    )
+    .into_styled(PrimitiveStyle::with_fill(BinaryColor::Off))
+    .draw(home);
+
+    let settings = &mut settings[0];
+    settings.fill(BinaryColor::Off);
+    let _ = Text::new(
+        "Settings",
+        Point::new(8, 24),
+        MonoTextStyle::new(&FONT_10X20, BinaryColor::On),
+    )
+    .draw(settings);
+    for i in 0..4 {
+        let _ = Rectangle::new(Point::new(8, 40 + i * 24), Size::new(size.width / 2, 16))
+            .into_styled(PrimitiveStyle::with_stroke(BinaryColor::On, 1))
+            .draw(settings);
+    }
+
+    display.blit_diff(&screens[0]);
+    display.flush_buffer().unwrap();
+
+    simulator.start_recording("transitions.gif", 1, 200, Some(40))?;
+    let transitions = [
+        Transition::WipeDown,
+        Transition::SlideLeft,
+        Transition::Dissolve,
+    ];
+    for (i, transition) in transitions.into_iter().enumerate() {
+        let mut run = transition.start(&screens[(i + 1) % 2]);
+        for step in 1..=STEPS {
+            run.step(&mut display, step as f32 / STEPS as f32).unwrap();
+        }
+    }
+    let frames = simulator.stop_recording()?;
+
+    println!("recorded {frames} frames to transitions.gif");
+    Ok(())
+}
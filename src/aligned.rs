@@ -0,0 +1,340 @@
+use crate::{DisplaySpec, Error, FlushStats, FrameBuffer, MemoryDisplay};
+use core::borrow::BorrowMut;
+use hal::blocking::delay::DelayMs;
+use hal::blocking::spi::Write;
+use hal::digital::v2::OutputPin;
+
+/// Margin kept between a flush and an edge of EXTCOMIN, in milliseconds.
+const EDGE_MARGIN_MS: u32 = 1;
+
+/// Timing of the EXTCOMIN signal of a panel in [`crate::VcomMode::External`], toggled by a timer
+/// or PWM outside of the driver.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct ExtcominSchedule {
+    /// Time between two edges of EXTCOMIN in milliseconds, half the period of the signal.
+    pub interval_ms: u32,
+    /// The timestamp of any one edge, in the same clock as passed to
+    /// [`MemoryDisplay::flush_aligned`].
+    pub edge_ms: u32,
+}
+
+impl ExtcominSchedule {
+    /// Milliseconds from `now_ms` until the next edge, 0 if it is right now. `now_ms` may be
+    /// before `edge_ms`, as long as both are less than 24 days apart.
+    pub fn next_edge_in(&self, now_ms: u32) -> u32 {
+        let interval = self.interval_ms.clamp(1, i32::MAX as u32) as i32;
+        let since = (now_ms.wrapping_sub(self.edge_ms) as i32).rem_euclid(interval);
+        ((interval - since) % interval) as u32
+    }
+}
+
+/// The EXTCOMIN pin of a panel in [`crate::VcomMode::External`], for boards toggling it in
+/// software, e.g. from a timer interrupt while the main loop owns the display.
+///
+/// It is kept apart from [`MemoryDisplay`] so it can be moved into the interrupt handler on
+/// its own. The pin starts low.
+pub struct Extcomin<P> {
+    pin: P,
+    high: bool,
+}
+
+impl<P: OutputPin> Extcomin<P> {
+    /// Take over `pin`, driving it low.
+    pub fn new(mut pin: P) -> Result<Self, P::Error> {
+        pin.set_low()?;
+        Ok(Self { pin, high: false })
+    }
+
+    /// Drive the pin to the opposite level, to be called whenever the VCOM polarity should
+    /// change, see [`crate::RECOMMENDED_VCOM_HZ`].
+    pub fn toggle(&mut self) -> Result<(), P::Error> {
+        if self.high {
+            self.pin.set_low()?;
+        } else {
+            self.pin.set_high()?;
+        }
+        self.high = !self.high;
+        Ok(())
+    }
+
+    /// Whether the pin is currently driven high.
+    pub fn is_high(&self) -> bool {
+        self.high
+    }
+
+    /// Give back the pin.
+    pub fn release(self) -> P {
+        self.pin
+    }
+}
+
+impl<SPI, CS, DISP, E, SPEC, BUF> MemoryDisplay<SPI, CS, DISP, SPEC, BUF>
+where
+    SPI: Write<u8, Error = E>,
+    CS: OutputPin,
+    DISP: OutputPin,
+    SPEC: DisplaySpec,
+    BUF: BorrowMut<FrameBuffer<SPEC>>,
+{
+    /// Tell the driver when EXTCOMIN toggles, for [`MemoryDisplay::flush_aligned`]. `None`
+    /// (the default) flushes right away.
+    pub fn set_extcomin_schedule(&mut self, schedule: Option<ExtcominSchedule>) {
+        self.extcomin = schedule;
+    }
+
+    /// Set the SPI clock frequency the bus is configured for, used to estimate the duration of
+    /// flushes. Defaults to [`crate::MAX_SCLK_HZ`].
+    pub fn set_sclk_hz(&mut self, hz: u32) {
+        self.sclk_hz = hz.max(1);
+    }
+
+    /// Milliseconds from `now_ms` until the next edge of EXTCOMIN, if its schedule is known.
+    pub fn next_vcom_edge_in(&self, now_ms: u32) -> Option<u32> {
+        self.extcomin.map(|schedule| schedule.next_edge_in(now_ms))
+    }
+
+    /// Flush so the transfer does not coincide with an edge of EXTCOMIN, which can show as a
+    /// faint flicker of the whole screen on some panels.
+    ///
+    /// If the flush is estimated to complete before the next edge it starts right away.
+    /// Otherwise it waits with `delay` until just after the edge, as long as that is within
+    /// `max_wait_ms`. If it isn't, the flush starts right away anyway, which is reported in the
+    /// returned [`FlushStats`]. Like [`MemoryDisplay::flush_buffer`] it holds back the lines
+    /// of an open frame, see [`MemoryDisplay::begin_frame`].
+    pub fn flush_aligned<D: DelayMs<u32>>(
+        &mut self,
+        now_ms: u32,
+        max_wait_ms: u32,
+        delay: &mut D,
+    ) -> Result<FlushStats, Error<E, CS::Error>> {
+        // The stats and the flush both leave out the lines of an open frame
+        self.presenting(|display| {
+            let mut stats = display.pending_stats();
+            let estimated_ms = stats.estimated_ms;
+
+            if let Some(schedule) = display.extcomin {
+                let edge_in = schedule.next_edge_in(now_ms);
+                let fits_before = edge_in > 0 && estimated_ms + EDGE_MARGIN_MS <= edge_in;
+                // Even right after an edge the flush wouldn't be done before the next one
+                let fits_between = estimated_ms + 2 * EDGE_MARGIN_MS <= schedule.interval_ms;
+                if !fits_before {
+                    let wait = edge_in + EDGE_MARGIN_MS;
+                    if fits_between && wait <= max_wait_ms {
+                        delay.delay_ms(wait);
+                        stats.waited_ms = wait;
+                    } else {
+                        stats.overlaps_edge = true;
+                    }
+                }
+            }
+
+            display.flush_buffer()?;
+            Ok(stats)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+    use std::{vec, vec::Vec};
+
+    /// A delay recording how long it was asked to wait, without waiting.
+    #[derive(Default)]
+    struct FakeDelay(Vec<u32>);
+
+    impl DelayMs<u32> for FakeDelay {
+        fn delay_ms(&mut self, ms: u32) {
+            self.0.push(ms);
+        }
+    }
+
+    const SCHEDULE: ExtcominSchedule = ExtcominSchedule {
+        interval_ms: 500,
+        edge_ms: 0,
+    };
+
+    /// A display with a few dirty lines, at an SPI clock sending them in 10 ms.
+    fn display(schedule: Option<ExtcominSchedule>) -> testing::TestDisplay {
+        let (_, mut display) = testing::display();
+        testing::draw_diagonal(&mut display, 0..4);
+        let bits = display.pending_stats().bytes_written as u32 * 8;
+        display.set_sclk_hz(bits * 100);
+        display.set_extcomin_schedule(schedule);
+        display
+    }
+
+    /// Flush at `now_ms` with `schedule`, returning the stats and the delays waited.
+    fn flush(schedule: ExtcominSchedule, now_ms: u32, max_wait_ms: u32) -> (FlushStats, Vec<u32>) {
+        let mut display = display(Some(schedule));
+        let mut delay = FakeDelay::default();
+        let stats = display
+            .flush_aligned(now_ms, max_wait_ms, &mut delay)
+            .unwrap();
+        assert_eq!(display.is_dirty(), cfg!(feature = "minimal-flush"));
+        (stats, delay.0)
+    }
+
+    #[test]
+    fn edges_are_found_on_either_side_of_the_schedule() {
+        assert_eq!(SCHEDULE.next_edge_in(0), 0);
+        assert_eq!(SCHEDULE.next_edge_in(1), 499);
+        assert_eq!(SCHEDULE.next_edge_in(499), 1);
+        assert_eq!(SCHEDULE.next_edge_in(1250), 250);
+        // Before the edge given, and across the wrap-around of the clock
+        assert_eq!(SCHEDULE.next_edge_in(u32::MAX - 2), 3);
+        let late = ExtcominSchedule {
+            edge_ms: 10_000,
+            ..SCHEDULE
+        };
+        assert_eq!(late.next_edge_in(9_999), 1);
+        assert_eq!(late.next_edge_in(9_500), 0);
+
+        let mut display = display(None);
+        assert_eq!(display.next_vcom_edge_in(100), None);
+        display.set_extcomin_schedule(Some(SCHEDULE));
+        assert_eq!(display.next_vcom_edge_in(100), Some(400));
+    }
+
+    #[test]
+    fn flushes_completing_before_the_edge_start_right_away() {
+        let (stats, waited) = flush(SCHEDULE, 100, 100);
+        assert_eq!(stats.estimated_ms, 10);
+        assert_eq!((stats.waited_ms, stats.overlaps_edge), (0, false));
+        assert!(waited.is_empty());
+        // With the margin to the edge
+        let (stats, waited) = flush(SCHEDULE, 489, 100);
+        assert_eq!((stats.waited_ms, waited), (0, vec![]));
+    }
+
+    #[test]
+    fn flushes_close_to_the_edge_wait_for_it() {
+        let (stats, waited) = flush(SCHEDULE, 495, 100);
+        assert_eq!((stats.waited_ms, stats.overlaps_edge), (6, false));
+        assert_eq!(waited, [6]);
+        let (stats, waited) = flush(SCHEDULE, 490, 11);
+        assert_eq!((stats.waited_ms, waited), (11, vec![11]));
+        // Right on the edge
+        let (stats, waited) = flush(SCHEDULE, 1000, 100);
+        assert_eq!((stats.waited_ms, waited), (1, vec![1]));
+    }
+
+    #[test]
+    fn flushes_past_the_budget_overlap_the_edge() {
+        let (stats, waited) = flush(SCHEDULE, 490, 10);
+        assert_eq!((stats.waited_ms, stats.overlaps_edge), (0, true));
+        assert!(waited.is_empty());
+        // Too long to fit between two edges at all
+        let fast = ExtcominSchedule {
+            interval_ms: 11,
+            edge_ms: 0,
+        };
+        let (stats, waited) = flush(fast, 5, 100);
+        assert_eq!((stats.waited_ms, stats.overlaps_edge), (0, true));
+        assert!(waited.is_empty());
+        let fast = ExtcominSchedule {
+            interval_ms: 12,
+            ..fast
+        };
+        let (stats, waited) = flush(fast, 5, 100);
+        assert_eq!((stats.waited_ms, stats.overlaps_edge), (8, false));
+        assert_eq!(waited, [8]);
+    }
+
+    #[test]
+    #[cfg(not(any(
+        feature = "half-height",
+        feature = "half-width",
+        feature = "minimal-flush"
+    )))]
+    fn lines_of_an_open_frame_are_held_back() {
+        use crate::Vcom;
+
+        let (log, mut display) = testing::display();
+        display.set_extcomin_schedule(Some(SCHEDULE));
+        testing::draw_diagonal(&mut display, 0..2);
+        display.begin_frame();
+        testing::draw_diagonal(&mut display, 5..7);
+        let stats = display
+            .flush_aligned(100, 100, &mut FakeDelay::default())
+            .unwrap();
+        assert_eq!(stats.lines_written, 2);
+        let expected = testing::frame(Vcom::Lo, &testing::diagonal(0..2));
+        assert_eq!(log.bytes(), expected);
+
+        display.end_frame();
+        let stats = display
+            .flush_aligned(200, 100, &mut FakeDelay::default())
+            .unwrap();
+        assert_eq!(stats.lines_written, 2);
+        let expected = testing::frame(Vcom::Hi, &testing::diagonal(5..7));
+        assert_eq!(log.bytes(), expected);
+    }
+
+    #[test]
+    fn stats_match_those_of_a_plain_flush() {
+        let mut display = display(None);
+        let (log, mut plain) = testing::display();
+        testing::draw_diagonal(&mut plain, 0..4);
+        plain.set_sclk_hz(display.sclk_hz);
+        let expected = plain.flush_buffer_stats().unwrap();
+        let sent = log.bytes();
+
+        let stats = display
+            .flush_aligned(495, 100, &mut FakeDelay::default())
+            .unwrap();
+        assert_eq!(stats, expected);
+        assert_eq!(stats.bytes_written, sent.len());
+        // Every line is sent each time with minimal-flush
+        let (first, again) = if cfg!(feature = "minimal-flush") {
+            (crate::BUFFER_HEIGHT, crate::BUFFER_HEIGHT)
+        } else {
+            (4, 0)
+        };
+        assert_eq!(stats.lines_written, first);
+        assert_eq!(display.flush_buffer_stats().unwrap().lines_written, again);
+    }
+
+    #[test]
+    fn extcomin_alternates_the_pin() {
+        let log = testing::Log::default();
+        let mut extcomin = Extcomin::new(log.disp()).unwrap();
+        assert!(!extcomin.is_high());
+        for _ in 0..3 {
+            extcomin.toggle().unwrap();
+        }
+        assert!(extcomin.is_high());
+        let levels: Vec<_> = log
+            .events()
+            .into_iter()
+            .map(|event| event == testing::Event::Disp(true))
+            .collect();
+        assert_eq!(levels, [false, true, false, true]);
+        extcomin.release();
+        assert_eq!(log.events(), []);
+    }
+
+    #[test]
+    fn commands_keep_m1_low_while_extcomin_toggles() {
+        let (log, mut display) = testing::display();
+        display.set_vcom_mode(crate::VcomMode::External);
+        let pins = testing::Log::default();
+        let mut extcomin = Extcomin::new(pins.disp()).unwrap();
+        for _ in 0..4 {
+            extcomin.toggle().unwrap();
+            display.flush_buffer().unwrap();
+            display.display_mode().unwrap();
+        }
+        // The first byte of every transaction
+        let events = log.events();
+        let transactions = events.split(|event| *event == testing::Event::Cs(true));
+        let commands: Vec<_> = transactions
+            .skip(1)
+            .map(|events| testing::written(events)[0])
+            .collect();
+        assert_eq!(commands, [0x80, 0x00].repeat(4));
+        assert_eq!(pins.events().len(), 5);
+    }
+}
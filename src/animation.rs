@@ -0,0 +1,669 @@
+//! Playing 1-bit animations stored in flash, e.g. on badges.
+//!
+//! An animation is a byte string starting with a header:
+//!
+//! | Offset | Size | Content                                           |
+//! |-------:|-----:|---------------------------------------------------|
+//! |      0 |    4 | `SMDA`                                            |
+//! |      4 |    2 | width in pixels, little endian                    |
+//! |      6 |    2 | height in pixels, little endian                   |
+//! |      8 |    2 | number of frames, little endian                   |
+//! |     10 |    1 | flags, bit 0 set if the frames are RLE compressed |
+//!
+//! followed by every frame: the time it is shown in milliseconds and the length of its data
+//! (both 2 bytes, little endian) and the data itself. The data is the framebuffer line by line
+//! in the layout of [`MemoryDisplay::buffer`](crate::MemoryDisplay::buffer). Compressed data
+//! is a sequence of runs, each starting with a control byte `n`: below 128 it is followed by
+//! `n + 1` bytes copied verbatim, otherwise by a single byte repeated `257 - n` times.
+//!
+//! With the `std` feature `encode` produces animations from a sequence of [`crate::SharpFrame`]s.
+use crate::spec::{self, DisplaySpec, Zeroed};
+#[cfg(feature = "std")]
+use crate::SharpFrame;
+use crate::{Error, FrameBuffer, MemoryDisplay};
+use core::borrow::BorrowMut;
+use core::marker::PhantomData;
+use hal::blocking::spi::Write;
+use hal::digital::v2::OutputPin;
+#[cfg(feature = "std")]
+use std::vec::Vec;
+
+const MAGIC: &[u8; 4] = b"SMDA";
+const HEADER_LEN: usize = 11;
+const FRAME_HEADER_LEN: usize = 4;
+const FLAG_RLE: u8 = 0x01;
+
+/// An error in the data of an [`Animation`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AnimationError {
+    /// The data doesn't start with the header of an animation.
+    BadMagic,
+    /// The frames are not of the size of the framebuffer.
+    SizeMismatch,
+    /// The data ends within a frame, or a frame has no data for every line.
+    Truncated,
+}
+
+/// How an [`AnimationPlayer`] continues after the last frame.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PlayMode {
+    /// Stop at the last frame.
+    Once,
+    /// Start over from the first frame.
+    #[default]
+    Loop,
+    /// Play the frames backwards down to the first one, then forwards again.
+    PingPong,
+}
+
+/// State of an [`AnimationPlayer`] after a tick.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AnimationState {
+    /// The frame with this index has just been presented.
+    Presented(usize),
+    /// The current frame is still being shown.
+    Waiting,
+    /// The last frame has been shown for its time with [`PlayMode::Once`].
+    Finished,
+}
+
+/// A validated animation, see the [module documentation](self) for the format.
+#[derive(Clone, Copy, Debug)]
+pub struct Animation<'a, SPEC: DisplaySpec = spec::Selected> {
+    data: &'a [u8],
+    frames: usize,
+    rle: bool,
+    spec: PhantomData<SPEC>,
+}
+
+impl<'a, SPEC: DisplaySpec> Animation<'a, SPEC> {
+    /// Check the header and the size of every frame of `data`, e.g. included with
+    /// `include_bytes!`. Compressed frames are decompressed once to check their size.
+    pub fn from_bytes(data: &'a [u8]) -> Result<Self, AnimationError> {
+        if data.len() < HEADER_LEN || &data[..4] != MAGIC {
+            return Err(AnimationError::BadMagic);
+        }
+        if read_u16(data, 4) as usize != SPEC::BUFFER_WIDTH
+            || read_u16(data, 6) as usize != SPEC::BUFFER_HEIGHT
+        {
+            return Err(AnimationError::SizeMismatch);
+        }
+        let animation = Self {
+            data,
+            frames: read_u16(data, 8) as usize,
+            rle: data[10] & FLAG_RLE != 0,
+            spec: PhantomData,
+        };
+
+        let mut offset = HEADER_LEN;
+        for _ in 0..animation.frames {
+            let (_, frame) = animation.frame_at(offset)?;
+            if Runs::new(frame, animation.rle).count() != SPEC::LINE_BYTES * SPEC::BUFFER_HEIGHT {
+                return Err(AnimationError::Truncated);
+            }
+            offset += FRAME_HEADER_LEN + frame.len();
+        }
+        Ok(animation)
+    }
+
+    /// Number of frames of the animation.
+    pub fn frames(&self) -> usize {
+        self.frames
+    }
+
+    /// The time frame `index` is shown in milliseconds and its data.
+    fn frame(&self, index: usize) -> (u16, &'a [u8]) {
+        let mut offset = HEADER_LEN;
+        for _ in 0..index {
+            offset += FRAME_HEADER_LEN + read_u16(self.data, offset + 2) as usize;
+        }
+        // Every frame has been checked by from_bytes
+        self.frame_at(offset).unwrap_or((0, &[]))
+    }
+
+    fn frame_at(&self, offset: usize) -> Result<(u16, &'a [u8]), AnimationError> {
+        let start = offset + FRAME_HEADER_LEN;
+        if self.data.len() < start {
+            return Err(AnimationError::Truncated);
+        }
+        let end = start + read_u16(self.data, offset + 2) as usize;
+        let data = self.data.get(start..end).ok_or(AnimationError::Truncated)?;
+        Ok((read_u16(self.data, offset), data))
+    }
+}
+
+/// Plays an [`Animation`] on a display, driven by [`AnimationPlayer::tick`] with a free-running
+/// timestamp in milliseconds.
+#[derive(Clone, Copy, Debug)]
+pub struct AnimationPlayer<'a, SPEC: DisplaySpec = spec::Selected> {
+    animation: Animation<'a, SPEC>,
+    mode: PlayMode,
+    frame: usize,
+    backwards: bool,
+    // When the current frame was presented, None before the first tick
+    shown_at: Option<u32>,
+    delay: u16,
+}
+
+impl<'a, SPEC: DisplaySpec> AnimationPlayer<'a, SPEC> {
+    pub fn new(animation: Animation<'a, SPEC>, mode: PlayMode) -> Self {
+        Self {
+            animation,
+            mode,
+            frame: 0,
+            backwards: false,
+            shown_at: None,
+            delay: 0,
+        }
+    }
+
+    /// Index of the frame shown currently, or presented by the first tick.
+    pub fn frame(&self) -> usize {
+        self.frame
+    }
+
+    /// Start over from the first frame with the next tick.
+    pub fn restart(&mut self) {
+        self.frame = 0;
+        self.backwards = false;
+        self.shown_at = None;
+    }
+
+    /// Present the next frame if the current one has been shown for its time, or the first
+    /// frame on the first tick. Only the lines which differ from the framebuffer are marked
+    /// dirty and flushed. `now_ms` may wrap around.
+    ///
+    /// If the flush fails the frame is presented again by the next tick.
+    pub fn tick<SPI, CS, DISP, E, BUF>(
+        &mut self,
+        display: &mut MemoryDisplay<SPI, CS, DISP, SPEC, BUF>,
+        now_ms: u32,
+    ) -> Result<AnimationState, Error<E, CS::Error>>
+    where
+        SPI: Write<u8, Error = E>,
+        CS: OutputPin,
+        DISP: OutputPin,
+        SPEC: DisplaySpec,
+        BUF: BorrowMut<FrameBuffer<SPEC>>,
+    {
+        let frames = self.animation.frames;
+        if frames == 0 {
+            return Ok(AnimationState::Finished);
+        }
+        if let Some(shown_at) = self.shown_at {
+            if now_ms.wrapping_sub(shown_at) < self.delay as u32 {
+                return Ok(AnimationState::Waiting);
+            }
+            match self.next() {
+                Some(next) => self.frame = next,
+                None => return Ok(AnimationState::Finished),
+            }
+        }
+
+        let (delay, data) = self.animation.frame(self.frame);
+        present(display, data, self.animation.rle);
+        if let Err(e) = display.flush_buffer() {
+            // Like the first tick, so the next one presents this frame again
+            self.shown_at = None;
+            return Err(e);
+        }
+        self.delay = delay;
+        self.shown_at = Some(now_ms);
+        Ok(AnimationState::Presented(self.frame))
+    }
+
+    /// The frame following the current one, None at the end with [`PlayMode::Once`].
+    fn next(&mut self) -> Option<usize> {
+        let last = self.animation.frames - 1;
+        match self.mode {
+            PlayMode::Once => (self.frame < last).then_some(self.frame + 1),
+            PlayMode::Loop => Some(if self.frame < last { self.frame + 1 } else { 0 }),
+            PlayMode::PingPong => {
+                if last == 0 {
+                    return Some(0);
+                }
+                if self.frame == last {
+                    self.backwards = true;
+                } else if self.frame == 0 {
+                    self.backwards = false;
+                }
+                Some(if self.backwards {
+                    self.frame - 1
+                } else {
+                    self.frame + 1
+                })
+            }
+        }
+    }
+}
+
+/// Copy the lines of a frame which differ into the framebuffer, marking them dirty.
+fn present<SPI, CS, DISP, SPEC, BUF>(
+    display: &mut MemoryDisplay<SPI, CS, DISP, SPEC, BUF>,
+    data: &[u8],
+    rle: bool,
+) where
+    SPEC: DisplaySpec,
+    BUF: BorrowMut<FrameBuffer<SPEC>>,
+{
+    let mut line = SPEC::Line::ZERO;
+    let mut runs = Runs::new(data, rle);
+    for y in 0..SPEC::BUFFER_HEIGHT {
+        for byte in line.as_mut() {
+            // The size of the frame has been checked by Animation::from_bytes
+            *byte = runs.next().unwrap_or(0);
+        }
+        if display.frame.borrow().buffer.line(y) != line {
+            let frame = display.frame.borrow_mut();
+            frame.buffer.set_line(y, &line);
+            frame.touched.set(y, true);
+        }
+    }
+}
+
+/// The bytes of the data of a frame, decompressed if `rle`.
+struct Runs<'a> {
+    data: &'a [u8],
+    rle: bool,
+    // Remaining length and whether the current run repeats a single byte
+    run: usize,
+    repeat: bool,
+}
+
+impl<'a> Runs<'a> {
+    fn new(data: &'a [u8], rle: bool) -> Self {
+        Self {
+            data,
+            rle,
+            run: 0,
+            repeat: false,
+        }
+    }
+}
+
+impl Iterator for Runs<'_> {
+    type Item = u8;
+
+    fn next(&mut self) -> Option<u8> {
+        if self.rle && self.run == 0 {
+            let (&control, rest) = self.data.split_first()?;
+            self.data = rest;
+            self.repeat = control >= 128;
+            self.run = if self.repeat {
+                257 - control as usize
+            } else {
+                control as usize + 1
+            };
+        }
+        let (&byte, rest) = self.data.split_first()?;
+        self.run = self.run.saturating_sub(1);
+        if !(self.rle && self.repeat && self.run > 0) {
+            self.data = rest;
+        }
+        Some(byte)
+    }
+}
+
+fn read_u16(data: &[u8], offset: usize) -> u16 {
+    match data.get(offset..offset + 2) {
+        Some(&[lo, hi]) => u16::from_le_bytes([lo, hi]),
+        _ => 0,
+    }
+}
+
+/// Encode `frames`, each with the time it is shown in milliseconds, into an animation for
+/// [`Animation::from_bytes`], RLE compressed if `rle`.
+#[cfg(feature = "std")]
+pub fn encode<SPEC: DisplaySpec>(frames: &[(&SharpFrame<SPEC>, u16)], rle: bool) -> Vec<u8> {
+    let mut out = Vec::from(&MAGIC[..]);
+    out.extend_from_slice(&(SPEC::BUFFER_WIDTH as u16).to_le_bytes());
+    out.extend_from_slice(&(SPEC::BUFFER_HEIGHT as u16).to_le_bytes());
+    out.extend_from_slice(&(frames.len() as u16).to_le_bytes());
+    out.push(if rle { FLAG_RLE } else { 0 });
+
+    for (frame, delay) in frames {
+        let raw: Vec<u8> = (0..SPEC::BUFFER_HEIGHT)
+            .flat_map(|y| frame.buffer.line(y).as_ref().to_vec())
+            .collect();
+        let data = if rle { compress(&raw) } else { raw };
+        out.extend_from_slice(&delay.to_le_bytes());
+        out.extend_from_slice(&(data.len() as u16).to_le_bytes());
+        out.extend_from_slice(&data);
+    }
+    out
+}
+
+/// Compress `raw` into runs of at most 128 bytes, repeating runs of three or more equal bytes.
+#[cfg(feature = "std")]
+fn compress(raw: &[u8]) -> Vec<u8> {
+    let mut out = Vec::new();
+    let mut literal_start = 0;
+    let mut i = 0;
+    while i < raw.len() {
+        let repeat = raw[i..]
+            .iter()
+            .take(128)
+            .take_while(|&&b| b == raw[i])
+            .count();
+        if repeat < 3 && i - literal_start < 128 {
+            i += 1;
+            continue;
+        }
+        for chunk in raw[literal_start..i].chunks(128) {
+            out.push(chunk.len() as u8 - 1);
+            out.extend_from_slice(chunk);
+        }
+        if repeat >= 3 {
+            out.push((257 - repeat) as u8);
+            out.push(raw[i]);
+            i += repeat;
+        }
+        literal_start = i;
+    }
+    for chunk in raw[literal_start..].chunks(128) {
+        out.push(chunk.len() as u8 - 1);
+        out.extend_from_slice(chunk);
+    }
+    out
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use crate::testing;
+    use crate::{BUFFER_HEIGHT, BUFFER_WIDTH, LINE_BYTES};
+    use std::{boxed::Box, vec};
+
+    /// A frame with a different pattern for every `seed`, the same on line 0 of all of them.
+    fn frame(seed: usize) -> Box<SharpFrame> {
+        let mut frame = Box::new(SharpFrame::new());
+        for y in 0..BUFFER_HEIGHT {
+            for x in 0..BUFFER_WIDTH {
+                let on = y > 0 && (x * 5 + y * 3 + seed * 7).is_multiple_of(seed + 3);
+                frame.set_pixel(x as u32, y as u32, on);
+            }
+        }
+        frame
+    }
+
+    fn shows(display: &testing::TestDisplay, frame: &SharpFrame) -> bool {
+        let buffer = &display.frame.buffer;
+        (0..BUFFER_HEIGHT).all(|y| buffer.line(y) == frame.buffer.line(y))
+    }
+
+    fn decompressed(data: &[u8]) -> Vec<u8> {
+        Runs::new(data, true).collect()
+    }
+
+    #[test]
+    fn encoded_frames_are_read_back() {
+        let frames = [frame(0), frame(1), frame(2)];
+        for rle in [false, true] {
+            let data = encode(&[(&frames[0], 100), (&frames[1], 7), (&frames[2], 0)], rle);
+            let animation = Animation::<spec::Selected>::from_bytes(&data).unwrap();
+            assert_eq!(animation.frames(), 3);
+            for (index, (delay, expected)) in [(100, &frames[0]), (7, &frames[1]), (0, &frames[2])]
+                .into_iter()
+                .enumerate()
+            {
+                let (shown, bytes) = animation.frame(index);
+                assert_eq!(shown, delay);
+                let raw: Vec<u8> = (0..BUFFER_HEIGHT)
+                    .flat_map(|y| expected.buffer.line(y))
+                    .collect();
+                let bytes: Vec<u8> = Runs::new(bytes, rle).collect();
+                assert_eq!(bytes, raw, "frame {index}, rle {rle}");
+            }
+        }
+        let empty = encode::<spec::Selected>(&[], true);
+        assert_eq!(
+            Animation::<spec::Selected>::from_bytes(&empty)
+                .unwrap()
+                .frames(),
+            0
+        );
+    }
+
+    #[test]
+    fn runs_are_at_most_128_bytes() {
+        let literal: Vec<u8> = (0..=255).collect();
+        for len in [1, 2, 127, 128, 129, 256] {
+            let raw = &literal[..len];
+            let compressed = compress(raw);
+            assert_eq!(compressed[0] as usize, len.min(128) - 1);
+            assert_eq!(compressed.len(), len + len.div_ceil(128));
+            assert_eq!(decompressed(&compressed), raw);
+        }
+        for (len, expected) in [
+            (3, vec![254, 9]),
+            (128, vec![129, 9]),
+            (129, vec![129, 9, 0, 9]),
+            (131, vec![129, 9, 254, 9]),
+            (256, vec![129, 9, 129, 9]),
+        ] {
+            let raw = vec![9; len];
+            assert_eq!(compress(&raw), expected, "{len}");
+            assert_eq!(decompressed(&expected), raw);
+        }
+        // Pairs stay within the literal around them
+        let raw = [1, 2, 2, 3, 4, 4, 4, 5];
+        assert_eq!(compress(&raw), [3, 1, 2, 2, 3, 254, 4, 0, 5]);
+        assert_eq!(decompressed(&compress(&raw)), raw);
+    }
+
+    #[test]
+    fn runs_end_with_their_frame() {
+        // The last line of the first frame and the first of the second are the same, the
+        // run is split between them
+        let mut first: SharpFrame = SharpFrame::new();
+        let mut second = SharpFrame::new();
+        first.fill(true);
+        second.fill(true);
+        second.set_pixel(0, BUFFER_HEIGHT as u32 - 1, false);
+        let data = encode(&[(&first, 10), (&second, 10)], true);
+        let animation = Animation::<spec::Selected>::from_bytes(&data).unwrap();
+        let (_, bytes) = animation.frame(1);
+        assert_eq!(decompressed(bytes).len(), LINE_BYTES * BUFFER_HEIGHT);
+        assert_eq!(
+            decompressed(animation.frame(0).1),
+            vec![0xFF; LINE_BYTES * BUFFER_HEIGHT]
+        );
+
+        // A run past the end of its frame doesn't carry over into the next
+        let mut long = data[..HEADER_LEN].to_vec();
+        long[8] = 1;
+        let size = LINE_BYTES * BUFFER_HEIGHT;
+        let mut runs = vec![];
+        for chunk in 0..size.div_ceil(128) {
+            runs.extend([129, chunk as u8]);
+        }
+        runs.extend([129, 0]);
+        long.extend(10u16.to_le_bytes());
+        long.extend((runs.len() as u16).to_le_bytes());
+        long.extend(&runs);
+        assert_eq!(
+            Animation::<spec::Selected>::from_bytes(&long).err(),
+            Some(AnimationError::Truncated)
+        );
+    }
+
+    #[test]
+    fn invalid_data_is_rejected() {
+        let mut data = encode(&[(&frame(0), 10)], false);
+        assert_eq!(
+            Animation::<spec::Selected>::from_bytes(&data[..HEADER_LEN - 1]).err(),
+            Some(AnimationError::BadMagic)
+        );
+        assert_eq!(
+            Animation::<spec::Selected>::from_bytes(&data[..data.len() - 1]).err(),
+            Some(AnimationError::Truncated)
+        );
+        data[4] += 1;
+        assert_eq!(
+            Animation::<spec::Selected>::from_bytes(&data).err(),
+            Some(AnimationError::SizeMismatch)
+        );
+        data[4] -= 1;
+        data[8] = 2;
+        assert_eq!(
+            Animation::<spec::Selected>::from_bytes(&data).err(),
+            Some(AnimationError::Truncated)
+        );
+        data[0] = b'X';
+        assert_eq!(
+            Animation::<spec::Selected>::from_bytes(&data).err(),
+            Some(AnimationError::BadMagic)
+        );
+    }
+
+    /// Tick a player of three frames shown for 100, 200 and 50 ms at every time of `ticks`,
+    /// checking the state, that presented frames are flushed and nothing else is sent.
+    fn play(mode: PlayMode, ticks: &[(u32, AnimationState)]) {
+        let frames = [frame(0), frame(1), frame(2)];
+        let data = encode(
+            &[(&frames[0], 100), (&frames[1], 200), (&frames[2], 50)],
+            true,
+        );
+        let mut player = AnimationPlayer::new(
+            Animation::<spec::Selected>::from_bytes(&data).unwrap(),
+            mode,
+        );
+        let (log, mut display) = testing::display();
+        for &(now, expected) in ticks {
+            assert_eq!(
+                player.tick(&mut display, now),
+                Ok(expected),
+                "{mode:?} at {now}"
+            );
+            match expected {
+                AnimationState::Presented(index) => {
+                    assert_eq!(player.frame(), index);
+                    assert!(!log.bytes().is_empty());
+                    assert!(!display.is_dirty());
+                }
+                _ => assert!(log.bytes().is_empty()),
+            }
+            assert!(
+                shows(&display, &frames[player.frame()]),
+                "{mode:?} at {now}"
+            );
+        }
+    }
+
+    #[test]
+    fn frames_loop() {
+        use AnimationState::*;
+        play(
+            PlayMode::Loop,
+            &[
+                (0, Presented(0)),
+                (99, Waiting),
+                (100, Presented(1)),
+                (299, Waiting),
+                (300, Presented(2)),
+                (349, Waiting),
+                (350, Presented(0)),
+                // A late tick counts the time of the frame from when it was presented
+                (500, Presented(1)),
+                (699, Waiting),
+                (700, Presented(2)),
+            ],
+        );
+    }
+
+    #[test]
+    fn frames_play_once() {
+        use AnimationState::*;
+        play(
+            PlayMode::Once,
+            &[
+                (10, Presented(0)),
+                (110, Presented(1)),
+                (310, Presented(2)),
+                (359, Waiting),
+                (360, Finished),
+                (1000, Finished),
+            ],
+        );
+    }
+
+    #[test]
+    fn frames_ping_pong() {
+        use AnimationState::*;
+        play(
+            PlayMode::PingPong,
+            &[
+                (0, Presented(0)),
+                (100, Presented(1)),
+                (300, Presented(2)),
+                (350, Presented(1)),
+                (549, Waiting),
+                (550, Presented(0)),
+                (650, Presented(1)),
+            ],
+        );
+    }
+
+    #[test]
+    fn clock_wraps_around() {
+        use AnimationState::*;
+        let start = u32::MAX - 150;
+        play(
+            PlayMode::Loop,
+            &[
+                (start, Presented(0)),
+                (start + 99, Waiting),
+                (start + 100, Presented(1)),
+                (start.wrapping_add(299), Waiting),
+                (start.wrapping_add(300), Presented(2)),
+            ],
+        );
+    }
+
+    #[test]
+    fn failed_flush_presents_the_frame_again() {
+        let data = encode(&[(&frame(0), 100), (&frame(1), 100)], false);
+        let mut player = AnimationPlayer::new(
+            Animation::<spec::Selected>::from_bytes(&data).unwrap(),
+            PlayMode::Loop,
+        );
+        let (log, mut display) = testing::display();
+        player.tick(&mut display, 0).unwrap();
+        log.reject_writes_over(0);
+        assert!(player.tick(&mut display, 100).is_err());
+        assert!(display.is_dirty());
+        log.reject_writes_over(usize::MAX);
+        assert_eq!(
+            player.tick(&mut display, 101),
+            Ok(AnimationState::Presented(1))
+        );
+        assert!(shows(&display, &frame(1)));
+    }
+
+    // The lines equal to the framebuffer are not sent
+    #[cfg(not(any(
+        feature = "half-height",
+        feature = "half-width",
+        feature = "column-major",
+        feature = "minimal-flush"
+    )))]
+    #[test]
+    fn only_changed_lines_are_flushed() {
+        let mut second = frame(0);
+        second.set_pixel(0, 3, !second.buffer.get(0, 3));
+        let data = encode(&[(&frame(0), 100), (&second, 100)], true);
+        let mut player = AnimationPlayer::new(
+            Animation::<spec::Selected>::from_bytes(&data).unwrap(),
+            PlayMode::Loop,
+        );
+        let (log, mut display) = testing::display();
+        player.tick(&mut display, 0).unwrap();
+        log.events();
+        player.tick(&mut display, 100).unwrap();
+        let records = crate::decode::decode(&log.bytes(), crate::WIDTH, crate::HEIGHT);
+        let [crate::decode::Record::WriteLines { lines, .. }] = &records[..] else {
+            panic!("{records:?}");
+        };
+        assert_eq!(lines.iter().map(|line| line.0).collect::<Vec<_>>(), [4]);
+    }
+}
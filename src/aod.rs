@@ -0,0 +1,315 @@
+use crate::{spec, DisplaySpec, Error, FrameBuffer, MemoryDisplay};
+use core::borrow::BorrowMut;
+use embedded_graphics::draw_target::{DrawTarget, DrawTargetExt};
+use embedded_graphics::geometry::Dimensions;
+use embedded_graphics::pixelcolor::BinaryColor;
+use embedded_graphics::primitives::Rectangle;
+use hal::blocking::spi::Write;
+use hal::digital::v2::OutputPin;
+
+/// Content of the region updated by an [`AodHelper`], e.g. a clock face.
+pub trait AodContent<T> {
+    /// Draw the content for the time `now`. The target is clipped to the region.
+    fn draw<D>(&mut self, target: &mut D, now: T) -> Result<(), D::Error>
+    where
+        D: DrawTarget<Color = BinaryColor>;
+}
+
+/// Always-on display support for firmware which sleeps most of the time and wakes up
+/// periodically, e.g. once a second from an RTC interrupt, to update a clock.
+///
+/// Every [`AodHelper::low_power_tick`] redraws the region and sends the lines of it which
+/// changed together with the VCOM toggle, so with
+/// [`CsStrategy::Software`](crate::CsStrategy::Software) there is exactly one bus transaction
+/// per wakeup. If the content did not change, only the VCOM toggle is sent.
+///
+/// To tell which lines changed, the lines of the framebuffer the region covers are copied to
+/// `snapshot` before drawing, a line of [`DisplaySpec::LINE_BYTES`] each:
+///
+/// ```ignore
+/// let mut rows = [[0; LINE_BYTES]; 16];
+/// let mut aod = AodHelper::new(clock_area, clock, &mut rows);
+/// ```
+pub struct AodHelper<'a, C, SPEC: DisplaySpec = spec::Selected> {
+    region: Rectangle,
+    content: C,
+    snapshot: &'a mut [SPEC::Line],
+}
+
+impl<'a, C> AodHelper<'a, C> {
+    pub fn new(
+        region: Rectangle,
+        content: C,
+        snapshot: &'a mut [<spec::Selected as DisplaySpec>::Line],
+    ) -> Self {
+        Self {
+            region,
+            content,
+            snapshot,
+        }
+    }
+}
+
+impl<'a, C, SPEC: DisplaySpec> AodHelper<'a, C, SPEC> {
+    /// Same as [`AodHelper::new`] for a display of the model `SPEC`, see
+    /// [`MemoryDisplay::new_with_spec`].
+    pub fn new_with_spec(
+        region: Rectangle,
+        content: C,
+        snapshot: &'a mut [SPEC::Line],
+        _spec: SPEC,
+    ) -> Self {
+        Self {
+            region,
+            content,
+            snapshot,
+        }
+    }
+
+    /// The region redrawn on every tick.
+    pub fn region(&self) -> Rectangle {
+        self.region
+    }
+
+    pub fn content(&self) -> &C {
+        &self.content
+    }
+
+    pub fn content_mut(&mut self) -> &mut C {
+        &mut self.content
+    }
+
+    /// Redraw the region for the time `now` and flush the lines of it which changed, or only
+    /// toggle VCOM if none did. Lines of the region which were already dirty are sent as well,
+    /// dirty lines outside of it stay dirty for the next flush, and lines drawn to in an open
+    /// frame are held back, see [`MemoryDisplay::begin_frame`]. Returns whether any lines were
+    /// sent.
+    ///
+    /// The content is drawn once and every line compared exactly against its copy in the
+    /// snapshot. Lines of the region past the end of the snapshot cannot be compared and are
+    /// sent whenever the content draws to them.
+    pub fn low_power_tick<SPI, CS, DISP, E, T, BUF>(
+        &mut self,
+        display: &mut MemoryDisplay<SPI, CS, DISP, SPEC, BUF>,
+        now: T,
+    ) -> Result<bool, Error<E, CS::Error>>
+    where
+        SPI: Write<u8, Error = E>,
+        CS: OutputPin,
+        DISP: OutputPin,
+        BUF: BorrowMut<FrameBuffer<SPEC>>,
+        C: AodContent<T>,
+    {
+        let region = self.region.intersection(&display.bounding_box());
+        let lines = display
+            .physical_region(region)
+            .map_or(0..0, |(_, lines)| lines);
+
+        let dirty = display.frame.borrow().touched;
+        for (y, row) in lines.clone().zip(self.snapshot.iter_mut()) {
+            *row = display.frame.borrow().buffer.line(y);
+        }
+        self.content
+            .draw(&mut display.clipped(&region), now)
+            .map_err(Error::Spi)?;
+        // Content is usually cleared and drawn again, which marks lines dirty even if they end
+        // up the same, so compare the contents
+        let FrameBuffer { buffer, touched } = display.frame.borrow_mut();
+        for (y, row) in lines.clone().zip(self.snapshot.iter()) {
+            if buffer.line(y) == *row {
+                touched.set(y, false);
+                touched.merge(y, &dirty);
+            }
+        }
+
+        display.presenting(|display| {
+            let touched = &mut display.frame.borrow_mut().touched;
+            let others = *touched;
+            for y in others.iter().filter(|y| !lines.contains(y)) {
+                touched.set(y, false);
+            }
+            let sent = touched.any();
+            let result = if sent {
+                display.flush_buffer()
+            } else {
+                display.display_mode()
+            };
+            let touched = &mut display.frame.borrow_mut().touched;
+            for y in others.iter().filter(|y| !lines.contains(y)) {
+                touched.merge(y, &others);
+            }
+            result.map(|()| sent)
+        })
+    }
+}
+
+// Every line sent as it is drawn
+#[cfg(all(
+    test,
+    not(any(
+        feature = "half-height",
+        feature = "half-width",
+        feature = "minimal-flush"
+    ))
+))]
+mod tests {
+    use super::*;
+    use crate::testing::{self, pixels, Event};
+    use crate::LINE_BYTES;
+    use crate::{CsStrategy, Vcom, DUMMY_DATA};
+    use embedded_graphics::prelude::{Point, Size};
+    use embedded_graphics::Pixel;
+    use std::vec::Vec;
+
+    // Lines 4 to 9, each copied to the snapshot
+    const REGION: Rectangle = Rectangle::new(Point::new(8, 4), Size::new(32, 6));
+
+    /// A dot on line 5 moving to the right every other second, over a cleared region.
+    struct Dot;
+
+    impl AodContent<u32> for Dot {
+        fn draw<D>(&mut self, target: &mut D, now: u32) -> Result<(), D::Error>
+        where
+            D: DrawTarget<Color = BinaryColor>,
+        {
+            target.clear(BinaryColor::Off)?;
+            let x = 8 + (now / 2 % 32) as i32;
+            target.draw_iter([Pixel(Point::new(x, 5), BinaryColor::On)])
+        }
+    }
+
+    /// The bytes of the events of a tick, checking they are a single transaction.
+    fn transaction(events: Vec<Event>) -> Vec<u8> {
+        let selects = events.iter().filter(|event| matches!(event, Event::Cs(_)));
+        assert!(
+            selects.eq(&[Event::Cs(true), Event::Cs(false)]),
+            "{events:?}"
+        );
+        assert_eq!(events.first(), Some(&Event::Cs(true)));
+        assert_eq!(events.last(), Some(&Event::Cs(false)));
+        testing::written(&events)
+    }
+
+    #[test]
+    fn one_transaction_per_tick() {
+        let (log, mut display) = testing::display();
+        display.set_cs_strategy(CsStrategy::Software);
+        let mut snapshot = [[0; LINE_BYTES]; 6];
+        let mut aod = AodHelper::new(REGION, Dot, &mut snapshot);
+        let mut vcom = Vcom::Hi;
+        for now in 0..8 {
+            vcom = !vcom;
+            let sent = aod.low_power_tick(&mut display, now).unwrap();
+            let bytes = transaction(log.events());
+            // The dot moves every other second, in between only VCOM is toggled
+            assert_eq!(sent, now % 2 == 0, "{now}");
+            if sent {
+                let dot = pixels(&[8 + now as usize / 2]);
+                assert_eq!(bytes, testing::frame(vcom, &[(5, dot)]));
+            } else {
+                assert_eq!(bytes, [vcom as u8, DUMMY_DATA]);
+            }
+            assert!(!display.is_dirty());
+        }
+    }
+
+    #[test]
+    fn only_the_region_is_sent() {
+        let (log, mut display) = testing::display();
+        let mut snapshot = [[0; LINE_BYTES]; 6];
+        let mut aod = AodHelper::new(REGION, Dot, &mut snapshot);
+        aod.low_power_tick(&mut display, 0).unwrap();
+        log.events();
+
+        // Drawn outside the region, and within it but erased by the content
+        display.set_pixel(3, 30, true).unwrap();
+        display.set_pixel(20, 6, true).unwrap();
+        assert!(aod.low_power_tick(&mut display, 1).unwrap());
+        let lines = [(6, pixels(&[]))];
+        assert_eq!(log.bytes(), testing::frame(Vcom::Hi, &lines));
+        assert_eq!(display.dirty_line_count(), 1);
+        display.flush_buffer().unwrap();
+        let lines = [(30, pixels(&[3]))];
+        assert_eq!(log.bytes(), testing::frame(Vcom::Lo, &lines));
+    }
+
+    #[test]
+    fn content_is_drawn_once_per_tick() {
+        struct Counted(u32);
+        impl AodContent<u32> for Counted {
+            fn draw<D>(&mut self, target: &mut D, now: u32) -> Result<(), D::Error>
+            where
+                D: DrawTarget<Color = BinaryColor>,
+            {
+                self.0 += 1;
+                Dot.draw(target, now)
+            }
+        }
+
+        let (log, mut display) = testing::display();
+        let mut snapshot = [[0; LINE_BYTES]; 6];
+        let mut aod = AodHelper::new(REGION, Counted(0), &mut snapshot);
+        for now in 0..4 {
+            aod.low_power_tick(&mut display, now).unwrap();
+        }
+        assert_eq!(aod.content().0, 4);
+
+        // Without a copy of a line, it is sent whenever it is drawn to
+        let mut short = [[0; LINE_BYTES]; 1];
+        let mut aod = AodHelper::new(REGION, Dot, &mut short);
+        log.events();
+        assert!(aod.low_power_tick(&mut display, 3).unwrap());
+        let dot = pixels(&[9]);
+        assert_eq!(log.bytes(), testing::frame(Vcom::Lo, &[(5, dot)]));
+    }
+
+    #[test]
+    fn every_change_is_sent() {
+        // Two pixels swapping places leave every sum and count of the line the same
+        struct Swap;
+        impl AodContent<bool> for Swap {
+            fn draw<D>(&mut self, target: &mut D, swapped: bool) -> Result<(), D::Error>
+            where
+                D: DrawTarget<Color = BinaryColor>,
+            {
+                target.clear(BinaryColor::Off)?;
+                let on = if swapped { [9, 16] } else { [8, 17] };
+                target.draw_iter(on.map(|x| Pixel(Point::new(x, 7), BinaryColor::On)))
+            }
+        }
+
+        let (log, mut display) = testing::display();
+        let mut snapshot = [[0; LINE_BYTES]; 6];
+        let mut aod = AodHelper::new(REGION, Swap, &mut snapshot);
+        let mut vcom = Vcom::Hi;
+        for (swapped, changed) in [(false, true), (true, true), (true, false), (false, true)] {
+            vcom = !vcom;
+            assert_eq!(aod.low_power_tick(&mut display, swapped).unwrap(), changed);
+            let on = if swapped { [9, 16] } else { [8, 17] };
+            if changed {
+                assert_eq!(log.bytes(), testing::frame(vcom, &[(7, pixels(&on))]));
+            } else {
+                assert_eq!(log.bytes(), [vcom as u8, DUMMY_DATA]);
+            }
+        }
+    }
+
+    #[test]
+    fn lines_of_an_open_frame_are_held_back() {
+        let (log, mut display) = testing::display();
+        let mut snapshot = [[0; LINE_BYTES]; 6];
+        let mut aod = AodHelper::new(REGION, Dot, &mut snapshot);
+        assert!(aod.low_power_tick(&mut display, 0).unwrap());
+        log.events();
+
+        // The dot moves while a frame is open, only VCOM is toggled until it ends
+        display.begin_frame();
+        assert!(!aod.low_power_tick(&mut display, 2).unwrap());
+        assert_eq!(log.bytes(), [Vcom::Hi as u8, DUMMY_DATA]);
+        display.end_frame();
+        assert_eq!(display.dirty_line_count(), 1);
+        assert!(aod.low_power_tick(&mut display, 2).unwrap());
+        let dot = pixels(&[9]);
+        assert_eq!(log.bytes(), testing::frame(Vcom::Lo, &[(5, dot)]));
+    }
+}
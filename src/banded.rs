@@ -0,0 +1,379 @@
+use crate::spec::{self, DisplaySpec, Zeroed};
+use crate::{
+    address_high_bits, carry_line, frame_end, transfer, Color, Command, Error, MemoryDisplay, Vcom,
+    DUMMY_DATA,
+};
+use bitvec::prelude::*;
+use core::ops::Range;
+use hal::blocking::spi::Write;
+use hal::digital::v2::OutputPin;
+
+/// A display driven through a framebuffer covering only a band of its lines, e.g. a status
+/// strip at the top of a large panel, created with [`MemoryDisplay::new_banded`].
+///
+/// Coordinates are those of the whole panel: `size()` reports the full panel, pixels outside
+/// the band are clipped and flushing only ever addresses lines within the band. The rest of the
+/// panel keeps whatever it showed before, [`BandedDisplay::clear`] blanks all of it.
+pub struct BandedDisplay<'a, SPI, CS, DISP, SPEC: DisplaySpec = spec::Selected> {
+    spi: SPI,
+    cs: CS,
+    disp: DISP,
+    lines: Range<usize>,
+    buffer: &'a mut [SPEC::PanelLine],
+    touched: SPEC::PanelLineBits,
+    vcom: Vcom,
+    clear_state: Color,
+}
+
+impl<SPI, CS, DISP, E> MemoryDisplay<SPI, CS, DISP>
+where
+    SPI: Write<u8, Error = E>,
+    CS: OutputPin,
+    DISP: OutputPin,
+{
+    /// Create a display whose framebuffer only covers `lines` of the panel, stored in `buffer`
+    /// with one array of `WIDTH.div_ceil(8)` bytes per line in the layout of
+    /// [`MemoryDisplay::buffer`].
+    ///
+    /// # Panics
+    /// If `lines` is empty, extends past the bottom of the panel or `buffer` holds fewer lines.
+    pub fn new_banded(
+        spi: SPI,
+        cs: CS,
+        disp: DISP,
+        lines: Range<usize>,
+        buffer: &mut [<spec::Selected as DisplaySpec>::PanelLine],
+    ) -> BandedDisplay<'_, SPI, CS, DISP> {
+        BandedDisplay::new(spi, cs, disp, lines, buffer)
+    }
+}
+
+impl<SPI, CS, DISP, E, SPEC> MemoryDisplay<SPI, CS, DISP, SPEC>
+where
+    SPI: Write<u8, Error = E>,
+    CS: OutputPin,
+    DISP: OutputPin,
+    SPEC: DisplaySpec,
+{
+    /// Same as [`MemoryDisplay::new_banded`] for the model `SPEC`, see
+    /// [`MemoryDisplay::new_with_spec`].
+    pub fn new_banded_with_spec(
+        spi: SPI,
+        cs: CS,
+        disp: DISP,
+        lines: Range<usize>,
+        buffer: &mut [SPEC::PanelLine],
+        _spec: SPEC,
+    ) -> BandedDisplay<'_, SPI, CS, DISP, SPEC> {
+        BandedDisplay::new(spi, cs, disp, lines, buffer)
+    }
+}
+
+impl<'a, SPI, CS, DISP, E, SPEC> BandedDisplay<'a, SPI, CS, DISP, SPEC>
+where
+    SPI: Write<u8, Error = E>,
+    CS: OutputPin,
+    DISP: OutputPin,
+    SPEC: DisplaySpec,
+{
+    fn new(
+        spi: SPI,
+        mut cs: CS,
+        mut disp: DISP,
+        lines: Range<usize>,
+        buffer: &'a mut [SPEC::PanelLine],
+    ) -> Self {
+        assert!(
+            !lines.is_empty() && lines.end <= SPEC::HEIGHT,
+            "band outside the display"
+        );
+        assert!(buffer.len() >= lines.len(), "buffer too short for the band");
+        let _ = disp.set_low();
+        let _ = cs.set_low();
+
+        let buffer = &mut buffer[..lines.len()];
+        buffer.fill(SPEC::PanelLine::ZERO);
+
+        BandedDisplay {
+            spi,
+            cs,
+            disp,
+            lines,
+            buffer,
+            touched: Zeroed::ZERO,
+            vcom: Vcom::Hi,
+            clear_state: Color::On,
+        }
+    }
+
+    fn touched(&mut self) -> &mut BitSlice<u8, Lsb0> {
+        &mut self.touched.as_mut().view_bits_mut()[..SPEC::HEIGHT]
+    }
+}
+
+impl<SPI, CS, DISP, E, SPEC> BandedDisplay<'_, SPI, CS, DISP, SPEC>
+where
+    SPI: Write<u8, Error = E>,
+    CS: OutputPin,
+    DISP: OutputPin,
+    SPEC: DisplaySpec,
+{
+    /// The lines of the panel covered by the framebuffer.
+    pub fn lines(&self) -> Range<usize> {
+        self.lines.clone()
+    }
+
+    /// Set the value the framebuffer is set to by [`BandedDisplay::clear`], see
+    /// [`MemoryDisplay::set_clear_state`].
+    pub fn set_clear_state(&mut self, clear_state: impl Into<Color>) {
+        self.clear_state = clear_state.into();
+    }
+
+    /// Enable the LCD by driving the display pin high.
+    pub fn enable(&mut self) -> Result<(), Error<E, DISP::Error>> {
+        self.disp.set_high().map_err(Error::Disp)
+    }
+
+    /// Disable the LCD.
+    pub fn disable(&mut self) -> Result<(), Error<E, DISP::Error>> {
+        self.disp.set_low().map_err(Error::Disp)
+    }
+
+    /// Set a single pixel in the framebuffer. Pixels outside the band are ignored.
+    pub fn set_pixel(&mut self, x: u32, y: u32, val: impl Into<Color>) {
+        let (x, y) = (x as usize, y as usize);
+        if x < SPEC::WIDTH && self.lines.contains(&y) {
+            let line = &mut self.buffer[y - self.lines.start];
+            line.as_mut()
+                .view_bits_mut::<Lsb0>()
+                .set(x, val.into().is_on());
+            self.touched().set(y, true);
+        }
+    }
+
+    /// Mark every line of the band to be sent by the next flush.
+    pub fn mark_all_dirty(&mut self) {
+        let lines = self.lines.clone();
+        self.touched()[lines].fill(true);
+    }
+
+    /// Clear just the framebuffer, without writing changes to the display.
+    pub fn clear_buffer(&mut self) {
+        let fill = if self.clear_state.is_on() { 0xFF } else { 0x00 };
+        for line in self.buffer.iter_mut() {
+            line.as_mut().fill(fill);
+        }
+        self.mark_all_dirty();
+    }
+
+    /// Clear the framebuffer and the whole screen, including the lines outside the band.
+    pub fn clear(&mut self) -> Result<(), Error<E, CS::Error>> {
+        self.clear_buffer();
+        self.vcom = !self.vcom;
+        self.write_spi(&[Command::ClearMemory | self.vcom, DUMMY_DATA])
+    }
+
+    /// Send the lines of the band which changed since the last flush, holding chip select for
+    /// the whole frame like [`crate::CsStrategy::Software`]. Like with
+    /// [`MemoryDisplay::flush_buffer`] the lines stay dirty if it fails.
+    pub fn flush_buffer(&mut self) -> Result<(), Error<E, CS::Error>> {
+        self.vcom = !self.vcom;
+
+        self.cs.set_high().map_err(Error::Cs)?;
+        let result = self.write_frame();
+        let deselect = self.cs.set_low().map_err(Error::Cs);
+        result.and(deselect)?;
+
+        self.touched().fill(false);
+        Ok(())
+    }
+
+    /// Toggle VCOM without changing the screen, see [`MemoryDisplay::display_mode`].
+    pub fn display_mode(&mut self) -> Result<(), Error<E, CS::Error>> {
+        self.vcom = !self.vcom;
+        self.write_spi(&[Command::Nop | self.vcom, DUMMY_DATA])
+    }
+
+    /// Send the command, the dirty lines and the frame trailer while chip select is held.
+    fn write_frame(&mut self) -> Result<(), Error<E, CS::Error>> {
+        let touched = &self.touched.as_ref().view_bits::<Lsb0>()[..SPEC::HEIGHT];
+        let mut lines = touched.iter_ones().peekable();
+        // The byte before the address of a line carries its high bits, so look ahead
+        let first = lines.peek().map_or(0, |&y| address_high_bits::<SPEC>(y));
+        // Every write starts with the last byte of the one before, like in
+        // MemoryDisplay::write_frame
+        let mut carry = Command::WriteLine | self.vcom | first;
+
+        while let Some(y) = lines.next() {
+            let line = &self.buffer[y - self.lines.start];
+            let mut packed = MemoryDisplay::<SPI, CS, DISP, SPEC>::pack_panel_line(y, line);
+            let packed = packed.as_mut();
+            packed[packed.len() - 1] |= lines.peek().map_or(0, |&y| address_high_bits::<SPEC>(y));
+            carry = carry_line(carry, packed);
+            self.spi.write(packed).map_err(Error::Spi)?;
+        }
+
+        self.spi
+            .write(frame_end::<SPEC>(carry).as_ref())
+            .map_err(Error::Spi)
+    }
+
+    fn write_spi(&mut self, data: &[u8]) -> Result<(), Error<E, CS::Error>> {
+        transfer(&mut self.spi, &mut self.cs, data)
+    }
+}
+
+#[cfg(feature = "graphics")]
+mod graphics {
+    use super::BandedDisplay;
+    use crate::DisplaySpec;
+    use embedded_graphics::draw_target::DrawTarget;
+    use embedded_graphics::pixelcolor::BinaryColor;
+    use embedded_graphics::prelude::{OriginDimensions, Size};
+    use embedded_graphics::Pixel;
+    use hal::blocking::spi::Write;
+    use hal::digital::v2::OutputPin;
+
+    impl<SPI, CS, DISP, SPEC: DisplaySpec> OriginDimensions for BandedDisplay<'_, SPI, CS, DISP, SPEC> {
+        fn size(&self) -> Size {
+            Size::new(SPEC::WIDTH as u32, SPEC::HEIGHT as u32)
+        }
+    }
+
+    impl<SPI, CS, DISP, E, SPEC> DrawTarget for BandedDisplay<'_, SPI, CS, DISP, SPEC>
+    where
+        SPI: Write<u8, Error = E>,
+        CS: OutputPin,
+        DISP: OutputPin,
+        SPEC: DisplaySpec,
+    {
+        type Color = BinaryColor;
+        type Error = E;
+
+        fn draw_iter<T>(&mut self, item_pixels: T) -> Result<(), E>
+        where
+            T: IntoIterator<Item = Pixel<Self::Color>>,
+        {
+            for Pixel(coord, color) in item_pixels {
+                if let (Ok(x), Ok(y)) = (coord.x.try_into(), coord.y.try_into()) {
+                    self.set_pixel(x, y, color);
+                }
+            }
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{self, pixels, Event};
+    use crate::{HEIGHT, PANEL_LINE_BYTES, WIDTH};
+    use std::{vec, vec::Vec};
+
+    const BAND: Range<usize> = HEIGHT / 4..HEIGHT / 2;
+
+    fn frame_of(log: &testing::Log) -> Vec<u8> {
+        let events = log.events();
+        assert_eq!(events.first(), Some(&Event::Cs(true)));
+        assert_eq!(events.last(), Some(&Event::Cs(false)));
+        testing::written(&events)
+    }
+
+    #[test]
+    fn pixels_outside_the_band_are_clipped() {
+        let log = testing::Log::default();
+        let mut buffer = vec![[0; PANEL_LINE_BYTES]; BAND.len()];
+        let mut display =
+            MemoryDisplay::new_banded(log.spi(), log.cs(), log.disp(), BAND, &mut buffer);
+        log.events();
+        let (first, last, right) = (BAND.start as u32, BAND.end as u32 - 1, WIDTH as u32);
+        for (x, y) in [
+            (0, first - 1),
+            (0, first),
+            (right - 1, last),
+            (right - 1, last + 1),
+        ] {
+            display.set_pixel(x, y, Color::On);
+        }
+        display.set_pixel(right, first + 1, Color::On);
+        display.flush_buffer().unwrap();
+        let lines = [
+            (BAND.start, pixels(&[0])),
+            (BAND.end - 1, pixels(&[WIDTH - 1])),
+        ];
+        assert_eq!(frame_of(&log), testing::frame(Vcom::Lo, &lines));
+
+        // Nothing is left to send
+        display.flush_buffer().unwrap();
+        assert_eq!(frame_of(&log), testing::frame(Vcom::Hi, &[]));
+    }
+
+    #[test]
+    fn only_the_lines_of_the_band_are_sent() {
+        let log = testing::Log::default();
+        let mut buffer = vec![[0xAA; PANEL_LINE_BYTES]; BAND.len() + 3];
+        let mut display =
+            MemoryDisplay::new_banded(log.spi(), log.cs(), log.disp(), BAND, &mut buffer);
+        log.events();
+        display.clear_buffer();
+        display.flush_buffer().unwrap();
+        let on = pixels(&(0..WIDTH).collect::<Vec<_>>());
+        let lines: Vec<_> = BAND.map(|y| (y, on)).collect();
+        assert_eq!(frame_of(&log), testing::frame(Vcom::Lo, &lines));
+
+        display.set_clear_state(Color::Off);
+        display.clear().unwrap();
+        let clear = Command::ClearMemory | Vcom::Hi;
+        assert_eq!(
+            log.events(),
+            [
+                Event::Cs(true),
+                Event::Write(vec![clear, DUMMY_DATA]),
+                Event::Cs(false)
+            ]
+        );
+        display.flush_buffer().unwrap();
+        let lines: Vec<_> = BAND.map(|y| (y, pixels(&[]))).collect();
+        assert_eq!(frame_of(&log), testing::frame(Vcom::Lo, &lines));
+    }
+
+    #[cfg(feature = "graphics")]
+    #[test]
+    fn drawing_is_clipped_to_the_band() {
+        use embedded_graphics::pixelcolor::BinaryColor;
+        use embedded_graphics::prelude::*;
+        use embedded_graphics::primitives::{PrimitiveStyle, Rectangle};
+
+        let log = testing::Log::default();
+        let mut buffer = vec![[0; PANEL_LINE_BYTES]; BAND.len()];
+        let mut display =
+            MemoryDisplay::new_banded(log.spi(), log.cs(), log.disp(), BAND, &mut buffer);
+        log.events();
+        assert_eq!(display.size(), Size::new(WIDTH as u32, HEIGHT as u32));
+        Rectangle::new(Point::new(-2, -2), Size::new(4, HEIGHT as u32 + 4))
+            .into_styled(PrimitiveStyle::with_fill(BinaryColor::On))
+            .draw(&mut display)
+            .unwrap();
+        display.flush_buffer().unwrap();
+        let lines: Vec<_> = BAND.map(|y| (y, pixels(&[0, 1]))).collect();
+        assert_eq!(frame_of(&log), testing::frame(Vcom::Lo, &lines));
+    }
+
+    #[test]
+    #[should_panic(expected = "band outside the display")]
+    fn band_past_the_bottom() {
+        let log = testing::Log::default();
+        let mut buffer = vec![[0; PANEL_LINE_BYTES]; 2];
+        let lines = HEIGHT - 1..HEIGHT + 1;
+        MemoryDisplay::new_banded(log.spi(), log.cs(), log.disp(), lines, &mut buffer);
+    }
+
+    #[test]
+    #[should_panic(expected = "buffer too short for the band")]
+    fn buffer_shorter_than_the_band() {
+        let log = testing::Log::default();
+        let mut buffer = vec![[0; PANEL_LINE_BYTES]; BAND.len() - 1];
+        MemoryDisplay::new_banded(log.spi(), log.cs(), log.disp(), BAND, &mut buffer);
+    }
+}
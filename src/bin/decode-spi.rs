@@ -0,0 +1,127 @@
+//! Decode a raw capture of the SPI traffic sent to a memory display into images.
+//!
+//! ```text
+//! cargo run --features std --bin decode-spi -- capture.bin --model ls027b7dh01 --out frames/
+//! ```
+//!
+//! Every command which changes the panel memory produces a PBM frame in the output directory,
+//! and a summary of the decoded commands is printed to stdout. Captures written by
+//! `capture::IoTransport` are read with `--framed`.
+use sharp_memory_display::capture::IoReplay;
+use sharp_memory_display::decode::{self, Panel, Record};
+use std::fs::{self, File};
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+const USAGE: &str = "usage: decode-spi <capture.bin> --model <model> [--out <dir>] [--framed]";
+
+fn main() -> ExitCode {
+    let mut capture = None;
+    let mut model = None;
+    let mut out = None;
+    let mut framed = false;
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--model" => model = args.next(),
+            "--out" => out = args.next().map(PathBuf::from),
+            "--framed" => framed = true,
+            "-h" | "--help" => {
+                println!("{USAGE}");
+                return ExitCode::SUCCESS;
+            }
+            _ if capture.is_none() => capture = Some(PathBuf::from(arg)),
+            _ => {
+                eprintln!("{USAGE}");
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+
+    let (Some(capture), Some(model)) = (capture, model) else {
+        eprintln!("{USAGE}");
+        return ExitCode::FAILURE;
+    };
+    let Some((width, height)) = decode::model_geometry(&model) else {
+        let models: Vec<_> = decode::MODELS.iter().map(|(name, _, _)| *name).collect();
+        eprintln!(
+            "unknown model '{model}', expected one of: {}",
+            models.join(", ")
+        );
+        return ExitCode::FAILURE;
+    };
+
+    let trailers = decode::model_trailers(&model).unwrap_or((1, 1));
+
+    match run(&capture, width, height, trailers, out, framed) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(
+    capture: &Path,
+    width: usize,
+    height: usize,
+    trailers: (usize, usize),
+    out: Option<PathBuf>,
+    framed: bool,
+) -> std::io::Result<()> {
+    let bytes = fs::read(capture)?;
+    let records = if framed {
+        IoReplay::new(&bytes[..]).records(width, height, trailers)?
+    } else {
+        decode::decode_with_trailers(&bytes, width, height, trailers)
+    };
+
+    if let Some(out) = &out {
+        fs::create_dir_all(out)?;
+    }
+
+    let mut panel = Panel::new(width, height);
+    let mut frames = 0;
+    let mut vcom = String::new();
+    let mut malformed = 0;
+
+    for record in &records {
+        match record {
+            Record::WriteLines { lines, .. } => {
+                println!("frame {frames}: write {} lines", lines.len());
+            }
+            Record::ClearMemory { .. } => println!("frame {frames}: clear memory"),
+            Record::Nop { .. } => {}
+            Record::Malformed { offset, reason } => {
+                println!("malformed record at offset {offset}: {reason}");
+                malformed += 1;
+            }
+        }
+        if let Some(bit) = record.vcom() {
+            vcom.push(if bit { '1' } else { '0' });
+        }
+
+        panel.apply(record);
+        if matches!(
+            record,
+            Record::WriteLines { .. } | Record::ClearMemory { .. }
+        ) {
+            if let Some(out) = &out {
+                let file = File::create(out.join(format!("frame_{frames:04}.pbm")))?;
+                panel.write_pbm(BufWriter::new(file))?;
+            }
+            frames += 1;
+        }
+    }
+
+    println!(
+        "{} bytes, {} commands, {frames} frames, {malformed} malformed records",
+        bytes.len(),
+        records.len()
+    );
+    println!("vcom sequence: {vcom}");
+    Ok(())
+}
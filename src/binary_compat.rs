@@ -0,0 +1,199 @@
+use embedded_graphics::draw_target::DrawTarget;
+use embedded_graphics::geometry::Dimensions;
+use embedded_graphics::pixelcolor::{BinaryColor, RgbColor};
+use embedded_graphics::primitives::Rectangle;
+use embedded_graphics::Pixel;
+
+/// Draw [`BinaryColor`] content onto a colour display.
+///
+/// On and off pixels are drawn with a configurable foreground and background colour, so
+/// existing monochrome UIs run unchanged while new code can draw to the colour display directly.
+/// All drawing goes through the wrapped display, including its dirty tracking.
+pub struct BinaryCompat<'a, D: DrawTarget> {
+    target: &'a mut D,
+    on: D::Color,
+    off: D::Color,
+}
+
+impl<'a, D> BinaryCompat<'a, D>
+where
+    D: DrawTarget,
+    D::Color: RgbColor,
+{
+    /// Draw on pixels in white and off pixels in black, like on a monochrome panel.
+    pub fn new(target: &'a mut D) -> Self {
+        Self::with_colors(target, D::Color::WHITE, D::Color::BLACK)
+    }
+}
+
+impl<'a, D> BinaryCompat<'a, D>
+where
+    D: DrawTarget,
+{
+    /// Draw on pixels with the colour `on` and off pixels with the colour `off`.
+    pub fn with_colors(target: &'a mut D, on: D::Color, off: D::Color) -> Self {
+        Self { target, on, off }
+    }
+
+    fn map(&self, color: BinaryColor) -> D::Color {
+        match color {
+            BinaryColor::On => self.on,
+            BinaryColor::Off => self.off,
+        }
+    }
+}
+
+impl<D> Dimensions for BinaryCompat<'_, D>
+where
+    D: DrawTarget,
+{
+    fn bounding_box(&self) -> Rectangle {
+        self.target.bounding_box()
+    }
+}
+
+impl<D> DrawTarget for BinaryCompat<'_, D>
+where
+    D: DrawTarget,
+{
+    type Color = BinaryColor;
+    type Error = D::Error;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        let (on, off) = (self.on, self.off);
+        self.target.draw_iter(
+            pixels
+                .into_iter()
+                .map(|Pixel(point, color)| Pixel(point, if color.is_on() { on } else { off })),
+        )
+    }
+
+    fn fill_contiguous<I>(&mut self, area: &Rectangle, colors: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Self::Color>,
+    {
+        let (on, off) = (self.on, self.off);
+        self.target.fill_contiguous(
+            area,
+            colors
+                .into_iter()
+                .map(|color| if color.is_on() { on } else { off }),
+        )
+    }
+
+    fn fill_solid(&mut self, area: &Rectangle, color: Self::Color) -> Result<(), Self::Error> {
+        let color = self.map(color);
+        self.target.fill_solid(area, color)
+    }
+
+    fn clear(&mut self, color: Self::Color) -> Result<(), Self::Error> {
+        let color = self.map(color);
+        self.target.clear(color)
+    }
+}
+
+#[cfg(all(test, any(feature = "lpm013m126a", feature = "ls013b7dh06")))]
+mod tests {
+    use super::*;
+    use crate::color::{ColorMemoryDisplay, COLOR_HEIGHT, COLOR_WIDTH};
+    use crate::testing::{Log, Pin, Spi};
+    use crate::Rgb111;
+    use embedded_graphics::prelude::*;
+    use embedded_graphics::primitives::{Circle, Line, PrimitiveStyle};
+    use std::vec::Vec;
+
+    type Display = ColorMemoryDisplay<Spi, Pin, Pin>;
+
+    /// Draw the same scene with `on` and `off` as its colors, through every method of
+    /// [`DrawTarget`].
+    fn scene<D: DrawTarget>(target: &mut D, on: D::Color, off: D::Color)
+    where
+        D::Error: core::fmt::Debug,
+    {
+        target.clear(off).unwrap();
+        Circle::new(Point::new(3, 5), 21)
+            .into_styled(PrimitiveStyle::with_stroke(on, 1))
+            .draw(target)
+            .unwrap();
+        Line::new(Point::new(0, 40), Point::new(60, 31))
+            .into_styled(PrimitiveStyle::with_stroke(on, 3))
+            .draw(target)
+            .unwrap();
+        let square = Rectangle::new(Point::new(30, 2), Size::new(9, 13));
+        target.fill_solid(&square, on).unwrap();
+        let hole = Rectangle::new(Point::new(32, 4), Size::new(3, 3));
+        target.fill_solid(&hole, off).unwrap();
+        let pattern = [0xA5u8, 0x3C, 0xFF, 0x00];
+        let colors = (0..32).map(|i| {
+            if pattern[i / 8] & 0x80 >> (i % 8) != 0 {
+                on
+            } else {
+                off
+            }
+        });
+        let area = Rectangle::new(Point::new(45, 20), Size::new(8, 4));
+        target.fill_contiguous(&area, colors).unwrap();
+    }
+
+    /// The colour display and its log, after drawing with `draw` and flushing every line.
+    fn flushed(draw: impl FnOnce(&mut Display)) -> (Vec<u8>, Display) {
+        let log = Log::default();
+        let mut display = ColorMemoryDisplay::new(log.spi(), log.cs(), log.disp());
+        draw(&mut display);
+        display.mark_all_dirty();
+        log.events();
+        display.flush_buffer().unwrap();
+        (log.bytes(), display)
+    }
+
+    #[test]
+    fn monochrome_scene_matches_the_colour_one() {
+        let (direct, _) = flushed(|display| scene(display, Rgb111::White, Rgb111::Black));
+        let (compat, display) = flushed(|display| {
+            scene(
+                &mut BinaryCompat::new(display),
+                BinaryColor::On,
+                BinaryColor::Off,
+            )
+        });
+        assert_eq!(compat, direct);
+        // Something was drawn in both colours
+        let pixels = (0..COLOR_HEIGHT as u32)
+            .flat_map(|y| (0..COLOR_WIDTH as u32).map(move |x| (x, y)))
+            .map(|(x, y)| display.get_pixel(x, y).unwrap());
+        let whites = pixels.filter(|&color| color == Rgb111::White).count();
+        assert!(whites > 0 && whites < COLOR_WIDTH * COLOR_HEIGHT);
+
+        let (direct, _) = flushed(|display| scene(display, Rgb111::Yellow, Rgb111::Blue));
+        let (compat, _) = flushed(|display| {
+            let mut compat = BinaryCompat::with_colors(display, Rgb111::Yellow, Rgb111::Blue);
+            scene(&mut compat, BinaryColor::On, BinaryColor::Off)
+        });
+        assert_eq!(compat, direct);
+    }
+
+    #[test]
+    fn drawing_marks_the_lines_of_the_colour_display() {
+        let log = Log::default();
+        let mut display = ColorMemoryDisplay::new(log.spi(), log.cs(), log.disp());
+        display.clear_buffer();
+        display.flush_buffer().unwrap();
+        log.events();
+
+        let line = Rectangle::new(Point::new(0, 7), Size::new(COLOR_WIDTH as u32, 1));
+        let mut compat = BinaryCompat::new(&mut display);
+        compat.fill_solid(&line, BinaryColor::Off).unwrap();
+        assert_eq!(compat.bounding_box().size, display.size());
+        display.flush_buffer().unwrap();
+        // The command, line 8 and the frame trailer
+        let bytes = log.bytes();
+        assert_eq!(bytes[1], 8u8.reverse_bits());
+        assert_eq!(bytes.len(), 1 + COLOR_WIDTH * 3 / 8 + 2 + 1);
+        assert!(bytes[2..2 + COLOR_WIDTH * 3 / 8]
+            .iter()
+            .all(|&byte| byte == 0));
+    }
+}
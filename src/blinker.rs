@@ -0,0 +1,269 @@
+use crate::{DisplaySpec, FrameBuffer, GetPixel, MemoryDisplay};
+use core::borrow::BorrowMut;
+use embedded_graphics::prelude::{Dimensions, PointsIter};
+use embedded_graphics::primitives::Rectangle;
+use hal::blocking::spi::Write;
+use hal::digital::v2::OutputPin;
+
+/// Blinks a region of the display by inverting it, e.g. a text cursor or an alert icon.
+///
+/// Every blinker keeps its own phase, so any number of them can run at once as long as their
+/// regions don't overlap. [`Blinker::tick`] is called regularly from the main loop with a
+/// free-running timestamp in milliseconds and inverts the region with
+/// [`MemoryDisplay::invert_region`] whenever the phase flips, leaving the flush to the caller.
+///
+/// If the region is redrawn while it is shown inverted, the new content is taken as the
+/// normal state: the next tick starts blinking it afresh, and [`Blinker::cancel`] leaves it
+/// alone.
+#[derive(Clone, Copy, Debug)]
+pub struct Blinker {
+    region: Rectangle,
+    period_ms: u32,
+    last_flip: Option<u32>,
+    // Checksum of the region while shown inverted, None while shown normally
+    inverted: Option<u32>,
+}
+
+impl Blinker {
+    /// Blink `region`, inverting it for `period_ms` and showing it normally for `period_ms` in
+    /// turn.
+    pub fn new(region: Rectangle, period_ms: u32) -> Self {
+        Self {
+            region,
+            period_ms: period_ms.max(1),
+            last_flip: None,
+            inverted: None,
+        }
+    }
+
+    /// The region which blinks.
+    pub fn region(&self) -> Rectangle {
+        self.region
+    }
+
+    /// Whether the region is currently shown inverted.
+    pub fn is_inverted(&self) -> bool {
+        self.inverted.is_some()
+    }
+
+    /// Flip the phase if `period_ms` has passed since the last flip, returning whether the
+    /// framebuffer changed. The first tick inverts the region right away. `now_ms` may wrap
+    /// around.
+    pub fn tick<SPI, CS, DISP, E, SPEC, BUF>(
+        &mut self,
+        display: &mut MemoryDisplay<SPI, CS, DISP, SPEC, BUF>,
+        now_ms: u32,
+    ) -> bool
+    where
+        SPI: Write<u8, Error = E>,
+        CS: OutputPin,
+        DISP: OutputPin,
+        SPEC: DisplaySpec,
+        BUF: BorrowMut<FrameBuffer<SPEC>>,
+    {
+        if self.redrawn(display) {
+            // Start over from the new content
+            self.inverted = None;
+            self.last_flip = None;
+        }
+        let due = self
+            .last_flip
+            .is_none_or(|last| now_ms.wrapping_sub(last) >= self.period_ms);
+        if !due {
+            return false;
+        }
+
+        display.invert_region(self.region);
+        self.inverted = match self.inverted {
+            Some(_) => None,
+            None => Some(checksum(display, self.region)),
+        };
+        self.last_flip = Some(now_ms);
+        true
+    }
+
+    /// Stop blinking, restoring the region if it is shown inverted, and return whether the
+    /// framebuffer changed. The next tick starts blinking again.
+    pub fn cancel<SPI, CS, DISP, E, SPEC, BUF>(
+        &mut self,
+        display: &mut MemoryDisplay<SPI, CS, DISP, SPEC, BUF>,
+    ) -> bool
+    where
+        SPI: Write<u8, Error = E>,
+        CS: OutputPin,
+        DISP: OutputPin,
+        SPEC: DisplaySpec,
+        BUF: BorrowMut<FrameBuffer<SPEC>>,
+    {
+        let restore = self.inverted.is_some() && !self.redrawn(display);
+        if restore {
+            display.invert_region(self.region);
+        }
+        self.inverted = None;
+        self.last_flip = None;
+        restore
+    }
+
+    /// Whether the region has been drawn to since it was inverted.
+    fn redrawn<SPI, CS, DISP, SPEC, BUF>(
+        &self,
+        display: &MemoryDisplay<SPI, CS, DISP, SPEC, BUF>,
+    ) -> bool
+    where
+        SPEC: DisplaySpec,
+        BUF: BorrowMut<FrameBuffer<SPEC>>,
+    {
+        self.inverted
+            .is_some_and(|sum| sum != checksum(display, self.region))
+    }
+}
+
+/// FNV-1a over the pixels of `region`, clipped to the display.
+fn checksum<SPI, CS, DISP, SPEC, BUF>(
+    display: &MemoryDisplay<SPI, CS, DISP, SPEC, BUF>,
+    region: Rectangle,
+) -> u32
+where
+    SPEC: DisplaySpec,
+    BUF: BorrowMut<FrameBuffer<SPEC>>,
+{
+    region
+        .intersection(&display.bounding_box())
+        .points()
+        .filter_map(|point| display.pixel(point))
+        .fold(0x811c_9dc5, |hash, color| {
+            (hash ^ color.is_on() as u32).wrapping_mul(0x0100_0193)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+    use embedded_graphics::prelude::{Point, Size};
+    use std::vec::Vec;
+
+    const CURSOR: Rectangle = Rectangle::new(Point::new(3, 2), Size::new(10, 5));
+
+    /// The pixels of `rows` with those of `region` inverted.
+    fn inverted(mut rows: Vec<Vec<bool>>, region: Rectangle) -> Vec<Vec<bool>> {
+        for Point { x, y } in region.points() {
+            let pixel = &mut rows[y as usize][x as usize];
+            *pixel = !*pixel;
+        }
+        rows
+    }
+
+    #[test]
+    fn phase_flips_every_period() {
+        let (_, mut display) = testing::display();
+        testing::draw_noise(&mut display, 243);
+        let normal = testing::snapshot(&display);
+        let mut blinker = Blinker::new(CURSOR, 500);
+
+        // The clock wraps around during the second period
+        let start = u32::MAX - 700;
+        assert!(blinker.tick(&mut display, start));
+        assert!(blinker.is_inverted());
+        assert_eq!(
+            testing::snapshot(&display),
+            inverted(normal.clone(), CURSOR)
+        );
+        assert!(!blinker.tick(&mut display, start + 499));
+        assert!(blinker.tick(&mut display, start + 500));
+        assert!(!blinker.is_inverted());
+        assert_eq!(testing::snapshot(&display), normal);
+        assert!(!blinker.tick(&mut display, start.wrapping_add(999)));
+        assert!(blinker.tick(&mut display, start.wrapping_add(1000)));
+        assert!(blinker.is_inverted());
+        // Late ticks count the period from when they happened
+        assert!(blinker.tick(&mut display, start.wrapping_add(1700)));
+        assert!(!blinker.tick(&mut display, start.wrapping_add(2199)));
+        assert!(blinker.tick(&mut display, start.wrapping_add(2200)));
+        assert_eq!(testing::snapshot(&display), inverted(normal, CURSOR));
+    }
+
+    #[test]
+    fn cancel_restores_the_region() {
+        let (_, mut display) = testing::display();
+        testing::draw_noise(&mut display, 7);
+        let normal = testing::snapshot(&display);
+        let mut blinker = Blinker::new(CURSOR, 100);
+
+        assert!(!blinker.cancel(&mut display));
+        blinker.tick(&mut display, 0);
+        assert!(blinker.cancel(&mut display));
+        assert!(!blinker.is_inverted());
+        assert_eq!(testing::snapshot(&display), normal);
+        // Blinking starts over with the next tick
+        assert!(blinker.tick(&mut display, 50));
+        assert!(blinker.is_inverted());
+        blinker.tick(&mut display, 150);
+        assert!(!blinker.cancel(&mut display));
+        assert_eq!(testing::snapshot(&display), normal);
+    }
+
+    #[test]
+    fn content_redrawn_while_inverted_is_captured_again() {
+        let (_, mut display) = testing::display();
+        let mut blinker = Blinker::new(CURSOR, 100);
+        blinker.tick(&mut display, 0);
+        display.set_pixel(5, 3, crate::Color::Off).unwrap();
+        display.set_pixel(6, 3, crate::Color::Off).unwrap();
+        let redrawn = testing::snapshot(&display);
+
+        // Cancelling leaves the new content alone
+        assert!(!blinker.cancel(&mut display));
+        assert_eq!(testing::snapshot(&display), redrawn);
+
+        // A tick after a redraw inverts the new content, and the one after restores it
+        blinker.tick(&mut display, 1000);
+        display.set_pixel(7, 4, crate::Color::On).unwrap();
+        let redrawn = testing::snapshot(&display);
+        assert!(blinker.tick(&mut display, 1050));
+        assert!(blinker.is_inverted());
+        assert_eq!(
+            testing::snapshot(&display),
+            inverted(redrawn.clone(), CURSOR)
+        );
+        assert!(blinker.tick(&mut display, 1150));
+        assert_eq!(testing::snapshot(&display), redrawn);
+    }
+
+    #[test]
+    fn blinkers_keep_their_own_phase() {
+        let (_, mut display) = testing::display();
+        testing::draw_noise(&mut display, 1);
+        let normal = testing::snapshot(&display);
+        let icon = Rectangle::new(Point::new(20, 10), Size::new(8, 8));
+        let mut cursor = Blinker::new(CURSOR, 300);
+        let mut alert = Blinker::new(icon, 200);
+
+        for now in (0..700).step_by(100) {
+            cursor.tick(&mut display, now);
+            alert.tick(&mut display, now);
+            assert_eq!(cursor.is_inverted(), now / 300 % 2 == 0, "{now}");
+            assert_eq!(alert.is_inverted(), now / 200 % 2 == 0, "{now}");
+            let mut expected = normal.clone();
+            if cursor.is_inverted() {
+                expected = inverted(expected, CURSOR);
+            }
+            if alert.is_inverted() {
+                expected = inverted(expected, icon);
+            }
+            assert_eq!(testing::snapshot(&display), expected, "{now}");
+        }
+        assert!(cursor.cancel(&mut display));
+        assert!(!alert.cancel(&mut display));
+        assert_eq!(testing::snapshot(&display), normal);
+    }
+
+    #[cfg(not(any(feature = "column-major", feature = "minimal-flush")))]
+    #[test]
+    fn inverting_marks_the_lines_of_the_region() {
+        let (_, mut display) = testing::display();
+        let mut blinker = Blinker::new(CURSOR, 100);
+        blinker.tick(&mut display, 0);
+        assert_eq!(display.dirty_line_count(), CURSOR.size.height as usize);
+    }
+}
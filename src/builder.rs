@@ -0,0 +1,261 @@
+use crate::{Color, CsStrategy, DisplaySpec, FrameBuffer, MemoryDisplay, Rotation, Vcom, VcomMode};
+use core::borrow::BorrowMut;
+use hal::blocking::spi::Write;
+use hal::digital::v2::OutputPin;
+
+/// The settings of a [`MemoryDisplay`] gathered before creating it, e.g. in a `const` shared
+/// by several boards:
+///
+/// ```
+/// # use embedded_hal_mock::pin::{Mock as PinMock, State, Transaction as PinTransaction};
+/// # use embedded_hal_mock::spi::Mock as SpiMock;
+/// use sharp_memory_display::{Color, MemoryDisplayBuilder, Rotation};
+///
+/// const SETTINGS: MemoryDisplayBuilder = MemoryDisplayBuilder::new()
+///     .with_rotation(Rotation::Deg90)
+///     .with_clear_state(Color::Off)
+///     .with_invert(true);
+///
+/// # let spi = SpiMock::new(&[]);
+/// # let low = || PinTransaction::set(State::Low);
+/// # let (cs, disp) = (PinMock::new(&[low(), low()]), PinMock::new(&[low()]));
+/// let mut display = SETTINGS.build(spi, cs, disp);
+/// assert_eq!(display.rotation(), Rotation::Deg90);
+/// assert!(display.is_inverted());
+///
+/// // The clear state applies to clearing the buffer
+/// display.clear_buffer();
+/// assert_eq!(display.get_pixel(0, 0), Some(Color::Off));
+/// # let (mut spi, mut cs, mut disp) = display.release();
+/// # spi.done();
+/// # cs.done();
+/// # disp.done();
+/// ```
+///
+/// Every setting starts out as with [`MemoryDisplay::new`] and is applied by the setter of the
+/// display of the same name, so the display behaves the same as if it was set afterwards.
+///
+/// The builder holds settings only, the pins are wrapped before they are passed to
+/// [`MemoryDisplayBuilder::build`], e.g. chip select behind an inverting level shifter and
+/// with the timing of the panel in [`ActiveLow`](crate::ActiveLow) and
+/// [`TimedCs`](crate::TimedCs):
+///
+/// ```
+/// # use embedded_hal_mock::delay::MockNoop;
+/// # use embedded_hal_mock::pin::{Mock as PinMock, State, Transaction as PinTransaction};
+/// # use embedded_hal_mock::spi::Mock as SpiMock;
+/// use sharp_memory_display::{ActiveLow, MemoryDisplayBuilder, Rotation, TimedCs};
+///
+/// const SETTINGS: MemoryDisplayBuilder = MemoryDisplayBuilder::new()
+///     .with_rotation(Rotation::Deg90);
+///
+/// # let spi = SpiMock::new(&[]);
+/// # let high = || PinTransaction::set(State::High);
+/// # let low = || PinTransaction::set(State::Low);
+/// # let (cs, disp) = (PinMock::new(&[high(), high()]), PinMock::new(&[low()]));
+/// # let delay = MockNoop::new();
+/// let cs = TimedCs::new(ActiveLow(cs), delay);
+/// let display = SETTINGS.build(spi, cs, disp);
+/// # let (mut spi, cs, mut disp) = display.release();
+/// # let (ActiveLow(mut cs), _) = cs.release();
+/// # spi.done();
+/// # cs.done();
+/// # disp.done();
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct MemoryDisplayBuilder {
+    clear_state: Color,
+    rotation: Rotation,
+    mirror: (bool, bool),
+    vcom_mode: VcomMode,
+    vcom_phase: Vcom,
+    cs_strategy: CsStrategy,
+    invert: bool,
+    // The maximum of the model unless set
+    sclk_hz: Option<u32>,
+}
+
+impl MemoryDisplayBuilder {
+    /// The settings of a display created with [`MemoryDisplay::new`].
+    pub const fn new() -> Self {
+        Self {
+            clear_state: Color::On,
+            rotation: Rotation::Deg0,
+            mirror: (false, false),
+            vcom_mode: VcomMode::Software,
+            vcom_phase: Vcom::Hi,
+            cs_strategy: CsStrategy::Software,
+            invert: false,
+            sclk_hz: None,
+        }
+    }
+
+    /// See [`MemoryDisplay::set_clear_state`].
+    pub const fn with_clear_state(mut self, clear_state: Color) -> Self {
+        self.clear_state = clear_state;
+        self
+    }
+
+    /// See [`MemoryDisplay::set_rotation`].
+    pub const fn with_rotation(mut self, rotation: Rotation) -> Self {
+        self.rotation = rotation;
+        self
+    }
+
+    /// See [`MemoryDisplay::set_mirror`].
+    pub const fn with_mirror(mut self, x: bool, y: bool) -> Self {
+        self.mirror = (x, y);
+        self
+    }
+
+    /// See [`MemoryDisplay::set_vcom_mode`].
+    pub const fn with_vcom_mode(mut self, mode: VcomMode) -> Self {
+        self.vcom_mode = mode;
+        self
+    }
+
+    /// The VCOM polarity the display starts from, see [`MemoryDisplay::set_vcom_phase`].
+    pub const fn with_initial_vcom(mut self, phase: Vcom) -> Self {
+        self.vcom_phase = phase;
+        self
+    }
+
+    /// See [`MemoryDisplay::set_cs_strategy`].
+    pub const fn with_cs_strategy(mut self, cs_strategy: CsStrategy) -> Self {
+        self.cs_strategy = cs_strategy;
+        self
+    }
+
+    /// See [`MemoryDisplay::set_invert`].
+    pub const fn with_invert(mut self, invert: bool) -> Self {
+        self.invert = invert;
+        self
+    }
+
+    /// See [`MemoryDisplay::set_sclk_hz`].
+    pub const fn with_sclk_hz(mut self, hz: u32) -> Self {
+        self.sclk_hz = Some(hz);
+        self
+    }
+
+    /// Create a display with these settings, like [`MemoryDisplay::new`].
+    pub fn build<SPI, CS, DISP, E>(
+        self,
+        spi: SPI,
+        cs: CS,
+        disp: DISP,
+    ) -> MemoryDisplay<SPI, CS, DISP>
+    where
+        SPI: Write<u8, Error = E>,
+        CS: OutputPin,
+        DISP: OutputPin,
+    {
+        self.build_with_buffer(spi, cs, disp, FrameBuffer::new())
+    }
+
+    /// Create a display of the model `SPEC` with these settings, like
+    /// [`MemoryDisplay::new_with_spec`].
+    pub fn build_with_spec<SPI, CS, DISP, E, SPEC>(
+        self,
+        spi: SPI,
+        cs: CS,
+        disp: DISP,
+        _spec: SPEC,
+    ) -> MemoryDisplay<SPI, CS, DISP, SPEC>
+    where
+        SPI: Write<u8, Error = E>,
+        CS: OutputPin,
+        DISP: OutputPin,
+        SPEC: DisplaySpec,
+    {
+        self.build_with_buffer(spi, cs, disp, FrameBuffer::new())
+    }
+
+    /// Create a display with these settings and its framebuffer in `frame`, like
+    /// [`MemoryDisplay::new_with_buffer`].
+    pub fn build_with_buffer<SPI, CS, DISP, E, SPEC, BUF>(
+        self,
+        spi: SPI,
+        cs: CS,
+        disp: DISP,
+        frame: BUF,
+    ) -> MemoryDisplay<SPI, CS, DISP, SPEC, BUF>
+    where
+        SPI: Write<u8, Error = E>,
+        CS: OutputPin,
+        DISP: OutputPin,
+        SPEC: DisplaySpec,
+        BUF: BorrowMut<FrameBuffer<SPEC>>,
+    {
+        let mut display = MemoryDisplay::new_with_buffer(spi, cs, disp, frame);
+        display.set_clear_state(self.clear_state);
+        display.set_rotation(self.rotation);
+        display.set_mirror(self.mirror.0, self.mirror.1);
+        display.set_vcom_mode(self.vcom_mode);
+        display.set_vcom_phase(self.vcom_phase);
+        display.set_cs_strategy(self.cs_strategy);
+        display.set_invert(self.invert);
+        if let Some(hz) = self.sclk_hz {
+            display.set_sclk_hz(hz);
+        }
+        display
+    }
+}
+
+impl Default for MemoryDisplayBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{Log, TestDisplay};
+
+    fn built(settings: MemoryDisplayBuilder) -> (Log, TestDisplay) {
+        let log = Log::default();
+        let mut display = settings.build(log.spi(), log.cs(), log.disp());
+        display.enable().unwrap();
+        log.events();
+        (log, display)
+    }
+
+    /// The command byte of the next flush.
+    fn next_command(log: &Log, display: &mut TestDisplay) -> u8 {
+        display.flush_buffer().unwrap();
+        log.bytes()[0]
+    }
+
+    #[test]
+    fn first_command_follows_the_seeded_phase() {
+        for (seed, command) in [(Vcom::Hi, 0x80), (Vcom::Lo, 0xC0)] {
+            let (log, mut display) = built(MemoryDisplayBuilder::new().with_initial_vcom(seed));
+            assert_eq!(display.vcom_phase(), seed);
+            assert_eq!(next_command(&log, &mut display) & 0xC0, command);
+        }
+    }
+
+    #[test]
+    fn phase_survives_a_reset() {
+        let (log, mut display) = built(MemoryDisplayBuilder::new());
+        let before = [0; 2].map(|_| next_command(&log, &mut display) & 0x40);
+        // Stashed before the reset, restored after it
+        let stashed = bool::from(display.vcom_phase());
+        let settings = MemoryDisplayBuilder::new().with_initial_vcom(stashed.into());
+        let (log, mut display) = built(settings);
+        let after = [0; 2].map(|_| next_command(&log, &mut display) & 0x40);
+        assert_eq!([before, after], [[0x00, 0x40], [0x00, 0x40]]);
+    }
+
+    #[test]
+    fn external_vcom_ignores_the_seed() {
+        let settings = MemoryDisplayBuilder::new()
+            .with_vcom_mode(VcomMode::External)
+            .with_initial_vcom(Vcom::Hi);
+        let (log, mut display) = built(settings);
+        assert_eq!(next_command(&log, &mut display) & 0x40, 0x00);
+        assert_eq!(next_command(&log, &mut display) & 0x40, 0x00);
+    }
+}
@@ -0,0 +1,324 @@
+//! Capturing the traffic of a display to any [`std::io::Write`] and replaying it.
+//!
+//! An [`IoTransport`] provides an SPI bus and pins which can be passed to
+//! [`MemoryDisplay::new`](crate::MemoryDisplay::new) in place of the hardware, e.g. to write a
+//! capture file for a bug report or to stream to a visualizer over TCP. Every transaction,
+//! everything sent while chip select is asserted, is written as a record: the marker byte `T`,
+//! the length of the transaction as 4 bytes little endian and its bytes. The boundaries of the
+//! transactions thereby survive the byte stream. [`IoReplay`] reads the records back and
+//! decodes them with [`crate::decode`], e.g. for `decode-spi --framed`.
+//!
+//! Only available with the `std` feature.
+use crate::decode::{self, Record};
+use hal::blocking::spi::Write;
+use hal::digital::v2::OutputPin;
+use std::cell::RefCell;
+use std::io::{self, Read};
+use std::rc::Rc;
+use std::vec::Vec;
+
+/// The marker starting every record.
+const MARKER: u8 = b'T';
+
+/// Writes the transactions sent to a display to `W`, see the [module documentation](self).
+pub struct IoTransport<W> {
+    state: Rc<RefCell<State<W>>>,
+}
+
+struct State<W> {
+    writer: W,
+    cs: bool,
+    transaction: Vec<u8>,
+}
+
+impl<W: io::Write> State<W> {
+    fn write_record(&mut self) -> io::Result<()> {
+        let len = self.transaction.len() as u32;
+        let result = self
+            .writer
+            .write_all(&[MARKER])
+            .and_then(|_| self.writer.write_all(&len.to_le_bytes()))
+            .and_then(|_| self.writer.write_all(&self.transaction));
+        self.transaction.clear();
+        result
+    }
+}
+
+impl<W: io::Write> IoTransport<W> {
+    pub fn new(writer: W) -> Self {
+        Self {
+            state: Rc::new(RefCell::new(State {
+                writer,
+                cs: false,
+                transaction: Vec::new(),
+            })),
+        }
+    }
+
+    /// The SPI bus to pass to the driver.
+    pub fn spi(&self) -> IoSpi<W> {
+        IoSpi {
+            state: self.state.clone(),
+        }
+    }
+
+    /// The chip select pin to pass to the driver. A record is written whenever it goes low.
+    pub fn cs(&self) -> IoPin<W> {
+        IoPin {
+            state: Some(self.state.clone()),
+        }
+    }
+
+    /// A display enable pin to pass to the driver, which is not recorded.
+    pub fn disp(&self) -> IoPin<W> {
+        IoPin { state: None }
+    }
+
+    /// Flush the writer, e.g. before inspecting a capture file while the driver keeps running.
+    pub fn flush(&self) -> io::Result<()> {
+        self.state.borrow_mut().writer.flush()
+    }
+
+    /// Get the writer back once the bus and pins handed out have been dropped, otherwise
+    /// return the transport unchanged.
+    pub fn into_inner(self) -> Result<W, Self> {
+        match Rc::try_unwrap(self.state) {
+            Ok(state) => Ok(state.into_inner().writer),
+            Err(state) => Err(Self { state }),
+        }
+    }
+}
+
+/// The SPI bus of an [`IoTransport`].
+pub struct IoSpi<W> {
+    state: Rc<RefCell<State<W>>>,
+}
+
+impl<W: io::Write> Write<u8> for IoSpi<W> {
+    type Error = io::Error;
+
+    fn write(&mut self, words: &[u8]) -> Result<(), Self::Error> {
+        let mut state = self.state.borrow_mut();
+        state.transaction.extend_from_slice(words);
+        // Writes outside of a transaction, e.g. with a dummy chip select, form records of
+        // their own
+        if !state.cs {
+            state.write_record()?;
+        }
+        Ok(())
+    }
+}
+
+/// A pin of an [`IoTransport`].
+pub struct IoPin<W> {
+    state: Option<Rc<RefCell<State<W>>>>,
+}
+
+impl<W: io::Write> OutputPin for IoPin<W> {
+    type Error = io::Error;
+
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        match &self.state {
+            Some(state) => {
+                let mut state = state.borrow_mut();
+                if state.cs {
+                    state.cs = false;
+                    state.write_record()?;
+                }
+                Ok(())
+            }
+            None => Ok(()),
+        }
+    }
+
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        if let Some(state) = &self.state {
+            state.borrow_mut().cs = true;
+        }
+        Ok(())
+    }
+}
+
+/// Reads the transactions written by an [`IoTransport`] back from `R`, see the
+/// [module documentation](self).
+///
+/// It iterates over the bytes of every transaction.
+pub struct IoReplay<R> {
+    reader: R,
+}
+
+impl<R: Read> IoReplay<R> {
+    pub fn new(reader: R) -> Self {
+        Self { reader }
+    }
+
+    /// Decode every transaction for a display of the given dimensions and trailers, see
+    /// [`decode::decode_with_trailers`].
+    pub fn records(
+        self,
+        width: usize,
+        height: usize,
+        trailers: (usize, usize),
+    ) -> io::Result<Vec<Record>> {
+        let mut records = Vec::new();
+        for transaction in self {
+            records.extend(decode::decode_with_trailers(
+                &transaction?,
+                width,
+                height,
+                trailers,
+            ));
+        }
+        Ok(records)
+    }
+
+    fn read_transaction(&mut self) -> io::Result<Option<Vec<u8>>> {
+        let mut marker = [0];
+        if self.reader.read(&mut marker)? == 0 {
+            return Ok(None);
+        }
+        if marker[0] != MARKER {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "missing transaction marker",
+            ));
+        }
+        let mut len = [0; 4];
+        self.reader.read_exact(&mut len)?;
+        let mut transaction = std::vec![0; u32::from_le_bytes(len) as usize];
+        self.reader.read_exact(&mut transaction)?;
+        Ok(Some(transaction))
+    }
+}
+
+impl<R: Read> Iterator for IoReplay<R> {
+    type Item = io::Result<Vec<u8>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read_transaction().transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::vec;
+
+    #[test]
+    fn transactions_are_framed() {
+        let transport = IoTransport::new(Vec::new());
+        let (mut spi, mut cs) = (transport.spi(), transport.cs());
+        cs.set_high().unwrap();
+        spi.write(&[1, 2]).unwrap();
+        spi.write(&[3]).unwrap();
+        cs.set_low().unwrap();
+        // Outside of a transaction every write is a record
+        spi.write(&[4]).unwrap();
+        spi.write(&[]).unwrap();
+        cs.set_low().unwrap();
+        transport.disp().set_high().unwrap();
+        cs.set_high().unwrap();
+        cs.set_low().unwrap();
+
+        let transport = transport.into_inner().err().unwrap();
+        drop((spi, cs));
+        let bytes = transport.into_inner().ok().unwrap();
+        assert_eq!(
+            bytes,
+            [
+                b"T\x03\0\0\0\x01\x02\x03".as_slice(),
+                b"T\x01\0\0\0\x04",
+                b"T\0\0\0\0",
+                b"T\0\0\0\0",
+            ]
+            .concat()
+        );
+        let transactions: Vec<_> = IoReplay::new(bytes.as_slice())
+            .collect::<io::Result<_>>()
+            .unwrap();
+        assert_eq!(transactions, [vec![1, 2, 3], vec![4], vec![], vec![]]);
+    }
+
+    #[test]
+    fn broken_captures_are_errors() {
+        let mut replay = IoReplay::new(b"T\x02\0\0\0\x01".as_slice());
+        let error = replay.next().unwrap().unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
+
+        let mut replay = IoReplay::new(b"T\x01\0\0\0\x01X".as_slice());
+        assert_eq!(replay.next().unwrap().unwrap(), [1]);
+        let error = replay.next().unwrap().unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert!(IoReplay::new(b"".as_slice()).next().is_none());
+    }
+
+    // The panel shows the framebuffer line by line
+    #[cfg(not(any(
+        feature = "half-height",
+        feature = "half-width",
+        feature = "column-major"
+    )))]
+    #[test]
+    fn replayed_capture_shows_the_framebuffer() {
+        use crate::{
+            CsStrategy, MemoryDisplay, FRAME_TRAILER_BYTES, HEIGHT, LINE_TRAILER_BYTES, WIDTH,
+        };
+
+        let trailers = (LINE_TRAILER_BYTES, FRAME_TRAILER_BYTES);
+        // Lines sent by a flush of `dirty` lines, all of them with minimal-flush
+        let sent = |dirty| {
+            if cfg!(feature = "minimal-flush") {
+                HEIGHT
+            } else {
+                dirty
+            }
+        };
+        for strategy in [CsStrategy::Software, CsStrategy::PerLine] {
+            let transport = IoTransport::new(Vec::new());
+            let mut display = MemoryDisplay::new(transport.spi(), transport.cs(), transport.disp());
+            display.set_cs_strategy(strategy);
+            display.clear().unwrap();
+            for y in 0..HEIGHT {
+                for x in (y % 7..WIDTH).step_by(7 + y % 3) {
+                    display.set_pixel(x as u32, y as u32, true).unwrap();
+                }
+            }
+            display.flush_buffer().unwrap();
+            display.set_pixel(3, 5, false).unwrap();
+            display.flush_buffer().unwrap();
+            let rows: Vec<Vec<bool>> = (0..HEIGHT)
+                .map(|y| {
+                    let pixel = |x| display.get_pixel(x as u32, y as u32) == Some(true.into());
+                    (0..WIDTH).map(pixel).collect()
+                })
+                .collect();
+            drop(display);
+
+            let bytes = transport.into_inner().ok().unwrap();
+            let transactions = IoReplay::new(bytes.as_slice()).count();
+            let flushes = if strategy == CsStrategy::Software {
+                2
+            } else {
+                0
+            };
+            let lines = if strategy == CsStrategy::PerLine {
+                sent(HEIGHT) + sent(1)
+            } else {
+                0
+            };
+            // The clear, then every flush or every line
+            assert_eq!(transactions, 1 + flushes + lines, "{strategy:?}");
+
+            let records = IoReplay::new(bytes.as_slice())
+                .records(WIDTH, HEIGHT, trailers)
+                .unwrap();
+            assert!(matches!(records[0], Record::ClearMemory { .. }));
+            let mut panel = decode::Panel::new(WIDTH, HEIGHT);
+            for record in &records {
+                assert!(!matches!(record, Record::Malformed { .. }), "{record:?}");
+                panel.apply(record);
+            }
+            assert_eq!(panel.rows(), rows, "{strategy:?}");
+        }
+    }
+}
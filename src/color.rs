@@ -0,0 +1,537 @@
+//! Driver for the 3-bit color memory-in-pixel panels, only available with the feature of a color
+//! model (`lpm013m126a` or `ls013b7dh06`).
+//!
+//! The color panels speak the protocol of the monochrome ones, but every pixel takes three bits
+//! (red, green and blue) on the wire, so a line is `3 * WIDTH / 8` bytes long. They are driven
+//! by [`ColorMemoryDisplay`], which is independent of the monochrome model selected for
+//! [`crate::MemoryDisplay`]: code using only the latter is unaffected by a color model feature.
+use crate::{carry_line, transfer, Color, Command, Error, OutOfBounds, Vcom, DUMMY_DATA};
+use bitvec::prelude::*;
+use hal::blocking::spi::Write;
+use hal::digital::v2::OutputPin;
+
+// The constants for the particular model of color display
+/// 1.28" JDI panel of 176x176 pixels, driven in its 3-bit data update mode.
+#[cfg(feature = "lpm013m126a")]
+mod panel {
+    pub(super) const WIDTH: usize = 176;
+    pub(super) const HEIGHT: usize = 176;
+    pub(super) const LINE_TRAILER_BYTES: usize = 1;
+    pub(super) const FRAME_TRAILER_BYTES: usize = 1;
+}
+
+/// 1.33" Sharp panel of 128x128 pixels.
+#[cfg(feature = "ls013b7dh06")]
+mod panel {
+    pub(super) const WIDTH: usize = 128;
+    pub(super) const HEIGHT: usize = 128;
+    pub(super) const LINE_TRAILER_BYTES: usize = 1;
+    pub(super) const FRAME_TRAILER_BYTES: usize = 1;
+}
+
+// Only one set of color display constants can be selected at a time
+#[cfg(all(feature = "lpm013m126a", feature = "ls013b7dh06"))]
+compile_error!("Please specify only one color display type via the feature flag");
+
+/// Width of the color display in pixels.
+pub const COLOR_WIDTH: usize = panel::WIDTH;
+
+/// Height of the color display in pixels.
+pub const COLOR_HEIGHT: usize = panel::HEIGHT;
+
+// Bytes of pixel data per line, three bits per pixel
+const LINE_BYTES: usize = (3 * panel::WIDTH).div_ceil(8);
+
+// Line number, data and the line trailer
+const WRITE_BUFFER_SIZE: usize = 1 + LINE_BYTES + panel::LINE_TRAILER_BYTES;
+
+/// The color of a pixel of a color panel, one bit each for red, green and blue.
+///
+/// With the `graphics` feature it is the color of the [`embedded_graphics`] integration, and
+/// converts from `Rgb565` and `Rgb888`, e.g. to draw content of those colors via
+/// `DrawTargetExt::color_converted`. Every channel is on from half its range up.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Rgb111 {
+    Black = 0b000,
+    Blue = 0b001,
+    Green = 0b010,
+    Cyan = 0b011,
+    Red = 0b100,
+    Magenta = 0b101,
+    Yellow = 0b110,
+    White = 0b111,
+}
+
+impl Rgb111 {
+    /// The color with the given channels switched on.
+    pub const fn new(red: bool, green: bool, blue: bool) -> Self {
+        match (red, green, blue) {
+            (false, false, false) => Rgb111::Black,
+            (false, false, true) => Rgb111::Blue,
+            (false, true, false) => Rgb111::Green,
+            (false, true, true) => Rgb111::Cyan,
+            (true, false, false) => Rgb111::Red,
+            (true, false, true) => Rgb111::Magenta,
+            (true, true, false) => Rgb111::Yellow,
+            (true, true, true) => Rgb111::White,
+        }
+    }
+
+    pub const fn red(self) -> bool {
+        self as u8 & 0b100 != 0
+    }
+
+    pub const fn green(self) -> bool {
+        self as u8 & 0b010 != 0
+    }
+
+    pub const fn blue(self) -> bool {
+        self as u8 & 0b001 != 0
+    }
+}
+
+/// [`Color::On`] is white and [`Color::Off`] black, as on the monochrome panels.
+impl From<Color> for Rgb111 {
+    fn from(color: Color) -> Self {
+        if color.is_on() {
+            Rgb111::White
+        } else {
+            Rgb111::Black
+        }
+    }
+}
+
+/// A 3-bit color memory-in-pixel display, see the [module documentation](self).
+///
+/// It works like [`crate::MemoryDisplay`] with chip select held for the whole flush, i.e.
+/// [`crate::CsStrategy::Software`], and VCOM toggled with every command.
+pub struct ColorMemoryDisplay<SPI, CS, DISP> {
+    spi: SPI,
+    cs: CS,
+    disp: DISP,
+    // Every line as sent: the bits of every pixel in the order red, green, blue, most
+    // significant bit first
+    buffer: [[u8; LINE_BYTES]; panel::HEIGHT],
+    touched: BitArr!(for panel::HEIGHT, in u8, Lsb0),
+    vcom: Vcom,
+    clear_state: Rgb111,
+}
+
+impl<SPI, CS, DISP, E> ColorMemoryDisplay<SPI, CS, DISP>
+where
+    SPI: Write<u8, Error = E>,
+    CS: OutputPin,
+    DISP: OutputPin,
+{
+    /// Create a new instance of `ColorMemoryDisplay`.
+    ///
+    /// Please issue a `clear` before drawing to the display.
+    pub fn new(spi: SPI, mut cs: CS, mut disp: DISP) -> Self {
+        let _ = disp.set_low();
+        let _ = cs.set_low();
+
+        Self {
+            spi,
+            cs,
+            disp,
+            buffer: [[0; LINE_BYTES]; panel::HEIGHT],
+            touched: BitArray::ZERO,
+            vcom: Vcom::Hi,
+            clear_state: Rgb111::White,
+        }
+    }
+
+    /// Give back the bus and the pins, see [`crate::MemoryDisplay::release`].
+    pub fn release(mut self) -> (SPI, CS, DISP) {
+        let _ = self.cs.set_low();
+        (self.spi, self.cs, self.disp)
+    }
+
+    /// Set the color the framebuffer is set to when clearing it, white by default.
+    pub fn set_clear_state(&mut self, clear_state: impl Into<Rgb111>) {
+        self.clear_state = clear_state.into();
+    }
+
+    /// Enable the LCD by driving the display pin high.
+    pub fn enable(&mut self) -> Result<(), Error<E, DISP::Error>> {
+        self.disp.set_high().map_err(Error::Disp)
+    }
+
+    /// Disable the LCD.
+    pub fn disable(&mut self) -> Result<(), Error<E, DISP::Error>> {
+        self.disp.set_low().map_err(Error::Disp)
+    }
+
+    /// Sets a single pixel in the internal framebuffer, marking its line dirty if the color
+    /// changes. Coordinates outside the framebuffer leave it and the dirty lines untouched.
+    pub fn set_pixel(
+        &mut self,
+        x: u32,
+        y: u32,
+        color: impl Into<Rgb111>,
+    ) -> Result<(), OutOfBounds> {
+        let (x, y) = (x as usize, y as usize);
+        if x >= panel::WIDTH || y >= panel::HEIGHT {
+            return Err(OutOfBounds);
+        }
+        let color = color.into();
+        let bits = &mut self.buffer[y].view_bits_mut::<Msb0>()[3 * x..3 * x + 3];
+        if bits.load_be::<u8>() != color as u8 {
+            bits.store_be(color as u8);
+            self.touched.set(y, true);
+        }
+        Ok(())
+    }
+
+    /// The color of a single pixel in the framebuffer, `None` outside it.
+    pub fn get_pixel(&self, x: u32, y: u32) -> Option<Rgb111> {
+        let (x, y) = (x as usize, y as usize);
+        (x < panel::WIDTH && y < panel::HEIGHT).then(|| {
+            let bits = self.buffer[y].view_bits::<Msb0>()[3 * x..3 * x + 3].load_be::<u8>();
+            Rgb111::new(bits & 0b100 != 0, bits & 0b010 != 0, bits & 0b001 != 0)
+        })
+    }
+
+    /// Mark every line to be sent by the next flush.
+    pub fn mark_all_dirty(&mut self) {
+        self.touched.fill(true);
+    }
+
+    /// Clear just the internal framebuffer to the clear state, without writing changes to the
+    /// display. Only lines which change are marked dirty.
+    pub fn clear_buffer(&mut self) {
+        let mut line = [0; LINE_BYTES];
+        let color = self.clear_state as u8;
+        for pixel in line.view_bits_mut::<Msb0>()[..3 * panel::WIDTH].chunks_exact_mut(3) {
+            pixel.store_be(color);
+        }
+        for (y, buffered) in self.buffer.iter_mut().enumerate() {
+            if *buffered != line {
+                *buffered = line;
+                self.touched.set(y, true);
+            }
+        }
+    }
+
+    /// Clear the screen and the internal framebuffer.
+    pub fn clear(&mut self) -> Result<(), Error<E, CS::Error>> {
+        // What the panel shows after clearing its memory may differ from the clear state, so
+        // every line is sent by the next flush
+        self.clear_buffer();
+        self.mark_all_dirty();
+        self.vcom = !self.vcom;
+        transfer(
+            &mut self.spi,
+            &mut self.cs,
+            &[Command::ClearMemory | self.vcom, DUMMY_DATA],
+        )
+    }
+
+    /// Toggle VCOM without touching the panel memory, see
+    /// [`crate::MemoryDisplay::display_mode`].
+    pub fn display_mode(&mut self) -> Result<(), Error<E, CS::Error>> {
+        self.vcom = !self.vcom;
+        transfer(
+            &mut self.spi,
+            &mut self.cs,
+            &[Command::Nop | self.vcom, DUMMY_DATA],
+        )
+    }
+
+    /// Draw all lines of the buffer to the screen which have changed since last calling this
+    /// function.
+    ///
+    /// If the bus or chip select fails the flush is aborted and every dirty line is kept, so
+    /// the next flush sends them again.
+    pub fn flush_buffer(&mut self) -> Result<(), Error<E, CS::Error>> {
+        self.vcom = !self.vcom;
+
+        self.cs.set_high().map_err(Error::Cs)?;
+        let result = self.write_frame();
+        // Deassert chip select even if the bus failed, ending the transaction
+        let deselect = self.cs.set_low().map_err(Error::Cs);
+        result.and(deselect)?;
+
+        self.touched.fill(false);
+        Ok(())
+    }
+
+    /// Send the command, the dirty lines and the frame trailer of a flush while chip select is
+    /// held.
+    fn write_frame(&mut self) -> Result<(), Error<E, CS::Error>> {
+        // With the mode bits M3 to M5 clear, the write command selects the 3-bit mode of the
+        // JDI panels
+        // Every write starts with the last byte of the one before, like with the monochrome
+        // panels
+        let mut carry = Command::WriteLine | self.vcom;
+
+        // The array is rounded up to whole bytes, so stop at the end of the framebuffer
+        for y in self.touched.iter_ones().take_while(|&y| y < panel::HEIGHT) {
+            let mut packed = Self::pack_line(y, &self.buffer[y]);
+            carry = carry_line(carry, &mut packed);
+            self.spi.write(&packed).map_err(Error::Spi)?;
+        }
+
+        // Write the rest of the last line and the frame trailer
+        let mut end = [DUMMY_DATA; 1 + panel::FRAME_TRAILER_BYTES];
+        end[0] = carry;
+        self.spi.write(&end).map_err(Error::Spi)
+    }
+
+    /// Pack a line of the framebuffer into the form it is sent as line `y`: line number, data
+    /// and the trailer. The line number is sent least significant bit first like on the
+    /// monochrome panels, the data is already in the order it is sent.
+    fn pack_line(y: usize, line: &[u8; LINE_BYTES]) -> [u8; WRITE_BUFFER_SIZE] {
+        let mut write_buffer = [DUMMY_DATA; WRITE_BUFFER_SIZE];
+        write_buffer[0] = ((y + 1) as u8).reverse_bits();
+        write_buffer[1..=LINE_BYTES].copy_from_slice(line);
+        write_buffer
+    }
+}
+
+#[cfg(feature = "graphics")]
+mod graphics {
+    use super::{panel, ColorMemoryDisplay, Rgb111};
+    use embedded_graphics::draw_target::DrawTarget;
+    use embedded_graphics::pixelcolor::{PixelColor, Rgb565, Rgb888, RgbColor};
+    use embedded_graphics::prelude::{OriginDimensions, Size};
+    use embedded_graphics::Pixel;
+    use hal::blocking::spi::Write;
+    use hal::digital::v2::OutputPin;
+
+    impl PixelColor for Rgb111 {
+        type Raw = ();
+    }
+
+    /// Every channel is a single bit, so [`RgbColor::r`] and the others are 0 or 1.
+    impl RgbColor for Rgb111 {
+        fn r(&self) -> u8 {
+            self.red().into()
+        }
+
+        fn g(&self) -> u8 {
+            self.green().into()
+        }
+
+        fn b(&self) -> u8 {
+            self.blue().into()
+        }
+
+        const MAX_R: u8 = 1;
+        const MAX_G: u8 = 1;
+        const MAX_B: u8 = 1;
+        const BLACK: Self = Rgb111::Black;
+        const RED: Self = Rgb111::Red;
+        const GREEN: Self = Rgb111::Green;
+        const BLUE: Self = Rgb111::Blue;
+        const YELLOW: Self = Rgb111::Yellow;
+        const MAGENTA: Self = Rgb111::Magenta;
+        const CYAN: Self = Rgb111::Cyan;
+        const WHITE: Self = Rgb111::White;
+    }
+
+    impl From<Rgb888> for Rgb111 {
+        fn from(color: Rgb888) -> Self {
+            Rgb111::new(color.r() >= 0x80, color.g() >= 0x80, color.b() >= 0x80)
+        }
+    }
+
+    impl From<Rgb565> for Rgb111 {
+        fn from(color: Rgb565) -> Self {
+            Rgb111::new(color.r() >= 0x10, color.g() >= 0x20, color.b() >= 0x10)
+        }
+    }
+
+    impl From<Rgb111> for Rgb888 {
+        fn from(color: Rgb111) -> Self {
+            let channel = |on: bool| if on { 0xFF } else { 0 };
+            Rgb888::new(
+                channel(color.red()),
+                channel(color.green()),
+                channel(color.blue()),
+            )
+        }
+    }
+
+    impl<SPI, CS, DISP> OriginDimensions for ColorMemoryDisplay<SPI, CS, DISP> {
+        fn size(&self) -> Size {
+            Size::new(panel::WIDTH as u32, panel::HEIGHT as u32)
+        }
+    }
+
+    impl<SPI, CS, DISP, E> DrawTarget for ColorMemoryDisplay<SPI, CS, DISP>
+    where
+        SPI: Write<u8, Error = E>,
+        CS: OutputPin,
+        DISP: OutputPin,
+    {
+        type Color = Rgb111;
+        type Error = E;
+
+        fn draw_iter<T>(&mut self, item_pixels: T) -> Result<(), E>
+        where
+            T: IntoIterator<Item = Pixel<Self::Color>>,
+        {
+            for Pixel(coord, color) in item_pixels {
+                if let (Ok(x), Ok(y)) = (coord.x.try_into(), coord.y.try_into()) {
+                    // Ignore attempts to draw outside of display bounds
+                    let _ = self.set_pixel(x, y, color);
+                }
+            }
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{self, Event};
+    use std::{vec, vec::Vec};
+
+    const COLORS: [Rgb111; 8] = [
+        Rgb111::Black,
+        Rgb111::Blue,
+        Rgb111::Green,
+        Rgb111::Cyan,
+        Rgb111::Red,
+        Rgb111::Magenta,
+        Rgb111::Yellow,
+        Rgb111::White,
+    ];
+
+    type TestDisplay = ColorMemoryDisplay<testing::Spi, testing::Pin, testing::Pin>;
+
+    fn display() -> (testing::Log, TestDisplay) {
+        let log = testing::Log::default();
+        let mut display = ColorMemoryDisplay::new(log.spi(), log.cs(), log.disp());
+        display.enable().unwrap();
+        log.events();
+        (log, display)
+    }
+
+    /// The bytes of a flush of `lines`, each given by its row and its data.
+    fn frame(vcom: Vcom, lines: &[(usize, [u8; LINE_BYTES])]) -> Vec<u8> {
+        let mut bytes = vec![Command::WriteLine | vcom];
+        for (row, data) in lines {
+            bytes.push(((row + 1) as u8).reverse_bits());
+            bytes.extend_from_slice(data);
+            bytes.extend([0; panel::LINE_TRAILER_BYTES]);
+        }
+        bytes.extend([0; panel::FRAME_TRAILER_BYTES]);
+        bytes
+    }
+
+    #[test]
+    fn pixels_keep_their_color() {
+        let (_, mut display) = display();
+        // Every color at every offset of the three bits in a byte
+        for y in 0..8 {
+            for x in 0..16 {
+                let color = COLORS[(x + y) % 8];
+                display.set_pixel(x as u32, y as u32, color).unwrap();
+            }
+        }
+        for y in 0..8 {
+            for x in 0..16 {
+                let color = display.get_pixel(x as u32, y as u32);
+                assert_eq!(color, Some(COLORS[(x + y) % 8]), "({x}, {y})");
+            }
+            assert_eq!(display.get_pixel(16, y as u32), Some(Rgb111::Black));
+        }
+        let (width, height) = (COLOR_WIDTH as u32, COLOR_HEIGHT as u32);
+        assert_eq!(display.set_pixel(width, 0, Rgb111::Red), Err(OutOfBounds));
+        assert_eq!(display.set_pixel(0, height, Rgb111::Red), Err(OutOfBounds));
+        assert_eq!(display.get_pixel(width, 0), None);
+        assert_eq!(display.get_pixel(0, height), None);
+    }
+
+    #[test]
+    fn lines_are_sent_three_bits_per_pixel() {
+        let (log, mut display) = display();
+        display.set_pixel(0, 0, Rgb111::Red).unwrap();
+        display.set_pixel(1, 0, Rgb111::Green).unwrap();
+        display.set_pixel(2, 0, Rgb111::Blue).unwrap();
+        display.set_pixel(7, 0, Rgb111::White).unwrap();
+        let last = COLOR_WIDTH as u32 - 1;
+        display.set_pixel(last, 9, Rgb111::Cyan).unwrap();
+        display.flush_buffer().unwrap();
+
+        let mut first = [0; LINE_BYTES];
+        first[..3].copy_from_slice(&[0b1000_1000, 0b1000_0000, 0b0000_0111]);
+        let mut tenth = [0; LINE_BYTES];
+        tenth[LINE_BYTES - 1] = 0b0000_0011;
+        let events = log.events();
+        assert_eq!(events.first(), Some(&Event::Cs(true)));
+        assert_eq!(events.last(), Some(&Event::Cs(false)));
+        assert_eq!(
+            testing::written(&events),
+            frame(Vcom::Lo, &[(0, first), (9, tenth)])
+        );
+        // Nothing is left to send
+        display.flush_buffer().unwrap();
+        assert_eq!(log.bytes(), frame(Vcom::Hi, &[]));
+    }
+
+    #[test]
+    fn only_changed_lines_are_sent() {
+        let (log, mut display) = display();
+        display.set_clear_state(Rgb111::Blue);
+        display.clear().unwrap();
+        let clear = Command::ClearMemory | Vcom::Lo;
+        assert_eq!(log.writes(), [[clear, DUMMY_DATA]]);
+        display.flush_buffer().unwrap();
+        let blue = [0b0010_0100, 0b1001_0010, 0b0100_1001].repeat(LINE_BYTES / 3);
+        let lines: Vec<_> = (0..COLOR_HEIGHT)
+            .map(|y| (y, blue[..].try_into().unwrap()))
+            .collect();
+        assert_eq!(log.bytes(), frame(Vcom::Hi, &lines));
+
+        // Clearing again and drawing the same colors changes nothing
+        display.clear_buffer();
+        display.set_pixel(5, 5, Rgb111::Blue).unwrap();
+        display.flush_buffer().unwrap();
+        assert_eq!(log.bytes(), frame(Vcom::Lo, &[]));
+        display.display_mode().unwrap();
+        assert_eq!(log.writes(), [[Command::Nop | Vcom::Hi, DUMMY_DATA]]);
+    }
+
+    #[test]
+    fn failed_flushes_keep_the_lines_dirty() {
+        let (log, mut display) = display();
+        display.set_pixel(3, 4, Rgb111::Yellow).unwrap();
+        log.reject_writes_over(0);
+        assert_eq!(display.flush_buffer(), Err(Error::Spi(testing::BusError)));
+        assert_eq!(log.events(), [Event::Cs(true), Event::Cs(false)]);
+        log.reject_writes_over(usize::MAX);
+        display.flush_buffer().unwrap();
+        let mut line = [0; LINE_BYTES];
+        line[1] = 0b0110_0000;
+        assert_eq!(log.bytes(), frame(Vcom::Hi, &[(4, line)]));
+    }
+
+    #[test]
+    fn monochrome_colors_are_black_and_white() {
+        assert_eq!(Rgb111::from(Color::On), Rgb111::White);
+        assert_eq!(Rgb111::from(Color::Off), Rgb111::Black);
+        for (index, color) in COLORS.into_iter().enumerate() {
+            assert_eq!(color as usize, index);
+            assert_eq!(Rgb111::new(color.red(), color.green(), color.blue()), color);
+        }
+    }
+
+    #[cfg(feature = "graphics")]
+    #[test]
+    fn channels_are_on_from_half_their_range() {
+        use embedded_graphics::pixelcolor::{Rgb565, Rgb888};
+
+        assert_eq!(Rgb111::from(Rgb888::new(0x80, 0x7F, 0xFF)), Rgb111::Magenta);
+        assert_eq!(Rgb111::from(Rgb888::new(0x7F, 0x80, 0x00)), Rgb111::Green);
+        assert_eq!(Rgb111::from(Rgb565::new(0x10, 0x1F, 0x0F)), Rgb111::Red);
+        assert_eq!(Rgb111::from(Rgb565::new(0x0F, 0x20, 0x1F)), Rgb111::Cyan);
+        for color in COLORS {
+            assert_eq!(Rgb111::from(Rgb888::from(color)), color);
+        }
+    }
+}
@@ -0,0 +1,167 @@
+use crate::{spec, DisplaySpec, FrameBuffer, MemoryDisplay};
+use core::borrow::BorrowMut;
+use core::fmt;
+use embedded_graphics::draw_target::DrawTarget;
+use embedded_graphics::mono_font::{MonoFont, MonoTextStyleBuilder};
+use embedded_graphics::pixelcolor::BinaryColor;
+use embedded_graphics::prelude::{Dimensions, OriginDimensions, Point};
+use embedded_graphics::text::{Baseline, Text};
+use embedded_graphics::Drawable;
+use hal::blocking::spi::Write;
+use hal::digital::v2::OutputPin;
+
+/// A scrolling text terminal on the whole display, e.g. for debug output during boot:
+///
+/// ```ignore
+/// let mut console = Console::new(&mut display, &FONT_6X10);
+/// writeln!(console, "boot stage {}", n).ok();
+/// ```
+///
+/// Text wraps at the right edge and `\n` starts a new line. Once the cursor passes the bottom,
+/// the screen scrolls up by a line with [`MemoryDisplay::scroll_region_up`], so only lines
+/// which change are sent. By default every `\n` flushes the display, failing the write if the
+/// flush fails; with [`Console::set_flush_on_newline`] the application flushes itself.
+/// Only available with the `console` feature.
+pub struct Console<
+    'a,
+    'f,
+    SPI,
+    CS,
+    DISP,
+    SPEC: DisplaySpec = spec::Selected,
+    BUF = FrameBuffer<SPEC>,
+> {
+    display: &'a mut MemoryDisplay<SPI, CS, DISP, SPEC, BUF>,
+    font: &'f MonoFont<'f>,
+    // Column and row of the next character, in characters
+    cursor: (u32, u32),
+    foreground: BinaryColor,
+    background: BinaryColor,
+    flush_on_newline: bool,
+}
+
+impl<'a, 'f, SPI, CS, DISP, E, SPEC, BUF> Console<'a, 'f, SPI, CS, DISP, SPEC, BUF>
+where
+    SPI: Write<u8, Error = E>,
+    CS: OutputPin,
+    DISP: OutputPin,
+    SPEC: DisplaySpec,
+    BUF: BorrowMut<FrameBuffer<SPEC>>,
+{
+    /// Write to `display` in `font`, black on white, starting at the top left. The framebuffer
+    /// is kept as it is until the text covers it.
+    pub fn new(
+        display: &'a mut MemoryDisplay<SPI, CS, DISP, SPEC, BUF>,
+        font: &'f MonoFont<'f>,
+    ) -> Self {
+        Self {
+            display,
+            font,
+            cursor: (0, 0),
+            foreground: BinaryColor::Off,
+            background: BinaryColor::On,
+            flush_on_newline: true,
+        }
+    }
+
+    pub fn set_colors(&mut self, foreground: BinaryColor, background: BinaryColor) {
+        self.foreground = foreground;
+        self.background = background;
+    }
+
+    /// Whether every `\n` flushes the display (the default).
+    pub fn set_flush_on_newline(&mut self, flush: bool) {
+        self.flush_on_newline = flush;
+    }
+
+    /// Move the cursor to `column` and `row`, counted in characters, clamped to the screen.
+    pub fn set_cursor(&mut self, column: u32, row: u32) {
+        let (columns, rows) = self.columns_rows();
+        self.cursor = (column.min(columns - 1), row.min(rows - 1));
+    }
+
+    /// Column and row of the next character.
+    pub fn cursor(&self) -> (u32, u32) {
+        self.cursor
+    }
+
+    /// Number of columns and rows of characters fitting on the screen, at least one each.
+    pub fn columns_rows(&self) -> (u32, u32) {
+        let size = self.display.size();
+        let (width, height) = self.cell_size();
+        ((size.width / width).max(1), (size.height / height).max(1))
+    }
+
+    /// Fill the framebuffer with the background color and move the cursor to the top left.
+    /// Nothing is sent until the next flush.
+    pub fn clear(&mut self) {
+        let _ = DrawTarget::clear(self.display, self.background);
+        self.cursor = (0, 0);
+    }
+
+    /// Width and height of a character including the spacing to the next one.
+    fn cell_size(&self) -> (u32, u32) {
+        let size = self.font.character_size;
+        (size.width + self.font.character_spacing, size.height)
+    }
+
+    /// Move the cursor to the start of the next line, scrolling the screen up if it passes the
+    /// bottom.
+    fn new_line(&mut self) {
+        let (_, rows) = self.columns_rows();
+        if self.cursor.1 + 1 < rows {
+            self.cursor = (0, self.cursor.1 + 1);
+        } else {
+            let area = self.display.bounding_box();
+            let (_, height) = self.cell_size();
+            self.display.scroll_region_up(area, height, self.background);
+            self.cursor.0 = 0;
+        }
+    }
+
+    fn put_char(&mut self, c: char) {
+        let (columns, _) = self.columns_rows();
+        if self.cursor.0 >= columns {
+            self.new_line();
+        }
+        let (width, height) = self.cell_size();
+        let position = Point::new(
+            (self.cursor.0 * width) as i32,
+            (self.cursor.1 * height) as i32,
+        );
+        let style = MonoTextStyleBuilder::new()
+            .font(self.font)
+            .text_color(self.foreground)
+            .background_color(self.background)
+            .build();
+        let mut bytes = [0; 4];
+        let text = Text::with_baseline(c.encode_utf8(&mut bytes), position, style, Baseline::Top);
+        let _ = text.draw(self.display);
+        self.cursor.0 += 1;
+    }
+}
+
+impl<SPI, CS, DISP, E, SPEC, BUF> fmt::Write for Console<'_, '_, SPI, CS, DISP, SPEC, BUF>
+where
+    SPI: Write<u8, Error = E>,
+    CS: OutputPin,
+    DISP: OutputPin,
+    SPEC: DisplaySpec,
+    BUF: BorrowMut<FrameBuffer<SPEC>>,
+{
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for c in s.chars() {
+            match c {
+                '\n' => {
+                    self.new_line();
+                    if self.flush_on_newline {
+                        self.display.flush_buffer().map_err(|_| fmt::Error)?;
+                    }
+                }
+                '\r' => self.cursor.0 = 0,
+                c => self.put_char(c),
+            }
+        }
+        Ok(())
+    }
+}
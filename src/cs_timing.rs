@@ -0,0 +1,79 @@
+use hal::blocking::delay::DelayUs;
+use hal::digital::v2::OutputPin;
+
+/// Minimum timing of the chip select signal around a transaction, in microseconds, see
+/// [`crate::CS_TIMING`] for the display model selected via the feature flag.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct CsTiming {
+    /// Time from raising chip select to the first clock edge (tsSCS).
+    pub setup_us: u32,
+    /// Time from the last clock edge to lowering chip select (thSCS).
+    pub hold_us: u32,
+    /// Time chip select stays low between two transactions (twSCSL).
+    pub low_us: u32,
+}
+
+impl CsTiming {
+    pub(crate) const fn from_us((setup_us, hold_us, low_us): (u32, u32, u32)) -> Self {
+        Self {
+            setup_us,
+            hold_us,
+            low_us,
+        }
+    }
+}
+
+/// A chip select pin observing the setup, hold and low times required by the panel, for MCUs
+/// fast enough to violate them otherwise:
+///
+/// ```ignore
+/// let mut display = MemoryDisplay::new(spi, TimedCs::new(cs, delay), disp);
+/// ```
+///
+/// It waits with `delay` after raising the pin and around lowering it, for every transaction
+/// of the driver. The hold time can only be observed if the SPI write returns once the last
+/// byte has been clocked out, as blocking writes of embedded-hal should. Without it the
+/// driver toggles chip select as fast as the pin allows.
+pub struct TimedCs<P, D> {
+    pin: P,
+    delay: D,
+    timing: CsTiming,
+}
+
+impl<P: OutputPin, D: DelayUs<u32>> TimedCs<P, D> {
+    with_selected_model! {
+        /// Wrap `pin` with the timing of the display model selected via the feature flag. For
+        /// another model pass its [`DisplaySpec::CS_TIMING`](crate::DisplaySpec::CS_TIMING) to [`TimedCs::with_timing`].
+        pub fn new(pin: P, delay: D) -> Self {
+            Self::with_timing(pin, delay, crate::CS_TIMING)
+        }
+    }
+
+    /// Wrap `pin` with a custom timing, e.g. with extra margin for a level shifter.
+    pub fn with_timing(pin: P, delay: D, timing: CsTiming) -> Self {
+        Self { pin, delay, timing }
+    }
+
+    /// Give back the pin and the delay.
+    pub fn release(self) -> (P, D) {
+        (self.pin, self.delay)
+    }
+}
+
+impl<P: OutputPin, D: DelayUs<u32>> OutputPin for TimedCs<P, D> {
+    type Error = P::Error;
+
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        self.delay.delay_us(self.timing.hold_us);
+        self.pin.set_low()?;
+        self.delay.delay_us(self.timing.low_us);
+        Ok(())
+    }
+
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        self.pin.set_high()?;
+        self.delay.delay_us(self.timing.setup_us);
+        Ok(())
+    }
+}
@@ -0,0 +1,328 @@
+//! Decoder for the memory-in-pixel serial protocol.
+//!
+//! Turns a raw capture of the bytes sent to the display (e.g. from a logic analyzer) back into
+//! commands and the image the panel would show. Only available with the `std` feature.
+use std::io;
+use std::vec;
+use std::vec::Vec;
+
+/// Geometry (width, height) of every supported display model, keyed by its feature name.
+pub const MODELS: &[(&str, usize, usize)] = crate::models::MODELS;
+
+/// Look up the geometry of a display model by its feature name.
+pub fn model_geometry(name: &str) -> Option<(usize, usize)> {
+    MODELS
+        .iter()
+        .find(|(model, _, _)| model.eq_ignore_ascii_case(name))
+        .map(|&(_, width, height)| (width, height))
+}
+
+/// Dummy bytes sent after every line and at the end of a frame of every supported display
+/// model, keyed by its feature name.
+pub const TRAILERS: &[(&str, usize, usize)] = crate::models::TRAILERS;
+
+/// Look up the dummy bytes (after every line, at the end of a frame) of a display model by its
+/// feature name.
+pub fn model_trailers(name: &str) -> Option<(usize, usize)> {
+    TRAILERS
+        .iter()
+        .find(|(model, _, _)| model.eq_ignore_ascii_case(name))
+        .map(|&(_, line, frame)| (line, frame))
+}
+
+/// A single command decoded from the byte stream.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Record {
+    /// A write command followed by one or more lines, given as
+    /// (line number starting at 1, pixel data in natural left-to-right MSB-first order).
+    WriteLines {
+        vcom: bool,
+        lines: Vec<(usize, Vec<u8>)>,
+    },
+    /// The memory clear command.
+    ClearMemory { vcom: bool },
+    /// The display mode (no operation) command, used to toggle VCOM.
+    Nop { vcom: bool },
+    /// Bytes which could not be decoded, starting at `offset` in the stream.
+    Malformed { offset: usize, reason: &'static str },
+}
+
+impl Record {
+    /// The VCOM bit carried by the command, if any.
+    pub fn vcom(&self) -> Option<bool> {
+        match self {
+            Record::WriteLines { vcom, .. }
+            | Record::ClearMemory { vcom }
+            | Record::Nop { vcom } => Some(*vcom),
+            Record::Malformed { .. } => None,
+        }
+    }
+}
+
+/// The bits of a line address beyond the first 8, sent at the end of the byte before the
+/// address byte by panels with more than 255 lines. For all others these are dummy bits.
+fn high_address_bits(before: u8, height: usize) -> usize {
+    if height < 256 {
+        return 0;
+    }
+    ((before & 0x03).reverse_bits() >> 6) as usize * 256
+}
+
+/// Decode a byte stream for a display of the given dimensions into its commands.
+///
+/// Malformed sections are reported as [`Record::Malformed`] and skipped byte by byte until
+/// decoding can resume.
+pub fn decode(bytes: &[u8], width: usize, height: usize) -> Vec<Record> {
+    decode_with_trailers(bytes, width, height, (1, 1))
+}
+
+/// Same as [`decode`], for a display sending `trailers.0` dummy bytes after every line and
+/// `trailers.1` at the end of a frame, see [`model_trailers`].
+pub fn decode_with_trailers(
+    bytes: &[u8],
+    width: usize,
+    height: usize,
+    trailers: (usize, usize),
+) -> Vec<Record> {
+    let (line_trailer, frame_trailer) = trailers;
+    let line_bytes = width.div_ceil(8);
+    let mut records = Vec::new();
+    let mut pos = 0;
+
+    while pos < bytes.len() {
+        let start = pos;
+        let command = bytes[pos];
+        let vcom = command & 0x40 != 0;
+        pos += 1;
+
+        if command & 0x80 != 0 {
+            let mut lines = Vec::new();
+            let mut error = None;
+            loop {
+                let Some(address) = bytes.get(pos) else {
+                    error = Some("missing frame trailer");
+                    break;
+                };
+                let line_no =
+                    address.reverse_bits() as usize | high_address_bits(bytes[pos - 1], height);
+                // A zero address marks the end of the frame (lines start at 1)
+                if line_no == 0 {
+                    if pos + frame_trailer <= bytes.len() {
+                        pos += frame_trailer;
+                    } else {
+                        error = Some("missing frame trailer");
+                    }
+                    break;
+                }
+                if line_no > height {
+                    error = Some("line address out of range");
+                    break;
+                }
+                // Address, line data and the dummy bytes following it
+                if pos + 1 + line_bytes + line_trailer > bytes.len() {
+                    error = Some("truncated line");
+                    break;
+                }
+                let data = &bytes[pos + 1..pos + 1 + line_bytes];
+                lines.push((line_no, data.to_vec()));
+                pos += 1 + line_bytes + line_trailer;
+            }
+            match error {
+                Some(reason) if lines.is_empty() => {
+                    records.push(Record::Malformed {
+                        offset: start,
+                        reason,
+                    });
+                    pos = start + 1;
+                }
+                Some(reason) => {
+                    let offset = pos;
+                    records.push(Record::WriteLines { vcom, lines });
+                    records.push(Record::Malformed { offset, reason });
+                    pos += 1;
+                }
+                None => records.push(Record::WriteLines { vcom, lines }),
+            }
+        } else if pos < bytes.len() {
+            // Both remaining commands are followed by a single dummy byte
+            pos += 1;
+            if command & 0x20 != 0 {
+                records.push(Record::ClearMemory { vcom });
+            } else {
+                records.push(Record::Nop { vcom });
+            }
+        } else {
+            records.push(Record::Malformed {
+                offset: start,
+                reason: "missing command trailer",
+            });
+        }
+    }
+
+    records
+}
+
+/// Decode a byte stream for a display of the given dimensions and return the image the panel
+/// shows afterwards, starting from a cleared panel, as rows of pixels with `true` being white.
+pub fn render(bytes: &[u8], width: usize, height: usize) -> Vec<Vec<bool>> {
+    let mut panel = Panel::new(width, height);
+    for record in decode(bytes, width, height) {
+        panel.apply(&record);
+    }
+    panel.rows()
+}
+
+/// The simulated contents of the panel's pixel memory.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Panel {
+    width: usize,
+    height: usize,
+    pixels: Vec<bool>,
+}
+
+impl Panel {
+    /// Create a panel in the cleared (all white) state.
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            pixels: vec![true; width * height],
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Get a pixel, `true` being white (`BinaryColor::On`).
+    pub fn pixel(&self, x: usize, y: usize) -> bool {
+        self.pixels[y * self.width + x]
+    }
+
+    /// All pixels as rows from top to bottom, `true` being white.
+    pub fn rows(&self) -> Vec<Vec<bool>> {
+        self.pixels
+            .chunks(self.width)
+            .map(<[bool]>::to_vec)
+            .collect()
+    }
+
+    /// Update the panel memory as the hardware would upon receiving the command.
+    pub fn apply(&mut self, record: &Record) {
+        match record {
+            Record::WriteLines { lines, .. } => {
+                for (line_no, data) in lines {
+                    let row = &mut self.pixels[(line_no - 1) * self.width..][..self.width];
+                    for (x, pixel) in row.iter_mut().enumerate() {
+                        *pixel = data[x / 8] & (0x80 >> (x % 8)) != 0;
+                    }
+                }
+            }
+            Record::ClearMemory { .. } => self.pixels.fill(true),
+            Record::Nop { .. } | Record::Malformed { .. } => {}
+        }
+    }
+
+    /// Write the panel contents as a binary PBM (P4) image.
+    pub fn write_pbm<W: io::Write>(&self, mut w: W) -> io::Result<()> {
+        write!(w, "P4\n{} {}\n", self.width, self.height)?;
+        let mut row = vec![0u8; self.width.div_ceil(8)];
+        for y in 0..self.height {
+            row.fill(0);
+            for x in 0..self.width {
+                // PBM uses 1 for black
+                if !self.pixel(x, y) {
+                    row[x / 8] |= 0x80 >> (x % 8);
+                }
+            }
+            w.write_all(&row)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line_numbers(records: &[Record]) -> Vec<usize> {
+        let [Record::WriteLines { lines, .. }] = records else {
+            panic!("{records:?}");
+        };
+        lines.iter().map(|(number, _)| *number).collect()
+    }
+
+    /// A frame of a panel `width` pixels wide, with lines `numbers` sending their high address
+    /// bits if `high`, else all the dummy bits set.
+    fn frame(width: usize, numbers: &[usize], high: bool) -> Vec<u8> {
+        let mut bytes = vec![0x80];
+        for &number in numbers {
+            *bytes.last_mut().unwrap() |= if high {
+                ((number >> 8 & 1) << 1 | number >> 9 & 1) as u8
+            } else {
+                0x03
+            };
+            bytes.push((number as u8).reverse_bits());
+            bytes.extend(vec![0xA5; width.div_ceil(8)]);
+            bytes.push(0);
+        }
+        bytes.push(0);
+        bytes
+    }
+
+    #[test]
+    fn high_address_bits_select_lines_past_255() {
+        let numbers = [1, 255, 256, 300, 511, 512, 536];
+        let records = decode(&frame(336, &numbers, true), 336, 536);
+        assert_eq!(line_numbers(&records), numbers);
+    }
+
+    #[test]
+    fn dummy_bits_are_ignored_by_panels_up_to_255_lines() {
+        let numbers = [1, 2, 128, 240];
+        let records = decode(&frame(400, &numbers, false), 400, 240);
+        assert_eq!(line_numbers(&records), numbers);
+    }
+
+    // The lines sent are the lines of the panel, the framebuffer isn't scaled
+    #[cfg(not(any(feature = "half-height", feature = "half-width")))]
+    #[test]
+    fn flushed_frames_decode_to_the_framebuffer() {
+        use crate::{testing, HEIGHT, WIDTH};
+        let trailers = (crate::LINE_TRAILER_BYTES, crate::FRAME_TRAILER_BYTES);
+        let (log, mut display) = testing::display();
+        testing::draw_noise(&mut display, 270);
+        display.flush_buffer().unwrap();
+        let bytes = log.bytes();
+        let records = decode_with_trailers(&bytes, WIDTH, HEIGHT, trailers);
+        assert!(line_numbers(&records).into_iter().eq(1..=HEIGHT));
+        let mut panel = Panel::new(WIDTH, HEIGHT);
+        panel.apply(&records[0]);
+        assert_eq!(panel.rows(), testing::snapshot(&display));
+
+        // Lines on either side of the high address bits on their own
+        let mut lines: Vec<_> = [0, 254, 255, 256, 300, HEIGHT - 1]
+            .into_iter()
+            .filter(|&y| y < HEIGHT)
+            .collect();
+        lines.sort();
+        lines.dedup();
+        for &y in &lines {
+            let x = (y % WIDTH) as u32;
+            let on = display.get_pixel(x, y as u32) == Some(crate::Color::On);
+            display.set_pixel(x, y as u32, !on).unwrap();
+        }
+        display.flush_buffer().unwrap();
+        let records = decode_with_trailers(&log.bytes(), WIDTH, HEIGHT, trailers);
+        if !cfg!(feature = "minimal-flush") {
+            let numbers = lines.iter().map(|y| y + 1);
+            assert!(line_numbers(&records).into_iter().eq(numbers));
+        }
+        panel.apply(&records[0]);
+        assert_eq!(panel.rows(), testing::snapshot(&display));
+    }
+}
@@ -0,0 +1,170 @@
+//! Tracking of the lines changed since the last flush. With the `minimal-flush` feature nothing
+//! is tracked and every line counts as dirty, so every flush sends the whole frame.
+//!
+//! With the `dirty-columns` feature the columns changed are tracked as well, as the first and
+//! last column of every dirty line. Lines marked dirty without giving the columns count as
+//! changed entirely.
+use crate::DisplaySpec;
+#[cfg(not(feature = "minimal-flush"))]
+use crate::Zeroed;
+#[cfg(not(feature = "minimal-flush"))]
+use bitvec::prelude::*;
+use core::marker::PhantomData;
+use core::ops::Range;
+
+#[cfg(not(feature = "minimal-flush"))]
+pub(crate) struct DirtyLines<SPEC: DisplaySpec> {
+    // A bit per line, rounded up to whole bytes
+    lines: SPEC::LineBits,
+    // The first and last column changed of every line, only meaningful while it is dirty
+    #[cfg(feature = "dirty-columns")]
+    first: SPEC::PerLine<u16>,
+    #[cfg(feature = "dirty-columns")]
+    last: SPEC::PerLine<u16>,
+    spec: PhantomData<SPEC>,
+}
+
+#[cfg(not(feature = "minimal-flush"))]
+impl<SPEC: DisplaySpec> DirtyLines<SPEC> {
+    pub(crate) const fn new() -> Self {
+        Self {
+            lines: Zeroed::ZERO,
+            #[cfg(feature = "dirty-columns")]
+            first: Zeroed::ZERO,
+            #[cfg(feature = "dirty-columns")]
+            last: Zeroed::ZERO,
+            spec: PhantomData,
+        }
+    }
+
+    fn bits(&self) -> &BitSlice<u8, Lsb0> {
+        // The array is rounded up to whole bytes, the bits past the framebuffer don't count
+        &self.lines.as_ref().view_bits()[..SPEC::BUFFER_HEIGHT]
+    }
+
+    fn bits_mut(&mut self) -> &mut BitSlice<u8, Lsb0> {
+        &mut self.lines.as_mut().view_bits_mut()[..SPEC::BUFFER_HEIGHT]
+    }
+
+    pub(crate) fn set(&mut self, y: usize, dirty: bool) {
+        if dirty {
+            self.mark_columns(y, 0..SPEC::BUFFER_WIDTH);
+        } else {
+            self.bits_mut().set(y, false);
+        }
+    }
+
+    /// Mark columns `x` of line `y` dirty, in addition to those marked since it was last
+    /// flushed. Nothing changes if `x` is empty.
+    pub(crate) fn mark_columns(&mut self, y: usize, x: Range<usize>) {
+        if x.is_empty() {
+            return;
+        }
+        #[cfg(feature = "dirty-columns")]
+        {
+            let (first, last) = (x.start as u16, x.end as u16 - 1);
+            let dirty = self.bits()[y];
+            let (first_of, last_of) = (&mut self.first.as_mut()[y], &mut self.last.as_mut()[y]);
+            if dirty {
+                *first_of = (*first_of).min(first);
+                *last_of = (*last_of).max(last);
+            } else {
+                (*first_of, *last_of) = (first, last);
+            }
+        }
+        self.bits_mut().set(y, true);
+    }
+
+    /// Mark line `y` dirty again if it is dirty in `other`, e.g. a copy taken before flushing,
+    /// with the columns marked there.
+    pub(crate) fn merge(&mut self, y: usize, other: &DirtyLines<SPEC>) {
+        if let Some((first, last)) = other.columns(y) {
+            self.mark_columns(y, first as usize..last as usize + 1);
+        }
+    }
+
+    /// The first and last column changed of line `y`, `None` if it isn't dirty.
+    pub(crate) fn columns(&self, y: usize) -> Option<(u16, u16)> {
+        #[cfg(feature = "dirty-columns")]
+        let columns = (self.first.as_ref()[y], self.last.as_ref()[y]);
+        #[cfg(not(feature = "dirty-columns"))]
+        let columns = (0, SPEC::BUFFER_WIDTH as u16 - 1);
+        self.bits()[y].then_some(columns)
+    }
+
+    /// Mark `lines` dirty, which have to be within the framebuffer.
+    pub(crate) fn mark(&mut self, lines: Range<usize>) {
+        #[cfg(feature = "dirty-columns")]
+        for y in lines.clone() {
+            self.first.as_mut()[y] = 0;
+            self.last.as_mut()[y] = SPEC::BUFFER_WIDTH as u16 - 1;
+        }
+        self.bits_mut()[lines].fill(true);
+    }
+
+    pub(crate) fn fill(&mut self, dirty: bool) {
+        if dirty {
+            self.mark(0..SPEC::BUFFER_HEIGHT);
+        } else {
+            self.bits_mut().fill(false);
+        }
+    }
+
+    pub(crate) fn any(&self) -> bool {
+        self.bits().any()
+    }
+
+    pub(crate) fn count(&self) -> usize {
+        self.bits().count_ones()
+    }
+
+    /// The dirty lines in ascending order.
+    pub(crate) fn iter(&self) -> impl Iterator<Item = usize> + '_ {
+        self.bits().iter_ones()
+    }
+}
+
+#[cfg(feature = "minimal-flush")]
+pub(crate) struct DirtyLines<SPEC: DisplaySpec>(PhantomData<SPEC>);
+
+#[cfg(feature = "minimal-flush")]
+impl<SPEC: DisplaySpec> DirtyLines<SPEC> {
+    pub(crate) const fn new() -> Self {
+        Self(PhantomData)
+    }
+
+    pub(crate) fn set(&mut self, _y: usize, _dirty: bool) {}
+
+    pub(crate) fn mark_columns(&mut self, _y: usize, _x: Range<usize>) {}
+
+    pub(crate) fn merge(&mut self, _y: usize, _other: &DirtyLines<SPEC>) {}
+
+    pub(crate) fn columns(&self, _y: usize) -> Option<(u16, u16)> {
+        Some((0, SPEC::BUFFER_WIDTH as u16 - 1))
+    }
+
+    pub(crate) fn mark(&mut self, _lines: Range<usize>) {}
+
+    pub(crate) fn fill(&mut self, _dirty: bool) {}
+
+    pub(crate) fn any(&self) -> bool {
+        true
+    }
+
+    pub(crate) fn count(&self) -> usize {
+        SPEC::BUFFER_HEIGHT
+    }
+
+    pub(crate) fn iter(&self) -> impl Iterator<Item = usize> + '_ {
+        0..SPEC::BUFFER_HEIGHT
+    }
+}
+
+// Only the arrays are copied, whatever the model
+impl<SPEC: DisplaySpec> Clone for DirtyLines<SPEC> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<SPEC: DisplaySpec> Copy for DirtyLines<SPEC> {}
@@ -0,0 +1,190 @@
+use crate::spec::{DisplaySpec, Zeroed};
+use crate::{
+    address_high_bits, Command, Error, FrameBuffer, MemoryDisplay, OutOfBounds, DUMMY_DATA, Y_SCALE,
+};
+use core::borrow::BorrowMut;
+use hal::blocking::spi::Write;
+use hal::digital::v2::OutputPin;
+
+/// A flush prepared by [`MemoryDisplay::prepare_flush`], to be sent by other means than the
+/// driver, e.g. a DMA engine.
+///
+/// The frame is sent as a single transaction with chip select held: the
+/// [command](PreparedFlush::command), every [line](PreparedFlush::lines) and the
+/// [trailer](PreparedFlush::trailer), or all of them at once from
+/// [`PreparedFlush::write_to`]. Every byte is ready to go out as it is, most significant bit
+/// first.
+pub struct PreparedFlush<'a, SPI, CS, DISP, SPEC: DisplaySpec, BUF> {
+    display: &'a MemoryDisplay<SPI, CS, DISP, SPEC, BUF>,
+}
+
+impl<SPI, CS, DISP, E, SPEC, BUF> PreparedFlush<'_, SPI, CS, DISP, SPEC, BUF>
+where
+    SPI: Write<u8, Error = E>,
+    CS: OutputPin,
+    DISP: OutputPin,
+    SPEC: DisplaySpec,
+    BUF: BorrowMut<FrameBuffer<SPEC>>,
+{
+    /// The command byte starting the frame, carrying the VCOM polarity.
+    pub fn command(&self) -> u8 {
+        let display = self.display;
+        let first = display.in_flight.iter().next().map_or(0, |y| {
+            address_high_bits::<SPEC>(display.panel_lines(y).start)
+        });
+        Command::WriteLine | display.vcom | first
+    }
+
+    /// Every line of the frame as it is sent: its address, pixel data and line trailer. Each
+    /// line of the framebuffer takes two lines of the panel with the `half-height` feature.
+    pub fn lines(&self) -> impl Iterator<Item = SPEC::LineWrite> + '_ {
+        let display = self.display;
+        let mut rows = display
+            .in_flight
+            .iter()
+            .flat_map(move |y| display.panel_lines(y).map(move |row| (y, row)))
+            .peekable();
+        core::iter::from_fn(move || {
+            let (y, row) = rows.next()?;
+            let mut packed =
+                MemoryDisplay::<SPI, CS, DISP, SPEC, BUF>::pack_line(row, &display.line_to_send(y));
+            // The high bits of the address of the next line go at the end of this one
+            packed.as_mut()[SPEC::WRITE_BUFFER_SIZE - 1] |= rows
+                .peek()
+                .map_or(0, |&(_, row)| address_high_bits::<SPEC>(row));
+            Some(packed)
+        })
+    }
+
+    /// The bytes ending the frame, after the trailer of its last line.
+    pub fn trailer(&self) -> SPEC::FrameTrailer {
+        let mut trailer = SPEC::FrameTrailer::ZERO;
+        trailer.as_mut().fill(DUMMY_DATA);
+        trailer
+    }
+
+    /// The number of bytes of the whole frame.
+    pub fn len(&self) -> usize {
+        self.display.in_flight.count() * Y_SCALE * SPEC::WRITE_BUFFER_SIZE + SPEC::FRAME_OVERHEAD
+    }
+
+    /// Whether the frame carries no lines, only the command and the trailer.
+    pub fn is_empty(&self) -> bool {
+        !self.display.in_flight.any()
+    }
+
+    /// Write the whole frame to the start of `out`, e.g. a DMA buffer, and return its length.
+    /// Returns [`OutOfBounds`] if it doesn't fit, see [`PreparedFlush::len`].
+    pub fn write_to(&self, out: &mut [u8]) -> Result<usize, OutOfBounds> {
+        let len = self.len();
+        let out = out.get_mut(..len).ok_or(OutOfBounds)?;
+        out[0] = self.command();
+        for (dst, line) in out[1..]
+            .chunks_exact_mut(SPEC::WRITE_BUFFER_SIZE)
+            .zip(self.lines())
+        {
+            dst.copy_from_slice(line.as_ref());
+        }
+        out[len - SPEC::FRAME_TRAILER_BYTES..].copy_from_slice(self.trailer().as_ref());
+        Ok(len)
+    }
+}
+
+impl<SPI, CS, DISP, E, SPEC, BUF> MemoryDisplay<SPI, CS, DISP, SPEC, BUF>
+where
+    SPI: Write<u8, Error = E>,
+    CS: OutputPin,
+    DISP: OutputPin,
+    SPEC: DisplaySpec,
+    BUF: BorrowMut<FrameBuffer<SPEC>>,
+{
+    /// Prepare a flush of the dirty lines for sending by other means than the driver, e.g. a
+    /// DMA engine, instead of [`MemoryDisplay::flush_buffer`] blocking until it is done. This
+    /// toggles VCOM like every flush.
+    ///
+    /// The lines of the frame stop being dirty, so drawing while it is being sent marks them
+    /// again. Lines drawn to in an open frame are held back, see
+    /// [`MemoryDisplay::begin_frame`]. Once the transfer is done call [`MemoryDisplay::commit_flush`], or
+    /// [`MemoryDisplay::abort_flush`] if it failed, which marks them dirty again. Preparing
+    /// another flush before either aborts the previous one.
+    ///
+    /// While the display is asleep nothing is prepared and [`Error::Asleep`] is returned.
+    #[allow(clippy::type_complexity)]
+    pub fn prepare_flush(
+        &mut self,
+    ) -> Result<PreparedFlush<'_, SPI, CS, DISP, SPEC, BUF>, Error<E, CS::Error>> {
+        if self.asleep {
+            return Err(Error::Asleep);
+        }
+        self.abort_flush();
+        self.toggle_vcom();
+
+        self.presenting(|display| {
+            let touched = &mut display.frame.borrow_mut().touched;
+            display.in_flight = *touched;
+            touched.fill(false);
+        });
+
+        debug!(
+            "prepare flush: {} lines, vcom={}",
+            self.in_flight.count(),
+            self.vcom == crate::Vcom::Hi
+        );
+        Ok(PreparedFlush { display: self })
+    }
+
+    /// Confirm that the flush prepared with [`MemoryDisplay::prepare_flush`] has been sent.
+    pub fn commit_flush(&mut self) {
+        self.in_flight.fill(false);
+    }
+
+    /// Mark the lines of the flush prepared with [`MemoryDisplay::prepare_flush`] dirty again,
+    /// e.g. after the transfer failed, so the next flush sends them, even while a frame is
+    /// open.
+    pub fn abort_flush(&mut self) {
+        let touched = match &mut self.committed {
+            Some(committed) => committed,
+            None => &mut self.frame.borrow_mut().touched,
+        };
+        for y in self.in_flight.iter() {
+            touched.merge(y, &self.in_flight);
+        }
+        self.in_flight.fill(false);
+    }
+}
+
+// Every line sent as it is drawn
+#[cfg(all(
+    test,
+    not(any(
+        feature = "half-height",
+        feature = "half-width",
+        feature = "minimal-flush"
+    ))
+))]
+mod tests {
+    use crate::testing::{self, diagonal};
+    use crate::Vcom;
+    use std::vec;
+
+    #[test]
+    fn lines_of_an_open_frame_are_held_back() {
+        let (_, mut display) = testing::display();
+        testing::draw_diagonal(&mut display, [10]);
+        display.begin_frame();
+        testing::draw_diagonal(&mut display, [11]);
+        let flush = display.prepare_flush().unwrap();
+        let mut out = vec![0; flush.len()];
+        assert_eq!(flush.write_to(&mut out), Ok(out.len()));
+        assert_eq!(out, testing::frame(Vcom::Lo, &diagonal([10])));
+
+        // An aborted flush is sent again before the frame ends
+        display.abort_flush();
+        assert_eq!(display.dirty_line_count(), 1);
+        display.end_frame();
+        let flush = display.prepare_flush().unwrap();
+        let mut out = vec![0; flush.len()];
+        flush.write_to(&mut out).unwrap();
+        assert_eq!(out, testing::frame(Vcom::Hi, &diagonal([10, 11])));
+    }
+}
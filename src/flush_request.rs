@@ -0,0 +1,161 @@
+use crate::{DisplaySpec, Error, FrameBuffer, MemoryDisplay};
+use core::borrow::BorrowMut;
+use core::sync::atomic::{fence, AtomicBool, Ordering};
+use hal::blocking::spi::Write;
+use hal::digital::v2::OutputPin;
+
+/// A flag for requesting a flush from contexts which must not touch the bus, e.g. interrupt
+/// handlers, serviced by [`MemoryDisplay::service`] in the main loop.
+///
+/// It is meant to be placed in a `static`:
+///
+/// ```ignore
+/// static FLUSH: FlushRequester = FlushRequester::new();
+///
+/// // In the button interrupt
+/// FLUSH.request();
+///
+/// // In the main loop
+/// display.service(&FLUSH)?;
+/// ```
+///
+/// Only atomic loads and stores are used, so this also works on targets without atomic
+/// read-modify-write instructions such as Cortex-M0.
+#[derive(Debug, Default)]
+pub struct FlushRequester {
+    pending: AtomicBool,
+}
+
+impl FlushRequester {
+    pub const fn new() -> Self {
+        Self {
+            pending: AtomicBool::new(false),
+        }
+    }
+
+    /// Request a flush. This never blocks and may be called from any context.
+    ///
+    /// Memory written before the request (with release ordering) is visible to the flush
+    /// performed for it.
+    pub fn request(&self) {
+        self.pending.store(true, Ordering::Release);
+    }
+
+    /// Whether a flush has been requested and not yet serviced.
+    pub fn is_pending(&self) -> bool {
+        self.pending.load(Ordering::Acquire)
+    }
+}
+
+impl<SPI, CS, DISP, E, SPEC, BUF> MemoryDisplay<SPI, CS, DISP, SPEC, BUF>
+where
+    SPI: Write<u8, Error = E>,
+    CS: OutputPin,
+    DISP: OutputPin,
+    SPEC: DisplaySpec,
+    BUF: BorrowMut<FrameBuffer<SPEC>>,
+{
+    /// Flush the buffer if a flush has been requested through `requester`, returning whether
+    /// it did. A failed flush is not requested again, but its lines stay dirty.
+    ///
+    /// The request is cleared before flushing, so every request is followed by a flush which
+    /// starts after it: a request made while a flush is in progress is serviced by the next
+    /// call.
+    pub fn service(&mut self, requester: &FlushRequester) -> Result<bool, Error<E, CS::Error>> {
+        if !requester.is_pending() {
+            return Ok(false);
+        }
+        requester.pending.store(false, Ordering::Relaxed);
+        // Keep the flush from starting before the request is cleared
+        fence(Ordering::SeqCst);
+        self.flush_buffer()?;
+        Ok(true)
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use crate::testing::Log;
+    use core::sync::atomic::AtomicU32;
+    use std::{thread, vec::Vec};
+
+    /// A bus reading what was drawn for every write, and optionally requesting a flush while
+    /// it is busy.
+    struct Spi<'a> {
+        drawn: &'a AtomicU32,
+        seen: Vec<u32>,
+        request_while_busy: Option<&'a FlushRequester>,
+    }
+
+    impl Write<u8> for Spi<'_> {
+        type Error = core::convert::Infallible;
+
+        fn write(&mut self, _: &[u8]) -> Result<(), Self::Error> {
+            self.seen.push(self.drawn.load(Ordering::Relaxed));
+            if let Some(requester) = self.request_while_busy.take() {
+                requester.request();
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn requests_during_a_flush_are_serviced_next() {
+        let requester = FlushRequester::new();
+        let drawn = AtomicU32::new(0);
+        let spi = Spi {
+            drawn: &drawn,
+            seen: Vec::new(),
+            request_while_busy: Some(&requester),
+        };
+        let log = Log::default();
+        let mut display = MemoryDisplay::new(spi, log.cs(), log.disp());
+
+        assert_eq!(display.service(&requester), Ok(false));
+        requester.request();
+        assert!(requester.is_pending());
+        assert_eq!(display.service(&requester), Ok(true));
+        // Requested again by the bus during the flush
+        assert!(requester.is_pending());
+        assert_eq!(display.service(&requester), Ok(true));
+        assert_eq!(display.service(&requester), Ok(false));
+        assert!(!requester.is_pending());
+    }
+
+    #[test]
+    fn every_request_is_followed_by_a_flush_seeing_it() {
+        static REQUESTER: FlushRequester = FlushRequester::new();
+        static DRAWN: AtomicU32 = AtomicU32::new(0);
+        const REQUESTS: u32 = 2000;
+
+        let spi = Spi {
+            drawn: &DRAWN,
+            seen: Vec::new(),
+            request_while_busy: None,
+        };
+        let log = Log::default();
+        let mut display = MemoryDisplay::new(spi, log.cs(), log.disp());
+
+        let signaller = thread::spawn(|| {
+            for i in 1..=REQUESTS {
+                // Published by the release ordering of the request
+                DRAWN.store(i, Ordering::Relaxed);
+                REQUESTER.request();
+            }
+        });
+        let mut flushes = 0;
+        while !signaller.is_finished() {
+            flushes += display.service(&REQUESTER).unwrap() as u32;
+        }
+        signaller.join().unwrap();
+        flushes += display.service(&REQUESTER).unwrap() as u32;
+        assert!(!REQUESTER.is_pending());
+        assert!((1..=REQUESTS).contains(&flushes));
+
+        // The last flush started after the last request
+        let (spi, ..) = display.release();
+        assert_eq!(spi.seen.last(), Some(&REQUESTS));
+        assert!(spi.seen.is_sorted());
+    }
+}
@@ -0,0 +1,28 @@
+//! Logging through `defmt` or `log`, whichever is enabled, compiling to nothing otherwise.
+//!
+//! The arguments are still evaluated by reference without a logger, so call sites need no cfgs.
+//! If both features are enabled `defmt` is used. Messages must stick to the format syntax both
+//! understand, plain `{}` placeholders.
+macro_rules! debug {
+    ($s:literal $(, $x:expr)* $(,)?) => {{
+        #[cfg(feature = "defmt")]
+        ::defmt::debug!($s $(, $x)*);
+        #[cfg(all(feature = "log", not(feature = "defmt")))]
+        ::log::debug!($s $(, $x)*);
+        #[cfg(not(any(feature = "defmt", feature = "log")))]
+        let _ = ($(&$x),*);
+    }};
+}
+
+// Only used with the `verbose-log` feature
+#[allow(unused_macros)]
+macro_rules! trace {
+    ($s:literal $(, $x:expr)* $(,)?) => {{
+        #[cfg(feature = "defmt")]
+        ::defmt::trace!($s $(, $x)*);
+        #[cfg(all(feature = "log", not(feature = "defmt")))]
+        ::log::trace!($s $(, $x)*);
+        #[cfg(not(any(feature = "defmt", feature = "log")))]
+        let _ = ($(&$x),*);
+    }};
+}
@@ -0,0 +1,266 @@
+use crate::dirty::DirtyLines;
+use crate::storage::Storage;
+use crate::{spec, Color, DisplaySpec, MemoryDisplay};
+use core::borrow::BorrowMut;
+use hal::blocking::spi::Write;
+use hal::digital::v2::OutputPin;
+
+/// An off-screen frame of the size of the display, stored in the same layout as the
+/// framebuffer of [`MemoryDisplay`], e.g. to prepare the screens of an application and switch
+/// between them with [`MemoryDisplay::blit_diff`], or to render the next frame of a double
+/// buffered UI.
+///
+/// It can be drawn to like the display with the `graphics` feature. At a few kilobytes it is
+/// best kept in a `static`, which is what the `const` constructor is for.
+///
+/// Like a display it is sized for a model, by default the one selected via the feature flag.
+pub struct SharpFrame<SPEC: DisplaySpec = spec::Selected> {
+    pub(crate) buffer: Storage<SPEC>,
+}
+
+impl<SPEC: DisplaySpec> SharpFrame<SPEC> {
+    /// A frame with all pixels [`Color::Off`], like the framebuffer of a new display.
+    pub const fn new() -> Self {
+        Self {
+            buffer: Storage::new(),
+        }
+    }
+
+    /// Set a single pixel. Pixels outside the frame are ignored.
+    pub fn set_pixel(&mut self, x: u32, y: u32, color: impl Into<Color>) {
+        let (x, y) = (x as usize, y as usize);
+        if x < SPEC::BUFFER_WIDTH && y < SPEC::BUFFER_HEIGHT {
+            self.buffer.set(x, y, color.into().is_on());
+        }
+    }
+
+    /// Set every pixel to `color`.
+    pub fn fill(&mut self, color: impl Into<Color>) {
+        self.buffer.fill(color.into().is_on());
+    }
+}
+
+impl<SPEC: DisplaySpec> Clone for SharpFrame<SPEC> {
+    fn clone(&self) -> Self {
+        Self {
+            buffer: self.buffer.clone(),
+        }
+    }
+}
+
+impl<SPEC: DisplaySpec> Default for SharpFrame<SPEC> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The framebuffer of a [`MemoryDisplay`] and the lines changed since the last flush.
+///
+/// A display owns one by default, making it a few kilobytes large. To place it in a specific
+/// memory region instead, e.g. a `static` with `#[link_section]` or one from `static_cell`,
+/// pass it to [`MemoryDisplay::new_with_buffer`], which is what the `const` constructor is for.
+///
+/// Its size is that of a line of the model `SPEC` times its lines, e.g.
+/// `FrameBuffer<spec::Ls013b7dh05>` for a display of that model.
+pub struct FrameBuffer<SPEC: DisplaySpec = spec::Selected> {
+    pub(crate) buffer: Storage<SPEC>,
+    pub(crate) touched: DirtyLines<SPEC>,
+}
+
+impl<SPEC: DisplaySpec> FrameBuffer<SPEC> {
+    /// A framebuffer with all pixels [`Color::Off`] and no dirty lines.
+    pub const fn new() -> Self {
+        Self {
+            buffer: Storage::new(),
+            touched: DirtyLines::new(),
+        }
+    }
+}
+
+impl<SPEC: DisplaySpec> Default for FrameBuffer<SPEC> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<SPI, CS, DISP, E, SPEC, BUF> MemoryDisplay<SPI, CS, DISP, SPEC, BUF>
+where
+    SPI: Write<u8, Error = E>,
+    CS: OutputPin,
+    DISP: OutputPin,
+    SPEC: DisplaySpec,
+    BUF: BorrowMut<FrameBuffer<SPEC>>,
+{
+    /// Copy `frame` into the framebuffer, marking only the lines which differ from it dirty, so
+    /// switching between similar frames (e.g. screens sharing a header) sends just the lines
+    /// which actually change. Lines which were already dirty stay dirty.
+    ///
+    /// Lines are compared as a whole, byte array against byte array, so this is cheap enough
+    /// to present every frame of a double buffered UI.
+    pub fn blit_diff(&mut self, frame: &SharpFrame<SPEC>) {
+        let FrameBuffer { buffer, touched } = self.frame.borrow_mut();
+        buffer.copy_changed(&frame.buffer, |y| touched.set(y, true));
+    }
+
+    /// Present the next frame of a double buffered UI, rendered into `frame` while the
+    /// previous one was shown. The same as [`MemoryDisplay::blit_diff`]: only the lines which
+    /// differ in any pixel are copied and marked dirty, so the next flush sends exactly those.
+    pub fn copy_from(&mut self, frame: &SharpFrame<SPEC>) {
+        self.blit_diff(frame);
+    }
+}
+
+#[cfg(all(test, not(feature = "minimal-flush")))]
+mod tests {
+    use super::*;
+    use crate::{testing, BUFFER_HEIGHT, BUFFER_WIDTH};
+    use std::{boxed::Box, vec::Vec};
+
+    /// A frame with the pixels of a screen: a header on the first lines and `seed` below it.
+    fn screen(seed: usize) -> Box<SharpFrame> {
+        let mut frame = Box::new(SharpFrame::new());
+        for y in 0..BUFFER_HEIGHT {
+            for x in 0..BUFFER_WIDTH {
+                let on = if y < 4 {
+                    x.is_multiple_of(3)
+                } else {
+                    (x * 7 + y * 13 + seed).is_multiple_of(11)
+                };
+                frame.set_pixel(x as u32, y as u32, on);
+            }
+        }
+        frame
+    }
+
+    /// The lines sent by the next flush.
+    fn flushed(display: &mut testing::TestDisplay) -> Vec<usize> {
+        let mut lines = Vec::new();
+        display
+            .flush_buffer_with_progress(|progress| lines.push(progress.line))
+            .unwrap();
+        lines
+    }
+
+    #[test]
+    fn identical_frames_mark_nothing() {
+        let (_, mut display) = testing::display();
+        let frame = screen(0);
+        display.blit_diff(&frame);
+        assert_eq!(display.dirty_line_count(), BUFFER_HEIGHT);
+        flushed(&mut display);
+        display.blit_diff(&frame);
+        assert!(!display.is_dirty());
+        assert!(flushed(&mut display).is_empty());
+    }
+
+    #[test]
+    fn only_differing_lines_are_marked() {
+        let (_, mut display) = testing::display();
+        let frame = screen(0);
+        display.blit_diff(&frame);
+        flushed(&mut display);
+
+        let mut changed = frame.clone();
+        changed.set_pixel(
+            BUFFER_WIDTH as u32 - 1,
+            9,
+            !frame.buffer.get(BUFFER_WIDTH - 1, 9),
+        );
+        display.blit_diff(&changed);
+        assert_eq!(display.dirty_line_count(), 1);
+        assert_eq!(flushed(&mut display), [9]);
+        assert_eq!(display.frame.buffer.line(9), changed.buffer.line(9));
+
+        // Lines marked before stay marked
+        display.mark_line_dirty(2);
+        display.blit_diff(&frame);
+        assert_eq!(flushed(&mut display), [2, 9]);
+    }
+
+    #[test]
+    fn lines_differing_in_a_single_pixel_are_marked() {
+        let (_, mut display) = testing::display();
+        let frame = screen(0);
+        display.copy_from(&frame);
+        flushed(&mut display);
+
+        // Every 7th line differs in one pixel, anywhere from its first to its last byte
+        let lines: Vec<_> = (0..BUFFER_HEIGHT).step_by(7).collect();
+        let mut changed = frame.clone();
+        for (i, &y) in lines.iter().enumerate() {
+            let x = (i * 37 + BUFFER_WIDTH - 1) % BUFFER_WIDTH;
+            changed.set_pixel(x as u32, y as u32, !frame.buffer.get(x, y));
+        }
+        display.copy_from(&changed);
+        assert_eq!(display.dirty_line_count(), lines.len());
+        assert_eq!(flushed(&mut display), lines);
+        for y in 0..BUFFER_HEIGHT {
+            assert_eq!(display.frame.buffer.line(y), changed.buffer.line(y));
+        }
+    }
+
+    /// Draw shapes reaching past every edge of `target`.
+    #[cfg(feature = "graphics")]
+    fn draw_shapes<D>(target: &mut D)
+    where
+        D: embedded_graphics::draw_target::DrawTarget<
+            Color = embedded_graphics::pixelcolor::BinaryColor,
+        >,
+        D::Error: core::fmt::Debug,
+    {
+        use embedded_graphics::pixelcolor::BinaryColor;
+        use embedded_graphics::prelude::*;
+        use embedded_graphics::primitives::{Circle, PrimitiveStyle, Rectangle};
+        let (width, height) = (BUFFER_WIDTH as i32, BUFFER_HEIGHT as i32);
+        Rectangle::new(Point::new(-5, 3), Size::new(width as u32 / 2, 9))
+            .into_styled(PrimitiveStyle::with_fill(BinaryColor::On))
+            .draw(target)
+            .unwrap();
+        Circle::new(Point::new(width - 20, height - 15), 30)
+            .into_styled(PrimitiveStyle::with_stroke(BinaryColor::On, 3))
+            .draw(target)
+            .unwrap();
+        Rectangle::new(Point::new(7, -4), Size::new(11, height as u32))
+            .into_styled(PrimitiveStyle::with_fill(BinaryColor::Off))
+            .draw(target)
+            .unwrap();
+    }
+
+    #[test]
+    #[cfg(feature = "graphics")]
+    fn frames_are_drawn_like_the_display() {
+        use embedded_graphics::geometry::{OriginDimensions, Size};
+        let mut frame = Box::new(SharpFrame::new());
+        let size = Size::new(BUFFER_WIDTH as u32, BUFFER_HEIGHT as u32);
+        assert_eq!(frame.size(), size);
+        draw_shapes(&mut *frame);
+
+        let (_, mut drawn) = testing::display();
+        draw_shapes(&mut drawn);
+        let (_, mut copied) = testing::display();
+        copied.copy_from(&frame);
+        assert_eq!(testing::snapshot(&copied), testing::snapshot(&drawn));
+        for y in 0..BUFFER_HEIGHT {
+            assert_eq!(
+                copied.dirty_extent(y).is_some(),
+                drawn.dirty_extent(y).is_some()
+            );
+        }
+    }
+
+    #[test]
+    fn different_frames_mark_every_line_but_the_header() {
+        let (_, mut display) = testing::display();
+        display.blit_diff(&screen(0));
+        flushed(&mut display);
+        let other = screen(5);
+        display.blit_diff(&other);
+        assert_eq!(
+            flushed(&mut display),
+            (4..BUFFER_HEIGHT).collect::<Vec<_>>()
+        );
+        for y in 0..BUFFER_HEIGHT {
+            assert_eq!(display.frame.buffer.line(y), other.buffer.line(y));
+        }
+    }
+}
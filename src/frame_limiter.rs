@@ -0,0 +1,214 @@
+use crate::{DisplaySpec, Error, FrameBuffer, MemoryDisplay};
+use core::borrow::BorrowMut;
+use hal::blocking::spi::Write;
+use hal::digital::v2::OutputPin;
+
+/// Limits the rate of flushes for code which changes the display far more often than anyone
+/// can see, e.g. a sensor task redrawing on every sample.
+///
+/// Flushes are requested with [`FrameLimiter::request_flush`] and performed by
+/// [`FrameLimiter::service`], called regularly from the main loop with a free-running timestamp
+/// in milliseconds. All requests within a period are coalesced into a single flush. Lines drawn
+/// in the meantime stay dirty until then, so nothing is lost.
+#[derive(Clone, Copy, Debug)]
+pub struct FrameLimiter {
+    period_ms: u32,
+    pending: bool,
+    last_flush: Option<u32>,
+    last_vcom: Option<u32>,
+}
+
+impl FrameLimiter {
+    /// Flush at most `max_hz` times per second.
+    pub fn new(max_hz: u32) -> Self {
+        Self {
+            period_ms: 1000 / max_hz.clamp(1, 1000),
+            pending: false,
+            last_flush: None,
+            last_vcom: None,
+        }
+    }
+
+    /// Request a flush, performed by the next [`FrameLimiter::service`] once the period since
+    /// the previous flush has passed.
+    pub fn request_flush(&mut self) {
+        self.pending = true;
+    }
+
+    /// Whether a flush has been requested and not yet performed.
+    pub fn is_pending(&self) -> bool {
+        self.pending
+    }
+
+    /// Flush `display` if a flush has been requested and the period since the previous flush
+    /// has passed, returning whether it did. `now_ms` may wrap around. A flush which fails stays
+    /// pending.
+    ///
+    /// If no flush has been sent for longer than the VCOM period of the panel (see
+    /// [`DisplaySpec::RECOMMENDED_VCOM_HZ`]), VCOM is toggled instead, so it stays on schedule while flushes
+    /// are suppressed or nothing is drawn.
+    pub fn service<SPI, CS, DISP, E, SPEC, BUF>(
+        &mut self,
+        now_ms: u32,
+        display: &mut MemoryDisplay<SPI, CS, DISP, SPEC, BUF>,
+    ) -> Result<bool, Error<E, CS::Error>>
+    where
+        SPI: Write<u8, Error = E>,
+        CS: OutputPin,
+        DISP: OutputPin,
+        SPEC: DisplaySpec,
+        BUF: BorrowMut<FrameBuffer<SPEC>>,
+    {
+        let elapsed = |since: Option<u32>, period: u32| {
+            since.is_none_or(|since| now_ms.wrapping_sub(since) >= period)
+        };
+
+        // Every flush toggles VCOM as well
+        if self.pending && elapsed(self.last_flush, self.period_ms) {
+            display.flush_buffer()?;
+            self.pending = false;
+            self.last_flush = Some(now_ms);
+            self.last_vcom = Some(now_ms);
+            Ok(true)
+        } else {
+            if elapsed(self.last_vcom, 1000 / SPEC::RECOMMENDED_VCOM_HZ as u32) {
+                display.display_mode()?;
+                self.last_vcom = Some(now_ms);
+            }
+            Ok(false)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{self, Event, Log};
+    use std::vec::Vec;
+
+    /// The first byte of every transaction logged, i.e. its command.
+    fn commands(log: &Log) -> Vec<u8> {
+        let events = log.events();
+        let starts = events.windows(2).filter_map(|pair| match pair {
+            [Event::Cs(true), Event::Write(bytes)] => Some(bytes[0]),
+            _ => None,
+        });
+        starts.collect()
+    }
+
+    fn is_flush(command: &u8) -> bool {
+        command & 0x80 != 0
+    }
+
+    #[test]
+    fn requests_within_a_period_are_coalesced() {
+        let (log, mut display) = testing::display();
+        let mut limiter = FrameLimiter::new(10);
+        limiter.request_flush();
+        assert!(limiter.service(0, &mut display).unwrap());
+        assert_eq!(commands(&log).len(), 1);
+
+        // 200 requests a second, every one drawing a line
+        for (i, now) in (5..100).step_by(5).enumerate() {
+            testing::draw_diagonal(&mut display, [i]);
+            limiter.request_flush();
+            assert!(!limiter.service(now, &mut display).unwrap(), "{now}");
+            assert!(limiter.is_pending());
+        }
+        assert!(commands(&log).is_empty());
+        assert!(display.is_dirty());
+
+        testing::draw_diagonal(&mut display, [25]);
+        assert!(limiter.service(100, &mut display).unwrap());
+        assert!(!limiter.is_pending());
+        // Every line is always dirty with `minimal-flush`
+        assert_eq!(display.is_dirty(), cfg!(feature = "minimal-flush"));
+        assert_eq!(commands(&log).len(), 1);
+        // Nothing requested since
+        assert!(!limiter.service(250, &mut display).unwrap());
+        assert!(commands(&log).iter().all(|command| !is_flush(command)));
+    }
+
+    // Every line sent as it is drawn
+    #[test]
+    #[cfg(not(any(
+        feature = "half-height",
+        feature = "half-width",
+        feature = "minimal-flush"
+    )))]
+    fn no_dirty_line_is_lost() {
+        let (log, mut display) = testing::display();
+        let mut limiter = FrameLimiter::new(20);
+        let (mut drawn, mut sent) = (Vec::new(), Vec::new());
+        for now in (0..300).chain([1000]) {
+            if now % 7 == 0 && now < 300 {
+                let y = now as usize / 7;
+                testing::draw_diagonal(&mut display, [y]);
+                drawn.push(y);
+                limiter.request_flush();
+            }
+            log.events();
+            if limiter.service(now, &mut display).unwrap() {
+                // The command, every line and the frame trailer
+                let bytes = log.bytes();
+                let lines = bytes[1..].chunks_exact(crate::WRITE_BUFFER_SIZE);
+                sent.extend(lines.map(|line| line[0].reverse_bits() as usize - 1));
+            }
+        }
+        assert_eq!(sent, drawn);
+        assert!(!display.is_dirty());
+    }
+
+    #[test]
+    fn vcom_is_maintained_while_flushes_are_suppressed() {
+        let (log, mut display) = testing::display();
+        let vcom_period = 1000 / crate::RECOMMENDED_VCOM_HZ as u32;
+        let mut limiter = FrameLimiter::new(1000 / vcom_period / 4 + 1);
+        limiter.service(0, &mut display).unwrap();
+        assert_eq!(commands(&log), [0x00]);
+
+        // Idle, only display mode commands keep VCOM toggling
+        let mut nops = Vec::new();
+        for now in (10..=3 * vcom_period).step_by(10) {
+            limiter.service(now, &mut display).unwrap();
+            if !commands(&log).is_empty() {
+                nops.push(now);
+            }
+        }
+        assert_eq!(nops, [1, 2, 3].map(|n| n * vcom_period));
+    }
+
+    #[test]
+    fn timestamps_wrap_around() {
+        let (log, mut display) = testing::display();
+        let mut limiter = FrameLimiter::new(10);
+        let start = u32::MAX - 50;
+        limiter.request_flush();
+        assert!(limiter.service(start, &mut display).unwrap());
+        limiter.request_flush();
+        assert!(!limiter
+            .service(start.wrapping_add(99), &mut display)
+            .unwrap());
+        assert!(limiter
+            .service(start.wrapping_add(100), &mut display)
+            .unwrap());
+        let flushes = commands(&log).into_iter().filter(is_flush).count();
+        assert_eq!(flushes, 2);
+    }
+
+    #[test]
+    fn failed_flushes_stay_pending() {
+        let (log, mut display) = testing::display();
+        let mut limiter = FrameLimiter::new(10);
+        testing::draw_diagonal(&mut display, [3]);
+        limiter.request_flush();
+        log.reject_writes_over(1);
+        assert!(limiter.service(0, &mut display).is_err());
+        assert!(limiter.is_pending() && display.is_dirty());
+
+        log.reject_writes_over(usize::MAX);
+        assert!(limiter.service(1, &mut display).unwrap());
+        assert!(!limiter.is_pending());
+        assert_eq!(display.is_dirty(), cfg!(feature = "minimal-flush"));
+    }
+}
@@ -0,0 +1,477 @@
+//! Drawing glyphs streamed from external storage, e.g. large CJK fonts in SPI flash.
+//! Only available with the `glyphs` feature.
+use crate::{Color, DisplaySpec, FrameBuffer, MemoryDisplay};
+use core::borrow::BorrowMut;
+use embedded_graphics::prelude::Point;
+use embedded_io::{Read, ReadExactError, Seek, SeekFrom};
+use hal::blocking::spi::Write;
+use hal::digital::v2::OutputPin;
+
+/// Longest glyph row supported, in bytes.
+const MAX_ROW_BYTES: usize = 32;
+
+/// Location and size of a glyph bitmap in storage.
+///
+/// A glyph is stored row by row, every row padded to whole bytes, with the leftmost pixel in the
+/// most significant bit of the first byte. Set bits are drawn, clear bits are left untouched.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct GlyphMetrics {
+    /// Offset of the bitmap from the start of the storage.
+    pub offset: u32,
+    pub width: u16,
+    pub height: u16,
+}
+
+impl GlyphMetrics {
+    fn row_bytes(&self) -> usize {
+        (self.width as usize).div_ceil(8)
+    }
+
+    fn len(&self) -> usize {
+        self.row_bytes() * self.height as usize
+    }
+}
+
+/// An error while drawing a glyph.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GlyphError<E> {
+    /// The reader returned an error.
+    Io(E),
+    /// The storage ended within the glyph bitmap.
+    UnexpectedEof,
+    /// The glyph is wider than 256 pixels.
+    TooWide,
+}
+
+impl<E> From<ReadExactError<E>> for GlyphError<E> {
+    fn from(e: ReadExactError<E>) -> Self {
+        match e {
+            ReadExactError::UnexpectedEof => GlyphError::UnexpectedEof,
+            ReadExactError::Other(e) => GlyphError::Io(e),
+        }
+    }
+}
+
+/// A slot of a [`GlyphCache`], holding a bitmap of at most `N` bytes.
+#[derive(Clone, Copy)]
+pub struct GlyphSlot<const N: usize> {
+    offset: Option<u32>,
+    last_used: u32,
+    data: [u8; N],
+}
+
+impl<const N: usize> GlyphSlot<N> {
+    pub const EMPTY: Self = Self {
+        offset: None,
+        last_used: 0,
+        data: [0; N],
+    };
+}
+
+/// A least recently used cache of glyph bitmaps in caller-provided slots, so common characters
+/// are not read from storage every time they are drawn. Glyphs larger than a slot bypass the
+/// cache.
+pub struct GlyphCache<'a, const N: usize> {
+    slots: &'a mut [GlyphSlot<N>],
+    clock: u32,
+    hits: u32,
+    misses: u32,
+}
+
+impl<'a, const N: usize> GlyphCache<'a, N> {
+    /// Create a cache using the given slots, e.g. `&mut [GlyphSlot::<72>::EMPTY; 32]`.
+    pub fn new(slots: &'a mut [GlyphSlot<N>]) -> Self {
+        slots.fill(GlyphSlot::EMPTY);
+        Self {
+            slots,
+            clock: 0,
+            hits: 0,
+            misses: 0,
+        }
+    }
+
+    /// Number of glyphs drawn from the cache.
+    pub fn hits(&self) -> u32 {
+        self.hits
+    }
+
+    /// Number of glyphs read from storage, including those too large for the cache.
+    pub fn misses(&self) -> u32 {
+        self.misses
+    }
+
+    /// Look up a glyph, reading it into the least recently used slot if it is not cached.
+    fn get<R: Read + Seek>(
+        &mut self,
+        metrics: &GlyphMetrics,
+        reader: &mut R,
+    ) -> Result<Option<&[u8]>, GlyphError<R::Error>> {
+        let len = metrics.len();
+        if len > N || self.slots.is_empty() {
+            self.misses += 1;
+            return Ok(None);
+        }
+        self.clock = self.clock.wrapping_add(1);
+
+        let index = match self
+            .slots
+            .iter()
+            .position(|slot| slot.offset == Some(metrics.offset))
+        {
+            Some(index) => {
+                self.hits += 1;
+                index
+            }
+            None => {
+                self.misses += 1;
+                // Empty slots have never been used and are picked first
+                let (index, slot) = self
+                    .slots
+                    .iter_mut()
+                    .enumerate()
+                    .max_by_key(|(_, slot)| match slot.offset {
+                        Some(_) => self.clock.wrapping_sub(slot.last_used),
+                        None => u32::MAX,
+                    })
+                    .unwrap();
+                slot.offset = None;
+                reader
+                    .seek(SeekFrom::Start(metrics.offset.into()))
+                    .map_err(GlyphError::Io)?;
+                reader.read_exact(&mut slot.data[..len])?;
+                slot.offset = Some(metrics.offset);
+                index
+            }
+        };
+
+        let slot = &mut self.slots[index];
+        slot.last_used = self.clock;
+        Ok(Some(&slot.data[..len]))
+    }
+}
+
+impl<SPI, CS, DISP, E, SPEC, BUF> MemoryDisplay<SPI, CS, DISP, SPEC, BUF>
+where
+    SPI: Write<u8, Error = E>,
+    CS: OutputPin,
+    DISP: OutputPin,
+    SPEC: DisplaySpec,
+    BUF: BorrowMut<FrameBuffer<SPEC>>,
+{
+    /// Draw the glyph described by `metrics` from `reader` with its top left corner at
+    /// `origin`. Set bits of the glyph are drawn with `color`, the rest of the glyph area is left
+    /// untouched. The glyph is clipped to the display.
+    ///
+    /// The bitmap is streamed row by row, so glyphs of any height can be drawn without a buffer.
+    pub fn draw_glyph<R: Read + Seek>(
+        &mut self,
+        origin: Point,
+        metrics: &GlyphMetrics,
+        color: Color,
+        mut reader: R,
+    ) -> Result<(), GlyphError<R::Error>> {
+        let row_bytes = metrics.row_bytes();
+        if row_bytes > MAX_ROW_BYTES {
+            return Err(GlyphError::TooWide);
+        } else if row_bytes == 0 {
+            return Ok(());
+        }
+
+        reader
+            .seek(SeekFrom::Start(metrics.offset.into()))
+            .map_err(GlyphError::Io)?;
+        let mut bits = [0; MAX_ROW_BYTES];
+        for row in 0..metrics.height as i32 {
+            reader.read_exact(&mut bits[..row_bytes])?;
+            self.blit_glyph_row(origin, row, &bits[..row_bytes], metrics.width, color);
+        }
+        Ok(())
+    }
+
+    /// Same as [`MemoryDisplay::draw_glyph`], but the glyph is taken from `cache` if it was
+    /// drawn recently and added to it otherwise.
+    pub fn draw_cached_glyph<R: Read + Seek, const N: usize>(
+        &mut self,
+        origin: Point,
+        metrics: &GlyphMetrics,
+        color: Color,
+        mut reader: R,
+        cache: &mut GlyphCache<'_, N>,
+    ) -> Result<(), GlyphError<R::Error>> {
+        let row_bytes = metrics.row_bytes();
+        if row_bytes > MAX_ROW_BYTES {
+            return Err(GlyphError::TooWide);
+        } else if row_bytes == 0 {
+            return Ok(());
+        }
+
+        match cache.get(metrics, &mut reader)? {
+            Some(bitmap) => {
+                for (row, bits) in bitmap.chunks_exact(row_bytes).enumerate() {
+                    self.blit_glyph_row(origin, row as i32, bits, metrics.width, color);
+                }
+                Ok(())
+            }
+            None => self.draw_glyph(origin, metrics, color, reader),
+        }
+    }
+
+    /// Draw the set bits of row `row` of a glyph `width` pixels wide at `origin`.
+    fn blit_glyph_row(&mut self, origin: Point, row: i32, bits: &[u8], width: u16, color: Color) {
+        let (x, y, width) = (origin.x, origin.y + row, width as usize);
+        if !self.is_untransformed() || self.clip.is_some() {
+            // A row of the glyph isn't a row of the framebuffer or may be clipped, so draw pixel
+            // by pixel
+            for i in (0..width).filter(|i| bits[i / 8] & (0x80 >> (i % 8)) != 0) {
+                if let (Ok(x), Ok(y)) = ((x + i as i32).try_into(), y.try_into()) {
+                    let _ = self.set_pixel(x, y, color);
+                }
+            }
+            return;
+        }
+        if y < 0 || y >= SPEC::BUFFER_HEIGHT as i32 {
+            return;
+        }
+        // Source byte `i` covers pixels from `x + 8 * i`, which span destination bytes `first + i`
+        // and `first + i + 1`
+        let (first, shift) = (x.div_euclid(8), x.rem_euclid(8) as u32);
+
+        for (i, &byte) in bits.iter().enumerate() {
+            let valid = (width - 8 * i).min(8);
+            // Leftmost pixel in the least significant bit, like the framebuffer
+            let byte = (byte.reverse_bits() as u16 & ((1 << valid) - 1)) << shift;
+            for (part, dst) in [
+                (byte as u8, first + i as i32),
+                ((byte >> 8) as u8, first + i as i32 + 1),
+            ] {
+                if part == 0 || dst < 0 || dst as usize >= SPEC::LINE_BYTES {
+                    continue;
+                }
+                // Pixels right of the display in the last byte are padding
+                let pixels = SPEC::BUFFER_WIDTH as i32 - dst * 8;
+                let part = if pixels < 8 {
+                    part & ((1u16 << pixels) - 1) as u8
+                } else {
+                    part
+                };
+                self.frame.borrow_mut().buffer.set_bits(
+                    y as usize,
+                    dst as usize,
+                    part,
+                    color.is_on(),
+                );
+            }
+        }
+        let end = (x + width as i32).clamp(0, SPEC::BUFFER_WIDTH as i32) as usize;
+        let start = (x.max(0) as usize).min(end);
+        self.frame
+            .borrow_mut()
+            .touched
+            .mark_columns(y as usize, start..end);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{self, TestDisplay};
+    use core::convert::Infallible;
+    use embedded_io::ErrorType;
+    use std::{vec, vec::Vec};
+
+    /// Storage in memory, counting the glyphs read from it.
+    struct Flash<'a> {
+        data: &'a [u8],
+        position: usize,
+        seeks: usize,
+    }
+
+    impl<'a> Flash<'a> {
+        fn new(data: &'a [u8]) -> Self {
+            Self {
+                data,
+                position: 0,
+                seeks: 0,
+            }
+        }
+    }
+
+    impl ErrorType for Flash<'_> {
+        type Error = Infallible;
+    }
+
+    impl Read for Flash<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize, Infallible> {
+            let mut rest = self.data.get(self.position..).unwrap_or_default();
+            let len = rest.read(buf)?;
+            self.position += len;
+            Ok(len)
+        }
+    }
+
+    impl Seek for Flash<'_> {
+        fn seek(&mut self, position: SeekFrom) -> Result<u64, Infallible> {
+            let SeekFrom::Start(position) = position else {
+                unimplemented!()
+            };
+            self.seeks += 1;
+            self.position = position as usize;
+            Ok(position)
+        }
+    }
+
+    // Two glyphs of 10x3 pixels, from offset 1 and 7, and one of 3x2 pixels at 13
+    const FONT: [u8; 15] = [
+        0xFF, // Not part of a glyph
+        0b1000_0000,
+        0b0100_0000,
+        0b1111_1111,
+        0b1100_0000,
+        0b0101_0101,
+        0b0000_0000,
+        0b0000_0001,
+        0b1000_0000,
+        0b1000_0000,
+        0b0000_0000,
+        0b0000_0000,
+        0b0100_0000,
+        0b1010_0000,
+        0b0100_0000,
+    ];
+    const A: GlyphMetrics = GlyphMetrics {
+        offset: 1,
+        width: 10,
+        height: 3,
+    };
+    const B: GlyphMetrics = GlyphMetrics { offset: 7, ..A };
+    const C: GlyphMetrics = GlyphMetrics {
+        offset: 13,
+        width: 3,
+        height: 2,
+    };
+
+    /// The pixels of `display` with `metrics` drawn at `origin` pixel by pixel.
+    fn drawn(
+        display: &TestDisplay,
+        origin: Point,
+        metrics: &GlyphMetrics,
+        on: bool,
+    ) -> Vec<Vec<bool>> {
+        let mut pixels = testing::snapshot(display);
+        let bitmap = &FONT[metrics.offset as usize..][..metrics.len()];
+        for (row, bits) in bitmap.chunks(metrics.row_bytes()).enumerate() {
+            for column in (0..metrics.width as usize).filter(|i| bits[i / 8] & 0x80 >> (i % 8) != 0)
+            {
+                let (x, y) = (origin.x + column as i32, origin.y + row as i32);
+                if let Some(pixel) =
+                    (pixels.get_mut(y as usize)).and_then(|line| line.get_mut(x as usize))
+                {
+                    *pixel = on;
+                }
+            }
+        }
+        pixels
+    }
+
+    #[test]
+    fn glyphs_are_drawn_at_any_origin() {
+        let (width, height) = testing::display().1.drawable_size();
+        let (width, height) = (width as i32, height as i32);
+        let origins = [
+            (0, 0),
+            (8, 2),
+            (5, 3),
+            (13, 7),
+            (-3, 1),
+            (-11, 1),
+            (width - 4, 5),
+            (2, -1),
+            (4, height - 2),
+        ];
+        for (x, y) in origins {
+            for (metrics, color) in [(A, Color::On), (B, Color::Off), (C, Color::On)] {
+                let (_, mut display) = testing::display();
+                testing::draw_noise(&mut display, (x * 31 + y) as u32 | 1);
+                let origin = Point::new(x, y);
+                let expected = drawn(&display, origin, &metrics, color.is_on());
+                display
+                    .draw_glyph(origin, &metrics, color, Flash::new(&FONT))
+                    .unwrap();
+                assert_eq!(
+                    testing::snapshot(&display),
+                    expected,
+                    "{metrics:?} at {origin:?}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    #[cfg(not(feature = "minimal-flush"))]
+    fn only_the_rows_of_a_glyph_are_dirty() {
+        let (_, mut display) = testing::display();
+        display
+            .draw_glyph(Point::new(5, 3), &A, Color::On, Flash::new(&FONT))
+            .unwrap();
+        assert!(display.dirty_line_count() <= 3);
+        assert!(display.is_dirty());
+    }
+
+    #[test]
+    fn recently_used_glyphs_are_cached() {
+        let mut slots = [GlyphSlot::<6>::EMPTY; 2];
+        let mut cache = GlyphCache::new(&mut slots);
+        let mut flash = Flash::new(&FONT);
+        let (_, mut display) = testing::display();
+        let (_, mut uncached) = testing::display();
+        // C evicts B, used before A, and B then evicts C
+        let mut seeks = vec![];
+        for (i, metrics) in [A, B, A, C, A, B, A].iter().enumerate() {
+            let origin = Point::new(11 * i as i32, 2);
+            display
+                .draw_cached_glyph(origin, metrics, Color::On, &mut flash, &mut cache)
+                .unwrap();
+            uncached
+                .draw_glyph(origin, metrics, Color::On, Flash::new(&FONT))
+                .unwrap();
+            seeks.push(flash.seeks);
+        }
+        assert_eq!(seeks, [1, 2, 2, 3, 3, 4, 4]);
+        assert_eq!((cache.hits(), cache.misses()), (3, 4));
+        assert_eq!(testing::snapshot(&display), testing::snapshot(&uncached));
+    }
+
+    #[test]
+    fn glyphs_larger_than_a_slot_bypass_the_cache() {
+        let mut slots = [GlyphSlot::<4>::EMPTY; 1];
+        let mut cache = GlyphCache::new(&mut slots);
+        let mut flash = Flash::new(&FONT);
+        let (_, mut display) = testing::display();
+        for _ in 0..2 {
+            display
+                .draw_cached_glyph(Point::new(1, 1), &A, Color::On, &mut flash, &mut cache)
+                .unwrap();
+        }
+        assert_eq!(flash.seeks, 2);
+        assert_eq!((cache.hits(), cache.misses()), (0, 2));
+        let (_, mut expected) = testing::display();
+        expected
+            .draw_glyph(Point::new(1, 1), &A, Color::On, Flash::new(&FONT))
+            .unwrap();
+        assert_eq!(testing::snapshot(&display), testing::snapshot(&expected));
+    }
+
+    #[test]
+    fn broken_glyphs_are_rejected() {
+        let (_, mut display) = testing::display();
+        let short = GlyphMetrics { offset: 13, ..A };
+        let result = display.draw_glyph(Point::zero(), &short, Color::On, Flash::new(&FONT));
+        assert_eq!(result, Err(GlyphError::UnexpectedEof));
+        let wide = GlyphMetrics { width: 257, ..A };
+        let result = display.draw_glyph(Point::zero(), &wide, Color::On, Flash::new(&FONT));
+        assert_eq!(result, Err(GlyphError::TooWide));
+        let empty = GlyphMetrics { width: 0, ..A };
+        let result = display.draw_glyph(Point::zero(), &empty, Color::On, Flash::new(&FONT));
+        assert_eq!(result, Ok(()));
+    }
+}
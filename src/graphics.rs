@@ -0,0 +1,924 @@
+//! The [`embedded_graphics`] integration, only available with the `graphics` feature.
+use crate::{
+    mirror_position, Color, DisplaySpec, Error, FrameBuffer, MemoryDisplay, Rotation, SharpFrame,
+};
+use bitvec::prelude::*;
+use core::borrow::BorrowMut;
+use core::convert::Infallible;
+use core::ops::Range;
+use embedded_graphics::draw_target::DrawTarget;
+use embedded_graphics::pixelcolor::BinaryColor;
+use embedded_graphics::prelude::{Dimensions, OriginDimensions, Point, Size};
+use embedded_graphics::primitives::Rectangle;
+use embedded_graphics::Pixel;
+use hal::blocking::spi::Write;
+use hal::digital::v2::OutputPin;
+
+impl From<BinaryColor> for Color {
+    fn from(color: BinaryColor) -> Self {
+        color.is_on().into()
+    }
+}
+
+impl From<Color> for BinaryColor {
+    fn from(color: Color) -> Self {
+        bool::from(color).into()
+    }
+}
+
+/// Reading back pixels, e.g. the framebuffer of [`MemoryDisplay`] or a [`SharpFrame`].
+///
+/// This is the `GetPixel` trait of later `embedded-graphics` releases.
+pub trait GetPixel {
+    type Color;
+
+    /// The color of the pixel at `point`, `None` outside the drawable area.
+    fn pixel(&self, point: Point) -> Option<Self::Color>;
+}
+
+/// Coordinates of `point` in the framebuffer, `None` outside it.
+fn buffer_coordinates<SPEC: DisplaySpec>(point: Point) -> Option<(usize, usize)> {
+    let (x, y) = (
+        usize::try_from(point.x).ok()?,
+        usize::try_from(point.y).ok()?,
+    );
+    (x < SPEC::BUFFER_WIDTH && y < SPEC::BUFFER_HEIGHT).then_some((x, y))
+}
+
+impl<SPI, CS, DISP, SPEC: DisplaySpec, BUF: BorrowMut<FrameBuffer<SPEC>>> GetPixel
+    for MemoryDisplay<SPI, CS, DISP, SPEC, BUF>
+{
+    type Color = BinaryColor;
+
+    fn pixel(&self, point: Point) -> Option<BinaryColor> {
+        let (x, y) = (u32::try_from(point.x).ok()?, u32::try_from(point.y).ok()?);
+        self.get_pixel(x, y).map(Into::into)
+    }
+}
+
+impl<SPEC: DisplaySpec> GetPixel for SharpFrame<SPEC> {
+    type Color = BinaryColor;
+
+    fn pixel(&self, point: Point) -> Option<BinaryColor> {
+        let (x, y) = buffer_coordinates::<SPEC>(point)?;
+        Some(self.buffer.get(x, y).into())
+    }
+}
+
+impl<SPI, CS, DISP, SPEC: DisplaySpec, BUF: BorrowMut<FrameBuffer<SPEC>>> OriginDimensions
+    for MemoryDisplay<SPI, CS, DISP, SPEC, BUF>
+{
+    /// The size of the drawable area, with width and height swapped by [`Rotation::Deg90`] and
+    /// [`Rotation::Deg270`].
+    fn size(&self) -> Size {
+        let (width, height) = self.drawable_size();
+        Size::new(width as u32, height as u32)
+    }
+}
+
+impl<SPI, CS, DISP, E, SPEC, BUF> DrawTarget for MemoryDisplay<SPI, CS, DISP, SPEC, BUF>
+where
+    SPI: Write<u8, Error = E>,
+    CS: OutputPin,
+    DISP: OutputPin,
+    SPEC: DisplaySpec,
+    BUF: BorrowMut<FrameBuffer<SPEC>>,
+{
+    type Color = BinaryColor;
+    type Error = E;
+
+    fn draw_iter<T>(&mut self, item_pixels: T) -> Result<(), E>
+    where
+        T: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        let (x, y) = self.clip_area();
+        for Pixel(coord, color) in item_pixels {
+            if coord.x < x.start as i32
+                || coord.x >= x.end as i32
+                || coord.y < y.start as i32
+                || coord.y >= y.end as i32
+            {
+                // Ignore attempts to draw outside of the clip, continue to next pixel
+                continue;
+            } else {
+                unsafe { self.set_pixel_unchecked(coord.x as u32, coord.y as u32, color) };
+            }
+        }
+        Ok(())
+    }
+
+    fn fill_contiguous<I>(&mut self, area: &Rectangle, colors: I) -> Result<(), E>
+    where
+        I: IntoIterator<Item = Self::Color>,
+    {
+        let visible = area.intersection(&self.clip_box());
+        if visible.is_zero_sized() {
+            return Ok(());
+        }
+        // The colors cover the whole area row by row, of which only the visible part is set:
+        // `len` pixels from `skip` pixels into every visible row
+        let width = area.size.width as usize;
+        let skip = (visible.top_left.x - area.top_left.x) as usize;
+        let len = visible.size.width as usize;
+        let rows_above = (visible.top_left.y - area.top_left.y) as usize;
+        let x = visible.top_left.x as usize..visible.top_left.x as usize + len;
+
+        let mut colors = colors.into_iter();
+        let advance = |colors: &mut I::IntoIter, n: usize| {
+            if n > 0 {
+                colors.nth(n - 1);
+            }
+        };
+        advance(&mut colors, rows_above * width);
+
+        for y in visible.rows() {
+            advance(&mut colors, skip);
+            let run = colors.by_ref().take(len).map(BinaryColor::is_on);
+            self.write_run(x.clone(), y as usize, run);
+            advance(&mut colors, width - skip - len);
+        }
+        Ok(())
+    }
+
+    fn fill_solid(&mut self, area: &Rectangle, color: Self::Color) -> Result<(), E> {
+        let area = area.intersection(&self.clip_box());
+        if let Some(bottom_right) = area.bottom_right() {
+            let x = area.top_left.x as usize..bottom_right.x as usize + 1;
+            let y = area.top_left.y as usize..bottom_right.y as usize + 1;
+            self.fill_rect(x, y, color.is_on());
+        }
+        Ok(())
+    }
+
+    /// Set every pixel of the framebuffer to `color`, marking the lines which change dirty
+    /// like [`MemoryDisplay::clear_buffer`]. The clear state and the clip don't apply and
+    /// nothing is sent, see [`MemoryDisplay::clear_to`] for clearing the panel as well.
+    fn clear(&mut self, color: Self::Color) -> Result<(), E> {
+        let FrameBuffer { buffer, touched } = self.frame.borrow_mut();
+        buffer.fill_changed(color.is_on(), |y| touched.set(y, true));
+        Ok(())
+    }
+}
+
+impl<SPEC: DisplaySpec> OriginDimensions for SharpFrame<SPEC> {
+    fn size(&self) -> Size {
+        Size::new(SPEC::BUFFER_WIDTH as u32, SPEC::BUFFER_HEIGHT as u32)
+    }
+}
+
+impl<SPEC: DisplaySpec> DrawTarget for SharpFrame<SPEC> {
+    type Color = BinaryColor;
+    type Error = Infallible;
+
+    fn draw_iter<T>(&mut self, item_pixels: T) -> Result<(), Infallible>
+    where
+        T: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        for Pixel(coord, color) in item_pixels {
+            if let (Ok(x), Ok(y)) = (coord.x.try_into(), coord.y.try_into()) {
+                self.set_pixel(x, y, color);
+            }
+        }
+        Ok(())
+    }
+
+    fn fill_solid(&mut self, area: &Rectangle, color: Self::Color) -> Result<(), Infallible> {
+        let area = area.intersection(&self.bounding_box());
+        if let Some(bottom_right) = area.bottom_right() {
+            let x = area.top_left.x as usize..bottom_right.x as usize + 1;
+            let y = area.top_left.y as usize..bottom_right.y as usize + 1;
+            self.buffer.fill_rect(x, y, color.is_on());
+        }
+        Ok(())
+    }
+}
+
+impl<SPI, CS, DISP, E, SPEC, BUF> MemoryDisplay<SPI, CS, DISP, SPEC, BUF>
+where
+    SPI: Write<u8, Error = E>,
+    CS: OutputPin,
+    DISP: OutputPin,
+    SPEC: DisplaySpec,
+    BUF: BorrowMut<FrameBuffer<SPEC>>,
+{
+    /// Restrict drawing to `area` of the drawable area, e.g. the pane of a widget, or lift the
+    /// restriction with `None`. Pixels outside it are discarded by [`MemoryDisplay::set_pixel`]
+    /// and everything drawn through `DrawTarget`, and don't mark their lines dirty.
+    ///
+    /// `area` is clipped to the drawable area, so an area outside it discards every pixel. The
+    /// clip doesn't move with a later rotation or mirroring. Clearing the whole framebuffer,
+    /// with [`MemoryDisplay::clear_buffer`] or `DrawTarget::clear`, and the operations taking a
+    /// region of their own, e.g. [`MemoryDisplay::scroll_region_up`], ignore it.
+    pub fn set_clip(&mut self, area: Option<Rectangle>) {
+        self.clip = area.map(|area| {
+            let area = area.intersection(&self.bounding_box());
+            match area.bottom_right() {
+                Some(bottom_right) => (
+                    area.top_left.x as usize..bottom_right.x as usize + 1,
+                    area.top_left.y as usize..bottom_right.y as usize + 1,
+                ),
+                None => (0..0, 0..0),
+            }
+        });
+    }
+
+    /// The area drawing is restricted to, see [`MemoryDisplay::set_clip`].
+    pub fn clip(&self) -> Option<Rectangle> {
+        self.clip.is_some().then(|| self.clip_box())
+    }
+
+    /// The part of the drawable area drawing is restricted to, all of it without a clip.
+    fn clip_box(&self) -> Rectangle {
+        let (x, y) = self.clip_area();
+        Rectangle::new(
+            Point::new(x.start as i32, y.start as i32),
+            Size::new(x.len() as u32, y.len() as u32),
+        )
+    }
+
+    /// Scroll the contents of `region` up by `n` pixels, filling the vacated rows at the bottom
+    /// of the region with `fill`. Pixels outside the region are left untouched and only the
+    /// lines covered by the region are marked dirty. The region is clipped to the display.
+    pub fn scroll_region_up(&mut self, region: Rectangle, n: u32, fill: BinaryColor) {
+        self.scroll(region, n, fill, true, true);
+    }
+
+    /// Scroll the contents of `region` down by `n` pixels, filling the vacated rows at the top
+    /// of the region with `fill`. See [`MemoryDisplay::scroll_region_up`].
+    pub fn scroll_region_down(&mut self, region: Rectangle, n: u32, fill: BinaryColor) {
+        self.scroll(region, n, fill, true, false);
+    }
+
+    /// Invert the pixels of `region`, clipped to the display, and mark its lines dirty.
+    /// Inverting the same region twice restores it.
+    pub fn invert_region(&mut self, region: Rectangle) {
+        if let Some((x, y)) = self.physical_region(region) {
+            self.frame.borrow_mut().buffer.invert_rect(x, y.clone());
+            self.mark_lines_dirty(y);
+        }
+    }
+
+    /// Draw a bitmap of 1 bit per pixel with its top left corner at `top_left`, clipped like
+    /// [`DrawTarget::draw_iter`], marking the lines which change dirty.
+    ///
+    /// Every row of the bitmap is `width` pixels in `width.div_ceil(8)` bytes, laid out like
+    /// the framebuffer: pixel `x` in bit `x % 8` of byte `x / 8`, a set bit being
+    /// [`BinaryColor::On`]. The bitmap has as many rows as fit into `data` completely. It may
+    /// start at any column, its rows are shifted into place.
+    pub fn blit(&mut self, top_left: Point, width: usize, data: &[u8]) {
+        self.draw_packed_image(top_left, width as u32, u32::MAX, data, false);
+    }
+
+    /// Draw an image of 1 bit per pixel, `width` by `height` pixels, like
+    /// [`MemoryDisplay::blit`]. This is faster than drawing it as an
+    /// [`Image`](embedded_graphics::image::Image), which turns every pixel into a color first;
+    /// the data of an [`ImageRaw<BinaryColor>`](embedded_graphics::image::ImageRaw) is drawn
+    /// the same with `msb_first`:
+    ///
+    /// ```ignore
+    /// display.draw_packed_image(Point::new(8, 8), 32, 32, &LOGO, true);
+    /// ```
+    ///
+    /// Every row is `width.div_ceil(8)` bytes, with the leftmost pixel in the most significant
+    /// bit of its byte if `msb_first`, else in the least significant one like the framebuffer.
+    /// Rows past the end of `data` aren't drawn.
+    pub fn draw_packed_image(
+        &mut self,
+        top_left: Point,
+        width: u32,
+        height: u32,
+        data: &[u8],
+        msb_first: bool,
+    ) {
+        if width == 0 {
+            return;
+        }
+        let stride = (width as usize).div_ceil(8);
+        let rows = height.min((data.len() / stride) as u32);
+        let visible =
+            Rectangle::new(top_left, Size::new(width, rows)).intersection(&self.clip_box());
+        if visible.is_zero_sized() {
+            return;
+        }
+        let skip = (visible.top_left.x - top_left.x) as usize;
+        let len = visible.size.width as usize;
+        let x = visible.top_left.x as usize..visible.top_left.x as usize + len;
+        for y in visible.rows() {
+            let row = &data[(y - top_left.y) as usize * stride..][..stride];
+            if msb_first {
+                let pixels = row.view_bits::<Msb0>()[skip..skip + len].iter().by_vals();
+                self.write_run(x.clone(), y as usize, pixels);
+            } else {
+                let pixels = row.view_bits::<Lsb0>()[skip..skip + len].iter().by_vals();
+                self.write_run(x.clone(), y as usize, pixels);
+            }
+        }
+    }
+
+    /// Set the pixels `x` of row `y` of the drawable area to `pixels` in turn, marking the
+    /// lines which change dirty.
+    fn write_run(&mut self, x: Range<usize>, y: usize, pixels: impl Iterator<Item = bool>) {
+        let (rotation, mirror) = (self.rotation, self.mirror);
+        let FrameBuffer { buffer, touched } = self.frame.borrow_mut();
+        if rotation == Rotation::Deg0 && !mirror.0 {
+            // The run stays a run of a single line, only the line may be mirrored
+            let (_, y) = mirror_position::<SPEC>(mirror, (0, y));
+            if buffer.set_run(x.clone(), y, pixels) {
+                touched.mark_columns(y, x);
+            }
+        } else {
+            for (x, on) in x.zip(pixels) {
+                let (x, y) = mirror_position::<SPEC>(mirror, rotation.to_physical::<SPEC>(x, y));
+                if buffer.get(x, y) != on {
+                    buffer.set(x, y, on);
+                    touched.mark_columns(y, x..x + 1);
+                }
+            }
+        }
+    }
+
+    /// Flush only the lines intersecting `area`, clipped to the display, whether they are
+    /// dirty or not. Other dirty lines stay dirty for the next flush. VCOM is toggled once, like
+    /// with [`MemoryDisplay::flush_buffer`], while an area outside the display sends nothing.
+    /// Lines drawn to in an open frame are held back, see [`MemoryDisplay::begin_frame`].
+    ///
+    /// With the `minimal-flush` feature every flush sends the whole frame, this one included.
+    pub fn flush_region(&mut self, area: Rectangle) -> Result<(), Error<E, CS::Error>> {
+        let Some((_, lines)) = self.physical_region(area) else {
+            return Ok(());
+        };
+        // The lines drawn to in an open frame are held back, those of the region included
+        let held = self.is_frame_open().then(|| self.frame.borrow().touched);
+        self.presenting(|display| {
+            let frame = display.frame.borrow_mut();
+            let others = frame.touched;
+            frame.touched.fill(false);
+            frame.touched.mark(lines.clone());
+            if let Some(held) = held {
+                for y in held.iter() {
+                    frame.touched.set(y, false);
+                }
+            }
+
+            let result = display.flush_buffer();
+            // The lines of the region which a failed flush didn't send are still dirty
+            let touched = &mut display.frame.borrow_mut().touched;
+            for y in others.iter().filter(|y| !lines.contains(y)) {
+                touched.merge(y, &others);
+            }
+            result
+        })
+    }
+
+    /// Mark the lines intersecting `area` to be sent by the next flush, clipped to the display
+    /// like drawing. Being line based, whole lines are sent, not just `area`.
+    pub fn mark_region_dirty(&mut self, area: Rectangle) {
+        if let Some((_, lines)) = self.physical_region(area) {
+            self.mark_lines_dirty(lines);
+        }
+    }
+
+    /// Columns and lines of the framebuffer covered by `region` of the drawable area, clipped
+    /// to it, `None` if nothing is left.
+    pub(crate) fn physical_region(
+        &self,
+        region: Rectangle,
+    ) -> Option<(Range<usize>, Range<usize>)> {
+        let region = region.intersection(&self.bounding_box());
+        let bottom_right = region.bottom_right()?;
+        let x = region.top_left.x as usize..bottom_right.x as usize + 1;
+        let y = region.top_left.y as usize..bottom_right.y as usize + 1;
+        Some(self.physical_rect(x, y))
+    }
+
+    /// Scroll `region` of the drawable area by `n` pixels vertically (towards the top if
+    /// `backwards`) or horizontally (towards the left if `backwards`).
+    fn scroll(
+        &mut self,
+        region: Rectangle,
+        n: u32,
+        fill: BinaryColor,
+        vertical: bool,
+        backwards: bool,
+    ) {
+        let Some((x, y)) = self.physical_region(region) else {
+            return;
+        };
+        // The direction on the panel, rotating the coordinates rotates the axes as well
+        let (vertical, backwards) = match self.rotation {
+            Rotation::Deg0 => (vertical, backwards),
+            Rotation::Deg90 => (!vertical, backwards != vertical),
+            Rotation::Deg180 => (vertical, !backwards),
+            Rotation::Deg270 => (!vertical, backwards == vertical),
+        };
+        // Mirroring reverses the direction along the mirrored axis
+        let backwards = backwards
+            != if vertical {
+                self.mirror.1
+            } else {
+                self.mirror.0
+            };
+        if vertical {
+            self.shift_vertically(x, y, n, backwards, fill);
+        } else {
+            self.shift_horizontally(x, y, n, backwards, fill);
+        }
+    }
+
+    /// Shift columns `x` of lines `y` of the framebuffer up (or down) by `n` pixels.
+    fn shift_vertically(
+        &mut self,
+        x: Range<usize>,
+        y: Range<usize>,
+        n: u32,
+        up: bool,
+        fill: BinaryColor,
+    ) {
+        if n == 0 {
+            return;
+        }
+        let n = (n as usize).min(y.len());
+        self.frame
+            .borrow_mut()
+            .buffer
+            .shift_vertically(x, y.clone(), n, up, fill.is_on());
+        self.mark_lines_dirty(y);
+    }
+
+    /// Shift columns `x` of lines `y` of the framebuffer left (or right) by `n` pixels.
+    pub(crate) fn shift_horizontally(
+        &mut self,
+        x: Range<usize>,
+        y: Range<usize>,
+        n: u32,
+        left: bool,
+        fill: BinaryColor,
+    ) {
+        if n == 0 {
+            return;
+        }
+        let n = (n as usize).min(x.len());
+        self.frame
+            .borrow_mut()
+            .buffer
+            .shift_horizontally(x, y.clone(), n, left, fill.is_on());
+        self.mark_lines_dirty(y);
+    }
+
+    /// Scroll the contents of `region` left by `n` pixels, filling the vacated columns at the
+    /// right of the region with `fill`. See [`MemoryDisplay::scroll_region_up`].
+    pub fn scroll_region_left(&mut self, region: Rectangle, n: u32, fill: BinaryColor) {
+        self.scroll(region, n, fill, false, true);
+    }
+
+    /// Scroll the contents of `region` right by `n` pixels, filling the vacated columns at the
+    /// left of the region with `fill`. See [`MemoryDisplay::scroll_region_up`].
+    pub fn scroll_region_right(&mut self, region: Rectangle, n: u32, fill: BinaryColor) {
+        self.scroll(region, n, fill, false, false);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{self, TestDisplay};
+    use crate::{BUFFER_HEIGHT, BUFFER_WIDTH};
+    use embedded_graphics::primitives::PointsIter;
+    use std::vec::Vec;
+
+    /// Scroll `region` of `pixels` like `scroll_region_up` (or down), pixel by pixel.
+    fn scrolled(pixels: &[Vec<bool>], region: Rectangle, n: usize, up: bool) -> Vec<Vec<bool>> {
+        let mut expected = pixels.to_vec();
+        let region = region.intersection(&Rectangle::new(
+            Point::zero(),
+            Size::new(pixels[0].len() as u32, pixels.len() as u32),
+        ));
+        let Some(bottom_right) = region.bottom_right() else {
+            return expected;
+        };
+        let (x, y) = (
+            region.top_left.x as usize..bottom_right.x as usize + 1,
+            region.top_left.y as usize..bottom_right.y as usize + 1,
+        );
+        for row in y.clone() {
+            let from = if up {
+                row.checked_add(n)
+            } else {
+                row.checked_sub(n)
+            };
+            for column in x.clone() {
+                expected[row][column] = match from.filter(|from| y.contains(from)) {
+                    Some(from) => pixels[from][column],
+                    None => true,
+                };
+            }
+        }
+        expected
+    }
+
+    fn check_scroll(region: Rectangle, n: u32) {
+        for up in [true, false] {
+            let (_, mut display): (_, TestDisplay) = testing::display();
+            testing::draw_noise(&mut display, 7);
+            display.clear_dirty();
+            let before = testing::snapshot(&display);
+            if up {
+                display.scroll_region_up(region, n, BinaryColor::On);
+            } else {
+                display.scroll_region_down(region, n, BinaryColor::On);
+            }
+            let expected = scrolled(&before, region, n as usize, up);
+            assert_eq!(
+                testing::snapshot(&display),
+                expected,
+                "{region:?} by {n}, up {up}"
+            );
+
+            // Only the lines of the region are dirty
+            if cfg!(feature = "minimal-flush") {
+                continue;
+            }
+            let dirty = (0..BUFFER_HEIGHT).filter(|&y| display.dirty_extent(y).is_some());
+            let lines = region.intersection(&display.bounding_box()).rows();
+            assert_eq!(
+                dirty.collect::<Vec<_>>(),
+                lines.map(|y| y as usize).collect::<Vec<_>>()
+            );
+        }
+    }
+
+    #[test]
+    fn scroll_region_with_unaligned_edges() {
+        check_scroll(Rectangle::new(Point::new(3, 4), Size::new(17, 9)), 2);
+        check_scroll(Rectangle::new(Point::new(9, 0), Size::new(5, 6)), 5);
+    }
+
+    #[test]
+    fn scroll_region_further_than_its_height() {
+        check_scroll(Rectangle::new(Point::new(5, 10), Size::new(30, 4)), 4);
+        check_scroll(Rectangle::new(Point::new(5, 10), Size::new(30, 4)), 1000);
+    }
+
+    #[test]
+    fn scroll_region_touching_the_edges() {
+        let (width, height) = (BUFFER_WIDTH as i32, BUFFER_HEIGHT as i32);
+        check_scroll(
+            Rectangle::new(Point::new(width - 11, 2), Size::new(11, 6)),
+            3,
+        );
+        check_scroll(
+            Rectangle::new(Point::new(0, height - 5), Size::new(13, 5)),
+            2,
+        );
+        // Beyond the display, clipped to it
+        check_scroll(
+            Rectangle::new(Point::new(width - 7, -3), Size::new(20, 12)),
+            2,
+        );
+        check_scroll(Rectangle::new(Point::zero(), Size::new(width as u32, 8)), 3);
+    }
+
+    /// The rectangles filled by the fill tests: unaligned, clipped on every side and empty.
+    fn fill_areas() -> Vec<Rectangle> {
+        let (width, height) = (BUFFER_WIDTH as i32, BUFFER_HEIGHT as i32);
+        [
+            (0, 0, 8, 1),
+            (3, 2, 17, 9),
+            (9, 5, 3, 3),
+            (-4, -6, 21, 12),
+            (width - 5, height - 3, 40, 40),
+            (-10, 4, width as u32 + 20, 2),
+            (0, 0, width as u32, height as u32),
+            (width, 0, 5, 5),
+            (4, 4, 0, 7),
+        ]
+        .into_iter()
+        .map(|(x, y, w, h)| Rectangle::new(Point::new(x, y), Size::new(w, h)))
+        .collect()
+    }
+
+    /// Fill `display` and a copy of it drawn pixel by pixel with `fill`, checking the pixels
+    /// and the dirty lines match.
+    fn check_fill(
+        area: Rectangle,
+        fill: impl Fn(&mut TestDisplay),
+        naive: impl Fn(&mut TestDisplay),
+    ) {
+        let (_, mut display) = testing::display();
+        let (_, mut expected) = testing::display();
+        for display in [&mut display, &mut expected] {
+            testing::draw_noise(display, 267);
+            display.clear_dirty();
+        }
+        fill(&mut display);
+        naive(&mut expected);
+        assert_eq!(
+            testing::snapshot(&display),
+            testing::snapshot(&expected),
+            "{area:?}"
+        );
+        // The same lines are dirty, the columns marked by a fill may extend over pixels which
+        // didn't change
+        for y in 0..BUFFER_HEIGHT {
+            match (display.dirty_extent(y), expected.dirty_extent(y)) {
+                (Some(marked), Some(changed)) => {
+                    assert!(
+                        marked.0 <= changed.0 && marked.1 >= changed.1,
+                        "{area:?} {y}"
+                    )
+                }
+                (marked, changed) => assert_eq!(marked, changed, "{area:?} {y}"),
+            }
+        }
+    }
+
+    #[test]
+    fn fill_solid_matches_drawing_pixels() {
+        for area in fill_areas() {
+            for color in [BinaryColor::On, BinaryColor::Off] {
+                check_fill(
+                    area,
+                    |display| display.fill_solid(&area, color).unwrap(),
+                    |display| {
+                        let pixels = area.points().map(|point| Pixel(point, color));
+                        display.draw_iter(pixels).unwrap()
+                    },
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn fill_contiguous_matches_drawing_pixels() {
+        // Clipped pixels still take their color from the iterator
+        let color = |i: usize| BinaryColor::from(i.is_multiple_of(3) || i % 7 == 1);
+        for area in fill_areas() {
+            check_fill(
+                area,
+                |display| {
+                    let colors = (0..area.size.width * area.size.height).map(|i| color(i as usize));
+                    display.fill_contiguous(&area, colors).unwrap()
+                },
+                |display| {
+                    let pixels = area
+                        .points()
+                        .enumerate()
+                        .map(|(i, point)| Pixel(point, color(i)));
+                    display.draw_iter(pixels).unwrap()
+                },
+            );
+        }
+    }
+
+    #[test]
+    fn clear_fills_the_framebuffer_only() {
+        let whole = Rectangle::new(
+            Point::zero(),
+            Size::new(BUFFER_WIDTH as u32, BUFFER_HEIGHT as u32),
+        );
+        for color in [BinaryColor::On, BinaryColor::Off] {
+            check_fill(
+                whole,
+                |display| DrawTarget::clear(display, color).unwrap(),
+                |display| {
+                    let pixels = whole.points().map(|point| Pixel(point, color));
+                    display.draw_iter(pixels).unwrap()
+                },
+            );
+        }
+        let (log, mut display) = testing::display();
+        DrawTarget::clear(&mut display, BinaryColor::On).unwrap();
+        assert_eq!(log.events(), []);
+    }
+
+    /// A bitmap for `blit`, 13 pixels wide in rows of 2 bytes, and its pixels.
+    fn bitmap(rows: usize) -> (Vec<u8>, impl Fn(Point) -> BinaryColor) {
+        let data: Vec<u8> = (0..rows * 2)
+            .map(|i| (i as u8).wrapping_mul(0x9D) ^ 0x5A)
+            .collect();
+        let bits = data.clone();
+        let pixel = move |point: Point| {
+            let (x, y) = (point.x as usize, point.y as usize);
+            BinaryColor::from(bits[y * 2 + x / 8] & 1 << (x % 8) != 0)
+        };
+        (data, pixel)
+    }
+
+    /// Blit [`bitmap`] at `top_left`, checking it against drawing every pixel of it.
+    fn check_blit(top_left: Point, rows: usize) {
+        let (data, pixel) = bitmap(rows);
+        let area = Rectangle::new(top_left, Size::new(13, rows as u32));
+        check_fill(
+            area,
+            |display| display.blit(top_left, 13, &data),
+            |display| {
+                let pixels = Rectangle::new(Point::zero(), area.size)
+                    .points()
+                    .map(|point| Pixel(top_left + point, pixel(point)));
+                display.draw_iter(pixels).unwrap()
+            },
+        );
+    }
+
+    #[test]
+    fn blit_round_trips_at_any_column() {
+        for x in 0..10 {
+            let top_left = Point::new(x, 3);
+            let (_, mut display) = testing::display();
+            testing::draw_noise(&mut display, 268);
+            let (data, pixel) = bitmap(5);
+            display.blit(top_left, 13, &data);
+            for point in Rectangle::new(Point::zero(), Size::new(13, 5)).points() {
+                let at = top_left + point;
+                let read = display.get_pixel(at.x as u32, at.y as u32);
+                assert_eq!(read, Some(pixel(point).into()), "{top_left:?} {point:?}");
+            }
+            check_blit(top_left, 5);
+        }
+    }
+
+    #[test]
+    fn blit_is_clipped_like_drawing_pixels() {
+        let (width, height) = (BUFFER_WIDTH as i32, BUFFER_HEIGHT as i32);
+        for top_left in [
+            Point::new(-3, -2),
+            Point::new(-13, 0),
+            Point::new(width - 6, height - 2),
+            Point::new(width, 0),
+            Point::new(0, height),
+        ] {
+            check_blit(top_left, 4);
+        }
+        // Only complete rows are drawn
+        let (_, mut display) = testing::display();
+        let (_, mut expected) = testing::display();
+        let (data, _) = bitmap(2);
+        display.blit(Point::new(1, 1), 13, &data[..3]);
+        expected.blit(Point::new(1, 1), 13, &data[..2]);
+        assert_eq!(testing::snapshot(&display), testing::snapshot(&expected));
+    }
+
+    #[test]
+    fn mirrored_fills_match_drawing_pixels() {
+        let (data, _) = bitmap(6);
+        // Any but the whole display, which mirrors to itself
+        let areas = fill_areas()
+            .into_iter()
+            .filter(|area| area.size.height < 60);
+        for area in areas {
+            for (x, y) in [(true, false), (false, true), (true, true)] {
+                check_fill(
+                    area,
+                    |display| {
+                        display.set_mirror(x, y);
+                        display.fill_solid(&area, BinaryColor::Off).unwrap();
+                        display.blit(area.top_left, 13, &data);
+                    },
+                    |display| {
+                        display.set_mirror(x, y);
+                        let pixels = area.points().map(|point| Pixel(point, BinaryColor::Off));
+                        display.draw_iter(pixels).unwrap();
+                        let (_, pixel) = bitmap(6);
+                        let pixels = Rectangle::new(Point::zero(), Size::new(13, 6))
+                            .points()
+                            .map(|point| Pixel(area.top_left + point, pixel(point)));
+                        display.draw_iter(pixels).unwrap();
+                    },
+                );
+            }
+        }
+    }
+
+    // The lines sent by flush_region, with the lines of the drawable area those of the panel
+    #[cfg(not(any(
+        feature = "half-height",
+        feature = "half-width",
+        feature = "column-major",
+        feature = "minimal-flush"
+    )))]
+    mod flush_region {
+        use super::*;
+        use crate::testing::{diagonal, pixels};
+        use crate::{Vcom, HEIGHT};
+
+        fn rows(y: i32, height: u32) -> Rectangle {
+            Rectangle::new(Point::new(0, y), Size::new(5, height))
+        }
+
+        #[test]
+        fn only_the_lines_of_the_area_are_sent() {
+            let (log, mut display) = testing::display();
+            testing::draw_diagonal(&mut display, [2, 10, 11, 30]);
+            display.flush_region(rows(10, 2)).unwrap();
+            assert_eq!(log.bytes(), testing::frame(Vcom::Lo, &diagonal([10, 11])));
+            // The other lines are left for the next flush
+            assert_eq!(display.dirty_line_count(), 2);
+            display.flush_buffer().unwrap();
+            assert_eq!(log.bytes(), testing::frame(Vcom::Hi, &diagonal([2, 30])));
+        }
+
+        #[test]
+        fn clean_lines_of_the_area_are_sent_too() {
+            let (log, mut display) = testing::display();
+            testing::draw_diagonal(&mut display, [2]);
+            display.flush_buffer().unwrap();
+            log.events();
+            display.flush_region(rows(2, 2)).unwrap();
+            let lines = [diagonal([2])[0], (3, pixels(&[]))];
+            assert_eq!(log.bytes(), testing::frame(Vcom::Hi, &lines));
+            assert!(!display.is_dirty());
+        }
+
+        #[test]
+        fn areas_are_clipped_to_the_display() {
+            let (log, mut display) = testing::display();
+            let last = HEIGHT - 1;
+            testing::draw_diagonal(&mut display, [0, last - 1, last]);
+            let area = Rectangle::new(Point::new(-5, last as i32 - 1), Size::new(10, 10));
+            display.flush_region(area).unwrap();
+            assert_eq!(
+                log.bytes(),
+                testing::frame(Vcom::Lo, &diagonal([last - 1, last]))
+            );
+            display.flush_region(rows(-3, 4)).unwrap();
+            assert_eq!(log.bytes(), testing::frame(Vcom::Hi, &diagonal([0])));
+        }
+
+        #[test]
+        fn empty_areas_send_nothing() {
+            let (log, mut display) = testing::display();
+            testing::draw_diagonal(&mut display, [4]);
+            for area in [
+                rows(4, 0),
+                Rectangle::new(Point::new(2, 4), Size::new(0, 3)),
+                rows(-10, 5),
+                rows(HEIGHT as i32, 1),
+                Rectangle::new(Point::new(-8, 4), Size::new(8, 1)),
+            ] {
+                display.flush_region(area).unwrap();
+                assert_eq!(log.events(), [], "{area:?}");
+            }
+            // VCOM wasn't toggled either
+            display.flush_region(rows(4, 1)).unwrap();
+            assert_eq!(log.bytes(), testing::frame(Vcom::Lo, &diagonal([4])));
+        }
+
+        #[test]
+        fn lines_of_an_open_frame_are_held_back() {
+            let (log, mut display) = testing::display();
+            testing::draw_diagonal(&mut display, [10, 30]);
+            display.begin_frame();
+            testing::draw_diagonal(&mut display, [11, 12]);
+            display.flush_region(rows(10, 3)).unwrap();
+            assert_eq!(log.bytes(), testing::frame(Vcom::Lo, &diagonal([10])));
+            // Nothing of the frame until it ends, the line outside the region still dirty
+            display.flush_buffer().unwrap();
+            assert_eq!(log.bytes(), testing::frame(Vcom::Hi, &diagonal([30])));
+            display.end_frame();
+            display.flush_buffer().unwrap();
+            assert_eq!(log.bytes(), testing::frame(Vcom::Lo, &diagonal([11, 12])));
+        }
+
+        #[test]
+        fn regions_flushed_across_frames_send_what_was_presented() {
+            let (log, mut display) = testing::display();
+            display.begin_frame();
+            testing::draw_diagonal(&mut display, [3]);
+            display.flush_region(rows(2, 3)).unwrap();
+            // The clean lines of the region, but not the one drawn in the frame
+            let clean = [(2, pixels(&[])), (4, pixels(&[]))];
+            assert_eq!(log.bytes(), testing::frame(Vcom::Lo, &clean));
+            display.end_frame();
+
+            // Drawing between frames is sent by the next flush, with what the frame drew
+            testing::draw_diagonal(&mut display, [4]);
+            display.begin_frame();
+            testing::draw_diagonal(&mut display, [5]);
+            display.flush_region(rows(3, 3)).unwrap();
+            let expected = testing::frame(Vcom::Hi, &diagonal([3, 4]));
+            assert_eq!(log.bytes(), expected);
+            assert_eq!(display.dirty_line_count(), 0);
+            display.end_frame();
+            assert_eq!(display.dirty_line_count(), 1);
+            display.flush_region(rows(0, 8)).unwrap();
+            let mut lines: Vec<_> = (0..8).map(|y| (y, pixels(&[]))).collect();
+            lines[3..6].copy_from_slice(&diagonal([3, 4, 5]));
+            assert_eq!(log.bytes(), testing::frame(Vcom::Lo, &lines));
+            assert!(!display.is_dirty());
+        }
+
+        #[test]
+        fn failed_flushes_keep_every_line_dirty() {
+            let (log, mut display) = testing::display();
+            testing::draw_diagonal(&mut display, [1, 5, 6, 9]);
+            log.reject_writes_over(0);
+            assert!(display.flush_region(rows(5, 2)).is_err());
+            assert_eq!(display.dirty_line_count(), 4);
+            log.reject_writes_over(usize::MAX);
+            log.events();
+            display.flush_buffer().unwrap();
+            let expected = testing::frame(Vcom::Hi, &diagonal([1, 5, 6, 9]));
+            assert_eq!(log.bytes(), expected);
+        }
+    }
+}
@@ -0,0 +1,142 @@
+//! Support for embedded-hal 1.0 buses and pins, only available with the `embedded-hal-1`
+//! feature.
+//!
+//! The driver itself is written against embedded-hal 0.2, the types here adapt the 1.0 traits
+//! to it. There are two ways to connect a display:
+//!
+//! - [`new_spi_bus`] takes an exclusive [`SpiBus`] and the chip select as an [`OutputPin`],
+//!   which the driver drives itself exactly like with embedded-hal 0.2.
+//! - [`new_spi_device`] takes an [`SpiDevice`], e.g. from `embedded-hal-bus` to share the bus
+//!   with other devices, which frames every write with chip select. The display is therefore
+//!   set to [`CsStrategy::PerLine`], where every write is a complete transaction.
+//!   [`CsStrategy::Hardware`] works as well, but [`CsStrategy::Software`] must not be used.
+//!
+//! The panels latch data on the falling edge of an active high chip select, while the devices
+//! of `embedded-hal-bus` drive chip select low during a transaction. Pass them the chip select
+//! wrapped in [`ActiveHigh`] to invert it.
+use crate::{spec, CsStrategy, DisplaySpec, MemoryDisplay};
+use core::convert::Infallible;
+use embedded_hal_1::digital::{self, OutputPin};
+use embedded_hal_1::spi::{self, Phase, Polarity, SpiBus, SpiDevice};
+use hal::blocking::spi::Write;
+
+with_selected_model! {
+    /// [`crate::MODE`] as the mode type of embedded-hal 1.0.
+    pub const MODE: spi::Mode = mode::<spec::Selected>();
+}
+
+/// [`DisplaySpec::MODE`] of the model `SPEC` as the mode type of embedded-hal 1.0.
+pub const fn mode<SPEC: DisplaySpec>() -> spi::Mode {
+    spi::Mode {
+        polarity: match SPEC::MODE.polarity {
+            hal::spi::Polarity::IdleLow => Polarity::IdleLow,
+            hal::spi::Polarity::IdleHigh => Polarity::IdleHigh,
+        },
+        phase: match SPEC::MODE.phase {
+            hal::spi::Phase::CaptureOnFirstTransition => Phase::CaptureOnFirstTransition,
+            hal::spi::Phase::CaptureOnSecondTransition => Phase::CaptureOnSecondTransition,
+        },
+    }
+}
+
+/// A display driving its chip select itself on an exclusive bus, see [`new_spi_bus`].
+pub type BusDisplay<BUS, CS, DISP, SPEC = spec::Selected> =
+    MemoryDisplay<Bus<BUS>, Pin<CS>, Pin<DISP>, SPEC>;
+
+/// A display whose chip select is driven by an [`SpiDevice`], see [`new_spi_device`].
+pub type DeviceDisplay<DEV, DISP, SPEC = spec::Selected> =
+    MemoryDisplay<Device<DEV>, DeviceCs, Pin<DISP>, SPEC>;
+
+/// Create a display on an exclusive bus, driving chip select `cs` itself.
+pub fn new_spi_bus<BUS, CS, DISP>(bus: BUS, cs: CS, disp: DISP) -> BusDisplay<BUS, CS, DISP>
+where
+    BUS: SpiBus,
+    CS: OutputPin,
+    DISP: OutputPin,
+{
+    MemoryDisplay::new(Bus(bus), Pin(cs), Pin(disp))
+}
+
+/// Create a display on an [`SpiDevice`] framing every transaction with chip select, using
+/// [`CsStrategy::PerLine`].
+pub fn new_spi_device<DEV, DISP>(device: DEV, disp: DISP) -> DeviceDisplay<DEV, DISP>
+where
+    DEV: SpiDevice,
+    DISP: OutputPin,
+{
+    let mut display = MemoryDisplay::new(Device(device), DeviceCs, Pin(disp));
+    display.set_cs_strategy(CsStrategy::PerLine);
+    display
+}
+
+/// An embedded-hal 1.0 [`SpiBus`] for the driver. Every write is flushed before it returns, so
+/// chip select isn't released while data is still being shifted out.
+pub struct Bus<BUS>(pub BUS);
+
+impl<BUS: SpiBus> Write<u8> for Bus<BUS> {
+    type Error = BUS::Error;
+
+    fn write(&mut self, words: &[u8]) -> Result<(), Self::Error> {
+        self.0.write(words)?;
+        self.0.flush()
+    }
+}
+
+/// An embedded-hal 1.0 [`SpiDevice`] for the driver, every write is a transaction of its own.
+pub struct Device<DEV>(pub DEV);
+
+impl<DEV: SpiDevice> Write<u8> for Device<DEV> {
+    type Error = DEV::Error;
+
+    fn write(&mut self, words: &[u8]) -> Result<(), Self::Error> {
+        self.0.write(words)
+    }
+}
+
+/// An embedded-hal 1.0 [`OutputPin`] for the driver.
+pub struct Pin<P>(pub P);
+
+impl<P: OutputPin> hal::digital::v2::OutputPin for Pin<P> {
+    type Error = P::Error;
+
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        self.0.set_low()
+    }
+
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        self.0.set_high()
+    }
+}
+
+/// Stand-in for the chip select pin, which is driven by the [`SpiDevice`].
+pub struct DeviceCs;
+
+impl hal::digital::v2::OutputPin for DeviceCs {
+    type Error = Infallible;
+
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+/// Inverts an [`OutputPin`], for passing the active high chip select of the display to an
+/// [`SpiDevice`] which drives it low during transactions.
+pub struct ActiveHigh<P>(pub P);
+
+impl<P: OutputPin> digital::ErrorType for ActiveHigh<P> {
+    type Error = P::Error;
+}
+
+impl<P: OutputPin> OutputPin for ActiveHigh<P> {
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        self.0.set_high()
+    }
+
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        self.0.set_low()
+    }
+}
@@ -0,0 +1,160 @@
+use embedded_graphics::draw_target::DrawTarget;
+use embedded_graphics::geometry::{Dimensions, Point};
+use embedded_graphics::pixelcolor::{BinaryColor, Gray2, GrayColor};
+use embedded_graphics::primitives::{PointsIter, Rectangle};
+use embedded_graphics::Pixel;
+
+/// Draw [`Gray2`] content onto a binary display using fixed halftone patterns.
+///
+/// The four levels are mapped to black, a 50% checkerboard, a 25% dot pattern and white.
+/// Patterns are anchored to absolute display coordinates, so adjacent fills line up seamlessly.
+pub struct Halftone<'a, D> {
+    target: &'a mut D,
+}
+
+impl<'a, D> Halftone<'a, D>
+where
+    D: DrawTarget<Color = BinaryColor>,
+{
+    pub fn new(target: &'a mut D) -> Self {
+        Self { target }
+    }
+
+    /// The binary color a pixel of the given level is drawn with at a position.
+    pub fn map(point: Point, color: Gray2) -> BinaryColor {
+        let (x, y) = (point.x & 1, point.y & 1);
+        let on = match color.luma() {
+            0 => false,
+            // Checkerboard
+            1 => x != y,
+            // One dark pixel per 2x2 tile
+            2 => x != 0 || y != 0,
+            _ => true,
+        };
+        on.into()
+    }
+}
+
+impl<D> Dimensions for Halftone<'_, D>
+where
+    D: DrawTarget<Color = BinaryColor>,
+{
+    fn bounding_box(&self) -> Rectangle {
+        self.target.bounding_box()
+    }
+}
+
+impl<D> DrawTarget for Halftone<'_, D>
+where
+    D: DrawTarget<Color = BinaryColor>,
+{
+    type Color = Gray2;
+    type Error = D::Error;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        self.target.draw_iter(
+            pixels
+                .into_iter()
+                .map(|Pixel(point, color)| Pixel(point, Self::map(point, color))),
+        )
+    }
+
+    fn fill_contiguous<I>(&mut self, area: &Rectangle, colors: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Self::Color>,
+    {
+        self.target.fill_contiguous(
+            area,
+            area.points()
+                .zip(colors)
+                .map(|(point, color)| Self::map(point, color)),
+        )
+    }
+
+    fn fill_solid(&mut self, area: &Rectangle, color: Self::Color) -> Result<(), Self::Error> {
+        match color.luma() {
+            0 => self.target.fill_solid(area, BinaryColor::Off),
+            3 => self.target.fill_solid(area, BinaryColor::On),
+            _ => self
+                .target
+                .fill_contiguous(area, area.points().map(|point| Self::map(point, color))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{self, TestDisplay};
+    use embedded_graphics::geometry::Size;
+    use std::vec::Vec;
+
+    /// The pixels of the 4x4 square at `origin`, row by row, as `#` for white and `.` for black.
+    fn square(display: &TestDisplay, origin: Point) -> Vec<&'static str> {
+        (0..4)
+            .map(|y| {
+                let line = (0..4).map(|x| {
+                    let point = origin + Point::new(x, y);
+                    display.get_pixel(point.x as u32, point.y as u32) == Some(crate::Color::On)
+                });
+                match line.collect::<Vec<_>>()[..] {
+                    [false, false, false, false] => "....",
+                    [true, false, true, false] => "#.#.",
+                    [false, true, false, true] => ".#.#",
+                    [true, true, true, true] => "####",
+                    _ => "?",
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn pattern_of_every_level() {
+        let (_, mut display) = testing::display();
+        let area = Rectangle::new(Point::new(8, 8), Size::new(4, 4));
+        let patterns = [
+            ["....", "....", "....", "...."],
+            [".#.#", "#.#.", ".#.#", "#.#."],
+            [".#.#", "####", ".#.#", "####"],
+            ["####", "####", "####", "####"],
+        ];
+        for (luma, pattern) in patterns.into_iter().enumerate() {
+            let color = Gray2::new(luma as u8);
+            Halftone::new(&mut display)
+                .fill_solid(&area, color)
+                .unwrap();
+            assert_eq!(square(&display, area.top_left), pattern, "level {luma}");
+
+            // Pixel by pixel like any other drawable
+            let pixels = area
+                .points()
+                .map(|point| Pixel(point + Point::new(8, 0), color));
+            Halftone::new(&mut display).draw_iter(pixels).unwrap();
+            assert_eq!(square(&display, Point::new(16, 8)), pattern, "level {luma}");
+        }
+    }
+
+    #[test]
+    fn adjacent_fills_line_up() {
+        let (_, mut whole) = testing::display();
+        let (_, mut parts) = testing::display();
+        let color = Gray2::new(1);
+        let area = Rectangle::new(Point::new(3, 5), Size::new(9, 7));
+        Halftone::new(&mut whole).fill_solid(&area, color).unwrap();
+        // Split at odd offsets, so each part starts on a different phase of the tile
+        for (x, y, width, height) in [(3, 5, 5, 3), (8, 5, 4, 3), (3, 8, 9, 4)] {
+            let part = Rectangle::new(Point::new(x, y), Size::new(width, height));
+            let colors = core::iter::repeat_n(color, (width * height) as usize);
+            Halftone::new(&mut parts)
+                .fill_contiguous(&part, colors)
+                .unwrap();
+        }
+        for point in Rectangle::new(Point::zero(), Size::new(16, 16)).points() {
+            let (x, y) = (point.x as u32, point.y as u32);
+            assert_eq!(whole.get_pixel(x, y), parts.get_pixel(x, y), "{point:?}");
+        }
+    }
+}
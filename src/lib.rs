@@ -20,44 +20,187 @@
 //! You must flush the framebuffer with [`MemoryDisplay::flush_buffer`] for the buffer to be written to the
 //! screen.
 //!
-//! Please specify one of the supported displays via the Cargo `feature` flag. This sets
-//! appropriate buffer and target sizes for the device at compile time.
+//! Grayscale content can be drawn with [`Halftone`], which approximates [`embedded_graphics::pixelcolor::Gray2`]
+//! levels with dither patterns, or with [`Thresholded`], which draws [`embedded_graphics::pixelcolor::Gray8`]
+//! content by comparing its luma against a threshold.
+//!
+//! The display model is a type parameter of [`MemoryDisplay`], one of the types of [`spec`],
+//! which sizes the framebuffer and addresses the lines for the device at compile time. The one
+//! selected via the Cargo `feature` flag is the default, other models are named with
+//! [`MemoryDisplay::new_with_spec`], so one binary can drive panels of different models.
+//!
+//! The [`embedded_graphics`] integration can be disabled by turning off the default `graphics`
+//! feature, leaving the framebuffer, [`MemoryDisplay::set_pixel`], flushing and VCOM handling.
 #![no_std]
+#[cfg(any(feature = "std", test))]
+extern crate std;
+
 extern crate bitvec;
+#[cfg(feature = "graphics")]
 extern crate embedded_graphics;
 extern crate embedded_hal as hal;
 
 use bitvec::prelude::*;
-use core::ops::{BitOr, Not};
-use embedded_graphics::draw_target::DrawTarget;
-use embedded_graphics::pixelcolor::BinaryColor;
-use embedded_graphics::prelude::{OriginDimensions, Size};
-use embedded_graphics::Pixel;
+use core::borrow::BorrowMut;
+use core::ops::{BitOr, Not, Range};
 use hal::blocking::spi::Write;
 use hal::digital::v2::OutputPin;
-use hal::spi::Mode;
-
-#[cfg(not(any(
-    feature = "ls027b7dh01",
-    feature = "ls012b7dd06",
-    feature = "ls010b7dh04",
-    feature = "ls013b7dh05",
-    feature = "ls011b7dh03",
-)))]
-compile_error!("Please specify a display type via the feature flag");
-
-// Pull in the appropriate set of constants for the particular model of display
-#[cfg_attr(feature = "ls027b7dh01", path = "ls027b7dh01.rs")]
-#[cfg_attr(feature = "ls012b7dd06", path = "ls012b7dd06.rs")]
-#[cfg_attr(feature = "ls010b7dh04", path = "ls010b7dh04.rs")]
-#[cfg_attr(feature = "ls013b7dh05", path = "ls013b7dh05.rs")]
-#[cfg_attr(feature = "ls011b7dh03", path = "ls011b7dh03.rs")]
-mod display;
+
+#[macro_use]
+mod fmt;
+#[macro_use]
+mod models;
+
+use dirty::DirtyLines;
+use models::display;
+pub use models::spec::{self, DisplaySpec};
+use spec::Zeroed;
+
+mod aligned;
+pub mod animation;
+#[cfg(feature = "graphics")]
+mod aod;
+mod banded;
+#[cfg(feature = "graphics")]
+mod binary_compat;
+#[cfg(feature = "graphics")]
+mod blinker;
+mod builder;
+#[cfg(feature = "std")]
+pub mod capture;
+#[cfg(any(feature = "lpm013m126a", feature = "ls013b7dh06"))]
+pub mod color;
+#[cfg(feature = "console")]
+mod console;
+mod cs_timing;
+#[cfg(feature = "std")]
+pub mod decode;
+mod dirty;
+mod dma;
+mod flush_request;
+mod frame;
+mod frame_limiter;
+#[cfg(feature = "glyphs")]
+mod glyph;
+#[cfg(feature = "graphics")]
+mod graphics;
+#[cfg(feature = "embedded-hal-1")]
+pub mod hal1;
+#[cfg(feature = "graphics")]
+mod halftone;
+#[cfg(feature = "graphics")]
+mod marquee;
+#[cfg(feature = "graphics")]
+mod matrix;
+mod pair;
+mod power;
+mod present;
+#[cfg(feature = "rppal")]
+pub mod rpi;
+mod self_check;
+#[cfg(feature = "embassy")]
+pub mod shared;
+mod shared_spi;
+#[cfg(feature = "simulator")]
+pub mod simulator;
+mod storage;
+#[cfg(feature = "test-utils")]
+mod strict;
+#[cfg(test)]
+mod testing;
+#[cfg(feature = "graphics")]
+mod threshold;
+#[cfg(feature = "graphics")]
+mod transitions;
+mod vcom_tick;
+#[cfg(feature = "widgets")]
+pub mod widgets;
+mod word_spi;
+
+pub use aligned::{Extcomin, ExtcominSchedule};
+#[cfg(feature = "graphics")]
+pub use aod::{AodContent, AodHelper};
+pub use banded::BandedDisplay;
+#[cfg(feature = "graphics")]
+pub use binary_compat::BinaryCompat;
+#[cfg(feature = "graphics")]
+pub use blinker::Blinker;
+pub use builder::MemoryDisplayBuilder;
+#[cfg(any(feature = "lpm013m126a", feature = "ls013b7dh06"))]
+pub use color::{ColorMemoryDisplay, Rgb111};
+#[cfg(feature = "console")]
+pub use console::Console;
+pub use cs_timing::{CsTiming, TimedCs};
+pub use dma::PreparedFlush;
+pub use flush_request::FlushRequester;
+pub use frame::{FrameBuffer, SharpFrame};
+pub use frame_limiter::FrameLimiter;
+#[cfg(feature = "glyphs")]
+pub use glyph::{GlyphCache, GlyphError, GlyphMetrics, GlyphSlot};
+#[cfg(feature = "graphics")]
+pub use graphics::GetPixel;
+#[cfg(feature = "graphics")]
+pub use halftone::Halftone;
+#[cfg(feature = "graphics")]
+pub use marquee::Marquee;
+#[cfg(feature = "graphics")]
+pub use matrix::MatrixStyle;
+pub use pair::{DisplayPair, PairError};
+pub use self_check::{BringUpError, BringUpReport, BringUpStep, StepReport};
+pub use shared_spi::SharedSpi;
+#[cfg(feature = "test-utils")]
+pub use strict::{StrictDisplay, StrictLimits};
+#[cfg(feature = "graphics")]
+pub use threshold::Thresholded;
+#[cfg(feature = "graphics")]
+pub use transitions::{Transition, TransitionRun};
+pub use vcom_tick::VcomTick;
+pub use word_spi::{WordOrder, WordSpi};
 
 const DUMMY_DATA: u8 = 0x00; // This can really be anything, but the spec sheet recommends 0s
 
-#[derive(Clone, Copy, PartialEq, Eq)]
-enum Vcom {
+/// The state of a pixel, the same as `BinaryColor` of [`embedded_graphics`] which it converts
+/// from and into with the `graphics` feature.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Color {
+    /// Black on the panel.
+    Off,
+    /// White on the panel.
+    On,
+}
+
+impl Color {
+    fn is_on(self) -> bool {
+        self == Color::On
+    }
+}
+
+impl From<bool> for Color {
+    fn from(on: bool) -> Self {
+        if on {
+            Color::On
+        } else {
+            Color::Off
+        }
+    }
+}
+
+impl From<Color> for bool {
+    fn from(color: Color) -> Self {
+        color.is_on()
+    }
+}
+
+/// Polarity of the VCOM signal, carried by the M1 bit of every command.
+///
+/// The polarity has to alternate to avoid a DC bias building up on the panel. To keep this
+/// balanced across resets of the MCU, the phase can be stashed with
+/// [`MemoryDisplay::vcom_phase`] and restored with [`MemoryDisplay::set_vcom_phase`]. It converts
+/// to and from `bool` (`true` being [`Vcom::Hi`]) for compact storage.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Vcom {
     // For details see the document https://www.sharpsde.com/fileadmin/products/Displays/2016_SDE_App_Note_for_Memory_LCD_programming_V1.3.pdf
     Lo = 0x00, // 0b_0______ M1 == 0
     Hi = 0x40, // 0b_1______ M1 == 1
@@ -74,6 +217,36 @@ impl Not for Vcom {
     }
 }
 
+impl From<bool> for Vcom {
+    fn from(hi: bool) -> Self {
+        if hi {
+            Vcom::Hi
+        } else {
+            Vcom::Lo
+        }
+    }
+}
+
+impl From<Vcom> for bool {
+    fn from(vcom: Vcom) -> Self {
+        vcom == Vcom::Hi
+    }
+}
+
+/// Where the panel takes its VCOM signal from, set by its EXTMODE pin.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum VcomMode {
+    /// EXTMODE is low and VCOM is taken from the M1 bit of the commands, which the driver
+    /// toggles with every command.
+    #[default]
+    Software,
+    /// EXTMODE is high and VCOM is taken from the EXTCOMIN pin. The M1 bit is ignored by the
+    /// panel, the driver always sends it as 0. A pin toggled in software can be driven with
+    /// [`Extcomin`].
+    External,
+}
+
 impl BitOr<Command> for Vcom {
     type Output = u8;
 
@@ -88,6 +261,10 @@ enum Command {
     Nop = 0x00,         // 0b0_0_____ M0 == 0, M2 == 0
     ClearMemory = 0x20, // 0b0_1_____ M0 == 0, M2 == 1
     WriteLine = 0x80,   // 0b1_0_____ M0 == 1, M2 == 0
+    // Only supported by the JDI panels, see their datasheets
+    BlinkBlack = 0x10,  // 0b0_010___ M0 == 0, M2 == 0, M3 == 1, M4 == 0
+    BlinkInvert = 0x14, // 0b0_0101__ M0 == 0, M2 == 0, M3 == 1, M5 == 1
+    BlinkWhite = 0x18,  // 0b0_011___ M0 == 0, M2 == 0, M3 == 1, M4 == 1
 }
 
 impl BitOr<Vcom> for Command {
@@ -98,51 +275,616 @@ impl BitOr<Vcom> for Command {
     }
 }
 
-/// Mode to configure the SPI device in in order to communicate with the display.
-pub const MODE: Mode = display::MODE;
+with_selected_model! {
+    /// Mode to configure the SPI device in in order to communicate with the display. For
+    /// embedded-hal 1.0 it is available as `hal1::MODE` with the `embedded-hal-1` feature.
+    pub const MODE: hal::spi::Mode = <spec::Selected as DisplaySpec>::MODE;
+}
+
+/// Read the maximum transfer size of the Linux `spidev` driver from
+/// `/sys/module/spidev/parameters/bufsiz`, for use with [`MemoryDisplay::set_max_transfer_len`].
+#[cfg(feature = "std")]
+pub fn spidev_bufsiz() -> Option<usize> {
+    std::fs::read_to_string("/sys/module/spidev/parameters/bufsiz")
+        .ok()?
+        .trim()
+        .parse()
+        .ok()
+}
+
+/// Outline of the active area of a display.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Shape {
+    Rect,
+    /// A circular panel inscribed in the pixel grid. Pixels outside the circle are not visible.
+    Round,
+}
+
+with_selected_model! {
+    /// Width of the display in pixels.
+    pub const WIDTH: usize = <spec::Selected as DisplaySpec>::WIDTH;
+}
+
+with_selected_model! {
+    /// Height of the display in pixels.
+    pub const HEIGHT: usize = <spec::Selected as DisplaySpec>::HEIGHT;
+}
+
+with_selected_model! {
+    /// Size of the display in pixels.
+    #[cfg(feature = "graphics")]
+    pub const SIZE: embedded_graphics::geometry::Size =
+        embedded_graphics::geometry::Size::new(WIDTH as u32, HEIGHT as u32);
+}
+
+with_selected_model! {
+    /// Physical (width, height) of the active area of the display in millimetres.
+    pub const ACTIVE_AREA_MM: (f32, f32) = <spec::Selected as DisplaySpec>::ACTIVE_AREA_MM;
+}
+
+with_selected_model! {
+    /// Pixel density of the display in pixels per inch.
+    pub const PPI: u16 = <spec::Selected as DisplaySpec>::PPI;
+}
+
+with_selected_model! {
+    /// Outline of the active area of the display.
+    pub const SHAPE: Shape = <spec::Selected as DisplaySpec>::SHAPE;
+}
+
+with_selected_model! {
+    /// Frequency at which the VCOM polarity should be toggled according to the datasheet, e.g.
+    /// by calling [`MemoryDisplay::maintain_vcom`].
+    pub const RECOMMENDED_VCOM_HZ: u8 = <spec::Selected as DisplaySpec>::RECOMMENDED_VCOM_HZ;
+}
+
+with_selected_model! {
+    /// Maximum SPI clock frequency supported by the display according to the datasheet.
+    pub const MAX_SCLK_HZ: u32 = <spec::Selected as DisplaySpec>::MAX_SCLK_HZ;
+}
+
+with_selected_model! {
+    /// Minimum timing of chip select required by the display according to the datasheet,
+    /// observed by wrapping the pin in [`TimedCs`].
+    pub const CS_TIMING: CsTiming = <spec::Selected as DisplaySpec>::CS_TIMING;
+}
+
+// Every pixel of the framebuffer covers X_SCALE by Y_SCALE pixels of the panel, saving RAM on
+// the larger panels with the `half-width` and `half-height` features
+const X_SCALE: usize = if cfg!(feature = "half-width") { 2 } else { 1 };
+const Y_SCALE: usize = if cfg!(feature = "half-height") { 2 } else { 1 };
+
+with_selected_model! {
+    /// Width of the framebuffer in pixels, the same as [`WIDTH`] unless the `half-width`
+    /// feature halves it.
+    pub const BUFFER_WIDTH: usize = <spec::Selected as DisplaySpec>::BUFFER_WIDTH;
+}
+
+with_selected_model! {
+    /// Height of the framebuffer in pixels, the same as [`HEIGHT`] unless the `half-height`
+    /// feature halves it.
+    pub const BUFFER_HEIGHT: usize = <spec::Selected as DisplaySpec>::BUFFER_HEIGHT;
+}
+
+with_selected_model! {
+    /// Bytes of pixel data per line of the framebuffer, e.g. of the lines passed to
+    /// [`MemoryDisplay::write_line`] and written by [`MemoryDisplay::to_packed_rows`].
+    pub const LINE_BYTES: usize = <spec::Selected as DisplaySpec>::LINE_BYTES;
+}
+
+// The internal sizes of the selected model, which the unit tests check the driver against
+#[cfg(test)]
+const PANEL_LINE_BYTES: usize = <spec::Selected as DisplaySpec>::PANEL_LINE_BYTES;
+#[cfg(test)]
+const WRITE_BUFFER_SIZE: usize = <spec::Selected as DisplaySpec>::WRITE_BUFFER_SIZE;
+#[cfg(test)]
+const FRAME_OVERHEAD: usize = <spec::Selected as DisplaySpec>::FRAME_OVERHEAD;
+#[cfg(all(test, feature = "std"))]
+const LINE_TRAILER_BYTES: usize = <spec::Selected as DisplaySpec>::LINE_TRAILER_BYTES;
+#[cfg(all(test, feature = "std"))]
+const FRAME_TRAILER_BYTES: usize = <spec::Selected as DisplaySpec>::FRAME_TRAILER_BYTES;
+#[cfg(all(test, feature = "transaction-buffer"))]
+const TRANSACTION_BUFFER_SIZE: usize = <spec::Selected as DisplaySpec>::TRANSACTION_BUFFER_SIZE;
+
+/// The bits of the last byte of a line `width` pixels wide past its end, in the layout of the
+/// framebuffer.
+const fn line_padding(width: usize) -> u8 {
+    if width.is_multiple_of(8) {
+        0
+    } else {
+        0xFF << (width % 8)
+    }
+}
+
+/// Most lines of the panel sent with a single write by [`CsStrategy::Software`], bounding the
+/// buffer the lines are packed into on the stack.
+const MAX_BURST_LINES: usize = 8;
 
-// Local write buffer size for a line: line number, then data (e.g. 400px / 8 bits = 50 bytes), followed by 8-bit trailer
-const WRITE_BUFFER_SIZE: usize = (display::WIDTH / 8) + 2;
+/// Stretch a line of the framebuffer to the width of the panel.
+#[cfg(not(feature = "half-width"))]
+fn widen<S: DisplaySpec>(line: &S::Line) -> S::PanelLine {
+    let mut wide = S::PanelLine::ZERO;
+    wide.as_mut().copy_from_slice(line.as_ref());
+    wide
+}
+
+/// Stretch a line of the framebuffer to the width of the panel, doubling every pixel.
+#[cfg(feature = "half-width")]
+fn widen<S: DisplaySpec>(line: &S::Line) -> S::PanelLine {
+    let (line, mut wide) = (line.as_ref(), S::PanelLine::ZERO);
+    for (i, dst) in wide.as_mut().iter_mut().enumerate() {
+        // Every byte of the panel line shows four pixels of the framebuffer
+        let nibble = (line[i / 2] >> (4 * (i % 2))) & 0x0F;
+        for bit in (0..4).filter(|bit| nibble & (1 << bit) != 0) {
+            *dst |= 0b11 << (2 * bit);
+        }
+    }
+    wide
+}
+
+/// The bits of the address of panel line `row` beyond the first 8, positioned at the end of the
+/// byte sent before the address byte: the command byte or the trailer of the previous line.
+/// They are 0 for every panel with fewer than 256 lines.
+fn address_high_bits<S: DisplaySpec>(row: usize) -> u8 {
+    let high = ((row + 1) >> 8) as u8;
+    (high.reverse_bits() as u16 >> (16 - S::ADDRESS_BITS)) as u8
+}
+
+/// How chip select is framed while flushing.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum CsStrategy {
+    /// The driver holds CS for the whole flush while the frame is sent with one SPI write per
+    /// run of consecutive dirty lines, of up to 8 lines each.
+    #[default]
+    Software,
+    /// Every line is sent in a transaction of its own, consisting of the command byte, the line
+    /// and the frame trailer. This costs two extra bytes per line, but bounds the time CS is held
+    /// to a single line so other devices on a shared bus can be serviced in between.
+    ///
+    /// VCOM is only toggled once per flush, all lines of a frame carry the same VCOM bit.
+    PerLine,
+    /// The frame is serialised into one buffer and sent with a single SPI write, for SPI
+    /// peripherals which manage chip select in hardware and deassert it between writes.
+    ///
+    /// The bytes sent are identical to [`CsStrategy::Software`], so this also suits a chip
+    /// select driven by the driver, wherever one large write is cheaper than many small ones,
+    /// e.g. a DMA transfer or a Linux `spidev` ioctl per write.
+    ///
+    /// The hardware chip select has to be configured as active-high, as the display latches
+    /// data on the falling edge of SCS (on STM32 parts with a configurable NSS polarity this is
+    /// the `SSIOP` bit). NSS pulse mode must be disabled so CS stays asserted for the entire
+    /// transfer. The CS pin given to the driver is still toggled around the transfer, so a
+    /// dummy pin may be passed.
+    ///
+    /// Requires the `transaction-buffer` feature, which adds a buffer of
+    /// `HEIGHT * (WIDTH.div_ceil(8) + 2) + 2` bytes to [`MemoryDisplay`].
+    #[cfg(feature = "transaction-buffer")]
+    Hardware,
+}
+
+/// Clockwise rotation of the drawing coordinates relative to the panel, e.g. for a panel mounted
+/// in portrait orientation. See [`MemoryDisplay::set_rotation`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Rotation {
+    #[default]
+    Deg0,
+    Deg90,
+    Deg180,
+    Deg270,
+}
+
+impl Rotation {
+    /// Position in the framebuffer of the model `S` of a pixel of the drawable area rotated by
+    /// `self`.
+    fn to_physical<S: DisplaySpec>(self, x: usize, y: usize) -> (usize, usize) {
+        match self {
+            Rotation::Deg0 => (x, y),
+            Rotation::Deg90 => (S::BUFFER_WIDTH - 1 - y, x),
+            Rotation::Deg180 => (S::BUFFER_WIDTH - 1 - x, S::BUFFER_HEIGHT - 1 - y),
+            Rotation::Deg270 => (y, S::BUFFER_HEIGHT - 1 - x),
+        }
+    }
+}
+
+/// Position in the framebuffer of a pixel mirrored as set by [`MemoryDisplay::set_mirror`].
+fn mirror_position<S: DisplaySpec>(
+    (mirror_x, mirror_y): (bool, bool),
+    (x, y): (usize, usize),
+) -> (usize, usize) {
+    (
+        if mirror_x { S::BUFFER_WIDTH - 1 - x } else { x },
+        if mirror_y {
+            S::BUFFER_HEIGHT - 1 - y
+        } else {
+            y
+        },
+    )
+}
+
+/// Blinking modes of the JDI panels, see [`MemoryDisplay::blink`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum BlinkMode {
+    /// Alternate between the shown image and an all white screen.
+    White,
+    /// Alternate between the shown image and an all black screen.
+    Black,
+    /// Show the image normally.
+    Off,
+}
+
+/// An error while talking to the display.
+///
+/// `PinError` is the error of the chip select pin for the bus traffic (e.g.
+/// [`MemoryDisplay::flush_buffer`]), or of the display enable pin for
+/// [`MemoryDisplay::enable`] and [`MemoryDisplay::disable`]. [`MemoryDisplay::sleep`] and
+/// [`MemoryDisplay::wake`] set both pins and report a failing display enable pin as
+/// `DispError`, which is the same as `PinError` unless the pins differ. With infallible pins
+/// both are [`core::convert::Infallible`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Error<E, PinError, DispError = PinError> {
+    /// The SPI bus failed.
+    Spi(E),
+    /// Setting the chip select pin failed.
+    Cs(PinError),
+    /// Setting the display enable pin failed.
+    Disp(DispError),
+    /// The display model doesn't support the command.
+    Unsupported,
+    /// The display is asleep, see [`MemoryDisplay::sleep`].
+    Asleep,
+}
+
+impl<E, PinError> Error<E, PinError> {
+    /// The error of bus traffic, which never sets the display enable pin, as that of a method
+    /// also setting it.
+    pub(crate) fn with_disp<DispError>(self) -> Error<E, PinError, DispError> {
+        match self {
+            Error::Spi(error) => Error::Spi(error),
+            Error::Cs(error) => Error::Cs(error),
+            Error::Unsupported => Error::Unsupported,
+            Error::Asleep => Error::Asleep,
+            Error::Disp(_) => unreachable!("bus traffic doesn't set the display enable pin"),
+        }
+    }
+}
+
+/// Stand-in for the display enable pin on boards where DISP is tied high, e.g.
+/// `MemoryDisplay::new(spi, cs, NoDisp)`. [`MemoryDisplay::enable`] and
+/// [`MemoryDisplay::disable`] then do nothing.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct NoDisp;
 
-pub struct MemoryDisplay<SPI, CS, DISP> {
+impl OutputPin for NoDisp {
+    type Error = core::convert::Infallible;
+
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+/// A pin driven at the opposite level of what the driver sets, for chip select or DISP behind
+/// an inverting level shifter, e.g. `MemoryDisplay::new(spi, ActiveLow(cs), disp)`. Every
+/// level goes through it, including the initial ones set by [`MemoryDisplay::new`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct ActiveLow<P>(pub P);
+
+impl<P: OutputPin> OutputPin for ActiveLow<P> {
+    type Error = P::Error;
+
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        self.0.set_high()
+    }
+
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        self.0.set_low()
+    }
+}
+
+/// Error returned when coordinates are outside the framebuffer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct OutOfBounds;
+
+/// Error returned when a maximum transfer length is too short to send a single line.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct TransferLenTooShort;
+
+/// Progress of a flush, reported by [`MemoryDisplay::flush_buffer_with_progress`] after every line.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct FlushProgress {
+    /// Index of the line which has just been transmitted (starting at 0).
+    pub line: usize,
+    /// Number of lines transmitted so far during this flush.
+    pub lines_sent: usize,
+    /// Total number of lines to be transmitted during this flush.
+    pub lines_total: usize,
+}
+
+/// What [`MemoryDisplay::flush_buffer_stats`] and [`MemoryDisplay::flush_aligned`] sent.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct FlushStats {
+    /// Number of lines of the framebuffer sent, 0 if nothing was dirty.
+    pub lines_written: usize,
+    /// Number of bytes sent over the bus, including the command and trailer bytes sent even
+    /// without any line.
+    pub bytes_written: usize,
+    /// Estimated duration of the flush in milliseconds, see [`MemoryDisplay::set_sclk_hz`].
+    pub estimated_ms: u32,
+    /// Milliseconds waited for an edge of EXTCOMIN to pass before flushing, only ever done by
+    /// [`MemoryDisplay::flush_aligned`].
+    pub waited_ms: u32,
+    /// Whether the flush may overlap an edge of EXTCOMIN, because waiting for it would have
+    /// exceeded the budget of [`MemoryDisplay::flush_aligned`] or the flush takes longer than
+    /// the time between two edges. Always `false` without an [`ExtcominSchedule`].
+    pub overlaps_edge: bool,
+}
+
+pub struct MemoryDisplay<SPI, CS, DISP, SPEC: DisplaySpec = spec::Selected, BUF = FrameBuffer<SPEC>>
+{
     spi: SPI,
     cs: CS,
     disp: DISP,
-    buffer: [BitArr!(for display::WIDTH, in u8, Lsb0); display::HEIGHT],
-    touched: BitArr!(for display::HEIGHT, in u8, Lsb0),
+    frame: BUF,
     vcom: Vcom,
-    clear_state: BinaryColor,
+    vcom_mode: VcomMode,
+    // Whether a command has been sent since the last tick serviced by `service_vcom`
+    vcom_sent: bool,
+    // Whether DISP is driven high
+    enabled: bool,
+    asleep: bool,
+    invert: bool,
+    scroll_offset: usize,
+    clear_state: Color,
+    cs_strategy: CsStrategy,
+    rotation: Rotation,
+    // Whether the columns and the lines of the panel are mirrored, see `set_mirror`
+    mirror: (bool, bool),
+    // Columns and rows of the drawable area drawing is restricted to, see `set_clip`
+    clip: Option<(Range<usize>, Range<usize>)>,
+    max_transfer_len: Option<usize>,
+    extcomin: Option<ExtcominSchedule>,
+    sclk_hz: u32,
+    // The lines of the flush prepared by `prepare_flush`, until it is committed or aborted
+    in_flight: DirtyLines<SPEC>,
+    // The lines dirty before the open frame started, see `begin_frame`
+    committed: Option<DirtyLines<SPEC>>,
+    #[cfg(feature = "transaction-buffer")]
+    transaction: SPEC::Transaction,
 }
 
-impl<SPI, CS, DISP> OriginDimensions for MemoryDisplay<SPI, CS, DISP> {
-    fn size(&self) -> Size {
-        Size::new(display::WIDTH as u32, display::HEIGHT as u32)
-    }
+impl<SPI, CS, DISP, SPEC: DisplaySpec, BUF> MemoryDisplay<SPI, CS, DISP, SPEC, BUF> {
+    /// Width of the display in pixels, see [`DisplaySpec::WIDTH`].
+    pub const WIDTH: usize = SPEC::WIDTH;
+    /// Height of the display in pixels, see [`DisplaySpec::HEIGHT`].
+    pub const HEIGHT: usize = SPEC::HEIGHT;
 }
 
-impl<SPI, CS, DISP, E> DrawTarget for MemoryDisplay<SPI, CS, DISP>
-where
-    SPI: Write<u8, Error = E>,
-    CS: OutputPin,
-    DISP: OutputPin,
+impl<SPI, CS, DISP, SPEC: DisplaySpec, BUF: BorrowMut<FrameBuffer<SPEC>>>
+    MemoryDisplay<SPI, CS, DISP, SPEC, BUF>
 {
-    type Color = BinaryColor;
-    type Error = E;
+    /// Rotate the coordinates of everything drawn from now on, see [`Rotation`]. Content drawn
+    /// before stays as it is.
+    ///
+    /// With [`Rotation::Deg90`] and [`Rotation::Deg270`] the drawable area is as wide as the
+    /// panel is high and vice versa. The framebuffer, its lines and flushing always refer to
+    /// the panel, e.g. [`MemoryDisplay::buffer`] and [`MemoryDisplay::mark_lines_dirty`].
+    pub fn set_rotation(&mut self, rotation: Rotation) {
+        self.rotation = rotation;
+    }
 
-    fn draw_iter<T>(&mut self, item_pixels: T) -> Result<(), E>
-    where
-        T: IntoIterator<Item = Pixel<Self::Color>>,
-    {
-        for Pixel(coord, color) in item_pixels {
-            if coord.x < 0 || coord.x >= (display::WIDTH as i32) || coord.y < 0 || coord.y >= (display::HEIGHT as i32) {
-                // Ignore attempts to draw outside of display bounds, continue to next pixel
-                continue
-            } else {
-                unsafe { self.set_pixel(coord.x as u32, coord.y as u32, color) };
+    pub fn rotation(&self) -> Rotation {
+        self.rotation
+    }
+
+    /// Mirror everything drawn from now on horizontally (`x`) and/or vertically (`y`), e.g. for
+    /// a panel mounted behind a mirror or upside down in a housing. Content drawn before stays
+    /// as it is.
+    ///
+    /// Mirroring applies to the panel, after the rotation: `x` reverses the pixels of every
+    /// line, `y` the order of the lines. It doesn't change the size of the drawable area.
+    pub fn set_mirror(&mut self, x: bool, y: bool) {
+        self.mirror = (x, y);
+    }
+
+    /// Whether the columns and lines are mirrored, see [`MemoryDisplay::set_mirror`].
+    pub fn mirror(&self) -> (bool, bool) {
+        self.mirror
+    }
+
+    /// The value of a single pixel in the framebuffer, `None` outside it. This is what the
+    /// next flush sends, not necessarily what the panel shows.
+    pub fn get_pixel(&self, x: u32, y: u32) -> Option<Color> {
+        let (x, y) = (x as usize, y as usize);
+        let (width, height) = self.drawable_size();
+        (x < width && y < height).then(|| {
+            let (x, y) = self.to_physical(x, y);
+            self.frame.borrow().buffer.get(x, y).into()
+        })
+    }
+
+    /// Line `y` of the framebuffer as it is sent, see [`MemoryDisplay::set_invert`].
+    fn line_to_send(&self, y: usize) -> SPEC::Line {
+        let mut line = self.frame.borrow().buffer.line(y);
+        if self.invert {
+            line.as_mut().iter_mut().for_each(|byte| *byte = !*byte);
+        }
+        line
+    }
+
+    /// Copy the framebuffer to `out` in natural order, e.g. to compare it against golden data
+    /// in tests or to wrap it in an `ImageRaw<BinaryColor>` of `embedded-graphics`.
+    ///
+    /// Every line takes `width.div_ceil(8)` bytes, for the width of the framebuffer, with the
+    /// leftmost pixel in the most significant bit of its first byte, and the padding bits at
+    /// the end of a line cleared. A set bit is [`Color::On`]. This is independent of the storage
+    /// layout, and like [`MemoryDisplay::buffer`] refers to the panel regardless of the rotation.
+    ///
+    /// Returns [`OutOfBounds`] if `out` cannot hold every line, leaving it untouched.
+    pub fn to_packed_rows(&self, out: &mut [u8]) -> Result<(), OutOfBounds> {
+        let Some(out) = out.get_mut(..SPEC::LINE_BYTES * SPEC::BUFFER_HEIGHT) else {
+            return Err(OutOfBounds);
+        };
+        let padding = if SPEC::BUFFER_WIDTH.is_multiple_of(8) {
+            0xFF
+        } else {
+            !(0xFF >> (SPEC::BUFFER_WIDTH % 8))
+        };
+        for (y, dst) in out.chunks_exact_mut(SPEC::LINE_BYTES).enumerate() {
+            for (dst, byte) in dst
+                .iter_mut()
+                .zip(self.frame.borrow().buffer.line(y).as_ref())
+            {
+                *dst = byte.reverse_bits();
+            }
+            dst[SPEC::LINE_BYTES - 1] &= padding;
+        }
+        Ok(())
+    }
+
+    /// Write the framebuffer to `w` as an ASCII PBM (P1) image, e.g. over RTT or semihosting
+    /// for viewing a frame on the host. Like [`MemoryDisplay::to_packed_rows`] it refers to the
+    /// panel regardless of the rotation. [`Color::Off`] is black, a 1 in PBM.
+    pub fn dump_pbm<W: core::fmt::Write>(&self, w: &mut W) -> core::fmt::Result {
+        write!(w, "P1\n{} {}\n", SPEC::BUFFER_WIDTH, SPEC::BUFFER_HEIGHT)?;
+        let buffer = &self.frame.borrow().buffer;
+        for y in 0..SPEC::BUFFER_HEIGHT {
+            // PBM asks for lines of at most 70 characters
+            for x in 0..SPEC::BUFFER_WIDTH {
+                let separator = if x % 32 == 31 || x == SPEC::BUFFER_WIDTH - 1 {
+                    '\n'
+                } else {
+                    ' '
+                };
+                write!(w, "{}{}", !buffer.get(x, y) as u8, separator)?;
             }
         }
         Ok(())
     }
+
+    /// Write the framebuffer to `w` as an XBM image, C source declaring `name_width`,
+    /// `name_height` and the array `name_bits`. [`Color::Off`] is black, a set bit in XBM.
+    pub fn dump_xbm<W: core::fmt::Write>(&self, name: &str, w: &mut W) -> core::fmt::Result {
+        writeln!(w, "#define {name}_width {}", SPEC::BUFFER_WIDTH)?;
+        writeln!(w, "#define {name}_height {}", SPEC::BUFFER_HEIGHT)?;
+        write!(w, "static unsigned char {name}_bits[] = {{")?;
+        let frame = self.frame.borrow();
+        let bytes = (0..SPEC::BUFFER_HEIGHT).flat_map(|y| {
+            let line = frame.buffer.line(y);
+            (0..SPEC::LINE_BYTES).map(move |byte| line.as_ref()[byte])
+        });
+        for (i, byte) in bytes.enumerate() {
+            // XBM has the leftmost pixel in the least significant bit, like the framebuffer
+            let padding = i % SPEC::LINE_BYTES == SPEC::LINE_BYTES - 1
+                && !SPEC::BUFFER_WIDTH.is_multiple_of(8);
+            let mask = if padding {
+                !(0xFF << (SPEC::BUFFER_WIDTH % 8))
+            } else {
+                0xFF
+            };
+            let separator = if i == 0 { "" } else { "," };
+            let indent = if i % 12 == 0 { "\n   " } else { "" };
+            write!(w, "{separator}{indent} 0x{:02x}", !byte & mask)?;
+        }
+        w.write_str(" };\n")
+    }
+
+    /// Copy `rows` into the framebuffer, the reverse of [`MemoryDisplay::to_packed_rows`] and
+    /// in the same layout, e.g. the data of an `ImageRaw<BinaryColor>` or of a frame rendered by
+    /// other code. Only the lines which change are marked dirty.
+    ///
+    /// `rows` holds exactly every line, `width.div_ceil(8)` bytes each for the width of the
+    /// framebuffer; an array of any other length fails to compile.
+    pub fn from_packed_rows<const N: usize>(&mut self, rows: &[u8; N]) {
+        const {
+            assert!(
+                N == SPEC::LINE_BYTES * SPEC::BUFFER_HEIGHT,
+                "not a whole frame"
+            )
+        };
+        let FrameBuffer { buffer, touched } = self.frame.borrow_mut();
+        for (y, row) in rows.chunks_exact(SPEC::LINE_BYTES).enumerate() {
+            let mut line = SPEC::Line::ZERO;
+            for (dst, byte) in line.as_mut().iter_mut().zip(row) {
+                *dst = byte.reverse_bits();
+            }
+            if buffer.line(y) != line {
+                buffer.set_line(y, &line);
+                touched.set(y, true);
+            }
+        }
+    }
+
+    /// Width and height of the drawable area in pixels, taking the rotation into account.
+    fn drawable_size(&self) -> (usize, usize) {
+        match self.rotation {
+            Rotation::Deg0 | Rotation::Deg180 => (SPEC::BUFFER_WIDTH, SPEC::BUFFER_HEIGHT),
+            Rotation::Deg90 | Rotation::Deg270 => (SPEC::BUFFER_HEIGHT, SPEC::BUFFER_WIDTH),
+        }
+    }
+
+    /// Columns and rows of the drawable area which drawing is restricted to, the whole of it
+    /// unless a clip is set.
+    fn clip_area(&self) -> (Range<usize>, Range<usize>) {
+        let (width, height) = self.drawable_size();
+        match &self.clip {
+            // The clip may have been set before a rotation swapping width and height
+            Some((x, y)) => (
+                x.start.min(width)..x.end.min(width),
+                y.start.min(height)..y.end.min(height),
+            ),
+            None => (0..width, 0..height),
+        }
+    }
+
+    /// Position in the framebuffer of a pixel of the drawable area.
+    fn to_physical(&self, x: usize, y: usize) -> (usize, usize) {
+        mirror_position::<SPEC>(self.mirror, self.rotation.to_physical::<SPEC>(x, y))
+    }
+
+    /// Whether the drawable area maps to the framebuffer as it is, neither rotated nor mirrored.
+    #[cfg_attr(not(feature = "glyphs"), allow(dead_code))]
+    fn is_untransformed(&self) -> bool {
+        self.rotation == Rotation::Deg0 && self.mirror == (false, false)
+    }
+
+    /// Columns and lines of the framebuffer covered by a rectangle of the drawable area.
+    #[cfg_attr(not(feature = "graphics"), allow(dead_code))]
+    fn physical_rect(&self, x: Range<usize>, y: Range<usize>) -> (Range<usize>, Range<usize>) {
+        let flip = |range: Range<usize>, len: usize| len - range.end..len - range.start;
+        let (x, y) = match self.rotation {
+            Rotation::Deg0 => (x, y),
+            Rotation::Deg90 => (flip(y, SPEC::BUFFER_WIDTH), x),
+            Rotation::Deg180 => (flip(x, SPEC::BUFFER_WIDTH), flip(y, SPEC::BUFFER_HEIGHT)),
+            Rotation::Deg270 => (y, flip(x, SPEC::BUFFER_HEIGHT)),
+        };
+        let (mirror_x, mirror_y) = self.mirror;
+        (
+            if mirror_x {
+                flip(x, SPEC::BUFFER_WIDTH)
+            } else {
+                x
+            },
+            if mirror_y {
+                flip(y, SPEC::BUFFER_HEIGHT)
+            } else {
+                y
+            },
+        )
+    }
 }
 
 impl<SPI, CS, DISP, E> MemoryDisplay<SPI, CS, DISP>
@@ -154,22 +896,66 @@ where
     /// Create a new instance of `MemoryDisplay`.
     ///
     /// Please issue a `clear` before drawing to the display.
-    pub fn new(spi: SPI, mut cs: CS, mut disp: DISP) -> Self {
+    pub fn new(spi: SPI, cs: CS, disp: DISP) -> Self {
+        Self::new_with_buffer(spi, cs, disp, FrameBuffer::new())
+    }
+}
+
+impl<SPI, CS, DISP, E, SPEC> MemoryDisplay<SPI, CS, DISP, SPEC>
+where
+    SPI: Write<u8, Error = E>,
+    CS: OutputPin,
+    DISP: OutputPin,
+    SPEC: DisplaySpec,
+{
+    /// Same as [`MemoryDisplay::new`] for the model `SPEC` instead of the one selected via the
+    /// feature flag, e.g. `MemoryDisplay::new_with_spec(spi, cs, disp, spec::Ls013b7dh05)` for
+    /// a second display of another model.
+    pub fn new_with_spec(spi: SPI, cs: CS, disp: DISP, _spec: SPEC) -> Self {
+        Self::new_with_buffer(spi, cs, disp, FrameBuffer::new())
+    }
+}
+
+impl<SPI, CS, DISP, E, SPEC, BUF> MemoryDisplay<SPI, CS, DISP, SPEC, BUF>
+where
+    SPI: Write<u8, Error = E>,
+    CS: OutputPin,
+    DISP: OutputPin,
+    SPEC: DisplaySpec,
+    BUF: BorrowMut<FrameBuffer<SPEC>>,
+{
+    /// Create an instance of `MemoryDisplay` with its framebuffer in `frame` instead of within
+    /// itself, see [`FrameBuffer`]. Whatever `frame` holds is kept, including its dirty lines.
+    ///
+    /// Please issue a `clear` before drawing to the display.
+    pub fn new_with_buffer(spi: SPI, mut cs: CS, mut disp: DISP, frame: BUF) -> Self {
         let _ = disp.set_low();
         let _ = cs.set_low();
 
-        // The framebuffer: a byte-array for every line
-        let buffer = [bitarr![u8, Lsb0; 0; display::WIDTH]; display::HEIGHT];
-        let touched = bitarr![u8, Lsb0; 0; display::HEIGHT];
-
         Self {
             spi,
             cs,
             disp,
-            buffer,
-            touched,
+            frame,
             vcom: Vcom::Hi,
-            clear_state: BinaryColor::On,
+            vcom_mode: VcomMode::Software,
+            vcom_sent: false,
+            enabled: false,
+            asleep: false,
+            invert: false,
+            scroll_offset: 0,
+            clear_state: Color::On,
+            cs_strategy: CsStrategy::Software,
+            rotation: Rotation::Deg0,
+            mirror: (false, false),
+            clip: None,
+            max_transfer_len: None,
+            extcomin: None,
+            sclk_hz: SPEC::MAX_SCLK_HZ,
+            in_flight: DirtyLines::new(),
+            committed: None,
+            #[cfg(feature = "transaction-buffer")]
+            transaction: Zeroed::ZERO,
         }
     }
 
@@ -177,115 +963,2185 @@ where
     /// Note that this might be different from the state the hardware will set itself to.
     /// You'll need to execute a flush_buffer following the call to clear if the
     /// desired state differs from the default one in the hardware.
-    pub fn set_clear_state(&mut self, clear_state: BinaryColor) {
-        self.clear_state = clear_state;
+    ///
+    /// It applies to [`MemoryDisplay::clear`], [`MemoryDisplay::clear_buffer`] and the lines
+    /// vacated by scrolling the whole buffer. [`MemoryDisplay::clear_to`] and the `clear` of
+    /// `DrawTarget` take their color as an argument instead.
+    pub fn set_clear_state(&mut self, clear_state: impl Into<Color>) {
+        self.clear_state = clear_state.into();
     }
 
-    /// Enable the LCD by driving the display pin high.
-    pub fn enable(&mut self) {
-        let _ = self.disp.set_high();
+    /// The VCOM polarity of the most recently sent command. The next command will be sent with
+    /// the opposite polarity.
+    ///
+    /// Store this value somewhere which survives a reset (e.g. an RTC backup register) and
+    /// restore it with [`MemoryDisplay::set_vcom_phase`] to keep VCOM alternating correctly.
+    pub fn vcom_phase(&self) -> Vcom {
+        self.vcom
     }
 
-    /// Disable the LCD.
-    pub fn disable(&mut self) {
-        let _ = self.disp.set_low();
+    /// Restore a VCOM phase previously obtained from [`MemoryDisplay::vcom_phase`]. The next
+    /// command will be sent with the opposite polarity.
+    ///
+    /// Ignored with [`VcomMode::External`].
+    pub fn set_vcom_phase(&mut self, phase: Vcom) {
+        if self.vcom_mode == VcomMode::Software {
+            self.vcom = phase;
+        }
     }
 
-    /// Sets a single pixel value in the internal framebuffer.
+    /// Set where the panel takes its VCOM signal from, matching the level of its EXTMODE pin.
     ///
-    /// N.B. This function does no bounds checking! Attempting to draw
-    /// to a location outside the bounds of the display will result in
-    /// a panic.
-    pub unsafe fn set_pixel(&mut self, x: u32, y: u32, val: BinaryColor) {
-        let line_buffer = &mut self.buffer[y as usize];
-        line_buffer.set(x as usize, val.is_on());
-        self.touched.set(y as usize, true);
+    /// With [`VcomMode::External`] the M1 bit of every command is sent as 0 and
+    /// [`MemoryDisplay::vcom_phase`] always returns [`Vcom::Lo`]. Switching back to
+    /// [`VcomMode::Software`] starts toggling again from there.
+    pub fn set_vcom_mode(&mut self, mode: VcomMode) {
+        self.vcom_mode = mode;
+        if mode == VcomMode::External {
+            self.vcom = Vcom::Lo;
+        }
     }
 
-    /// Draw all lines of the buffer to the screen which have changed since last calling this
-    /// function.
-    pub fn flush_buffer(&mut self) {
-        let _ = self.cs.set_high();
+    /// Where the panel takes its VCOM signal from, see [`MemoryDisplay::set_vcom_mode`].
+    pub fn vcom_mode(&self) -> VcomMode {
+        self.vcom_mode
+    }
 
-        self.vcom = !self.vcom;
-        let _ = self.spi.write(&[Command::WriteLine | self.vcom]);
+    /// Toggle the VCOM bit for the next command, unless VCOM is taken from EXTCOMIN.
+    fn toggle_vcom(&mut self) {
+        self.vcom_sent = true;
+        if self.vcom_mode == VcomMode::Software {
+            self.vcom = !self.vcom;
+        }
+    }
 
-        // Pack buffer into byte form and send
-        for y in self.touched.iter_ones() {
-            // Known problem with BitArr where if it's length isn't exactly divisible by the underlying storage size
-            // it will return indexes greater than its length. Break loop early if we've exceeded the size of buffer.
-            // https://github.com/bitvecto-rs/bitvec/issues/159 for details.
-            if y >= self.buffer.len() {
-                break;
-            }
-            // Write line number (starting at 1)
-            let line_no = (y + 1) as u8;
-            let line_no_bits_msb = BitSlice::<u8, Lsb0>::from_element(&line_no);
-            let line_no_bits = Self::swap(line_no_bits_msb);
-
-            let line_buffer_msb = self.buffer[y as usize];
-
-            let mut write_buffer = [0u8; WRITE_BUFFER_SIZE];
-            write_buffer[0] = line_no_bits;
-
-            let mut chunks = line_buffer_msb.chunks(8);
-            (1..(write_buffer.len() - 1)).for_each(|x| {
-                write_buffer[x] = Self::swap(chunks.next().unwrap());
-            });
-            // Technically this is supposed to be part of the address of the following line, but we'll just send it here because it's easier
-            write_buffer[write_buffer.len() - 1] = DUMMY_DATA;
-            let _ = self.spi.write(&write_buffer);
+    /// Swap in a new SPI bus, e.g. after reconfiguring the peripheral, and return the old one.
+    /// The framebuffer, the dirty lines and the VCOM phase are kept, so the next flush sends
+    /// everything drawn before the swap.
+    pub fn replace_spi(&mut self, spi: SPI) -> SPI {
+        core::mem::replace(&mut self.spi, spi)
+    }
+
+    /// Same as [`MemoryDisplay::replace_spi`], but allows the new bus to be of a different type.
+    pub fn map_spi<SPI2, E2>(
+        self,
+        f: impl FnOnce(SPI) -> SPI2,
+    ) -> MemoryDisplay<SPI2, CS, DISP, SPEC, BUF>
+    where
+        SPI2: Write<u8, Error = E2>,
+    {
+        MemoryDisplay {
+            spi: f(self.spi),
+            cs: self.cs,
+            disp: self.disp,
+            frame: self.frame,
+            vcom: self.vcom,
+            vcom_mode: self.vcom_mode,
+            vcom_sent: self.vcom_sent,
+            enabled: self.enabled,
+            asleep: self.asleep,
+            invert: self.invert,
+            scroll_offset: self.scroll_offset,
+            clear_state: self.clear_state,
+            cs_strategy: self.cs_strategy,
+            rotation: self.rotation,
+            mirror: self.mirror,
+            clip: self.clip,
+            max_transfer_len: self.max_transfer_len,
+            extcomin: self.extcomin,
+            sclk_hz: self.sclk_hz,
+            in_flight: self.in_flight,
+            committed: self.committed,
+            #[cfg(feature = "transaction-buffer")]
+            transaction: self.transaction,
         }
+    }
 
-        // Write the 16-bit frame trailer (first 8 bits come from the end of the last line written)
-        let _ = self.spi.write(&[DUMMY_DATA]);
+    /// Give back the bus and the pins, e.g. to deinitialise the bus before a deep sleep. Chip
+    /// select is driven low, the display pin is left as it is, so the panel keeps showing its
+    /// image unless [`MemoryDisplay::disable`] is called first. The framebuffer is dropped.
+    ///
+    /// A display constructed again from them starts with a blank framebuffer. Drawing the
+    /// content again, or [`MemoryDisplay::mark_all_dirty`] after restoring it, repaints the
+    /// panel; the panel memory itself needs no clear. The constructors drive the display pin
+    /// low, so enable the display again afterwards, and stash [`MemoryDisplay::vcom_phase`] to
+    /// keep VCOM alternating across the two.
+    pub fn release(self) -> (SPI, CS, DISP) {
+        let (spi, cs, disp, _) = self.release_with_buffer();
+        (spi, cs, disp)
+    }
 
+    /// Same as [`MemoryDisplay::release`], but also gives back the framebuffer including its
+    /// dirty lines, to continue where it left off with [`MemoryDisplay::new_with_buffer`].
+    pub fn release_with_buffer(mut self) -> (SPI, CS, DISP, BUF) {
         let _ = self.cs.set_low();
+        (self.spi, self.cs, self.disp, self.frame)
+    }
 
-        self.touched.fill(false);
+    /// Set how chip select is framed while flushing. See [`CsStrategy`] for details.
+    pub fn set_cs_strategy(&mut self, cs_strategy: CsStrategy) {
+        self.cs_strategy = cs_strategy;
     }
 
-    /// Contrary to the MSB order most SPI devices use, the memory-in-pixel displays use LSB byte
-    /// order. This function swaps the order of a single byte (viewed via a `BitSlice`) and converts it to `u8`.
-    pub fn swap(byte: &BitSlice<u8, Lsb0>) -> u8 {
-        // View slice with most-significant bit first (inverted)
-        let mut local_buffer = bitarr!(u8, Msb0; 0; 8);
-        for (i, bit) in byte.iter().by_ref().enumerate() {
-            local_buffer.set(i, *bit);
+    /// Limit the number of bytes passed to a single SPI write, e.g. for Linux `spidev` which
+    /// rejects transfers larger than its `bufsiz` parameter (4096 bytes by default, see
+    /// [`spidev_bufsiz`]). `None` removes the limit.
+    ///
+    /// Every write of the driver then stays within the limit, and is only ever split between
+    /// lines. This changes what [`CsStrategy::Hardware`] sends, which then splits a frame
+    /// into several complete transactions, and the size of the bursts of lines of
+    /// [`CsStrategy::Software`]. Every other write, including those of
+    /// [`MemoryDisplay::clear`] and [`MemoryDisplay::display_mode`], is at most one line plus
+    /// the command and trailer bytes, so a limit shorter than that is rejected.
+    pub fn set_max_transfer_len(&mut self, len: Option<usize>) -> Result<(), TransferLenTooShort> {
+        match len {
+            Some(len) if len < SPEC::WRITE_BUFFER_SIZE + SPEC::FRAME_OVERHEAD => {
+                Err(TransferLenTooShort)
+            }
+            _ => {
+                self.max_transfer_len = len;
+                Ok(())
+            }
         }
-        local_buffer.load::<u8>()
     }
 
-    /// Clear just the internal framebuffer, without writing changes to the display.
-    pub fn clear_buffer(&mut self) {
-        for y in 0..(self.size().height as usize) {
-            let line_buffer = &mut self.buffer[y];
-            line_buffer.fill(self.clear_state.is_on());
+    /// Number of lines which fit into a single transaction given the transfer limit.
+    #[cfg(feature = "transaction-buffer")]
+    fn lines_per_transaction(&self) -> usize {
+        self.max_transfer_len.map_or(SPEC::HEIGHT, |len| {
+            (len - SPEC::FRAME_OVERHEAD) / SPEC::WRITE_BUFFER_SIZE
+        })
+    }
+
+    /// Number of bytes sent when flushing the given number of lines.
+    fn transmitted_len(&self, lines: usize) -> usize {
+        match self.cs_strategy {
+            CsStrategy::Software => lines * SPEC::WRITE_BUFFER_SIZE + SPEC::FRAME_OVERHEAD,
+            CsStrategy::PerLine => {
+                (lines * (SPEC::WRITE_BUFFER_SIZE + SPEC::FRAME_OVERHEAD)).max(2)
+            }
+            #[cfg(feature = "transaction-buffer")]
+            CsStrategy::Hardware => {
+                let transactions = lines.div_ceil(self.lines_per_transaction()).max(1);
+                lines * SPEC::WRITE_BUFFER_SIZE + SPEC::FRAME_OVERHEAD * transactions
+            }
         }
-        self.touched.fill(true);
     }
 
-    /// Clear the screen and the internal framebuffer.
-    pub fn clear(&mut self) {
-        self.clear_buffer();
-        self.vcom = !self.vcom;
-        self.write_spi(&[Command::ClearMemory | self.vcom, DUMMY_DATA]);
+    /// Enable the LCD by driving the display pin high.
+    pub fn enable(&mut self) -> Result<(), Error<E, DISP::Error>> {
+        self.disp.set_high().map_err(Error::Disp)?;
+        self.enabled = true;
+        Ok(())
     }
 
-    /// Puts the display into power saving mode. This can also be used to send
-    /// the VCOM signal which Sharp recommends sending at least once a second.
-    /// No actual harm seems to come from failing to do so however.
-    pub fn display_mode(&mut self) {
-        self.vcom = !self.vcom;
-        self.write_spi(&[Command::Nop | self.vcom, DUMMY_DATA]);
+    /// Disable the LCD.
+    pub fn disable(&mut self) -> Result<(), Error<E, DISP::Error>> {
+        self.disp.set_low().map_err(Error::Disp)?;
+        self.enabled = false;
+        Ok(())
     }
 
-    /// Internal function for handling the chip select
-    fn write_spi(&mut self, data: &[u8]) {
-        let _ = self.cs.set_high();
+    /// Whether the display has been enabled with [`MemoryDisplay::enable`] or
+    /// [`MemoryDisplay::wake`] since it was constructed or last disabled.
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
 
-        let _ = self.spi.write(data);
+    /// Whether the display is asleep, see [`MemoryDisplay::sleep`].
+    pub fn is_asleep(&self) -> bool {
+        self.asleep
+    }
 
-        let _ = self.cs.set_low();
+    /// Sets a single pixel value in the internal framebuffer, marking its line dirty if the value
+    /// changes. Coordinates outside the framebuffer leave it and the dirty lines untouched.
+    ///
+    /// A pixel outside the clip, see `set_clip` with the `graphics` feature, is discarded
+    /// without an error.
+    pub fn set_pixel(&mut self, x: u32, y: u32, val: impl Into<Color>) -> Result<(), OutOfBounds> {
+        let (width, height) = self.drawable_size();
+        if (x as usize) < width && (y as usize) < height {
+            let (clip_x, clip_y) = self.clip_area();
+            if clip_x.contains(&(x as usize)) && clip_y.contains(&(y as usize)) {
+                // Just checked
+                unsafe { self.set_pixel_unchecked(x, y, val) };
+            }
+            Ok(())
+        } else {
+            Err(OutOfBounds)
+        }
+    }
+
+    /// Same as [`MemoryDisplay::set_pixel`] without the bounds check, for hot paths. The clip
+    /// doesn't apply either.
+    ///
+    /// # Safety
+    /// N.B. This function does no bounds checking! Attempting to draw
+    /// to a location outside the bounds of the display will result in
+    /// a panic.
+    pub unsafe fn set_pixel_unchecked(&mut self, x: u32, y: u32, val: impl Into<Color>) {
+        let (x, y) = self.to_physical(x as usize, y as usize);
+        let on = val.into().is_on();
+        let frame = self.frame.borrow_mut();
+        if frame.buffer.get(x, y) != on {
+            frame.buffer.set(x, y, on);
+            frame.touched.mark_columns(y, x..x + 1);
+        }
+    }
+
+    /// Direct access to the framebuffer, one array of bytes per line.
+    ///
+    /// Pixel `x` of a line is stored in bit `x % 8` of byte `x / 8`, i.e. the leftmost pixel of
+    /// each byte is its least significant bit. A set bit is [`Color::On`] (white on the
+    /// panel). On the wire this order is reversed: every byte is sent most significant bit first
+    /// after being bit-reversed, so pixels are transmitted left to right.
+    ///
+    /// Not available with the `column-major` feature, which stores the framebuffer by column.
+    #[cfg(not(feature = "column-major"))]
+    pub fn buffer(&self) -> &SPEC::PerLine<SPEC::Line> {
+        &self.frame.borrow().buffer.lines
+    }
+
+    /// Mutable access to the framebuffer, see [`MemoryDisplay::buffer`] for the layout.
+    ///
+    /// The driver cannot track writes through this reference, so affected lines have to be
+    /// marked with [`MemoryDisplay::mark_lines_dirty`] or [`MemoryDisplay::mark_all_dirty`]
+    /// for them to be sent by the next flush.
+    #[cfg(not(feature = "column-major"))]
+    pub fn buffer_mut(&mut self) -> &mut SPEC::PerLine<SPEC::Line> {
+        &mut self.frame.borrow_mut().buffer.lines
+    }
+
+    /// Replace line `y` of the framebuffer with pre-packed pixels, marking it dirty if it
+    /// changes. `packed` is laid out like a line of [`MemoryDisplay::buffer`], least
+    /// significant bit first, and has to hold at least a whole line; further bytes are ignored.
+    ///
+    /// Like the other line based methods `y` is a line of the panel, regardless of the rotation.
+    pub fn write_line(&mut self, y: usize, packed: &[u8]) -> Result<(), OutOfBounds> {
+        let Some(packed) = packed
+            .get(..SPEC::LINE_BYTES)
+            .filter(|_| y < SPEC::BUFFER_HEIGHT)
+        else {
+            return Err(OutOfBounds);
+        };
+        let mut line = SPEC::Line::ZERO;
+        line.as_mut().copy_from_slice(packed);
+        let FrameBuffer { buffer, touched } = self.frame.borrow_mut();
+        if buffer.line(y) != line {
+            buffer.set_line(y, &line);
+            touched.set(y, true);
+        }
+        Ok(())
+    }
+
+    /// Mark a range of lines to be sent by the next flush. Lines outside the display are ignored.
+    pub fn mark_lines_dirty(&mut self, lines: Range<usize>) {
+        let end = lines.end.min(SPEC::BUFFER_HEIGHT);
+        if lines.start < end {
+            self.frame.borrow_mut().touched.mark(lines.start..end);
+        }
+    }
+
+    /// Mark line `y` to be sent by the next flush. A line outside the display is ignored.
+    pub fn mark_line_dirty(&mut self, y: usize) {
+        self.mark_lines_dirty(y..y.saturating_add(1));
+    }
+
+    /// Mark every line to be sent by the next flush.
+    pub fn mark_all_dirty(&mut self) {
+        self.frame.borrow_mut().touched.fill(true);
+    }
+
+    /// Forget which lines changed, e.g. when the panel is known to show the framebuffer
+    /// already. The next flush then only sends lines changed from now on. Has no effect with
+    /// the `minimal-flush` feature.
+    pub fn clear_dirty(&mut self) {
+        self.frame.borrow_mut().touched.fill(false);
+        if let Some(committed) = &mut self.committed {
+            committed.fill(false);
+        }
+    }
+
+    /// Whether any line is dirty, i.e. the next flush sends more than the command, e.g. to
+    /// decide whether to power up the bus for it. Always true with the `minimal-flush`
+    /// feature.
+    pub fn is_dirty(&self) -> bool {
+        self.pending_lines().any()
+    }
+
+    /// Number of lines of the framebuffer the next flush sends.
+    pub fn dirty_line_count(&self) -> usize {
+        self.pending_lines().count()
+    }
+
+    /// The first and last column of line `y` of the framebuffer changed since it was last
+    /// sent, or `None` if the line isn't dirty or outside the framebuffer. Both are columns of
+    /// the framebuffer, not of the rotated drawable area.
+    ///
+    /// Columns are only tracked with the `dirty-columns` feature, which takes 4 bytes of RAM
+    /// per line; without it every dirty line spans the whole width.
+    pub fn dirty_extent(&self, y: usize) -> Option<(u16, u16)> {
+        if y >= SPEC::BUFFER_HEIGHT {
+            return None;
+        }
+        self.pending_lines().columns(y)
+    }
+
+    /// Send the whole framebuffer, whether its lines are dirty or not, e.g. after the panel
+    /// memory has been lost.
+    pub fn redraw_all(&mut self) -> Result<(), Error<E, CS::Error>> {
+        self.mark_all_dirty();
+        self.flush_buffer()
+    }
+
+    /// Draw all lines of the buffer to the screen which have changed since last calling this
+    /// function.
+    ///
+    /// A line stops being dirty once the write completing it succeeded. If the bus or chip
+    /// select fails the flush is aborted, with chip select deasserted, and the lines not sent
+    /// yet are kept dirty, including the one being sent, so the next flush sends them again.
+    /// See [`MemoryDisplay::flush_buffer_with_retries`] for retrying right away. While the
+    /// display is asleep nothing is sent and [`Error::Asleep`] is returned.
+    pub fn flush_buffer(&mut self) -> Result<(), Error<E, CS::Error>> {
+        self.flush_buffer_with_progress(|_| {})
+    }
+
+    /// Same as [`MemoryDisplay::flush_buffer`], but reports how much was sent, e.g. to skip
+    /// waiting for the next frame when nothing changed, or to track the bandwidth used.
+    pub fn flush_buffer_stats(&mut self) -> Result<FlushStats, Error<E, CS::Error>> {
+        let stats = self.pending_stats();
+        self.flush_buffer()?;
+        Ok(stats)
+    }
+
+    /// What the next flush would send, without any waiting.
+    fn pending_stats(&self) -> FlushStats {
+        let lines_written = self.dirty_line_count();
+        let bytes_written = self.transmitted_len(lines_written * Y_SCALE);
+        let bits = bytes_written as u64 * 8;
+        FlushStats {
+            lines_written,
+            bytes_written,
+            estimated_ms: (bits * 1000).div_ceil(self.sclk_hz as u64) as u32,
+            ..FlushStats::default()
+        }
+    }
+
+    /// Send at most `max_lines` of the dirty lines, in ascending order, and return how many
+    /// are left dirty, to spread a large update over several iterations of a main loop with a
+    /// fixed time budget. Once it returns 0 the panel shows the whole framebuffer.
+    ///
+    /// Each call is a complete flush of its own, toggling VCOM once, so a frame sent in `n`
+    /// parts toggles it `n` times. If the flush fails, the lines it didn't send stay dirty like
+    /// with [`MemoryDisplay::flush_buffer`]. With the `minimal-flush` feature every call sends
+    /// the whole frame and returns 0.
+    pub fn flush_lines(&mut self, max_lines: usize) -> Result<usize, Error<E, CS::Error>> {
+        self.presenting(|display| {
+            let touched = &mut display.frame.borrow_mut().touched;
+            let dirty = *touched;
+            // Lines beyond the first `max_lines` wait for the next call
+            for y in dirty.iter().skip(max_lines) {
+                touched.set(y, false);
+            }
+
+            let result = display.flush_buffer();
+            let touched = &mut display.frame.borrow_mut().touched;
+            for y in dirty.iter().skip(max_lines) {
+                touched.merge(y, &dirty);
+            }
+            result?;
+            Ok(if cfg!(feature = "minimal-flush") {
+                0
+            } else {
+                touched.count()
+            })
+        })
+    }
+
+    /// Same as [`MemoryDisplay::flush_buffer`], but if the bus fails the lines left dirty are
+    /// sent again right away, up to `retries` times in a row without a line getting through,
+    /// e.g. for boards where noise causes the occasional overrun. Every attempt is a flush of
+    /// its own, toggling VCOM. Other errors are returned right away, as is the error of the bus
+    /// once no retries are left.
+    pub fn flush_buffer_with_retries(&mut self, retries: u32) -> Result<(), Error<E, CS::Error>> {
+        let mut failed = 0;
+        loop {
+            let left = self.dirty_line_count();
+            match self.flush_buffer() {
+                Err(Error::Spi(error)) => {
+                    // Only count the failures since the last line got through
+                    if self.dirty_line_count() < left {
+                        failed = 0;
+                    }
+                    if failed == retries {
+                        return Err(Error::Spi(error));
+                    }
+                    failed += 1;
+                    debug!("flush: retrying, attempt {}", failed);
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Same as [`MemoryDisplay::flush_buffer`], ignoring errors like before it returned them.
+    #[deprecated(note = "use `flush_buffer` and handle its errors")]
+    pub fn flush_buffer_infallible(&mut self) {
+        let _ = self.flush_buffer();
+    }
+
+    /// Same as [`MemoryDisplay::flush_buffer`], but invokes `progress` after each line has been
+    /// transmitted. This can be used to feed a watchdog or yield to a scheduler during long
+    /// flushes. Note that chip select stays asserted while the callback runs.
+    ///
+    /// With [`CsStrategy::Hardware`] the callback is invoked as each line is packed into the
+    /// transaction buffer, before the single transfer starts, and likewise with
+    /// [`CsStrategy::Software`] as each line is packed into its burst.
+    pub fn flush_buffer_with_progress(
+        &mut self,
+        progress: impl FnMut(FlushProgress),
+    ) -> Result<(), Error<E, CS::Error>> {
+        self.presenting(|display| display.send_dirty(progress))
+    }
+
+    /// Send the dirty lines, the body of [`MemoryDisplay::flush_buffer_with_progress`].
+    fn send_dirty(
+        &mut self,
+        mut progress: impl FnMut(FlushProgress),
+    ) -> Result<(), Error<E, CS::Error>> {
+        if self.asleep {
+            return Err(Error::Asleep);
+        }
+        self.toggle_vcom();
+
+        let total = self.frame.borrow().touched.count();
+
+        match self.cs_strategy {
+            CsStrategy::Software => {
+                self.cs.set_high().map_err(Error::Cs)?;
+                let result = self.write_frame(total, &mut progress);
+                // Deassert chip select even if the bus failed, ending the transaction
+                let deselect = self.cs.set_low().map_err(Error::Cs);
+                result.and(deselect)?;
+            }
+            CsStrategy::PerLine => {
+                let command = Command::WriteLine | self.vcom;
+
+                if total == 0 {
+                    // Nothing to draw, but still send the command so VCOM keeps toggling
+                    self.write_spi(&[command, DUMMY_DATA])?;
+                }
+
+                let dirty = self.frame.borrow().touched;
+                for (sent, y) in dirty.iter().enumerate() {
+                    let line = self.line_to_send(y);
+                    for row in self.panel_lines(y) {
+                        let transaction = Self::line_frame(command, row, &line);
+                        transfer(&mut self.spi, &mut self.cs, transaction.as_ref())?;
+                    }
+                    self.frame.borrow_mut().touched.set(y, false);
+
+                    #[cfg(feature = "verbose-log")]
+                    trace!("flush: line {}", y + 1);
+
+                    progress(FlushProgress {
+                        line: y,
+                        lines_sent: sent + 1,
+                        lines_total: total,
+                    });
+                }
+            }
+            #[cfg(feature = "transaction-buffer")]
+            CsStrategy::Hardware => {
+                let lines_per_transaction = self.lines_per_transaction();
+                let command = Command::WriteLine | self.vcom;
+                self.transaction.as_mut()[0] = command;
+                let mut len = 1;
+                let mut rows_sent = 0;
+                let dirty = self.frame.borrow().touched;
+                // The lines packed completely since the last transfer, cleared once it succeeds
+                let mut unsent = dirty.iter();
+                let mut packed = 0;
+
+                for (sent, y) in dirty.iter().enumerate() {
+                    let line = self.line_to_send(y);
+                    for row in self.panel_lines(y) {
+                        self.transaction.as_mut()[len - 1] |= address_high_bits::<SPEC>(row);
+                        self.transaction.as_mut()[len..len + SPEC::WRITE_BUFFER_SIZE]
+                            .copy_from_slice(Self::pack_line(row, &line).as_ref());
+                        len += SPEC::WRITE_BUFFER_SIZE;
+                        rows_sent += 1;
+
+                        // Split the frame into complete transactions if it exceeds the transfer
+                        // limit
+                        if rows_sent % lines_per_transaction == 0 && rows_sent < total * Y_SCALE {
+                            let end = len + SPEC::FRAME_TRAILER_BYTES;
+                            self.transaction.as_mut()[len..end].fill(DUMMY_DATA);
+                            transfer(
+                                &mut self.spi,
+                                &mut self.cs,
+                                &self.transaction.as_ref()[..end],
+                            )?;
+                            self.mark_sent(unsent.by_ref().take(packed));
+                            packed = 0;
+                            self.transaction.as_mut()[0] = command;
+                            len = 1;
+                        }
+                    }
+                    packed += 1;
+
+                    #[cfg(feature = "verbose-log")]
+                    trace!("flush: line {}", y + 1);
+
+                    progress(FlushProgress {
+                        line: y,
+                        lines_sent: sent + 1,
+                        lines_total: total,
+                    });
+                }
+
+                self.transaction.as_mut()[len..len + SPEC::FRAME_TRAILER_BYTES].fill(DUMMY_DATA);
+                len += SPEC::FRAME_TRAILER_BYTES;
+
+                transfer(
+                    &mut self.spi,
+                    &mut self.cs,
+                    &self.transaction.as_ref()[..len],
+                )?;
+                self.mark_sent(unsent);
+            }
+        }
+
+        debug!(
+            "flush: {} lines, {} bytes, vcom={}",
+            total,
+            self.transmitted_len(total * Y_SCALE),
+            self.vcom == Vcom::Hi
+        );
+        Ok(())
+    }
+
+    /// Mark `lines` as no longer dirty, once the writes carrying them succeeded.
+    fn mark_sent(&mut self, lines: impl Iterator<Item = usize>) {
+        let touched = &mut self.frame.borrow_mut().touched;
+        for y in lines {
+            touched.set(y, false);
+        }
+    }
+
+    /// Send the command, the dirty lines and the frame trailer of a flush with
+    /// [`CsStrategy::Software`], while chip select is held.
+    ///
+    /// Runs of consecutive dirty lines are packed into bursts of up to [`MAX_BURST_LINES`] and
+    /// sent with a write per burst, so an isolated line takes a write of its own.
+    fn write_frame(
+        &mut self,
+        total: usize,
+        progress: &mut impl FnMut(FlushProgress),
+    ) -> Result<(), Error<E, CS::Error>> {
+        let dirty = self.frame.borrow().touched;
+        // The byte before the address of a line carries its high bits, so look ahead
+        let mut lines = dirty.iter().peekable();
+        let first = lines
+            .peek()
+            .map_or(0, |&y| address_high_bits::<SPEC>(self.panel_lines(y).start));
+        // Every write starts with the byte before a line, the command or the last byte of the
+        // previous line, so all writes but the last are whole lines long, see `WordSpi`
+        let mut carry = Command::WriteLine | self.vcom | first;
+        // A line counts as sent once the write carrying its last byte succeeds, so the next
+        // write completes the first `completed` lines of `unsent`
+        let mut unsent = dirty.iter();
+        let mut completed = 0;
+        let mut carry_ends_line = false;
+
+        let burst_len = self.max_transfer_len.map_or(MAX_BURST_LINES, |len| {
+            (len / SPEC::WRITE_BUFFER_SIZE).clamp(1, MAX_BURST_LINES)
+        }) * SPEC::WRITE_BUFFER_SIZE;
+        let mut burst = SPEC::Burst::ZERO;
+        let burst = burst.as_mut();
+        let mut len = 0;
+        let mut previous = None;
+
+        // Pack buffer into byte form and send
+        let mut sent = 0;
+        while let Some(y) = lines.next() {
+            let line = self.line_to_send(y);
+            let rows = self.panel_lines(y);
+            for row in rows.clone() {
+                let contiguous = row > rows.start || previous == Some(y.wrapping_sub(1));
+                if len == burst_len || (len > 0 && !contiguous) {
+                    self.spi.write(&burst[..len]).map_err(Error::Spi)?;
+                    self.mark_sent(unsent.by_ref().take(completed));
+                    (len, completed) = (0, 0);
+                }
+
+                let next = if row + 1 < rows.end {
+                    Some(row + 1)
+                } else {
+                    lines.peek().map(|&y| self.panel_lines(y).start)
+                };
+                let mut packed = Self::pack_line(row, &line);
+                let packed = packed.as_mut();
+                packed[SPEC::WRITE_BUFFER_SIZE - 1] |= next.map_or(0, address_high_bits::<SPEC>);
+                completed += carry_ends_line as usize;
+                carry = carry_line(carry, packed);
+                carry_ends_line = row + 1 == rows.end;
+                burst[len..len + SPEC::WRITE_BUFFER_SIZE].copy_from_slice(packed);
+                len += SPEC::WRITE_BUFFER_SIZE;
+            }
+            previous = Some(y);
+            sent += 1;
+
+            #[cfg(feature = "verbose-log")]
+            trace!("flush: line {}", y + 1);
+
+            progress(FlushProgress {
+                line: y,
+                lines_sent: sent,
+                lines_total: total,
+            });
+        }
+        if len > 0 {
+            self.spi.write(&burst[..len]).map_err(Error::Spi)?;
+            self.mark_sent(unsent.by_ref().take(completed));
+        }
+
+        // Write the rest of the last line and the frame trailer
+        self.spi
+            .write(frame_end::<SPEC>(carry).as_ref())
+            .map_err(Error::Spi)?;
+        self.mark_sent(unsent);
+        Ok(())
+    }
+
+    /// The lines of the panel showing line `y` of the framebuffer, see
+    /// [`MemoryDisplay::set_scroll_offset`].
+    fn panel_lines(&self, y: usize) -> Range<usize> {
+        let y = (y + self.scroll_offset) % SPEC::BUFFER_HEIGHT;
+        y * Y_SCALE..(y + 1) * Y_SCALE
+    }
+
+    /// A frame of its own sending `line` as line `row` of the panel: `command`, the packed line
+    /// and the frame trailer.
+    fn line_frame(command: u8, row: usize, line: &SPEC::Line) -> SPEC::LineFrame {
+        let mut frame = SPEC::LineFrame::ZERO;
+        let bytes = frame.as_mut();
+        bytes.fill(DUMMY_DATA);
+        bytes[0] = command | address_high_bits::<SPEC>(row);
+        bytes[1..=SPEC::WRITE_BUFFER_SIZE].copy_from_slice(Self::pack_line(row, line).as_ref());
+        frame
+    }
+
+    /// Pack a line of the framebuffer into the form it is sent as line `y` of the panel: line
+    /// number, data and the trailer.
+    fn pack_line(y: usize, line: &SPEC::Line) -> SPEC::LineWrite {
+        Self::pack_panel_line(y, &widen::<SPEC>(line))
+    }
+
+    /// Same as [`MemoryDisplay::pack_line`] for a line already of the width of the panel.
+    ///
+    /// The panels expect every byte least significant bit first, so the bits are reversed for
+    /// SPI buses sending most significant bit first.
+    fn pack_panel_line(y: usize, line_buffer_msb: &SPEC::PanelLine) -> SPEC::LineWrite {
+        let mut packed = SPEC::LineWrite::ZERO;
+        let write_buffer = packed.as_mut();
+        // Write line number (starting at 1)
+        write_buffer[0] = ((y + 1) as u8).reverse_bits();
+
+        let trailer = write_buffer.len() - SPEC::LINE_TRAILER_BYTES;
+        for (dst, byte) in write_buffer[1..trailer]
+            .iter_mut()
+            .zip(line_buffer_msb.as_ref())
+        {
+            *dst = byte.reverse_bits();
+        }
+        // The panel ignores the bits past its width, but they may be set in the framebuffer
+        write_buffer[trailer - 1] &= !SPEC::PANEL_LINE_PADDING.reverse_bits();
+        // Technically this is supposed to be part of the address of the following line, but we'll just send it here because it's easier
+        write_buffer[trailer..].fill(DUMMY_DATA);
+        packed
+    }
+
+    /// Contrary to the MSB order most SPI devices use, the memory-in-pixel displays use LSB byte
+    /// order. This function swaps the order of a single byte (viewed via a `BitSlice`) and converts it to `u8`.
+    #[deprecated(note = "use `u8::reverse_bits`")]
+    pub fn swap(byte: &BitSlice<u8, Lsb0>) -> u8 {
+        let value = byte
+            .iter()
+            .enumerate()
+            .fold(0u8, |value, (i, bit)| value | (*bit as u8) << i);
+        value.reverse_bits()
+    }
+
+    /// Clear just the internal framebuffer, without writing changes to the display.
+    ///
+    /// Only lines which weren't in the clear state already are marked dirty, so clearing and
+    /// flushing a cleared framebuffer sends no lines. Use [`MemoryDisplay::mark_all_dirty`] to
+    /// send every line regardless, e.g. if the panel may show something else.
+    ///
+    /// The whole framebuffer is cleared, whatever the clip set with `set_clip`.
+    pub fn clear_buffer(&mut self) {
+        let FrameBuffer { buffer, touched } = self.frame.borrow_mut();
+        buffer.fill_changed(self.clear_state.is_on(), |y| touched.set(y, true));
+    }
+
+    /// Show every pixel inverted, [`Color::On`] as black, without touching the framebuffer: the
+    /// lines are inverted as they are sent. If the setting changes, every line is marked dirty
+    /// so the next flush repaints the panel.
+    ///
+    /// This only applies to what is sent, so the clear state and [`MemoryDisplay::get_pixel`]
+    /// still refer to the framebuffer, while the clear command always blanks the panel to
+    /// white. For the JDI panels, [`MemoryDisplay::invert_display`] has the panel do the same.
+    pub fn set_invert(&mut self, invert: bool) {
+        if invert != self.invert {
+            self.invert = invert;
+            self.mark_all_dirty();
+        }
+    }
+
+    /// Whether every pixel is shown inverted, see [`MemoryDisplay::set_invert`].
+    pub fn is_inverted(&self) -> bool {
+        self.invert
+    }
+
+    /// Invert every pixel of the framebuffer, marking all lines dirty, e.g. to flash the
+    /// screen. Inverting it again restores it.
+    pub fn invert_buffer(&mut self) {
+        self.frame.borrow_mut().buffer.invert();
+        self.mark_all_dirty();
+    }
+
+    /// Show line `y` of the framebuffer on line `(y + lines) mod height` of the panel, in lines
+    /// of the framebuffer, scrolling its whole content down by `lines` (up if negative) with
+    /// wrap-around. The panel addresses every line explicitly, so this costs nothing while
+    /// flushing. If the offset changes, every line is marked dirty so the next flush repaints
+    /// the panel.
+    ///
+    /// Like [`MemoryDisplay::mark_lines_dirty`], the framebuffer refers to the panel regardless
+    /// of the rotation, and its lines keep their numbers; only where they are shown changes.
+    pub fn set_scroll_offset(&mut self, lines: i32) {
+        let offset = (lines as i64).rem_euclid(SPEC::BUFFER_HEIGHT as i64) as usize;
+        if offset != self.scroll_offset {
+            self.scroll_offset = offset;
+            self.mark_all_dirty();
+        }
+    }
+
+    /// The offset set with [`MemoryDisplay::set_scroll_offset`], normalised to
+    /// `0..height`.
+    pub fn scroll_offset(&self) -> usize {
+        self.scroll_offset
+    }
+
+    /// Move the lines of the framebuffer up by `n`, filling the vacated lines at the bottom
+    /// with the clear state, and mark all lines dirty. Unlike
+    /// [`MemoryDisplay::set_scroll_offset`] this changes the framebuffer itself, and like it
+    /// refers to the lines of the panel regardless of the rotation.
+    pub fn scroll_buffer_up(&mut self, n: usize) {
+        self.shift_buffer(n, true);
+    }
+
+    /// Move the lines of the framebuffer down by `n`, filling the vacated lines at the top
+    /// with the clear state, see [`MemoryDisplay::scroll_buffer_up`].
+    pub fn scroll_buffer_down(&mut self, n: usize) {
+        self.shift_buffer(n, false);
+    }
+
+    fn shift_buffer(&mut self, n: usize, up: bool) {
+        let n = n.min(SPEC::BUFFER_HEIGHT);
+        if n == 0 {
+            return;
+        }
+        let fill = self.clear_state.is_on();
+        self.frame.borrow_mut().buffer.shift_vertically(
+            0..SPEC::BUFFER_WIDTH,
+            0..SPEC::BUFFER_HEIGHT,
+            n,
+            up,
+            fill,
+        );
+        self.mark_all_dirty();
+    }
+
+    /// Set the pixels `x` of rows `y` of the drawable area to `on`, marking the lines which
+    /// change dirty.
+    #[cfg_attr(not(feature = "graphics"), allow(dead_code))]
+    fn fill_rect(&mut self, x: Range<usize>, y: Range<usize>, on: bool) {
+        if x.is_empty() || y.is_empty() {
+            return;
+        }
+        let (x, y) = self.physical_rect(x, y);
+        let FrameBuffer { buffer, touched } = self.frame.borrow_mut();
+        let columns = x.clone();
+        buffer.fill_rect_changed(x, y, on, |y| touched.mark_columns(y, columns.clone()));
+    }
+
+    /// Clear the screen and the internal framebuffer.
+    pub fn clear(&mut self) -> Result<(), Error<E, CS::Error>> {
+        // What the panel shows after clearing its memory may differ from the clear state, so
+        // every line is sent by the next flush
+        self.clear_buffer();
+        self.mark_all_dirty();
+        self.toggle_vcom();
+        self.write_spi(&[Command::ClearMemory | self.vcom, DUMMY_DATA])?;
+
+        debug!("clear: vcom={}", self.vcom == Vcom::Hi);
+        Ok(())
+    }
+
+    /// Clear the screen and the framebuffer to `color`, whatever the clear state.
+    ///
+    /// If `color` is what the panel shows after clearing its memory, white or black while
+    /// [inverted](MemoryDisplay::set_invert), the clear command alone brings the panel in sync
+    /// and no line is left dirty. Otherwise nothing is sent and every line is marked dirty, so
+    /// the next flush sends the whole frame.
+    pub fn clear_to(&mut self, color: impl Into<Color>) -> Result<(), Error<E, CS::Error>> {
+        let on = color.into().is_on();
+        let FrameBuffer { buffer, touched } = self.frame.borrow_mut();
+        buffer.fill(on);
+        touched.fill(true);
+        if on != self.invert {
+            self.toggle_vcom();
+            self.write_spi(&[Command::ClearMemory | self.vcom, DUMMY_DATA])?;
+            debug!("clear: vcom={}", self.vcom == Vcom::Hi);
+            self.clear_dirty();
+        }
+        Ok(())
+    }
+
+    /// Clear the screen and immediately send the whole framebuffer, e.g. a splash screen drawn
+    /// before the first flush, so the panel is blank only while the frame is transmitted.
+    ///
+    /// The protocol ends every command with chip select, so the clear and the frame are two
+    /// transactions sent back to back. Unlike [`MemoryDisplay::clear`], the framebuffer is kept.
+    pub fn clear_and_show(&mut self) -> Result<(), Error<E, CS::Error>> {
+        self.toggle_vcom();
+        self.write_spi(&[Command::ClearMemory | self.vcom, DUMMY_DATA])?;
+
+        debug!("clear: vcom={}", self.vcom == Vcom::Hi);
+
+        self.mark_all_dirty();
+        self.flush_buffer()
+    }
+
+    /// Send the display mode command, which leaves the panel memory as it is and only carries
+    /// the VCOM bit. See [`MemoryDisplay::maintain_vcom`], which is the same under a clearer
+    /// name.
+    ///
+    /// While the display is asleep nothing is sent and [`Error::Asleep`] is returned.
+    pub fn display_mode(&mut self) -> Result<(), Error<E, CS::Error>> {
+        if self.asleep {
+            return Err(Error::Asleep);
+        }
+        self.toggle_vcom();
+        self.write_spi(&[Command::Nop | self.vcom, DUMMY_DATA])?;
+
+        debug!("display mode: vcom={}", self.vcom == Vcom::Hi);
+        Ok(())
+    }
+
+    /// Alternate VCOM without changing the image, to be called at least at
+    /// [`RECOMMENDED_VCOM_HZ`] while nothing else is sent: Sharp requires the polarity to keep
+    /// alternating to avoid a DC bias building up on the panel. Every other command alternates
+    /// it as well, so this is only needed while the display is idle, see also [`VcomTick`].
+    ///
+    /// The polarity sent can be read back with [`MemoryDisplay::vcom_phase`], e.g. to restore
+    /// it after a reset. While the display is asleep nothing is sent and [`Error::Asleep`] is
+    /// returned.
+    pub fn maintain_vcom(&mut self) -> Result<(), Error<E, CS::Error>> {
+        self.display_mode()
+    }
+
+    /// Make the panel blink, or stop blinking with [`BlinkMode::Off`]. The effect is produced by
+    /// the panel itself, without any further bus traffic.
+    ///
+    /// Only JDI panels support this, other models return [`Error::Unsupported`].
+    pub fn blink(&mut self, mode: BlinkMode) -> Result<(), Error<E, CS::Error>> {
+        let command = match mode {
+            BlinkMode::White => Command::BlinkWhite,
+            BlinkMode::Black => Command::BlinkBlack,
+            BlinkMode::Off => Command::Nop,
+        };
+        self.send_jdi_command(command)
+    }
+
+    /// Show the panel memory inverted, without touching the framebuffer or the panel memory.
+    ///
+    /// Only JDI panels support this, other models return [`Error::Unsupported`].
+    pub fn invert_display(&mut self, invert: bool) -> Result<(), Error<E, CS::Error>> {
+        self.send_jdi_command(if invert {
+            Command::BlinkInvert
+        } else {
+            Command::Nop
+        })
+    }
+
+    fn send_jdi_command(&mut self, command: Command) -> Result<(), Error<E, CS::Error>> {
+        if !SPEC::JDI_COMMANDS {
+            return Err(Error::Unsupported);
+        }
+        self.toggle_vcom();
+        self.write_spi(&[command | self.vcom, DUMMY_DATA])
+    }
+
+    /// Internal function for handling the chip select
+    fn write_spi(&mut self, data: &[u8]) -> Result<(), Error<E, CS::Error>> {
+        transfer(&mut self.spi, &mut self.cs, data)
+    }
+}
+
+/// Shift `line` by a byte, putting `carry` in front, and return its last byte which falls off.
+fn carry_line(carry: u8, line: &mut [u8]) -> u8 {
+    line.rotate_right(1);
+    core::mem::replace(&mut line[0], carry)
+}
+
+/// The end of a frame: the byte carried over from the last line, see [`carry_line`], and the
+/// frame trailer.
+fn frame_end<SPEC: DisplaySpec>(carry: u8) -> SPEC::FrameEnd {
+    let mut end = SPEC::FrameEnd::ZERO;
+    end.as_mut().fill(DUMMY_DATA);
+    end.as_mut()[0] = carry;
+    end
+}
+
+/// Send `data` in a transaction framed by chip select. Chip select is deasserted even if the
+/// bus fails, and the error of the bus takes precedence.
+fn transfer<SPI, CS, E>(spi: &mut SPI, cs: &mut CS, data: &[u8]) -> Result<(), Error<E, CS::Error>>
+where
+    SPI: Write<u8, Error = E>,
+    CS: OutputPin,
+{
+    cs.set_high().map_err(Error::Cs)?;
+    let result = spi.write(data).map_err(Error::Spi);
+    let deselect = cs.set_low().map_err(Error::Cs);
+    result.and(deselect)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+    use std::vec::Vec;
+
+    fn progress_of(strategy: CsStrategy, lines: &[usize]) -> Vec<FlushProgress> {
+        let (_, mut display) = testing::display();
+        display.set_cs_strategy(strategy);
+        for &y in lines {
+            display.mark_line_dirty(y);
+        }
+        let mut progress = Vec::new();
+        display
+            .flush_buffer_with_progress(|p| progress.push(p))
+            .unwrap();
+        progress
+    }
+
+    /// The progress reported for `lines`, or every line if each flush sends all of them.
+    fn progress_for(lines: &[usize]) -> Vec<FlushProgress> {
+        let lines = if cfg!(feature = "minimal-flush") {
+            (0..BUFFER_HEIGHT).collect()
+        } else {
+            lines.to_vec()
+        };
+        let total = lines.len();
+        (lines.into_iter().enumerate())
+            .map(|(sent, line)| FlushProgress {
+                line,
+                lines_sent: sent + 1,
+                lines_total: total,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn progress_once_per_dirty_line_in_order() {
+        for strategy in testing::strategies() {
+            let progress = progress_of(strategy, &[6, 2, 5]);
+            assert_eq!(progress, progress_for(&[2, 5, 6]), "{strategy:?}");
+            assert_eq!(
+                progress_of(strategy, &[]),
+                progress_for(&[]),
+                "{strategy:?}"
+            );
+        }
+    }
+
+    /// The lengths of the writes of a flush of every line with `strategy` and writes limited to
+    /// `max_len`.
+    fn write_lens(strategy: CsStrategy, max_len: usize) -> Vec<usize> {
+        let (log, mut display) = testing::display();
+        log.reject_writes_over(max_len);
+        display.set_cs_strategy(strategy);
+        display.set_max_transfer_len(Some(max_len)).unwrap();
+        display.mark_all_dirty();
+        display.flush_buffer().unwrap();
+        // Every line is always dirty with `minimal-flush`
+        assert_eq!(display.is_dirty(), cfg!(feature = "minimal-flush"));
+        log.writes().iter().map(Vec::len).collect()
+    }
+
+    #[test]
+    fn writes_are_split_at_the_limit() {
+        // Room for 3 lines and a bit
+        let max_len = 3 * WRITE_BUFFER_SIZE + FRAME_OVERHEAD + 5;
+        let bursts = [3 * WRITE_BUFFER_SIZE].repeat(HEIGHT / 3);
+        let rest = Some(HEIGHT % 3 * WRITE_BUFFER_SIZE).filter(|&len| len > 0);
+        let software = [&bursts[..], rest.as_slice(), &[FRAME_OVERHEAD]].concat();
+        assert_eq!(write_lens(CsStrategy::Software, max_len), software);
+
+        let per_line = [WRITE_BUFFER_SIZE + FRAME_OVERHEAD].repeat(HEIGHT);
+        assert_eq!(write_lens(CsStrategy::PerLine, max_len), per_line);
+
+        #[cfg(feature = "transaction-buffer")]
+        {
+            let frames = [3 * WRITE_BUFFER_SIZE + FRAME_OVERHEAD].repeat(HEIGHT / 3);
+            let rest = rest.map(|len| len + FRAME_OVERHEAD);
+            let hardware = [&frames[..], rest.as_slice()].concat();
+            assert_eq!(write_lens(CsStrategy::Hardware, max_len), hardware);
+        }
+
+        let shortest = WRITE_BUFFER_SIZE + FRAME_OVERHEAD;
+        assert_eq!(
+            write_lens(CsStrategy::Software, shortest)[0],
+            WRITE_BUFFER_SIZE
+        );
+        let (_, mut display) = testing::display();
+        let too_short = display.set_max_transfer_len(Some(shortest - 1));
+        assert_eq!(too_short, Err(TransferLenTooShort));
+    }
+
+    // Only the writes change with a limit, except for `Hardware` splitting the frame into
+    // complete frames, see `spidev_limit_is_kept`
+    #[test]
+    fn limited_writes_send_the_same_bytes() {
+        let max_len = 2 * WRITE_BUFFER_SIZE + FRAME_OVERHEAD;
+        for strategy in [CsStrategy::Software, CsStrategy::PerLine] {
+            let sent = [None, Some(max_len)].map(|limit| {
+                let (log, mut display) = testing::display();
+                log.reject_writes_over(limit.unwrap_or(usize::MAX));
+                display.set_cs_strategy(strategy);
+                display.set_max_transfer_len(limit).unwrap();
+                testing::draw_noise(&mut display, 276);
+                display.flush_buffer().unwrap();
+                display.clear().unwrap();
+                display.display_mode().unwrap();
+                log.bytes()
+            });
+            assert_eq!(sent[0], sent[1], "{strategy:?}");
+        }
+    }
+
+    #[test]
+    fn padding_is_the_pixels_past_the_width() {
+        assert_eq!(line_padding(400), 0);
+        assert_eq!(line_padding(72), 0);
+        assert_eq!(line_padding(70), 0b1100_0000);
+        assert_eq!(line_padding(1), 0b1111_1110);
+        for width in 1..=24 {
+            let last = (width - 1) / 8 * 8;
+            let past = (last..last + 8).filter(|&x| x >= width);
+            let bits = past.fold(0, |bits, x| bits | 1 << (x % 8));
+            assert_eq!(line_padding(width), bits, "{width}");
+        }
+    }
+
+    /// A display with every third pixel of the first line on and every other pixel off.
+    fn dump_pattern() -> testing::TestDisplay {
+        let (_, mut display) = testing::display();
+        for x in (0..BUFFER_WIDTH).step_by(3) {
+            display.frame.borrow_mut().buffer.set(x, 0, true);
+        }
+        display
+    }
+
+    #[test]
+    fn pbm_dump_of_a_pattern() {
+        let mut pbm = std::string::String::new();
+        dump_pattern().dump_pbm(&mut pbm).unwrap();
+        let header = std::format!("P1\n{BUFFER_WIDTH} {BUFFER_HEIGHT}\n");
+        let first = "0 1 1 0 1 1 0 1 1 0 1 1 0 1 1 0 1 1 0 1 1 0 1 1 0 1 1 0 1 1 0 1\n";
+        assert!(pbm.starts_with(&(header.clone() + first)), "{pbm}");
+        assert!(pbm.lines().all(|line| line.len() <= 70));
+
+        // Every pixel, a 1 for black
+        let pixels: Vec<_> = pbm[header.len()..].split_whitespace().collect();
+        assert_eq!(pixels.len(), BUFFER_WIDTH * BUFFER_HEIGHT);
+        for (i, pixel) in pixels.into_iter().enumerate() {
+            let (x, y) = (i % BUFFER_WIDTH, i / BUFFER_WIDTH);
+            let on = y == 0 && x.is_multiple_of(3);
+            assert_eq!(pixel, if on { "0" } else { "1" }, "{x} {y}");
+        }
+    }
+
+    #[test]
+    fn xbm_dump_of_a_pattern() {
+        let mut xbm = std::string::String::new();
+        dump_pattern().dump_xbm("frame", &mut xbm).unwrap();
+        let header = std::format!(
+            "#define frame_width {BUFFER_WIDTH}\n#define frame_height {BUFFER_HEIGHT}\n\
+             static unsigned char frame_bits[] = {{\n    0xb6, 0x6d, 0xdb,"
+        );
+        assert!(xbm.starts_with(&header), "{xbm}");
+        assert!(xbm.ends_with(" };\n"));
+
+        // Every byte, a set bit for black, the bits past the width cleared
+        let start = xbm.find('{').unwrap() + 1;
+        let bytes: Vec<u8> = xbm[start..xbm.len() - 3]
+            .split(',')
+            .map(|byte| u8::from_str_radix(byte.trim().trim_start_matches("0x"), 16).unwrap())
+            .collect();
+        assert_eq!(bytes.len(), LINE_BYTES * BUFFER_HEIGHT);
+        let lines = bytes.chunks(LINE_BYTES);
+        for (y, line) in lines.enumerate() {
+            for x in 0..LINE_BYTES * 8 {
+                let black = line[x / 8] & 1 << (x % 8) != 0;
+                let on = y == 0 && x.is_multiple_of(3);
+                assert_eq!(black, x < BUFFER_WIDTH && !on, "{x} {y}");
+            }
+        }
+    }
+
+    // A frame of the larger panels is longer than the default `bufsiz` of Linux `spidev`
+    #[test]
+    #[cfg(feature = "transaction-buffer")]
+    fn spidev_limit_is_kept() {
+        let (log, mut display) = testing::display();
+        log.reject_writes_over(4096);
+        display.set_cs_strategy(CsStrategy::Hardware);
+        display.mark_all_dirty();
+        if TRANSACTION_BUFFER_SIZE > 4096 {
+            let failed = display.flush_buffer();
+            assert_eq!(failed, Err(Error::Spi(testing::BusError)));
+            assert!(display.is_dirty());
+        }
+        for strategy in testing::strategies() {
+            assert!(write_lens(strategy, 4096).iter().all(|&len| len <= 4096));
+        }
+    }
+
+    // Every level set by the driver reaches a pin wrapped in `ActiveLow` inverted, from the
+    // initial ones of `new` to the one of `release`
+    #[test]
+    fn active_low_pins_see_every_level_inverted() {
+        use embedded_hal_mock::pin::{Mock as PinMock, State, Transaction as PinTransaction};
+        use embedded_hal_mock::spi::{Mock as SpiMock, Transaction as SpiTransaction};
+
+        fn enable_command_disable<CS: OutputPin, DISP: OutputPin>(
+            display: &mut MemoryDisplay<SpiMock, CS, DISP>,
+        ) where
+            CS::Error: core::fmt::Debug,
+            DISP::Error: core::fmt::Debug,
+        {
+            display.enable().unwrap();
+            display.display_mode().unwrap();
+            display.disable().unwrap();
+        }
+
+        let pin = |levels: &[bool], active_low: bool| {
+            let transactions: Vec<_> = (levels.iter())
+                .map(|&high| {
+                    let state = if high != active_low {
+                        State::High
+                    } else {
+                        State::Low
+                    };
+                    PinTransaction::set(state)
+                })
+                .collect();
+            PinMock::new(&transactions)
+        };
+        let cs = [false, true, false, false];
+        let disp = [false, true, false];
+        let spi = || SpiMock::new(&[SpiTransaction::write(std::vec![0x00, DUMMY_DATA])]);
+
+        let mut display = MemoryDisplay::new(spi(), pin(&cs, false), pin(&disp, false));
+        enable_command_disable(&mut display);
+        let (mut spi_mock, mut cs_mock, mut disp_mock) = display.release();
+        spi_mock.done();
+        cs_mock.done();
+        disp_mock.done();
+
+        let (cs, disp) = (ActiveLow(pin(&cs, true)), ActiveLow(pin(&disp, true)));
+        let mut display = MemoryDisplay::new(spi(), cs, disp);
+        enable_command_disable(&mut display);
+        let (mut spi_mock, ActiveLow(mut cs_mock), ActiveLow(mut disp_mock)) = display.release();
+        spi_mock.done();
+        cs_mock.done();
+        disp_mock.done();
+    }
+
+    #[test]
+    #[cfg(feature = "lpm009m360a")]
+    fn jdi_commands_are_sent_with_vcom() {
+        use testing::Event::{Cs, Write};
+        let (log, mut display) = testing::display();
+        display.blink(BlinkMode::White).unwrap();
+        display.blink(BlinkMode::Black).unwrap();
+        display.invert_display(true).unwrap();
+        display.invert_display(false).unwrap();
+        display.blink(BlinkMode::Off).unwrap();
+        let expected = [0x18, 0x50, 0x14, 0x40, 0x00]
+            .map(|command| [Cs(true), Write(std::vec![command, 0]), Cs(false)]);
+        assert_eq!(log.events(), expected.concat());
+    }
+
+    #[test]
+    #[cfg(not(feature = "lpm009m360a"))]
+    fn jdi_commands_are_unsupported_on_sharp_panels() {
+        let (log, mut display) = testing::display();
+        let vcom = display.vcom_phase();
+        for mode in [BlinkMode::White, BlinkMode::Black, BlinkMode::Off] {
+            assert_eq!(display.blink(mode), Err(Error::Unsupported));
+        }
+        for invert in [true, false] {
+            assert_eq!(display.invert_display(invert), Err(Error::Unsupported));
+        }
+        assert_eq!(log.events(), []);
+        assert_eq!(display.vcom_phase(), vcom);
+    }
+
+    /// The command byte of every command sent: a flush, a clear and a display mode command,
+    /// twice, then a flush with [`CsStrategy::PerLine`].
+    fn command_bytes(display: &mut testing::TestDisplay, log: &testing::Log) -> Vec<u8> {
+        let mut commands = Vec::new();
+        for _ in 0..2 {
+            display.flush_buffer().unwrap();
+            commands.push(log.writes()[0][0]);
+            display.clear().unwrap();
+            commands.push(log.writes()[0][0]);
+            display.display_mode().unwrap();
+            commands.push(log.writes()[0][0]);
+        }
+        display.set_cs_strategy(CsStrategy::PerLine);
+        display.mark_line_dirty(0);
+        display.flush_buffer().unwrap();
+        commands.push(log.writes()[0][0]);
+        display.set_cs_strategy(CsStrategy::Software);
+        commands
+    }
+
+    #[test]
+    fn software_vcom_toggles_the_m1_bit() {
+        let (log, mut display) = testing::display();
+        assert_eq!(
+            command_bytes(&mut display, &log),
+            [0x80, 0x60, 0x00, 0xC0, 0x20, 0x40, 0x80]
+        );
+        assert_eq!(display.vcom_phase(), Vcom::Lo);
+        display.set_vcom_phase(Vcom::Lo);
+        display.display_mode().unwrap();
+        assert_eq!(log.writes(), [[0x40, DUMMY_DATA]]);
+    }
+
+    #[test]
+    fn external_vcom_holds_the_m1_bit_low() {
+        let (log, mut display) = testing::display();
+        display.set_vcom_mode(VcomMode::External);
+        assert_eq!(display.vcom_mode(), VcomMode::External);
+        assert_eq!(
+            command_bytes(&mut display, &log),
+            [0x80, 0x20, 0x00, 0x80, 0x20, 0x00, 0x80]
+        );
+        display.set_vcom_phase(Vcom::Hi);
+        assert_eq!(display.vcom_phase(), Vcom::Lo);
+        display.display_mode().unwrap();
+        assert_eq!(log.writes(), [[0x00, DUMMY_DATA]]);
+
+        // Toggling resumes from the fixed phase
+        display.set_vcom_mode(VcomMode::Software);
+        display.display_mode().unwrap();
+        display.display_mode().unwrap();
+        assert_eq!(log.writes(), [[0x40, DUMMY_DATA], [0x00, DUMMY_DATA]]);
+    }
+
+    // Every line of the framebuffer sent as one line of the panel, as it is drawn
+    #[cfg(not(any(
+        feature = "half-height",
+        feature = "half-width",
+        feature = "minimal-flush"
+    )))]
+    mod wire {
+        use super::*;
+        use crate::testing::{pixels, Event};
+        use embedded_hal_mock::pin::{Mock as PinMock, State, Transaction as PinTransaction};
+        use embedded_hal_mock::spi::{Mock as SpiMock, Transaction as SpiTransaction};
+        use std::vec;
+
+        /// A display expecting exactly `writes`, and chip select to frame a single transaction
+        /// with [`CsStrategy::Software`] before it is released by [`done`].
+        fn expecting(writes: &[&[u8]]) -> MemoryDisplay<SpiMock, PinMock, PinMock> {
+            let writes: Vec<_> = writes
+                .iter()
+                .map(|bytes| SpiTransaction::write(bytes.to_vec()))
+                .collect();
+            let cs = [State::Low, State::High, State::Low, State::Low].map(PinTransaction::set);
+            let disp = [State::Low, State::High].map(PinTransaction::set);
+            let mut display = MemoryDisplay::new(
+                SpiMock::new(&writes),
+                PinMock::new(&cs),
+                PinMock::new(&disp),
+            );
+            display.enable().unwrap();
+            display
+        }
+
+        fn done(display: MemoryDisplay<SpiMock, PinMock, PinMock>) {
+            let (mut spi, mut cs, mut disp) = display.release();
+            spi.done();
+            cs.done();
+            disp.done();
+        }
+
+        #[test]
+        fn clearing_a_cleared_buffer_sends_no_lines() {
+            // What the first flush sends, every line in the clear state
+            let (log, mut reference) = testing::display();
+            reference.clear_buffer();
+            reference.flush_buffer().unwrap();
+            let first = log.writes();
+            let on = pixels(&(0..WIDTH).collect::<Vec<_>>());
+            let lines: Vec<_> = (0..HEIGHT).map(|y| (y, on)).collect();
+            assert_eq!(first.concat(), testing::frame(Vcom::Lo, &lines));
+
+            let second = [Command::WriteLine | Vcom::Hi, DUMMY_DATA];
+            let writes: Vec<_> = first
+                .iter()
+                .map(|write| SpiTransaction::write(write.clone()))
+                .chain([SpiTransaction::write(second.to_vec())])
+                .collect();
+            let cs = [
+                State::Low,
+                State::High,
+                State::Low,
+                State::High,
+                State::Low,
+                State::Low,
+            ]
+            .map(PinTransaction::set);
+            let disp = [State::Low, State::High].map(PinTransaction::set);
+            let mut display = MemoryDisplay::new(
+                SpiMock::new(&writes),
+                PinMock::new(&cs),
+                PinMock::new(&disp),
+            );
+            display.enable().unwrap();
+            for _ in 0..2 {
+                display.clear_buffer();
+                display.flush_buffer().unwrap();
+            }
+            done(display);
+        }
+
+        #[test]
+        fn progress_follows_the_lines_sent() {
+            let frame = testing::frame(Vcom::Lo, &[(2, pixels(&[0])), (3, pixels(&[1, 9]))]);
+            // Both lines in one burst, then the rest of the last line and the frame trailer
+            let (burst, end) = frame.split_at(2 * WRITE_BUFFER_SIZE);
+            let mut display = expecting(&[burst, end]);
+            display.set_pixel(0, 2, Color::On).unwrap();
+            display.set_pixel(1, 3, Color::On).unwrap();
+            display.set_pixel(9, 3, Color::On).unwrap();
+
+            let mut lines = vec![];
+            display
+                .flush_buffer_with_progress(|p| lines.push((p.line, p.lines_sent, p.lines_total)))
+                .unwrap();
+            assert_eq!(lines, [(2, 1, 2), (3, 2, 2)]);
+            done(display);
+        }
+
+        #[test]
+        fn carry_shifts_a_line_by_a_byte() {
+            let mut line = [1, 2, 3];
+            assert_eq!(carry_line(0xAA, &mut line), 3);
+            assert_eq!(line, [0xAA, 1, 2]);
+            let end = frame_end::<spec::Selected>(3);
+            assert_eq!(end[0], 3);
+            assert!(end[1..].iter().all(|&byte| byte == DUMMY_DATA));
+        }
+
+        // Lines 0 to 9 fill a burst and start another, 20 and 30 take a burst of their own
+        const LINES: [usize; 12] = [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 20, 30];
+
+        fn flush_lines_with(strategy: CsStrategy) -> Vec<Event> {
+            let (log, mut display) = testing::display();
+            display.set_cs_strategy(strategy);
+            testing::draw_diagonal(&mut display, LINES);
+            display.flush_buffer().unwrap();
+            assert!(!display.is_dirty());
+            log.events()
+        }
+
+        #[test]
+        fn software_bursts_carry_the_last_byte() {
+            let frame = testing::frame(Vcom::Lo, &testing::diagonal(LINES));
+            let lens = [MAX_BURST_LINES, 2, 1, 1].map(|lines| lines * WRITE_BUFFER_SIZE);
+            let writes = testing::split(&frame, &lens);
+            assert_eq!(writes.last().unwrap().len(), FRAME_OVERHEAD);
+            // Every write after the first starts with the last byte of the line before
+            assert_eq!(writes[1][0], DUMMY_DATA);
+            assert_eq!(writes[1][1], 9u8.reverse_bits());
+
+            let mut expected = vec![Event::Cs(true)];
+            expected.extend(writes.into_iter().map(Event::Write));
+            expected.push(Event::Cs(false));
+            assert_eq!(flush_lines_with(CsStrategy::Software), expected);
+        }
+
+        #[test]
+        fn bursts_are_split_at_the_transfer_limit() {
+            // Room for two lines and a bit, not for a third
+            let max_len = 3 * WRITE_BUFFER_SIZE - 1;
+            let frame = testing::frame(Vcom::Lo, &testing::diagonal(LINES));
+            let lens = [2, 2, 2, 2, 2, 1, 1].map(|lines| lines * WRITE_BUFFER_SIZE);
+            let writes = testing::split(&frame, &lens);
+            assert!(writes.iter().all(|write| write.len() <= max_len));
+
+            let writes: Vec<_> = writes.iter().map(Vec::as_slice).collect();
+            let mut display = expecting(&writes);
+            display.set_max_transfer_len(Some(max_len)).unwrap();
+            for y in LINES {
+                let x = y % BUFFER_WIDTH;
+                display.set_pixel(x as u32, y as u32, Color::On).unwrap();
+            }
+            display.flush_buffer().unwrap();
+            done(display);
+        }
+
+        #[test]
+        fn runs_of_dirty_lines_take_a_burst_each() {
+            // Three runs, the last at the end of the panel to cover the high address bits
+            let lines = [3, 4, 5, 40, HEIGHT - 2, HEIGHT - 1];
+            let frame = testing::frame(Vcom::Lo, &testing::diagonal(lines));
+            let lens = [3, 1, 2].map(|lines| lines * WRITE_BUFFER_SIZE);
+            let writes = testing::split(&frame, &lens);
+            assert_eq!(writes.len(), 4);
+            assert_eq!(writes[3].len(), FRAME_OVERHEAD);
+            assert_eq!(writes[0][0], Command::WriteLine | Vcom::Lo);
+            // Every burst starts with the trailer of the line before, carrying the high bits
+            assert_eq!(writes[1][..2], [DUMMY_DATA, 41u8.reverse_bits()]);
+            let last = [
+                DUMMY_DATA | address_high_bits::<spec::Selected>(HEIGHT - 2),
+                ((HEIGHT - 1) as u8).reverse_bits(),
+            ];
+            assert_eq!(writes[2][..2], last);
+
+            let writes: Vec<_> = writes.iter().map(Vec::as_slice).collect();
+            let mut display = expecting(&writes);
+            for y in lines {
+                let x = y % BUFFER_WIDTH;
+                display.set_pixel(x as u32, y as u32, Color::On).unwrap();
+            }
+            display.flush_buffer().unwrap();
+            done(display);
+        }
+
+        #[test]
+        fn per_line_frames_every_line() {
+            let mut expected = vec![];
+            for line in testing::diagonal(LINES) {
+                expected.push(Event::Cs(true));
+                expected.push(Event::Write(testing::frame(Vcom::Lo, &[line])));
+                expected.push(Event::Cs(false));
+            }
+            assert_eq!(flush_lines_with(CsStrategy::PerLine), expected);
+        }
+
+        #[test]
+        #[cfg(not(feature = "column-major"))]
+        fn raw_writes_are_sent_once_marked() {
+            let (log, mut display) = testing::display();
+            // Pixels 0 and 2, and pixel 15
+            display.buffer_mut()[10][0] = 0b0000_0101;
+            display.buffer_mut()[11][1] = 0b1000_0000;
+            assert!(!display.is_dirty());
+            display.mark_lines_dirty(10..12);
+            display.flush_buffer().unwrap();
+
+            let bytes = log.bytes();
+            let lines = [(10, pixels(&[0, 2])), (11, pixels(&[15]))];
+            assert_eq!(bytes, testing::frame(Vcom::Lo, &lines));
+            #[cfg(feature = "std")]
+            {
+                let rows = decode::render(&bytes, WIDTH, HEIGHT);
+                let on = |y: usize| (0..WIDTH).filter(|&x| rows[y][x]).collect::<Vec<_>>();
+                assert_eq!((on(10), on(11)), (vec![0, 2], vec![15]));
+                // Starting from a cleared panel, white
+                assert_eq!(on(12).len(), WIDTH);
+            }
+
+            display.mark_all_dirty();
+            assert_eq!(display.dirty_line_count(), BUFFER_HEIGHT);
+            display.flush_buffer().unwrap();
+            let mut lines: Vec<_> = (0..HEIGHT).map(|y| (y, pixels(&[]))).collect();
+            (lines[10].1, lines[11].1) = (pixels(&[0, 2]), pixels(&[15]));
+            assert_eq!(log.bytes(), testing::frame(Vcom::Hi, &lines));
+        }
+
+        #[test]
+        fn per_line_toggles_vcom_once_per_flush() {
+            let (log, mut display) = testing::display();
+            display.set_cs_strategy(CsStrategy::PerLine);
+            for (vcom, lines) in [
+                (Vcom::Lo, [3, 4, 40]),
+                (Vcom::Hi, [5, 6, 41]),
+                (Vcom::Lo, [7, 8, 42]),
+            ] {
+                testing::draw_diagonal(&mut display, lines);
+                display.flush_buffer().unwrap();
+                let lines = testing::diagonal(lines);
+                let expected: Vec<_> = lines.iter().map(|&l| testing::frame(vcom, &[l])).collect();
+                assert_eq!(log.writes(), expected);
+            }
+
+            // Without a dirty line the command alone keeps VCOM toggling
+            display.flush_buffer().unwrap();
+            assert_eq!(log.writes(), [[0xC0, DUMMY_DATA]]);
+            let mut first = [DUMMY_DATA; WRITE_BUFFER_SIZE + FRAME_OVERHEAD];
+            (first[0], first[1], first[2]) = (0x80, 0x80, 0x80);
+            display.set_pixel(0, 0, Color::On).unwrap();
+            display.flush_buffer().unwrap();
+            assert_eq!(log.writes(), [first]);
+        }
+
+        #[test]
+        #[cfg(feature = "transaction-buffer")]
+        fn hardware_sends_the_frame_at_once() {
+            let frame = testing::frame(Vcom::Lo, &testing::diagonal(LINES));
+            let expected = [Event::Cs(true), Event::Write(frame), Event::Cs(false)];
+            assert_eq!(flush_lines_with(CsStrategy::Hardware), expected);
+        }
+
+        #[test]
+        fn strategies_send_the_same_bytes() {
+            let frame = testing::frame(Vcom::Lo, &testing::diagonal(LINES));
+            for strategy in testing::strategies() {
+                let events = flush_lines_with(strategy);
+                if strategy == CsStrategy::PerLine {
+                    // Every line is framed as a frame of its own
+                    let lines = testing::diagonal(LINES);
+                    let frames = lines.iter().map(|&line| testing::frame(Vcom::Lo, &[line]));
+                    assert_eq!(
+                        testing::written(&events),
+                        frames.collect::<Vec<_>>().concat()
+                    );
+                } else {
+                    assert_eq!(testing::written(&events), frame, "{strategy:?}");
+                }
+            }
+        }
+
+        #[test]
+        #[cfg(all(feature = "std", feature = "transaction-buffer"))]
+        fn decoded_single_buffer_matches_the_bursts() {
+            let [Event::Cs(true), Event::Write(buffer), Event::Cs(false)] =
+                &flush_lines_with(CsStrategy::Hardware)[..]
+            else {
+                panic!("not a single transaction");
+            };
+            let stream = testing::written(&flush_lines_with(CsStrategy::Software));
+            let records = decode::decode(buffer, WIDTH, HEIGHT);
+            assert_eq!(records, decode::decode(&stream, WIDTH, HEIGHT));
+            let [decode::Record::WriteLines { lines, .. }] = &records[..] else {
+                panic!("{records:?}");
+            };
+            let numbers: Vec<_> = lines.iter().map(|(number, _)| number - 1).collect();
+            assert_eq!(numbers, LINES);
+        }
+
+        #[test]
+        fn replaced_spi_sends_what_was_drawn_before() {
+            let (old, mut display) = testing::display();
+            testing::draw_diagonal(&mut display, [10, 11]);
+            display.flush_buffer().unwrap();
+            old.events();
+            testing::draw_diagonal(&mut display, LINES);
+
+            let new = testing::Log::default();
+            let mut spi = display.replace_spi(new.spi());
+            assert!(display.is_dirty());
+            display.flush_buffer().unwrap();
+            // The lines drawn before the swap, with the VCOM phase following the first flush
+            let lines = testing::diagonal(LINES.into_iter().filter(|y| !(10..12).contains(y)));
+            assert_eq!(new.bytes(), testing::frame(Vcom::Hi, &lines));
+            assert_eq!(old.writes(), [] as [Vec<u8>; 0]);
+
+            // The old bus is still usable
+            hal::blocking::spi::Write::write(&mut spi, &[1]).unwrap();
+            assert_eq!(old.bytes(), [1]);
+        }
+
+        #[test]
+        fn mapped_spi_sends_what_was_drawn_before() {
+            let (_, mut display) = testing::display();
+            testing::draw_diagonal(&mut display, LINES);
+            let frame = testing::frame(Vcom::Lo, &testing::diagonal(LINES));
+            let lens = [MAX_BURST_LINES, 2, 1, 1].map(|lines| lines * WRITE_BUFFER_SIZE);
+            let writes: Vec<_> = (testing::split(&frame, &lens).into_iter())
+                .map(SpiTransaction::write)
+                .collect();
+
+            let mut display = display.map_spi(|_| SpiMock::new(&writes));
+            display.flush_buffer().unwrap();
+            assert!(!display.is_dirty());
+            display.release().0.done();
+        }
+
+        // The same pixels give the same bytes whichever way the framebuffer is stored
+        #[test]
+        fn wire_follows_the_pixels_in_either_layout() {
+            let (log, mut display) = testing::display();
+            testing::draw_noise(&mut display, 5);
+            display.flush_buffer().unwrap();
+            let bytes = log.bytes();
+
+            let pixels = testing::snapshot(&display);
+            let lines: Vec<_> = (pixels.iter().enumerate())
+                .map(|(y, line)| {
+                    let on: Vec<_> = (0..WIDTH).filter(|&x| line[x]).collect();
+                    (y, testing::pixels(&on))
+                })
+                .collect();
+            assert_eq!(bytes, testing::frame(Vcom::Lo, &lines));
+            #[cfg(feature = "std")]
+            assert_eq!(decode::render(&bytes, WIDTH, HEIGHT), pixels);
+        }
+
+        #[test]
+        fn written_lines_are_sent_as_packed() {
+            let (log, mut display) = testing::display();
+            // Pixels 0, 3 and 9, least significant bit first, and a byte past the line
+            let mut packed = [0; LINE_BYTES + 1];
+            packed[..2].copy_from_slice(&[0b0000_1001, 0b0000_0010]);
+            packed[LINE_BYTES] = 0xFF;
+            display.write_line(4, &packed).unwrap();
+            assert_eq!(
+                display.frame.borrow_mut().buffer.line(4),
+                packed[..LINE_BYTES]
+            );
+            display.flush_buffer().unwrap();
+            let sent = testing::frame(Vcom::Lo, &[(4, pixels(&[0, 3, 9]))]);
+            assert_eq!(log.bytes(), sent);
+
+            // Rewriting the same pixels leaves the line clean
+            display.write_line(4, &packed).unwrap();
+            assert_eq!(display.dirty_line_count(), 0);
+        }
+
+        #[test]
+        fn written_lines_outside_or_too_short_are_rejected() {
+            let (_, mut display) = testing::display();
+            let before = testing::snapshot(&display);
+            let line = [0xFF; LINE_BYTES];
+            assert_eq!(display.write_line(HEIGHT, &line), Err(OutOfBounds));
+            assert_eq!(
+                display.write_line(0, &line[..LINE_BYTES - 1]),
+                Err(OutOfBounds)
+            );
+            assert_eq!(display.dirty_line_count(), 0);
+            assert_eq!(testing::snapshot(&display), before);
+        }
+
+        // Every line and the frame end with the dummy bytes of the selected model, however the
+        // frame is split into transactions
+        #[test]
+        #[cfg(feature = "std")]
+        fn padding_of_the_selected_model() {
+            let trailers = decode::model_trailers(spec::Selected::NAME).unwrap();
+            assert_eq!(trailers, (LINE_TRAILER_BYTES, FRAME_TRAILER_BYTES));
+            let line_len = 1 + PANEL_LINE_BYTES + LINE_TRAILER_BYTES;
+            let lines = testing::diagonal(LINES);
+
+            for strategy in testing::strategies() {
+                let bytes = testing::written(&flush_lines_with(strategy));
+                let frames = if strategy == CsStrategy::PerLine {
+                    lines.iter().map(|&line| (1, vec![line])).collect()
+                } else {
+                    vec![(LINES.len(), lines.clone())]
+                };
+                let mut rest = &bytes[..];
+                for (count, lines) in frames {
+                    let len = 1 + count * line_len + FRAME_TRAILER_BYTES;
+                    let (frame, tail) = rest.split_at(len);
+                    for i in 0..count {
+                        let trailer = 1 + i * line_len + 1 + PANEL_LINE_BYTES;
+                        let padding = &frame[trailer..trailer + LINE_TRAILER_BYTES];
+                        assert!(padding.iter().all(|&byte| byte == DUMMY_DATA));
+                    }
+                    let end = &frame[len - FRAME_TRAILER_BYTES..];
+                    assert!(end.iter().all(|&byte| byte == DUMMY_DATA));
+                    assert_eq!(frame, testing::frame(Vcom::Lo, &lines));
+
+                    let records = decode::decode_with_trailers(frame, WIDTH, HEIGHT, trailers);
+                    let [decode::Record::WriteLines {
+                        vcom: false,
+                        lines: decoded,
+                    }] = &records[..]
+                    else {
+                        panic!("{strategy:?}: {records:?}");
+                    };
+                    let numbers = decoded.iter().map(|(number, _)| number - 1);
+                    assert!(numbers.eq(lines.iter().map(|&(y, _)| y)));
+                    rest = tail;
+                }
+                assert!(rest.is_empty(), "{strategy:?}");
+            }
+        }
+
+        // The clear command leaves the panel white, so clearing to white leaves nothing to send
+        #[test]
+        fn clear_to_the_cleared_panel_sends_only_the_command() {
+            let (log, mut display) = testing::display();
+            testing::draw_noise(&mut display, 285);
+            display.clear_to(Color::On).unwrap();
+            let clear = vec![Command::ClearMemory | Vcom::Lo, DUMMY_DATA];
+            let expected = [Event::Cs(true), Event::Write(clear), Event::Cs(false)];
+            assert_eq!(log.events(), expected);
+            assert_eq!(display.dirty_line_count(), 0);
+            assert!(testing::snapshot(&display).iter().flatten().all(|&on| on));
+            display.flush_buffer().unwrap();
+            assert_eq!(log.bytes(), testing::frame(Vcom::Hi, &[]));
+        }
+
+        #[test]
+        fn clear_to_black_sends_every_line_with_the_next_flush() {
+            let (log, mut display) = testing::display();
+            display.clear_to(Color::Off).unwrap();
+            assert_eq!(log.events(), []);
+            assert!(testing::snapshot(&display).iter().flatten().all(|&on| !on));
+            display.flush_buffer().unwrap();
+            let lines: Vec<_> = (0..HEIGHT).map(|y| (y, pixels(&[]))).collect();
+            assert_eq!(log.bytes(), testing::frame(Vcom::Lo, &lines));
+
+            // Inverted, the cleared panel shows black
+            display.set_invert(true);
+            display.clear_to(Color::Off).unwrap();
+            let clear = [Command::ClearMemory | Vcom::Hi, DUMMY_DATA];
+            assert_eq!(log.writes(), [clear]);
+            assert_eq!(display.dirty_line_count(), 0);
+        }
+
+        // Only the clear state decides what the inherent clear fills the framebuffer with
+        #[test]
+        fn clear_fills_the_framebuffer_with_the_clear_state() {
+            let (log, mut display) = testing::display();
+            display.set_clear_state(Color::Off);
+            display.clear().unwrap();
+            let clear = [Command::ClearMemory | Vcom::Lo, DUMMY_DATA];
+            assert_eq!(log.writes(), [clear]);
+            assert!(testing::snapshot(&display).iter().flatten().all(|&on| !on));
+            display.flush_buffer().unwrap();
+            let lines: Vec<_> = (0..HEIGHT).map(|y| (y, pixels(&[]))).collect();
+            assert_eq!(log.bytes(), testing::frame(Vcom::Hi, &lines));
+
+            #[cfg(feature = "graphics")]
+            {
+                use embedded_graphics::{draw_target::DrawTarget, pixelcolor::BinaryColor};
+                DrawTarget::clear(&mut display, BinaryColor::On).unwrap();
+                assert_eq!(log.events(), []);
+                assert!(testing::snapshot(&display).iter().flatten().all(|&on| on));
+                assert_eq!(display.dirty_line_count(), HEIGHT);
+                display.clear_buffer();
+                assert!(testing::snapshot(&display).iter().flatten().all(|&on| !on));
+            }
+        }
+
+        #[test]
+        fn clear_and_show_sends_the_clear_then_every_line() {
+            let (log, mut display) = testing::display();
+            testing::draw_diagonal(&mut display, LINES);
+            display.clear_and_show().unwrap();
+            assert!(!display.is_dirty());
+
+            let events = log.events();
+            let (clear, frame) = events.split_at(3);
+            assert_eq!(
+                clear,
+                [
+                    Event::Cs(true),
+                    Event::Write(vec![0x20, DUMMY_DATA]),
+                    Event::Cs(false)
+                ]
+            );
+            assert_eq!(frame.first(), Some(&Event::Cs(true)));
+            assert_eq!(frame.last(), Some(&Event::Cs(false)));
+            let selects = frame.iter().filter(|event| matches!(event, Event::Cs(_)));
+            assert_eq!(selects.count(), 2);
+            // Every line, with the framebuffer kept and VCOM toggled again
+            let mut lines: Vec<_> = (0..HEIGHT).map(|y| (y, pixels(&[]))).collect();
+            for (y, line) in testing::diagonal(LINES) {
+                lines[y].1 = line;
+            }
+            let bytes = testing::written(frame);
+            assert_eq!(bytes, testing::frame(Vcom::Hi, &lines));
+
+            #[cfg(feature = "std")]
+            {
+                let stream = testing::written(&events);
+                let records = decode::decode(&stream, WIDTH, HEIGHT);
+                let [decode::Record::ClearMemory { vcom: false }, decode::Record::WriteLines {
+                    vcom: true,
+                    lines: decoded,
+                }] = &records[..]
+                else {
+                    panic!("{records:?}");
+                };
+                assert_eq!(decoded.len(), HEIGHT);
+                let rows = decode::render(&stream, WIDTH, HEIGHT);
+                assert_eq!(rows, testing::snapshot(&display));
+            }
+        }
+
+        // Each display is sized and addressed for its own model, whichever is selected
+        #[test]
+        fn displays_of_different_models_side_by_side() {
+            fn expected<S: DisplaySpec>() -> Vec<u8> {
+                let line = testing::pixels_of::<S>(&[0, S::WIDTH - 1]);
+                testing::frame_of::<S>(Vcom::Lo, &[(S::HEIGHT - 1, line)])
+            }
+            let large = testing::last_line_of(spec::Ls027b7dh01);
+            let small = testing::last_line_of(spec::Ls013b7dh05);
+            let tall = testing::last_line_of(spec::Ls032b7dd02);
+            assert_eq!(large, expected::<spec::Ls027b7dh01>());
+            assert_eq!(small, expected::<spec::Ls013b7dh05>());
+            assert_eq!(tall, expected::<spec::Ls032b7dd02>());
+            assert_eq!((large.len(), small.len(), tall.len()), (54, 22, 46));
+            // Line 536 takes the high bits of its address in the byte before it
+            assert_eq!(tall[..2], [0x81, 0x18]);
+
+            // The framebuffer of the small panel holds about a quarter of the pixels
+            let buffers = [
+                size_of::<FrameBuffer<spec::Ls027b7dh01>>(),
+                size_of::<FrameBuffer<spec::Ls013b7dh05>>(),
+            ];
+            assert!(
+                buffers[0] >= 50 * 240 && buffers[1] < buffers[0] / 3,
+                "{buffers:?}"
+            );
+        }
+    }
+
+    // Every line of the framebuffer sent to each line of the panel it covers
+    #[cfg(all(
+        feature = "std",
+        any(feature = "half-height", feature = "half-width"),
+        not(any(feature = "column-major", feature = "minimal-flush"))
+    ))]
+    mod scaled {
+        use super::*;
+        use crate::testing::pixels;
+        use std::vec;
+
+        /// The panel pixels covered by the framebuffer pixels `xs`.
+        fn covered(xs: &[usize]) -> Vec<u8> {
+            let xs: Vec<_> = xs
+                .iter()
+                .flat_map(|&x| x * X_SCALE..(x + 1) * X_SCALE)
+                .collect();
+            pixels(&xs).to_vec()
+        }
+
+        /// Every line written by `bytes`, as (line number starting at 1, pixels).
+        fn decoded(bytes: &[u8]) -> Vec<(usize, Vec<u8>)> {
+            let records = decode::decode(bytes, WIDTH, HEIGHT);
+            let lines = records.into_iter().flat_map(|record| match record {
+                decode::Record::WriteLines { lines, .. } => lines,
+                record => panic!("{record:?}"),
+            });
+            lines.collect()
+        }
+
+        #[test]
+        fn lines_are_duplicated_on_the_panel() {
+            for strategy in testing::strategies() {
+                let (log, mut display) = testing::display();
+                display.set_cs_strategy(strategy);
+                assert_eq!(display.drawable_size(), (BUFFER_WIDTH, BUFFER_HEIGHT));
+                display.set_pixel(3, 5, Color::On).unwrap();
+                display.set_pixel(17, 5, Color::On).unwrap();
+                display.flush_buffer().unwrap();
+                let expected: Vec<_> = (5 * Y_SCALE..6 * Y_SCALE)
+                    .map(|y| (y + 1, covered(&[3, 17])))
+                    .collect();
+                assert_eq!(decoded(&log.bytes()), expected, "{strategy:?}");
+            }
+        }
+
+        #[test]
+        fn only_the_panel_lines_of_dirty_lines_are_sent() {
+            let (log, mut display) = testing::display();
+            testing::draw_diagonal(&mut display, 0..BUFFER_HEIGHT);
+            display.flush_buffer().unwrap();
+            log.events();
+
+            // Unchanged pixels mark nothing
+            display.set_pixel(7, 7, Color::On).unwrap();
+            display.set_pixel(2, 3, Color::Off).unwrap();
+            display.set_pixel(4, 4, Color::Off).unwrap();
+            display
+                .set_pixel(0, BUFFER_HEIGHT as u32 - 1, Color::On)
+                .unwrap();
+            assert_eq!(display.dirty_line_count(), 2);
+            display.flush_buffer().unwrap();
+            let last = BUFFER_HEIGHT - 1;
+            let mut expected = vec![];
+            for (y, xs) in [(4, vec![]), (last, vec![0, last % BUFFER_WIDTH])] {
+                for line in y * Y_SCALE..(y + 1) * Y_SCALE {
+                    expected.push((line + 1, covered(&xs)));
+                }
+            }
+            assert_eq!(decoded(&log.bytes()), expected);
+            assert!(!display.is_dirty());
+        }
+
+        #[test]
+        fn panel_shows_the_framebuffer_scaled_up() {
+            let (log, mut display) = testing::display();
+            testing::draw_noise(&mut display, 240);
+            display.flush_buffer().unwrap();
+            let snapshot = testing::snapshot(&display);
+            let rows = decode::render(&log.bytes(), WIDTH, HEIGHT);
+            for (y, row) in rows.iter().enumerate() {
+                for (x, &pixel) in row.iter().enumerate() {
+                    assert_eq!(pixel, snapshot[y / Y_SCALE][x / X_SCALE], "({x}, {y})");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn flush_stats_count_what_was_sent() {
+        // Every line is dirty all the time with minimal-flush
+        let all = cfg!(feature = "minimal-flush");
+        let dirty = |lines| if all { BUFFER_HEIGHT } else { lines };
+        for strategy in testing::strategies() {
+            let (log, mut display) = testing::display();
+            display.set_cs_strategy(strategy);
+            assert_eq!(display.is_dirty(), all);
+            let stats = display.flush_buffer_stats().unwrap();
+            assert_eq!(stats.lines_written, dirty(0), "{strategy:?}");
+            assert_eq!(stats.bytes_written, log.bytes().len(), "{strategy:?}");
+
+            // Up to the last line, not past it
+            testing::draw_diagonal(&mut display, [1, 5, BUFFER_HEIGHT - 1]);
+            assert!(display.is_dirty());
+            assert_eq!(display.dirty_line_count(), dirty(3));
+            let stats = display.flush_buffer_stats().unwrap();
+            assert_eq!(stats.lines_written, dirty(3), "{strategy:?}");
+            assert_eq!(stats.bytes_written, log.bytes().len(), "{strategy:?}");
+            assert!(stats.estimated_ms > 0);
+            assert_eq!((stats.waited_ms, stats.overlaps_edge), (0, false));
+
+            display.mark_all_dirty();
+            assert_eq!(display.dirty_line_count(), BUFFER_HEIGHT);
+        }
+    }
+
+    #[test]
+    fn set_pixel_marks_only_pixels_which_change() {
+        let (_, mut display) = testing::display();
+        let dirty = |lines| {
+            if cfg!(feature = "minimal-flush") {
+                BUFFER_HEIGHT
+            } else {
+                lines
+            }
+        };
+        let (width, height) = display.drawable_size();
+        let (x, y) = (width as u32 - 1, height as u32 - 1);
+        assert_eq!(display.get_pixel(x, y), Some(Color::Off));
+        display.set_pixel(x, y, Color::Off).unwrap();
+        assert_eq!(display.dirty_line_count(), dirty(0));
+        display.set_pixel(x, y, Color::On).unwrap();
+        assert_eq!(display.get_pixel(x, y), Some(Color::On));
+        assert_eq!(display.dirty_line_count(), dirty(1));
+        display.flush_buffer().unwrap();
+        // Setting it again changes nothing
+        display.set_pixel(x, y, true).unwrap();
+        assert_eq!(display.dirty_line_count(), dirty(0));
+        unsafe { display.set_pixel_unchecked(0, 0, Color::On) };
+        assert_eq!(display.get_pixel(0, 0), Some(Color::On));
+        assert_eq!(display.dirty_line_count(), dirty(1));
+    }
+
+    #[test]
+    fn set_pixel_outside_changes_nothing() {
+        let (log, mut display) = testing::display();
+        testing::draw_noise(&mut display, 254);
+        display.flush_buffer().unwrap();
+        log.events();
+        let before = testing::snapshot(&display);
+        let (width, height) = display.drawable_size();
+        let (width, height) = (width as u32, height as u32);
+        for (x, y) in [
+            (width, 0),
+            (0, height),
+            (width, height),
+            (u32::MAX, u32::MAX),
+        ] {
+            assert_eq!(display.set_pixel(x, y, Color::On), Err(OutOfBounds));
+            assert_eq!(display.set_pixel(x, y, Color::Off), Err(OutOfBounds));
+            assert_eq!(display.get_pixel(x, y), None);
+        }
+        assert_eq!(testing::snapshot(&display), before);
+        if !cfg!(feature = "minimal-flush") {
+            assert!(!display.is_dirty());
+            display.flush_buffer().unwrap();
+            assert_eq!(log.bytes(), testing::frame(Vcom::Hi, &[]));
+        }
+    }
+
+    #[test]
+    fn packed_rows_round_trip() {
+        let (_, mut drawn) = testing::display();
+        testing::draw_noise(&mut drawn, 301);
+        let mut rows = [0; LINE_BYTES * BUFFER_HEIGHT];
+        drawn.to_packed_rows(&mut rows).unwrap();
+        // The leftmost pixel in the most significant bit, whatever the storage layout
+        for y in 0..BUFFER_HEIGHT {
+            for x in 0..BUFFER_WIDTH {
+                let on = rows[y * LINE_BYTES + x / 8] & (0x80 >> (x % 8)) != 0;
+                let pixel = drawn.get_pixel(x as u32, y as u32);
+                assert_eq!(pixel, Some(on.into()), "({x}, {y})");
+            }
+        }
+
+        let (_, mut display) = testing::display();
+        display.from_packed_rows(&rows);
+        assert_eq!(testing::snapshot(&display), testing::snapshot(&drawn));
+        let mut copied = [0xAA; LINE_BYTES * BUFFER_HEIGHT];
+        display.to_packed_rows(&mut copied).unwrap();
+        assert_eq!(copied, rows);
+
+        // Too short to hold every line
+        let mut short = [0xAA; LINE_BYTES * BUFFER_HEIGHT - 1];
+        assert_eq!(display.to_packed_rows(&mut short), Err(OutOfBounds));
+        assert!(short.iter().all(|&byte| byte == 0xAA));
+    }
+
+    #[test]
+    #[cfg(not(feature = "minimal-flush"))]
+    fn packed_rows_mark_only_changed_lines() {
+        let (_, mut display) = testing::display();
+        testing::draw_noise(&mut display, 289);
+        let mut rows = [0; LINE_BYTES * BUFFER_HEIGHT];
+        display.to_packed_rows(&mut rows).unwrap();
+        display.flush_buffer().unwrap();
+        display.from_packed_rows(&rows);
+        assert!(!display.is_dirty());
+
+        rows[7 * LINE_BYTES] ^= 0x80;
+        display.from_packed_rows(&rows);
+        assert_eq!(display.dirty_line_count(), 1);
+        let pixel = display.get_pixel(0, 7).unwrap();
+        assert_eq!(pixel == Color::On, rows[7 * LINE_BYTES] & 0x80 != 0);
+    }
+
+    /// The framebuffer with the pixels `on` set, given by their position in the framebuffer.
+    #[cfg(not(feature = "column-major"))]
+    fn buffer_with(on: &[(usize, usize)]) -> Vec<[u8; LINE_BYTES]> {
+        let mut lines = std::vec![[0; LINE_BYTES]; BUFFER_HEIGHT];
+        for &(x, y) in on {
+            lines[y][x / 8] |= 1 << (x % 8);
+        }
+        lines
+    }
+
+    #[test]
+    #[cfg(not(feature = "column-major"))]
+    fn mirroring_flips_what_is_drawn_next() {
+        // An L, which is different in every mirroring
+        let glyph = [(0, 0), (0, 1), (0, 2), (1, 2)];
+        let (x, y) = (BUFFER_WIDTH - 1, BUFFER_HEIGHT - 1);
+        for (mirror, expected) in [
+            ((false, false), glyph),
+            ((true, false), [(x, 0), (x, 1), (x, 2), (x - 1, 2)]),
+            ((false, true), [(0, y), (0, y - 1), (0, y - 2), (1, y - 2)]),
+            (
+                (true, true),
+                [(x, y), (x, y - 1), (x, y - 2), (x - 1, y - 2)],
+            ),
+        ] {
+            let (_, mut display) = testing::display();
+            let size = display.drawable_size();
+            display.set_mirror(mirror.0, mirror.1);
+            assert_eq!(display.mirror(), mirror);
+            for (x, y) in glyph {
+                display.set_pixel(x as u32, y as u32, Color::On).unwrap();
+            }
+            assert_eq!(display.buffer()[..], buffer_with(&expected), "{mirror:?}");
+            for (x, y) in glyph {
+                assert_eq!(display.get_pixel(x as u32, y as u32), Some(Color::On));
+            }
+            assert_eq!(display.drawable_size(), size);
+
+            // Every pixel inside lands inside, every pixel outside is still rejected
+            let (width, height) = (size.0 as u32, size.1 as u32);
+            assert_eq!(display.set_pixel(width - 1, height - 1, Color::On), Ok(()));
+            assert_eq!(display.get_pixel(width - 1, height - 1), Some(Color::On));
+            assert_eq!(display.set_pixel(width, 0, Color::On), Err(OutOfBounds));
+            assert_eq!(display.set_pixel(0, height, Color::On), Err(OutOfBounds));
+        }
+
+        // What was drawn before stays
+        let (_, mut display) = testing::display();
+        for (x, y) in glyph {
+            display.set_pixel(x as u32, y as u32, Color::On).unwrap();
+        }
+        display.set_mirror(true, true);
+        display.set_pixel(0, 0, Color::On).unwrap();
+        let expected = [&glyph[..], &[(x, y)]].concat();
+        assert_eq!(display.buffer()[..], buffer_with(&expected));
+    }
+
+    #[test]
+    #[cfg(feature = "transaction-buffer")]
+    fn hardware_sends_a_whole_frame_in_one_write() {
+        let flush = |strategy, seed| {
+            let (log, mut display) = testing::display();
+            display.set_cs_strategy(strategy);
+            testing::draw_noise(&mut display, seed);
+            display.mark_all_dirty();
+            display.flush_buffer().unwrap();
+            log.events()
+        };
+        for seed in [1, 256] {
+            let software = flush(CsStrategy::Software, seed);
+            assert!(software.len() > 3);
+            let [testing::Event::Cs(true), testing::Event::Write(frame), testing::Event::Cs(false)] =
+                &flush(CsStrategy::Hardware, seed)[..]
+            else {
+                panic!("not a single transaction");
+            };
+            assert_eq!(frame.len(), TRANSACTION_BUFFER_SIZE);
+            assert_eq!(*frame, testing::written(&software));
+        }
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn packed_bytes_match_the_deprecated_swap() {
+        // The implementation of swap flushes used before reverse_bits
+        let copied = |byte: &BitSlice<u8, Lsb0>| {
+            let mut local_buffer = bitarr!(u8, Msb0; 0; 8);
+            for (i, bit) in byte.iter().by_ref().enumerate() {
+                local_buffer.set(i, *bit);
+            }
+            local_buffer.load::<u8>()
+        };
+        for value in 0..=u8::MAX {
+            let bits = value.view_bits::<Lsb0>();
+            let swapped = testing::TestDisplay::swap(bits);
+            assert_eq!(swapped, copied(bits), "{value:#04x}");
+            assert_eq!(swapped, value.reverse_bits(), "{value:#04x}");
+
+            let packed = testing::TestDisplay::pack_panel_line(0, &[value; PANEL_LINE_BYTES]);
+            let data = &packed[1..=PANEL_LINE_BYTES];
+            let (last, full) = data.split_last().unwrap();
+            assert!(full.iter().all(|&byte| byte == swapped), "{value:#04x}");
+            let padding = <spec::Selected as DisplaySpec>::PANEL_LINE_PADDING;
+            assert_eq!(*last, swapped & !padding.reverse_bits());
+        }
     }
 }
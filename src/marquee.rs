@@ -0,0 +1,223 @@
+use crate::{DisplaySpec, FrameBuffer, MemoryDisplay};
+use core::borrow::BorrowMut;
+use embedded_graphics::draw_target::{DrawTarget, DrawTargetExt};
+use embedded_graphics::pixelcolor::BinaryColor;
+use embedded_graphics::prelude::{Drawable, Point, Size, Transform};
+use embedded_graphics::primitives::Rectangle;
+use hal::blocking::spi::Write;
+use hal::digital::v2::OutputPin;
+
+/// Content scrolling through a fixed region from right to left, e.g. a long string on a badge.
+///
+/// The content is any drawable positioned at the top left corner of the region, e.g. a `Text`,
+/// and repeats every `period` pixels, so the period should include the gap between
+/// repetitions. Every [`Marquee::tick`] shifts the region by `step` pixels using
+/// [`MemoryDisplay::scroll_region_left`] and only redraws the newly exposed columns.
+pub struct Marquee {
+    region: Rectangle,
+    step: u32,
+    period: u32,
+    offset: u32,
+    background: BinaryColor,
+}
+
+impl Marquee {
+    pub fn new(region: Rectangle, step: u32, period: u32, background: BinaryColor) -> Self {
+        Self {
+            region,
+            step: step.max(1),
+            period: period.max(1),
+            offset: 0,
+            background,
+        }
+    }
+
+    /// The region the content scrolls through.
+    pub fn region(&self) -> Rectangle {
+        self.region
+    }
+
+    /// How far the content has scrolled within the current cycle, in pixels.
+    pub fn offset(&self) -> u32 {
+        self.offset
+    }
+
+    /// Clear the region and draw the content at the current offset, e.g. before the first tick
+    /// or after the content has changed.
+    pub fn redraw<SPI, CS, DISP, E, T, SPEC, BUF>(
+        &self,
+        display: &mut MemoryDisplay<SPI, CS, DISP, SPEC, BUF>,
+        content: &T,
+    ) -> Result<(), E>
+    where
+        SPI: Write<u8, Error = E>,
+        CS: OutputPin,
+        DISP: OutputPin,
+        SPEC: DisplaySpec,
+        BUF: BorrowMut<FrameBuffer<SPEC>>,
+        T: Drawable<Color = BinaryColor> + Transform,
+    {
+        display.fill_solid(&self.region, self.background)?;
+        self.draw_columns(display, 0, self.region.size.width, content)
+    }
+
+    /// Scroll the content by one step, drawing it clipped to the newly exposed columns. Returns
+    /// whether a full cycle of the content has completed.
+    ///
+    /// Steps as wide as the region redraw it completely.
+    pub fn tick<SPI, CS, DISP, E, T, SPEC, BUF>(
+        &mut self,
+        display: &mut MemoryDisplay<SPI, CS, DISP, SPEC, BUF>,
+        content: &T,
+    ) -> Result<bool, E>
+    where
+        SPI: Write<u8, Error = E>,
+        CS: OutputPin,
+        DISP: OutputPin,
+        SPEC: DisplaySpec,
+        BUF: BorrowMut<FrameBuffer<SPEC>>,
+        T: Drawable<Color = BinaryColor> + Transform,
+    {
+        let width = self.region.size.width;
+        let step = self.step.min(width);
+        let offset = self.offset + self.step;
+        self.offset = offset % self.period;
+
+        display.scroll_region_left(self.region, step, self.background);
+        self.draw_columns(display, width - step, width, content)?;
+        Ok(offset >= self.period)
+    }
+
+    /// Draw the columns `from..to` of the region.
+    fn draw_columns<SPI, CS, DISP, E, T, SPEC, BUF>(
+        &self,
+        display: &mut MemoryDisplay<SPI, CS, DISP, SPEC, BUF>,
+        from: u32,
+        to: u32,
+        content: &T,
+    ) -> Result<(), E>
+    where
+        SPI: Write<u8, Error = E>,
+        CS: OutputPin,
+        DISP: OutputPin,
+        SPEC: DisplaySpec,
+        BUF: BorrowMut<FrameBuffer<SPEC>>,
+        T: Drawable<Color = BinaryColor> + Transform,
+    {
+        if from >= to {
+            return Ok(());
+        }
+        let area = Rectangle::new(
+            self.region.top_left + Point::new(from as i32, 0),
+            Size::new(to - from, self.region.size.height),
+        );
+        let mut target = display.clipped(&area);
+
+        // Content shifted by `x` covers the columns `x..x + period` of the region, so the first
+        // repetition reaching column `from` starts at or before it
+        let (period, offset) = (self.period as i32, self.offset as i32);
+        let mut x = from as i32 - (from as i32 + offset) % period;
+        while x < to as i32 {
+            content.translate(Point::new(x, 0)).draw(&mut target)?;
+            x += period;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+    use embedded_graphics::primitives::{Primitive, PrimitiveStyle, Styled};
+
+    const PERIOD: u32 = 7;
+
+    // Columns 1 to 3 of every period, on rows 1 and 2 of the region
+    fn content(region: Rectangle) -> Styled<Rectangle, PrimitiveStyle<BinaryColor>> {
+        Rectangle::new(region.top_left + Point::new(1, 1), Size::new(3, 2))
+            .into_styled(PrimitiveStyle::with_fill(BinaryColor::On))
+    }
+
+    /// Check the region shows the content scrolled by `scrolled` pixels and the rest of the
+    /// display is `outside`.
+    fn check(
+        display: &testing::TestDisplay,
+        region: Rectangle,
+        scrolled: u32,
+        outside: &[std::vec::Vec<bool>],
+    ) {
+        let pixels = testing::snapshot(display);
+        for (y, line) in pixels.iter().enumerate() {
+            for (x, &pixel) in line.iter().enumerate() {
+                let point = Point::new(x as i32, y as i32);
+                let expected = if region.contains(point) {
+                    let column = (point - region.top_left).x as u32;
+                    let row = (point - region.top_left).y;
+                    (1..4).contains(&((column + scrolled) % PERIOD)) && (1..3).contains(&row)
+                } else {
+                    outside[y][x]
+                };
+                assert_eq!(pixel, expected, "({x}, {y}) scrolled by {scrolled}");
+            }
+        }
+    }
+
+    fn scroll(region: Rectangle, step: u32, ticks: u32) {
+        let (_, mut display) = testing::display();
+        testing::draw_noise(&mut display, 7);
+        let outside = testing::snapshot(&display);
+        let content = content(region);
+        let mut marquee = Marquee::new(region, step, PERIOD, BinaryColor::Off);
+        marquee.redraw(&mut display, &content).unwrap();
+        check(&display, region, 0, &outside);
+
+        for tick in 1..=ticks {
+            let completed = marquee.tick(&mut display, &content).unwrap();
+            let scrolled = tick * step;
+            assert_eq!(marquee.offset(), scrolled % PERIOD);
+            assert_eq!(completed, scrolled / PERIOD > (scrolled - step) / PERIOD);
+            check(&display, region, scrolled, &outside);
+        }
+    }
+
+    #[test]
+    fn content_wraps_around() {
+        for step in [1, 2, 3, PERIOD] {
+            scroll(Rectangle::new(Point::new(5, 3), Size::new(20, 4)), step, 16);
+        }
+    }
+
+    #[test]
+    fn steps_wider_than_the_region() {
+        let region = Rectangle::new(Point::new(9, 0), Size::new(6, 3));
+        for step in [5, 6, 11, 23] {
+            scroll(region, step, 6);
+        }
+    }
+
+    #[test]
+    #[cfg(not(any(
+        feature = "half-height",
+        feature = "column-major",
+        feature = "minimal-flush"
+    )))]
+    fn only_the_lines_of_the_region_are_dirty() {
+        let (_, mut display) = testing::display();
+        let region = Rectangle::new(Point::new(3, 10), Size::new(30, 4));
+        let content = content(region);
+        let mut marquee = Marquee::new(region, 2, PERIOD, BinaryColor::Off);
+        marquee.redraw(&mut display, &content).unwrap();
+        display.flush_buffer().unwrap();
+        marquee.tick(&mut display, &content).unwrap();
+        let mut lines = std::vec![];
+        display
+            .flush_buffer_with_progress(|progress| lines.push(progress.line))
+            .unwrap();
+        assert!(!lines.is_empty());
+        assert!(
+            lines.iter().all(|line| (10..14).contains(line)),
+            "{lines:?}"
+        );
+    }
+}
@@ -0,0 +1,295 @@
+use crate::{DisplaySpec, FrameBuffer, MemoryDisplay};
+use core::borrow::BorrowMut;
+use core::ops::Range;
+use embedded_graphics::pixelcolor::BinaryColor;
+use embedded_graphics::prelude::Point;
+use hal::blocking::spi::Write;
+use hal::digital::v2::OutputPin;
+
+/// How [`MemoryDisplay::draw_scaled_matrix`] draws a matrix of modules.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MatrixStyle {
+    /// Width and height of the block drawn for every module, in pixels.
+    pub scale: usize,
+    /// Width of the border drawn around the matrix in the `clear` colour, in modules. QR codes
+    /// need a quiet zone of 4 modules to scan reliably.
+    pub quiet_zone: usize,
+    /// Colour of set modules.
+    pub set: BinaryColor,
+    /// Colour of clear modules, `None` to leave them untouched.
+    pub clear: Option<BinaryColor>,
+}
+
+impl Default for MatrixStyle {
+    /// Black modules on white at a scale of 1, without a quiet zone.
+    fn default() -> Self {
+        Self {
+            scale: 1,
+            quiet_zone: 0,
+            set: BinaryColor::Off,
+            clear: Some(BinaryColor::On),
+        }
+    }
+}
+
+impl<SPI, CS, DISP, E, SPEC, BUF> MemoryDisplay<SPI, CS, DISP, SPEC, BUF>
+where
+    SPI: Write<u8, Error = E>,
+    CS: OutputPin,
+    DISP: OutputPin,
+    SPEC: DisplaySpec,
+    BUF: BorrowMut<FrameBuffer<SPEC>>,
+{
+    /// Draw a matrix of `size.0` by `size.1` modules, e.g. a QR code or the cells of a cellular
+    /// automaton, with its top left corner (including the quiet zone) at `top_left`. Module
+    /// `(x, y)` is set if `modules(x, y)` returns `true`.
+    ///
+    /// Every module is drawn as a filled block, a byte at a time where possible. The matrix is
+    /// clipped to the display and the clip, see [`MemoryDisplay::set_clip`].
+    pub fn draw_scaled_matrix(
+        &mut self,
+        top_left: Point,
+        size: (usize, usize),
+        style: &MatrixStyle,
+        modules: impl Fn(usize, usize) -> bool,
+    ) {
+        if style.scale == 0 {
+            return;
+        }
+        let scale = style.scale as i32;
+        let quiet = style.quiet_zone as i32;
+        let (width, height) = (size.0 as i32 + 2 * quiet, size.1 as i32 + 2 * quiet);
+
+        // Only the modules overlapping the clip, by default the display, are visited
+        let visible = |origin: i32, count: i32, extent: &Range<usize>| {
+            let first = (extent.start as i32 - origin).div_euclid(scale).max(0);
+            let last = (extent.end as i32 - origin + scale - 1)
+                .div_euclid(scale)
+                .min(count);
+            first..last
+        };
+        let clamp = |start: i32, end: i32, extent: &Range<usize>| {
+            start.max(extent.start as i32) as usize..(end.max(0) as usize).min(extent.end)
+        };
+
+        let (clip_x, clip_y) = self.clip_area();
+        for my in visible(top_left.y, height, &clip_y) {
+            let y0 = top_left.y + my * scale;
+            let rows = clamp(y0, y0 + scale, &clip_y);
+            let mut mx = visible(top_left.x, width, &clip_x);
+
+            // Extend runs of modules of the same colour into a single span
+            while let Some(start) = mx.next() {
+                let color = module_color(style, size, &modules, start, my);
+                let mut end = start + 1;
+                while mx.start < mx.end
+                    && module_color(style, size, &modules, mx.start, my) == color
+                {
+                    end = mx.next().unwrap() + 1;
+                }
+
+                let Some(color) = color else {
+                    continue;
+                };
+                let x0 = top_left.x + start * scale;
+                let x1 = top_left.x + end * scale;
+                let columns = clamp(x0, x1, &clip_x);
+                self.fill_rect(columns, rows.clone(), color.is_on());
+            }
+        }
+    }
+
+    /// Same as [`MemoryDisplay::draw_scaled_matrix`], with the modules packed row by row, every
+    /// row padded to whole bytes and the leftmost module in the most significant bit.
+    pub fn draw_scaled_bitmap(
+        &mut self,
+        top_left: Point,
+        size: (usize, usize),
+        style: &MatrixStyle,
+        data: &[u8],
+    ) {
+        let row_bytes = size.0.div_ceil(8);
+        self.draw_scaled_matrix(top_left, size, style, |x, y| {
+            data.get(y * row_bytes + x / 8)
+                .is_some_and(|byte| byte & (0x80 >> (x % 8)) != 0)
+        });
+    }
+}
+
+/// The colour of module `(x, y)` of the matrix including the quiet zone.
+fn module_color(
+    style: &MatrixStyle,
+    size: (usize, usize),
+    modules: &impl Fn(usize, usize) -> bool,
+    x: i32,
+    y: i32,
+) -> Option<BinaryColor> {
+    let quiet = style.quiet_zone as i32;
+    let (x, y) = (x - quiet, y - quiet);
+    let inside = x >= 0 && y >= 0 && (x as usize) < size.0 && (y as usize) < size.1;
+    if inside && modules(x as usize, y as usize) {
+        Some(style.set)
+    } else {
+        style.clear
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{self, TestDisplay};
+    use embedded_graphics::prelude::Size;
+    use embedded_graphics::primitives::Rectangle;
+    use std::vec::Vec;
+
+    // A 5x4 matrix of modules, packed row by row
+    const BITMAP: [u8; 4] = [0b1011_0000, 0b0100_1000, 0b1111_1000, 0b0000_0000];
+
+    fn module(x: usize, y: usize) -> bool {
+        BITMAP[y] & (0x80 >> x) != 0
+    }
+
+    /// The pixels of `display` with the matrix drawn pixel by pixel, only within `clip`.
+    fn drawn(
+        display: &TestDisplay,
+        top_left: Point,
+        style: &MatrixStyle,
+        clip: Option<Rectangle>,
+    ) -> Vec<Vec<bool>> {
+        let mut pixels = testing::snapshot(display);
+        let quiet = style.quiet_zone;
+        let (width, height) = ((5 + 2 * quiet) * style.scale, (4 + 2 * quiet) * style.scale);
+        for dy in 0..height {
+            for dx in 0..width {
+                let point = top_left + Point::new(dx as i32, dy as i32);
+                let (mx, my) = (
+                    (dx / style.scale) as isize - quiet as isize,
+                    (dy / style.scale) as isize - quiet as isize,
+                );
+                let set = (0..5).contains(&mx)
+                    && (0..4).contains(&my)
+                    && module(mx as usize, my as usize);
+                let color = if set { Some(style.set) } else { style.clear };
+                let inside = clip.is_none_or(|clip| clip.contains(point));
+                if let (Some(color), true) = (color, inside) {
+                    if let Some(pixel) = (pixels.get_mut(point.y as usize))
+                        .and_then(|line| line.get_mut(point.x as usize))
+                    {
+                        *pixel = color.is_on();
+                    }
+                }
+            }
+        }
+        pixels
+    }
+
+    fn check(top_left: Point, style: MatrixStyle, clip: Option<Rectangle>) {
+        let (_, mut display) = testing::display();
+        testing::draw_noise(&mut display, 3);
+        display.set_clip(clip);
+        let expected = drawn(&display, top_left, &style, clip);
+        display.draw_scaled_matrix(top_left, (5, 4), &style, module);
+        assert_eq!(
+            testing::snapshot(&display),
+            expected,
+            "{style:?} at {top_left:?}"
+        );
+
+        let (_, mut packed) = testing::display();
+        testing::draw_noise(&mut packed, 3);
+        packed.set_clip(clip);
+        packed.draw_scaled_bitmap(top_left, (5, 4), &style, &BITMAP);
+        assert_eq!(testing::snapshot(&packed), expected);
+    }
+
+    #[test]
+    fn scale_of_a_pixel() {
+        let style = MatrixStyle::default();
+        check(Point::new(0, 0), style, None);
+        check(Point::new(3, 2), style, None);
+        let style = MatrixStyle {
+            quiet_zone: 2,
+            clear: None,
+            ..style
+        };
+        check(Point::new(8, 1), style, None);
+    }
+
+    #[test]
+    fn scales_across_byte_boundaries() {
+        for scale in [3, 5, 8, 11] {
+            let style = MatrixStyle {
+                scale,
+                quiet_zone: 1,
+                ..MatrixStyle::default()
+            };
+            check(Point::new(5, 7), style, None);
+            check(Point::new(16, 0), style, None);
+            let inverted = MatrixStyle {
+                set: BinaryColor::On,
+                clear: None,
+                ..style
+            };
+            check(Point::new(13, 3), inverted, None);
+        }
+    }
+
+    #[test]
+    fn matrices_partially_off_screen() {
+        let (width, height) = testing::display().1.drawable_size();
+        let (width, height) = (width as i32, height as i32);
+        let style = MatrixStyle {
+            scale: 3,
+            quiet_zone: 4,
+            ..MatrixStyle::default()
+        };
+        for top_left in [
+            (-7, -5),
+            (-40, 2),
+            (width - 10, 3),
+            (2, height - 8),
+            (width - 5, height - 5),
+        ] {
+            check(Point::new(top_left.0, top_left.1), style, None);
+        }
+        let clip = Rectangle::new(Point::new(6, 4), Size::new(13, 9));
+        check(Point::new(0, 0), style, Some(clip));
+        check(
+            Point::new(9, 8),
+            MatrixStyle { scale: 2, ..style },
+            Some(clip),
+        );
+    }
+
+    #[test]
+    #[cfg(not(any(
+        feature = "half-height",
+        feature = "column-major",
+        feature = "minimal-flush"
+    )))]
+    fn only_the_rows_of_the_matrix_are_dirty() {
+        let (_, mut display) = testing::display();
+        let style = MatrixStyle {
+            scale: 3,
+            ..MatrixStyle::default()
+        };
+        display.draw_scaled_matrix(Point::new(5, 10), (5, 4), &style, module);
+        let mut lines = std::vec![];
+        display
+            .flush_buffer_with_progress(|progress| lines.push(progress.line))
+            .unwrap();
+        // The third row of modules is all set, drawing black on black
+        assert_eq!(lines, [10, 11, 12, 13, 14, 15, 19, 20, 21]);
+    }
+
+    #[test]
+    fn scale_zero_draws_nothing() {
+        let (_, mut display) = testing::display();
+        let style = MatrixStyle {
+            scale: 0,
+            ..MatrixStyle::default()
+        };
+        display.draw_scaled_matrix(Point::zero(), (5, 4), &style, module);
+        assert!(!display.is_dirty() || cfg!(feature = "minimal-flush"));
+    }
+}
@@ -0,0 +1,801 @@
+//! The table of supported display models.
+//!
+//! Everything model-specific is derived from this table: a [`DisplaySpec`](spec::DisplaySpec)
+//! type per model, which sizes the buffers of a display for it, the default model selected via
+//! the feature flag along with the check that at most one is selected, the list of all models
+//! used by the decoder, and for the tests the entries as written, including the bytes
+//! addressing the last line of the model as sent. Adding a model only requires a new entry
+//! here and a Cargo feature of the same name.
+
+/// Compile each item only with the feature of the same position selected and none before it,
+/// failing the build once for every further model selected.
+macro_rules! first_selected {
+    ([$($prior:literal)*] [$feature:literal $($rest:literal)*] $item:item $($items:item)*) => {
+        #[cfg(all(feature = $feature, not(any($(feature = $prior),*))))]
+        $item
+
+        #[cfg(all(feature = $feature, any($(feature = $prior),*)))]
+        compile_error!(concat!(
+            "Please specify only one display type via the feature flag, not also ",
+            $feature,
+            " (you may need default-features = false)"
+        ));
+
+        first_selected!([$($prior)* $feature] [$($rest)*] $($items)*);
+    };
+    ([$($prior:literal)*] []) => {};
+}
+
+macro_rules! models {
+    ($(
+        $(#[doc = $doc:literal])*
+        $feature:literal as $spec:ident => {
+            phase: $phase:ident,
+            width: $width:expr,
+            height: $height:expr,
+            active_area_mm: $area:expr,
+            ppi: $ppi:expr,
+            shape: $shape:ident,
+            recommended_vcom_hz: $vcom_hz:expr,
+            jdi_commands: $jdi:expr,
+            max_sclk_hz: $sclk:expr,
+            cs_timing_us: $cs_timing:expr,
+            line_trailer_bytes: $line_trailer:expr,
+            frame_trailer_bytes: $frame_trailer:expr,
+            address_bits: $address_bits:expr,
+            last_address: $last_address:expr,
+        }
+    )*) => {
+        // With several models selected only the first is compiled, so the error below is the
+        // only one
+        first_selected! {
+            [] [$($feature)*]
+            $(
+                // The model selected via the feature flag
+                pub(crate) mod display {
+                    pub(crate) type Spec = crate::spec::$spec;
+                }
+            )*
+        }
+
+        #[cfg(not(any($(feature = $feature),*)))]
+        pub(crate) mod display {
+            pub(crate) type Spec = crate::spec::NoModelSelected;
+        }
+
+        /// Compile `item` only if a model is selected via the feature flag, for the constants
+        /// describing it.
+        macro_rules! with_selected_model {
+            ($item:item) => {
+                #[cfg(any($(feature = $feature),*))]
+                $item
+            };
+        }
+
+        /// The properties of every supported display model as types, whichever model is selected
+        /// via the feature flag.
+        ///
+        /// [`MemoryDisplay`](crate::MemoryDisplay) takes the model as a type parameter, which
+        /// defaults to the one selected via the feature flag, [`Selected`](spec::Selected). Its
+        /// framebuffer is sized by the array types of the model, so a single binary can drive
+        /// several models, each display naming its own, e.g.
+        /// `MemoryDisplay<SPI, CS, DISP, spec::Ls013b7dh05>`.
+        pub mod spec {
+            use crate::Shape;
+            use hal::spi::{Mode, Phase, Polarity};
+
+            /// A value with every bit cleared, to build the buffers of a model in `const`
+            /// constructors.
+            pub trait Zeroed: Copy + PartialEq + core::fmt::Debug + 'static {
+                /// The value with every bit cleared.
+                const ZERO: Self;
+            }
+
+            impl Zeroed for u8 {
+                const ZERO: Self = 0;
+            }
+
+            impl Zeroed for u16 {
+                const ZERO: Self = 0;
+            }
+
+            impl<T: Zeroed, const N: usize> Zeroed for [T; N] {
+                const ZERO: Self = [T::ZERO; N];
+            }
+
+            /// An array of `T` of a length given by a model, see [`DisplaySpec`].
+            pub trait Array<T>: Zeroed + AsRef<[T]> + AsMut<[T]> {}
+
+            impl<T: Zeroed, const N: usize> Array<T> for [T; N] {}
+
+            /// The properties of a display model, see the [module documentation](self).
+            ///
+            /// Besides the figures of the datasheet, a model gives the array types holding its
+            /// lines, whose lengths cannot be derived from the constants of a type parameter on
+            /// stable Rust. The types of this module implement it from the model table.
+            #[diagnostic::on_unimplemented(
+                message = "`{Self}` is not a display model",
+                note = "the models are the types of `sharp_memory_display::spec`"
+            )]
+            pub trait DisplaySpec: 'static {
+                /// The name of the model, which is also the name of its Cargo feature.
+                const NAME: &'static str;
+                /// Width of the display in pixels.
+                const WIDTH: usize;
+                /// Height of the display in pixels.
+                const HEIGHT: usize;
+                /// Mode to configure the SPI device in, see [`crate::MODE`].
+                const MODE: Mode;
+                /// Physical (width, height) of the active area in millimetres.
+                const ACTIVE_AREA_MM: (f32, f32);
+                /// Pixel density in pixels per inch.
+                const PPI: u16;
+                /// Outline of the active area.
+                const SHAPE: Shape;
+                /// Frequency at which the VCOM polarity should be toggled.
+                const RECOMMENDED_VCOM_HZ: u8;
+                /// Whether the blink and invert commands of the JDI panels are supported.
+                const JDI_COMMANDS: bool;
+                /// Maximum SPI clock frequency.
+                const MAX_SCLK_HZ: u32;
+                /// Minimum timing of chip select, see [`crate::CsTiming`].
+                const CS_TIMING: crate::CsTiming;
+                /// Dummy bytes sent after every line.
+                const LINE_TRAILER_BYTES: usize;
+                /// Dummy bytes sent at the end of a frame, after the trailer of its last line.
+                const FRAME_TRAILER_BYTES: usize;
+                /// Bits of the line address. Beyond 8 the remaining bits are sent at the end of
+                /// the byte before the address, the command byte or the trailer of the previous
+                /// line.
+                const ADDRESS_BITS: u32;
+
+                /// Width of the framebuffer in pixels, which is half the width of the display
+                /// with the `half-width` feature.
+                const BUFFER_WIDTH: usize = Self::WIDTH / crate::X_SCALE;
+                /// Height of the framebuffer in pixels, which is half the height of the display
+                /// with the `half-height` feature.
+                const BUFFER_HEIGHT: usize = Self::HEIGHT / crate::Y_SCALE;
+                /// Bytes per line of the framebuffer.
+                const LINE_BYTES: usize = Self::BUFFER_WIDTH.div_ceil(8);
+
+                // Bytes of pixel data per line sent, of the full width of the panel
+                #[doc(hidden)]
+                const PANEL_LINE_BYTES: usize = Self::WIDTH.div_ceil(8);
+                // Bytes sent per line: the address, the data and the trailer
+                #[doc(hidden)]
+                const WRITE_BUFFER_SIZE: usize =
+                    1 + Self::PANEL_LINE_BYTES + Self::LINE_TRAILER_BYTES;
+                // The bits of the last byte of a line of the panel past its width
+                #[doc(hidden)]
+                const PANEL_LINE_PADDING: u8 = crate::line_padding(Self::WIDTH);
+                // Bytes sent per frame besides its lines: the command and the frame trailer
+                #[doc(hidden)]
+                const FRAME_OVERHEAD: usize = 1 + Self::FRAME_TRAILER_BYTES;
+                // A complete frame: the command, every line and the frame trailer
+                #[doc(hidden)]
+                const TRANSACTION_BUFFER_SIZE: usize =
+                    Self::HEIGHT * Self::WRITE_BUFFER_SIZE + Self::FRAME_OVERHEAD;
+
+                /// A line of the framebuffer, `[u8; LINE_BYTES]`.
+                type Line: Array<u8>;
+                /// A value per line of the framebuffer, `[T; BUFFER_HEIGHT]`.
+                type PerLine<T: Zeroed>: Array<T>;
+                // A value per column of the framebuffer, `[T; BUFFER_WIDTH]`
+                #[doc(hidden)]
+                type PerColumn<T: Zeroed>: Array<T>;
+                // A bit per line of the framebuffer, `[u8; BUFFER_HEIGHT.div_ceil(8)]`
+                #[doc(hidden)]
+                type LineBits: Array<u8>;
+                // A line of the panel, `[u8; PANEL_LINE_BYTES]`
+                #[doc(hidden)]
+                type PanelLine: Array<u8>;
+                // A bit per line of the panel, `[u8; HEIGHT.div_ceil(8)]`
+                #[doc(hidden)]
+                type PanelLineBits: Array<u8>;
+                // A line as sent, `[u8; WRITE_BUFFER_SIZE]`
+                #[doc(hidden)]
+                type LineWrite: Array<u8>;
+                // A frame of a single line, `[u8; WRITE_BUFFER_SIZE + FRAME_OVERHEAD]`
+                #[doc(hidden)]
+                type LineFrame: Array<u8>;
+                // The end of a frame, `[u8; FRAME_OVERHEAD]`
+                #[doc(hidden)]
+                type FrameEnd: Array<u8>;
+                // The trailer of a frame, `[u8; FRAME_TRAILER_BYTES]`
+                #[doc(hidden)]
+                type FrameTrailer: Array<u8>;
+                // A burst of lines as sent, `[u8; MAX_BURST_LINES * WRITE_BUFFER_SIZE]`
+                #[doc(hidden)]
+                type Burst: Array<u8>;
+                // A complete frame as sent, `[u8; TRANSACTION_BUFFER_SIZE]`
+                #[doc(hidden)]
+                type Transaction: Array<u8>;
+            }
+
+            $(
+                $(#[doc = $doc])*
+                #[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+                pub struct $spec;
+
+                impl DisplaySpec for $spec {
+                    const NAME: &'static str = $feature;
+                    const WIDTH: usize = $width;
+                    const HEIGHT: usize = $height;
+                    const MODE: Mode = Mode {
+                        polarity: Polarity::IdleLow,
+                        phase: Phase::$phase,
+                    };
+                    const ACTIVE_AREA_MM: (f32, f32) = $area;
+                    const PPI: u16 = $ppi;
+                    const SHAPE: Shape = Shape::$shape;
+                    const RECOMMENDED_VCOM_HZ: u8 = $vcom_hz;
+                    const JDI_COMMANDS: bool = $jdi;
+                    const MAX_SCLK_HZ: u32 = $sclk;
+                    const CS_TIMING: crate::CsTiming = crate::CsTiming::from_us($cs_timing);
+                    const LINE_TRAILER_BYTES: usize = $line_trailer;
+                    const FRAME_TRAILER_BYTES: usize = $frame_trailer;
+                    const ADDRESS_BITS: u32 = $address_bits;
+
+                    type Line = [u8; <$spec as DisplaySpec>::LINE_BYTES];
+                    type PerLine<T: Zeroed> = [T; <$spec as DisplaySpec>::BUFFER_HEIGHT];
+                    type PerColumn<T: Zeroed> = [T; <$spec as DisplaySpec>::BUFFER_WIDTH];
+                    type LineBits = [u8; <$spec as DisplaySpec>::BUFFER_HEIGHT.div_ceil(8)];
+                    type PanelLine = [u8; <$spec as DisplaySpec>::PANEL_LINE_BYTES];
+                    type PanelLineBits = [u8; <$spec as DisplaySpec>::HEIGHT.div_ceil(8)];
+                    type LineWrite = [u8; <$spec as DisplaySpec>::WRITE_BUFFER_SIZE];
+                    type LineFrame = [u8; <$spec as DisplaySpec>::WRITE_BUFFER_SIZE
+                        + <$spec as DisplaySpec>::FRAME_OVERHEAD];
+                    type FrameEnd = [u8; <$spec as DisplaySpec>::FRAME_OVERHEAD];
+                    type FrameTrailer = [u8; <$spec as DisplaySpec>::FRAME_TRAILER_BYTES];
+                    type Burst =
+                        [u8; crate::MAX_BURST_LINES * <$spec as DisplaySpec>::WRITE_BUFFER_SIZE];
+                    type Transaction = [u8; <$spec as DisplaySpec>::TRANSACTION_BUFFER_SIZE];
+                }
+
+                const _: () = {
+                    let (width, height) = <$spec as DisplaySpec>::ACTIVE_AREA_MM;
+                    let ppi = <$spec as DisplaySpec>::PPI as f32;
+                    let ppi_x = $width as f32 * 25.4 / width;
+                    let ppi_y = $height as f32 * 25.4 / height;
+                    assert!(
+                        (ppi_x - ppi).abs() < 2.0 && (ppi_y - ppi).abs() < 2.0,
+                        "PPI does not match the resolution and the active area"
+                    );
+                    assert!(
+                        $height < 1 << $address_bits,
+                        "line addresses too narrow"
+                    );
+                    assert!(
+                        $address_bits == 8 || $line_trailer > 0,
+                        "wide line addresses need a line trailer"
+                    );
+                };
+            )*
+
+            /// Stands in for [`Selected`] while no model is selected via the feature flag, so
+            /// that every display has to name its model. Using it fails to compile.
+            #[cfg(not(any($(feature = $feature),*)))]
+            #[derive(Clone, Copy, Debug)]
+            pub enum NoModelSelected {}
+
+            #[cfg(not(any($(feature = $feature),*)))]
+            impl DisplaySpec for NoModelSelected {
+                const NAME: &'static str = NO_MODEL;
+                const WIDTH: usize = panic!("{}", NO_MODEL);
+                const HEIGHT: usize = panic!("{}", NO_MODEL);
+                const MODE: Mode = panic!("{}", NO_MODEL);
+                const ACTIVE_AREA_MM: (f32, f32) = panic!("{}", NO_MODEL);
+                const PPI: u16 = panic!("{}", NO_MODEL);
+                const SHAPE: Shape = panic!("{}", NO_MODEL);
+                const RECOMMENDED_VCOM_HZ: u8 = panic!("{}", NO_MODEL);
+                const JDI_COMMANDS: bool = panic!("{}", NO_MODEL);
+                const MAX_SCLK_HZ: u32 = panic!("{}", NO_MODEL);
+                const CS_TIMING: crate::CsTiming = panic!("{}", NO_MODEL);
+                const LINE_TRAILER_BYTES: usize = panic!("{}", NO_MODEL);
+                const FRAME_TRAILER_BYTES: usize = panic!("{}", NO_MODEL);
+                const ADDRESS_BITS: u32 = panic!("{}", NO_MODEL);
+
+                type Line = [u8; 0];
+                type PerLine<T: Zeroed> = [T; 0];
+                type PerColumn<T: Zeroed> = [T; 0];
+                type LineBits = [u8; 0];
+                type PanelLine = [u8; 0];
+                type PanelLineBits = [u8; 0];
+                type LineWrite = [u8; 0];
+                type LineFrame = [u8; 0];
+                type FrameEnd = [u8; 0];
+                type FrameTrailer = [u8; 0];
+                type Burst = [u8; 0];
+                type Transaction = [u8; 0];
+            }
+
+            #[cfg(not(any($(feature = $feature),*)))]
+            const NO_MODEL: &str =
+                "no display model selected: enable its Cargo feature or name its type from `spec`";
+
+            /// The model selected via the feature flag, the default of every display.
+            pub type Selected = crate::display::Spec;
+        }
+
+        /// Geometry (width, height) of every supported display model, keyed by its feature name.
+        #[cfg(feature = "std")]
+        pub(crate) const MODELS: &[(&str, usize, usize)] = &[$(($feature, $width, $height)),*];
+
+        /// Dummy bytes (per line, per frame) of every supported display model, keyed by its
+        /// feature name.
+        #[cfg(feature = "std")]
+        pub(crate) const TRAILERS: &[(&str, usize, usize)] =
+            &[$(($feature, $line_trailer, $frame_trailer)),*];
+
+        /// Every entry of the table, in its order, to check the types of the models against.
+        #[cfg(test)]
+        const ALL: &[Entry] = &[$(
+            Entry {
+                name: $feature,
+                width: $width,
+                height: $height,
+                phase: hal::spi::Phase::$phase,
+                active_area_mm: $area,
+                ppi: $ppi,
+                shape: crate::Shape::$shape,
+                max_sclk_hz: $sclk,
+                cs_timing_us: $cs_timing,
+                line_trailer_bytes: $line_trailer,
+                last_address: $last_address,
+            }
+        ),*];
+
+        /// The result of `check::<S>()` for the type `S` of every model, in the order of the
+        /// table.
+        #[cfg(test)]
+        macro_rules! every_model {
+            ($check:ident) => {
+                [$($check::<crate::spec::$spec>()),*]
+            };
+        }
+    };
+}
+
+/// An entry of the model table as written, see `ALL`.
+#[cfg(test)]
+struct Entry {
+    name: &'static str,
+    width: usize,
+    height: usize,
+    phase: hal::spi::Phase,
+    active_area_mm: (f32, f32),
+    ppi: u16,
+    shape: crate::Shape,
+    max_sclk_hz: u32,
+    cs_timing_us: (u32, u32, u32),
+    line_trailer_bytes: usize,
+    // The byte before the address of the last line and the address itself, as sent
+    last_address: [u8; 2],
+}
+
+models! {
+    "ls027b7dh01" as Ls027b7dh01 => {
+        phase: CaptureOnSecondTransition,
+        width: 400,
+        height: 240,
+        active_area_mm: (58.8, 35.28),
+        ppi: 173,
+        shape: Rect,
+        recommended_vcom_hz: 1,
+        jdi_commands: false,
+        max_sclk_hz: 2_000_000,
+        cs_timing_us: (6, 2, 2),
+        line_trailer_bytes: 1,
+        frame_trailer_bytes: 1,
+        address_bits: 8,
+        last_address: [0x80, 0x0F],
+    }
+    "ls012b7dd06" as Ls012b7dd06 => {
+        phase: CaptureOnSecondTransition,
+        width: 240,
+        height: 240,
+        active_area_mm: (30.24, 30.24),
+        ppi: 202,
+        shape: Round,
+        recommended_vcom_hz: 1,
+        jdi_commands: false,
+        max_sclk_hz: 1_100_000,
+        cs_timing_us: (3, 1, 1),
+        line_trailer_bytes: 1,
+        frame_trailer_bytes: 1,
+        address_bits: 8,
+        last_address: [0x80, 0x0F],
+    }
+    "ls010b7dh04" as Ls010b7dh04 => {
+        phase: CaptureOnSecondTransition,
+        width: 128,
+        height: 128,
+        active_area_mm: (18.0, 18.0),
+        ppi: 181,
+        shape: Rect,
+        recommended_vcom_hz: 1,
+        jdi_commands: false,
+        max_sclk_hz: 1_100_000,
+        cs_timing_us: (3, 1, 1),
+        line_trailer_bytes: 1,
+        frame_trailer_bytes: 1,
+        address_bits: 8,
+        last_address: [0x80, 0x01],
+    }
+    "ls013b7dh05" as Ls013b7dh05 => {
+        phase: CaptureOnSecondTransition,
+        width: 144,
+        height: 168,
+        active_area_mm: (20.88, 24.36),
+        ppi: 175,
+        shape: Rect,
+        recommended_vcom_hz: 1,
+        jdi_commands: false,
+        max_sclk_hz: 1_100_000,
+        cs_timing_us: (3, 1, 1),
+        line_trailer_bytes: 1,
+        frame_trailer_bytes: 1,
+        address_bits: 8,
+        last_address: [0x80, 0x15],
+    }
+    "ls011b7dh03" as Ls011b7dh03 => {
+        phase: CaptureOnFirstTransition,
+        width: 160,
+        height: 68,
+        active_area_mm: (25.25, 10.73),
+        ppi: 161,
+        shape: Rect,
+        recommended_vcom_hz: 1,
+        jdi_commands: false,
+        max_sclk_hz: 1_000_000,
+        cs_timing_us: (3, 1, 1),
+        line_trailer_bytes: 1,
+        frame_trailer_bytes: 1,
+        address_bits: 8,
+        last_address: [0x80, 0x22],
+    }
+    /// Previous-generation 4.4" QVGA panel. Note that it needs longer SCS setup/hold times than
+    /// the newer B7 parts.
+    "ls044q4dh01" as Ls044q4dh01 => {
+        phase: CaptureOnSecondTransition,
+        width: 320,
+        height: 240,
+        active_area_mm: (89.6, 67.2),
+        ppi: 91,
+        shape: Rect,
+        recommended_vcom_hz: 1,
+        jdi_commands: false,
+        max_sclk_hz: 1_000_000,
+        cs_timing_us: (12, 12, 12),
+        line_trailer_bytes: 1,
+        frame_trailer_bytes: 1,
+        address_bits: 8,
+        last_address: [0x80, 0x0F],
+    }
+    /// Current 4.4" QVGA panel, succeeding the LS044Q4DH01 with the timing of the B7 parts.
+    "ls044q7dh01" as Ls044q7dh01 => {
+        phase: CaptureOnSecondTransition,
+        width: 320,
+        height: 240,
+        active_area_mm: (89.28, 66.96),
+        ppi: 91,
+        shape: Rect,
+        recommended_vcom_hz: 1,
+        jdi_commands: false,
+        max_sclk_hz: 1_000_000,
+        cs_timing_us: (3, 1, 1),
+        line_trailer_bytes: 1,
+        frame_trailer_bytes: 1,
+        address_bits: 8,
+        last_address: [0x80, 0x0F],
+    }
+    /// 0.56" panel, the smallest of the family at 8 bytes per line.
+    "ls006b7dh03" as Ls006b7dh03 => {
+        phase: CaptureOnFirstTransition,
+        width: 64,
+        height: 64,
+        active_area_mm: (10.08, 10.08),
+        ppi: 161,
+        shape: Rect,
+        recommended_vcom_hz: 1,
+        jdi_commands: false,
+        max_sclk_hz: 1_000_000,
+        cs_timing_us: (3, 1, 1),
+        line_trailer_bytes: 1,
+        frame_trailer_bytes: 1,
+        address_bits: 8,
+        last_address: [0x80, 0x02],
+    }
+    /// 0.9" JDI panel found in many wearables. Only the monochrome variant is supported, which
+    /// accepts the same line format as the Sharp panels.
+    "lpm009m360a" as Lpm009m360a => {
+        phase: CaptureOnFirstTransition,
+        width: 72,
+        height: 144,
+        active_area_mm: (10.22, 20.45),
+        ppi: 179,
+        shape: Rect,
+        recommended_vcom_hz: 1,
+        jdi_commands: true,
+        max_sclk_hz: 2_000_000,
+        cs_timing_us: (3, 1, 1),
+        line_trailer_bytes: 1,
+        frame_trailer_bytes: 1,
+        address_bits: 8,
+        last_address: [0x80, 0x09],
+    }
+    /// 3.16" panel with more lines than fit into a byte, addressed with 10 bits.
+    "ls032b7dd02" as Ls032b7dd02 => {
+        phase: CaptureOnSecondTransition,
+        width: 336,
+        height: 536,
+        active_area_mm: (41.66, 66.46),
+        ppi: 205,
+        shape: Rect,
+        recommended_vcom_hz: 1,
+        jdi_commands: false,
+        max_sclk_hz: 2_000_000,
+        cs_timing_us: (6, 2, 2),
+        line_trailer_bytes: 1,
+        frame_trailer_bytes: 1,
+        address_bits: 10,
+        // Line 536 is 0x218, bit 9 ends the command byte
+        last_address: [0x81, 0x18],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::spec::*;
+    use super::ALL;
+    use hal::spi::{Mode, Polarity, MODE_0, MODE_1, MODE_2, MODE_3};
+    use std::vec::Vec;
+
+    fn geometry<S: DisplaySpec>() -> (&'static str, usize, usize, u16) {
+        (S::NAME, S::WIDTH, S::HEIGHT, S::PPI)
+    }
+
+    #[test]
+    fn model_table() {
+        let table: Vec<_> = ALL
+            .iter()
+            .map(|entry| (entry.name, entry.width, entry.height, entry.ppi))
+            .collect();
+        assert_eq!(every_model!(geometry), table[..]);
+        assert_eq!(Selected::WIDTH, crate::WIDTH);
+        assert_eq!(Selected::HEIGHT, crate::HEIGHT);
+    }
+
+    /// The number of an SPI mode.
+    fn mode_number(mode: Mode) -> usize {
+        let modes = [MODE_0, MODE_1, MODE_2, MODE_3];
+        modes.iter().position(|&known| known == mode).unwrap()
+    }
+
+    /// How a model is driven: the SPI mode by its number, the fastest clock and the chip
+    /// select setup, hold and low times.
+    fn bus<S: DisplaySpec>() -> (&'static str, usize, u32, (u32, u32, u32)) {
+        let timing = S::CS_TIMING;
+        let cs = (timing.setup_us, timing.hold_us, timing.low_us);
+        (S::NAME, mode_number(S::MODE), S::MAX_SCLK_HZ, cs)
+    }
+
+    #[test]
+    fn bus_of_every_model() {
+        let table: Vec<_> = ALL
+            .iter()
+            .map(|entry| {
+                let mode = mode_number(Mode {
+                    polarity: Polarity::IdleLow,
+                    phase: entry.phase,
+                });
+                (entry.name, mode, entry.max_sclk_hz, entry.cs_timing_us)
+            })
+            .collect();
+        assert_eq!(every_model!(bus), table[..]);
+        // Every model samples on one of the edges of a clock idling low
+        assert!(table.iter().all(|&(_, mode, ..)| mode < 2));
+        let selected = bus::<Selected>();
+        assert!(crate::MODE == Selected::MODE);
+        assert_eq!(selected.2, crate::MAX_SCLK_HZ);
+        let timing = crate::CS_TIMING;
+        assert_eq!(selected.3, (timing.setup_us, timing.hold_us, timing.low_us));
+    }
+
+    /// The outline of a model: its shape and active area in millimetres.
+    fn outline<S: DisplaySpec>() -> (&'static str, crate::Shape, (f32, f32)) {
+        (S::NAME, S::SHAPE, S::ACTIVE_AREA_MM)
+    }
+
+    #[test]
+    fn outline_of_every_model() {
+        let table: Vec<_> = ALL
+            .iter()
+            .map(|entry| (entry.name, entry.shape, entry.active_area_mm))
+            .collect();
+        assert_eq!(every_model!(outline), table[..]);
+        assert_eq!(outline::<Selected>().1, crate::SHAPE);
+    }
+
+    fn line_transfer<S: DisplaySpec>() -> (&'static str, usize) {
+        (S::NAME, S::WRITE_BUFFER_SIZE)
+    }
+
+    // A line is sent as its address, a byte per 8 pixels or part of it and the dummy bytes
+    #[test]
+    fn line_transfer_of_every_model() {
+        let table: Vec<_> = ALL
+            .iter()
+            .map(|entry| {
+                let len = 1 + entry.width.div_ceil(8) + entry.line_trailer_bytes;
+                (entry.name, len)
+            })
+            .collect();
+        assert_eq!(every_model!(line_transfer), table[..]);
+        assert_eq!(line_transfer::<Selected>().1, crate::WRITE_BUFFER_SIZE);
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn tables_follow_the_spec_types() {
+        let names = super::MODELS.iter().map(|&(name, ..)| name);
+        let trailer_names = super::TRAILERS.iter().map(|&(name, ..)| name);
+        assert!(names.eq(trailer_names));
+        let specs = every_model!(geometry).map(|(name, width, height, _)| (name, width, height));
+        assert_eq!(super::MODELS, specs);
+
+        // Every model sends a dummy byte after each line and at the end of a frame
+        assert!(super::TRAILERS
+            .iter()
+            .all(|&(_, line, frame)| (line, frame) == (1, 1)));
+        let trailers = (crate::LINE_TRAILER_BYTES, crate::FRAME_TRAILER_BYTES);
+        assert_eq!(
+            crate::decode::model_trailers(Selected::NAME),
+            Some(trailers)
+        );
+        let geometry = (crate::WIDTH, crate::HEIGHT);
+        assert_eq!(
+            crate::decode::model_geometry(Selected::NAME),
+            Some(geometry)
+        );
+        assert_eq!(crate::decode::model_geometry("ls000"), None);
+    }
+
+    /// Checks the datasheet figures of a model against each other.
+    fn check_metadata<S: DisplaySpec>() {
+        let (width_mm, height_mm) = S::ACTIVE_AREA_MM;
+        assert!(width_mm > 0.0 && height_mm > 0.0, "{}", S::NAME);
+        // The pixels are square, so both axes give the same density
+        for (pixels, mm) in [(S::WIDTH, width_mm), (S::HEIGHT, height_mm)] {
+            let ppi = pixels as f32 / (mm / 25.4);
+            assert!((ppi - S::PPI as f32).abs() < 2.0, "{}: {ppi}", S::NAME);
+        }
+        if S::SHAPE == crate::Shape::Round {
+            assert_eq!(S::WIDTH, S::HEIGHT, "{}", S::NAME);
+        }
+        assert!((1..=60).contains(&S::RECOMMENDED_VCOM_HZ), "{}", S::NAME);
+        assert!(S::MAX_SCLK_HZ >= 1_000_000, "{}", S::NAME);
+    }
+
+    #[test]
+    fn metadata_of_every_model() {
+        every_model!(check_metadata);
+    }
+
+    /// The byte before the address of the last line and the address itself.
+    fn last_address<S: DisplaySpec>() -> (&'static str, [u8; 2]) {
+        let last = S::HEIGHT - 1;
+        let address = (S::HEIGHT as u8).reverse_bits();
+        (
+            S::NAME,
+            [0x80 | crate::address_high_bits::<S>(last), address],
+        )
+    }
+
+    #[test]
+    fn last_address_of_every_model() {
+        let table: Vec<_> = ALL
+            .iter()
+            .map(|entry| (entry.name, entry.last_address))
+            .collect();
+        assert_eq!(every_model!(last_address), table[..]);
+    }
+
+    #[test]
+    fn address_width_fits_every_line() {
+        // Only panels of more than 255 lines need the high bits
+        assert_eq!(Selected::ADDRESS_BITS > 8, crate::HEIGHT > 255);
+        assert_eq!(crate::address_high_bits::<Selected>(0), 0);
+        assert_eq!(
+            crate::address_high_bits::<Selected>(crate::HEIGHT.min(254)),
+            0
+        );
+    }
+
+    // Every line sent as it is drawn
+    #[cfg(not(any(
+        feature = "half-height",
+        feature = "half-width",
+        feature = "minimal-flush"
+    )))]
+    mod wire {
+        use super::*;
+        use crate::{testing, Vcom};
+
+        /// Checks a flush of the last line of a display of the model `S` against the table.
+        fn check_last_line<S: DisplaySpec + Default>() {
+            let entry = ALL.iter().find(|entry| entry.name == S::NAME).unwrap();
+            let bytes = testing::last_line_of(S::default());
+            assert_eq!(bytes[..2], entry.last_address, "{}", S::NAME);
+            assert_eq!(bytes[2], 0x80, "{}", S::NAME);
+            assert_eq!(
+                bytes[1 + S::PANEL_LINE_BYTES],
+                1 << (7 - (S::WIDTH - 1) % 8),
+                "{}",
+                S::NAME
+            );
+            // Line and frame trailer
+            assert_eq!(bytes[2 + S::PANEL_LINE_BYTES..], [0, 0], "{}", S::NAME);
+            let on = testing::pixels_of::<S>(&[0, S::WIDTH - 1]);
+            let frame = testing::frame_of::<S>(Vcom::Lo, &[(S::HEIGHT - 1, on)]);
+            assert_eq!(bytes, frame, "{}", S::NAME);
+        }
+
+        #[test]
+        fn last_line_of_every_model() {
+            every_model!(check_last_line);
+        }
+
+        // The 72 pixels of a line fill 9 bytes exactly, with no padding before the trailer
+        #[test]
+        fn lpm009m360a_frame() {
+            use hal::spi::Phase;
+            assert!(Lpm009m360a::MODE.polarity == Polarity::IdleLow);
+            assert!(Lpm009m360a::MODE.phase == Phase::CaptureOnFirstTransition);
+            assert_eq!(Lpm009m360a::MAX_SCLK_HZ, 2_000_000);
+            assert_eq!(Lpm009m360a::PANEL_LINE_BYTES, 9);
+
+            let (log, mut display) = testing::display_of(Lpm009m360a);
+            for (x, y) in [(0, 0), (7, 0), (8, 0), (71, 0), (35, 143)] {
+                display.set_pixel(x, y, true).unwrap();
+            }
+            display.flush_buffer().unwrap();
+            #[rustfmt::skip]
+            let expected = [
+                0x80,
+                0x80, 0x81, 0x80, 0, 0, 0, 0, 0, 0, 0x01, 0,
+                0x09, 0, 0, 0, 0, 0x10, 0, 0, 0, 0, 0,
+                0,
+            ];
+            assert_eq!(log.bytes(), expected);
+        }
+
+        // Lines 255 to 257 and 512 cross the 8- and 9-bit boundaries of their addresses
+        #[test]
+        fn ten_bit_addresses() {
+            assert_eq!(crate::address_high_bits::<Ls032b7dd02>(254), 0x00);
+            assert_eq!(crate::address_high_bits::<Ls032b7dd02>(255), 0x02);
+            assert_eq!(crate::address_high_bits::<Ls032b7dd02>(511), 0x01);
+            assert_eq!(crate::address_high_bits::<Ls032b7dd02>(767), 0x03);
+
+            let (log, mut display) = testing::display_of(Ls032b7dd02);
+            display.mark_lines_dirty(254..256);
+            display.mark_line_dirty(511);
+            display.flush_buffer().unwrap();
+
+            let blank = [0; Ls032b7dd02::PANEL_LINE_BYTES];
+            let mut expected = std::vec![0x80, 0xFF];
+            // Line 256, its address byte 0x00 and bit 8 ending the trailer of line 255
+            expected.extend(blank);
+            expected.extend([0x02, 0x00]);
+            expected.extend(blank);
+            // Line 512, only bit 9 set
+            expected.extend([0x01, 0x00]);
+            expected.extend(blank);
+            expected.extend([0x00, 0x00]);
+            assert_eq!(log.bytes(), expected);
+            let lines = [(254, blank), (255, blank), (511, blank)];
+            assert_eq!(testing::frame_of::<Ls032b7dd02>(Vcom::Lo, &lines), expected);
+        }
+    }
+}
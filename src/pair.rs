@@ -0,0 +1,414 @@
+use crate::{spec, Command, DisplaySpec, Error, FrameBuffer, MemoryDisplay, VcomMode, DUMMY_DATA};
+use core::borrow::BorrowMut;
+use hal::blocking::spi::Write;
+use hal::digital::v2::OutputPin;
+
+/// An error of a [`DisplayPair`], identifying the display whose bus or chip select failed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum PairError<E1, PinError1, E2, PinError2> {
+    First(Error<E1, PinError1>),
+    Second(Error<E2, PinError2>),
+}
+
+/// Two displays driven together, e.g. a main and a status panel, keeping their VCOM phases
+/// aligned and sharing the bus fairly between them.
+///
+/// The panels may be of different models, e.g. a `spec::Ls027b7dh01` next to a
+/// `spec::Ls013b7dh05`, by default both are of the model selected by the Cargo feature. They
+/// may sit on separate buses or share one through a bus sharing crate, in which case
+/// interleaving their lines (see [`DisplayPair::flush_both`]) keeps one large flush from
+/// holding the bus for a whole frame.
+///
+/// A display which is asleep, see [`MemoryDisplay::sleep`], is left out until it wakes up,
+/// while the other one is driven on its own.
+pub struct DisplayPair<
+    SPI1,
+    CS1,
+    DISP1,
+    SPI2,
+    CS2,
+    DISP2,
+    SPEC1: DisplaySpec = spec::Selected,
+    SPEC2: DisplaySpec = spec::Selected,
+> {
+    first: MemoryDisplay<SPI1, CS1, DISP1, SPEC1>,
+    second: MemoryDisplay<SPI2, CS2, DISP2, SPEC2>,
+    last_vcom: Option<u32>,
+}
+
+impl<SPI1, CS1, DISP1, E1, SPI2, CS2, DISP2, E2, SPEC1, SPEC2>
+    DisplayPair<SPI1, CS1, DISP1, SPI2, CS2, DISP2, SPEC1, SPEC2>
+where
+    SPI1: Write<u8, Error = E1>,
+    CS1: OutputPin,
+    DISP1: OutputPin,
+    SPI2: Write<u8, Error = E2>,
+    CS2: OutputPin,
+    DISP2: OutputPin,
+    SPEC1: DisplaySpec,
+    SPEC2: DisplaySpec,
+{
+    /// Drive both displays together. The VCOM phase of the second display is aligned to the
+    /// first one.
+    pub fn new(
+        first: MemoryDisplay<SPI1, CS1, DISP1, SPEC1>,
+        mut second: MemoryDisplay<SPI2, CS2, DISP2, SPEC2>,
+    ) -> Self {
+        second.set_vcom_phase(first.vcom_phase());
+        Self {
+            first,
+            second,
+            last_vcom: None,
+        }
+    }
+
+    pub fn first_mut(&mut self) -> &mut MemoryDisplay<SPI1, CS1, DISP1, SPEC1> {
+        &mut self.first
+    }
+
+    pub fn second_mut(&mut self) -> &mut MemoryDisplay<SPI2, CS2, DISP2, SPEC2> {
+        &mut self.second
+    }
+
+    /// Give up driving the displays together.
+    #[allow(clippy::type_complexity)]
+    pub fn release(
+        self,
+    ) -> (
+        MemoryDisplay<SPI1, CS1, DISP1, SPEC1>,
+        MemoryDisplay<SPI2, CS2, DISP2, SPEC2>,
+    ) {
+        (self.first, self.second)
+    }
+
+    /// Toggle VCOM of both displays back to back once per VCOM period of the panels, the
+    /// shorter one for panels of different models (see
+    /// [`DisplaySpec::RECOMMENDED_VCOM_HZ`]), returning whether it did. Called regularly from
+    /// the main loop with a free-running timestamp in milliseconds, which may wrap around.
+    ///
+    /// Phases which drifted apart, e.g. because one of the displays was flushed on its own,
+    /// are aligned again. Nothing is sent to a display which is asleep.
+    pub fn service(
+        &mut self,
+        now_ms: u32,
+    ) -> Result<bool, PairError<E1, CS1::Error, E2, CS2::Error>> {
+        let period = vcom_period_ms::<SPEC1>().min(vcom_period_ms::<SPEC2>());
+        let due = self
+            .last_vcom
+            .is_none_or(|last| now_ms.wrapping_sub(last) >= period);
+        if !due {
+            return Ok(false);
+        }
+        self.align_vcom();
+        if !self.first.is_asleep() {
+            self.first.display_mode().map_err(PairError::First)?;
+        }
+        if !self.second.is_asleep() {
+            self.second.display_mode().map_err(PairError::Second)?;
+        }
+        self.last_vcom = Some(now_ms);
+        Ok(true)
+    }
+
+    /// Flush the dirty lines of both displays, alternating between them line by line, so
+    /// neither waits for a whole frame of the other. Like with [`crate::CsStrategy::PerLine`]
+    /// every line is a transaction of its own, VCOM is toggled once for both displays and a
+    /// display without dirty lines is sent the command alone. Lines drawn to in an open frame
+    /// are held back, see [`MemoryDisplay::begin_frame`], and a display which is asleep is
+    /// skipped, keeping its lines dirty.
+    ///
+    /// The first error aborts the flush. Lines not sent yet stay dirty.
+    pub fn flush_both(&mut self) -> Result<(), PairError<E1, CS1::Error, E2, CS2::Error>> {
+        self.align_vcom();
+        let (mut first, mut second) = (
+            self.first.start_line_flush(),
+            self.second.start_line_flush(),
+        );
+        while first.is_some() || second.is_some() {
+            if let Some(from) = first {
+                first = self.first.send_line_from(from).map_err(PairError::First)?;
+            }
+            if let Some(from) = second {
+                second = self
+                    .second
+                    .send_line_from(from)
+                    .map_err(PairError::Second)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Align the VCOM phase of the second display to the first one, so toggling both keeps
+    /// them in phase. While either is asleep each keeps its own phase.
+    fn align_vcom(&mut self) {
+        let driven = |mode, asleep: bool| mode == VcomMode::Software && !asleep;
+        if driven(self.first.vcom_mode, self.first.asleep)
+            && driven(self.second.vcom_mode, self.second.asleep)
+        {
+            self.second.vcom = self.first.vcom;
+        }
+    }
+}
+
+/// The VCOM period of the model `SPEC` in milliseconds.
+fn vcom_period_ms<SPEC: DisplaySpec>() -> u32 {
+    1000 / SPEC::RECOMMENDED_VCOM_HZ as u32
+}
+
+impl<SPI, CS, DISP, E, SPEC, BUF> MemoryDisplay<SPI, CS, DISP, SPEC, BUF>
+where
+    SPI: Write<u8, Error = E>,
+    CS: OutputPin,
+    DISP: OutputPin,
+    SPEC: DisplaySpec,
+    BUF: BorrowMut<FrameBuffer<SPEC>>,
+{
+    /// Start a flush sent line by line with [`MemoryDisplay::send_line_from`], toggling VCOM
+    /// once for all of its lines. Returns the line to continue from, or None if the display is
+    /// asleep, which sends nothing.
+    fn start_line_flush(&mut self) -> Option<usize> {
+        if self.asleep {
+            return None;
+        }
+        self.toggle_vcom();
+        Some(0)
+    }
+
+    /// Send the first [pending line](Self::pending_lines) from line `from` on in a transaction
+    /// of its own, returning where to continue, or None if there was none. Without any pending
+    /// line the first call sends the command alone, so VCOM keeps toggling.
+    fn send_line_from(&mut self, from: usize) -> Result<Option<usize>, Error<E, CS::Error>> {
+        self.presenting(|display| {
+            let command = Command::WriteLine | display.vcom;
+            let touched = display.frame.borrow().touched;
+            let Some(y) = touched.iter().find(|&y| y >= from) else {
+                if from == 0 {
+                    display.write_spi(&[command, DUMMY_DATA])?;
+                }
+                return Ok(None);
+            };
+            let line = display.line_to_send(y);
+            for row in display.panel_lines(y) {
+                let data = Self::line_frame(command, row, &line);
+                display.write_spi(data.as_ref())?;
+            }
+            display.frame.borrow_mut().touched.set(y, false);
+            Ok(Some(y + 1))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{self, BusError};
+    use crate::{Color, Vcom};
+    use std::{vec, vec::Vec};
+
+    type TestPair = DisplayPair<
+        testing::Spi,
+        testing::Pin,
+        testing::Pin,
+        testing::Spi,
+        testing::Pin,
+        testing::Pin,
+    >;
+
+    /// Two enabled displays on the same bus, logging into one log.
+    fn pair() -> (testing::Log, TestPair) {
+        let log = testing::Log::default();
+        let mut first = MemoryDisplay::new(log.spi(), log.cs(), log.disp());
+        let mut second = MemoryDisplay::new(log.spi(), log.cs(), log.disp());
+        first.enable().unwrap();
+        second.enable().unwrap();
+        log.events();
+        (log, DisplayPair::new(first, second))
+    }
+
+    #[test]
+    fn new_aligns_the_phases() {
+        let log = testing::Log::default();
+        let first = MemoryDisplay::new(log.spi(), log.cs(), log.disp());
+        let mut second = MemoryDisplay::new(log.spi(), log.cs(), log.disp());
+        second.display_mode().unwrap();
+        assert_eq!(second.vcom_phase(), Vcom::Lo);
+        let (first, second) = DisplayPair::new(first, second).release();
+        assert_eq!(second.vcom_phase(), first.vcom_phase());
+    }
+
+    #[test]
+    fn vcom_of_both_is_toggled_once_per_period() {
+        let (log, mut pair) = pair();
+        let period = 1000 / crate::RECOMMENDED_VCOM_HZ as u32;
+        let start = u32::MAX - period / 2;
+        assert_eq!(pair.service(start), Ok(true));
+        let nop = |vcom| vec![Command::Nop | vcom, DUMMY_DATA];
+        assert_eq!(log.writes(), [nop(Vcom::Lo), nop(Vcom::Lo)]);
+        assert_eq!(pair.service(start.wrapping_add(period - 1)), Ok(false));
+        assert_eq!(log.writes(), Vec::<Vec<u8>>::new());
+        assert_eq!(pair.service(start.wrapping_add(period)), Ok(true));
+        assert_eq!(log.writes(), [nop(Vcom::Hi), nop(Vcom::Hi)]);
+
+        // One display toggled on its own is brought back in phase
+        pair.first_mut().display_mode().unwrap();
+        log.events();
+        assert_eq!(pair.service(start.wrapping_add(2 * period)), Ok(true));
+        assert_eq!(log.writes(), [nop(Vcom::Hi), nop(Vcom::Hi)]);
+    }
+
+    #[cfg(not(any(
+        feature = "half-height",
+        feature = "half-width",
+        feature = "column-major",
+        feature = "minimal-flush"
+    )))]
+    #[test]
+    fn lines_of_both_alternate() {
+        let (log, mut pair) = pair();
+        testing::draw_diagonal(pair.first_mut(), [1, 2, 3]);
+        testing::draw_diagonal(pair.second_mut(), [10, 20]);
+        pair.flush_both().unwrap();
+        let events = log.events();
+
+        // Every line is a transaction of its own, all with the same VCOM
+        let transactions: Vec<_> = events.chunks(3).collect();
+        for transaction in &transactions {
+            assert_eq!(transaction[0], testing::Event::Cs(true));
+            assert_eq!(transaction[2], testing::Event::Cs(false));
+        }
+        let writes: Vec<_> = transactions
+            .iter()
+            .map(|t| testing::written(&t[1..2]))
+            .collect();
+        let expected: Vec<_> = [1, 10, 2, 20, 3]
+            .into_iter()
+            .map(|y| testing::frame(Vcom::Lo, &testing::diagonal([y])))
+            .collect();
+        assert_eq!(writes, expected);
+        let (first, second) = pair.release();
+        assert!(!first.is_dirty() && !second.is_dirty());
+    }
+
+    #[cfg(not(feature = "minimal-flush"))]
+    #[test]
+    fn commands_are_sent_without_dirty_lines() {
+        let (log, mut pair) = pair();
+        pair.flush_both().unwrap();
+        let write = vec![Command::WriteLine | Vcom::Lo, DUMMY_DATA];
+        assert_eq!(log.writes(), [write.clone(), write]);
+    }
+
+    #[cfg(not(any(
+        feature = "half-height",
+        feature = "half-width",
+        feature = "minimal-flush"
+    )))]
+    #[test]
+    fn panels_of_different_models() {
+        use crate::spec::{Ls013b7dh05, Ls027b7dh01};
+        let (log, mut first) = testing::display_of(Ls027b7dh01);
+        let (second_log, mut second) = testing::display_of(Ls013b7dh05);
+        second.replace_spi(log.spi());
+        second_log.events();
+        first.set_pixel(399, 239, Color::On).unwrap();
+        first.set_pixel(0, 1, Color::On).unwrap();
+        second.set_pixel(143, 167, Color::On).unwrap();
+        let mut pair = DisplayPair::new(first, second);
+        pair.flush_both().unwrap();
+
+        let large = |y, x| {
+            let line = testing::pixels_of::<Ls027b7dh01>(&[x]);
+            testing::frame_of::<Ls027b7dh01>(Vcom::Lo, &[(y, line)])
+        };
+        let small = testing::pixels_of::<Ls013b7dh05>(&[143]);
+        let small = testing::frame_of::<Ls013b7dh05>(Vcom::Lo, &[(167, small)]);
+        assert_eq!(log.writes(), [large(1, 0), small, large(239, 399)]);
+        let (first, second) = pair.release();
+        assert!(!first.is_dirty() && !second.is_dirty());
+    }
+
+    #[cfg(not(any(
+        feature = "half-height",
+        feature = "half-width",
+        feature = "minimal-flush"
+    )))]
+    #[test]
+    fn lines_of_an_open_frame_are_held_back() {
+        let (log, mut pair) = pair();
+        pair.first_mut().set_pixel(0, 1, Color::On).unwrap();
+        pair.first_mut().begin_frame();
+        pair.first_mut().set_pixel(0, 2, Color::On).unwrap();
+        pair.flush_both().unwrap();
+
+        // Only the line drawn before the frame is sent, the second display gets the command
+        let line = testing::frame(Vcom::Lo, &[(1, testing::pixels(&[0]))]);
+        let command = vec![Command::WriteLine | Vcom::Lo, DUMMY_DATA];
+        assert_eq!(log.writes(), [line, command]);
+        let first = pair.first_mut();
+        assert!(!first.is_dirty());
+        first.end_frame();
+        assert_eq!(first.dirty_line_count(), 1);
+        pair.flush_both().unwrap();
+        assert_eq!(log.writes().len(), 2);
+        assert!(!pair.first_mut().is_dirty());
+    }
+
+    #[test]
+    fn a_sleeping_display_is_left_out() {
+        let (log, mut pair) = pair();
+        pair.second_mut().sleep().unwrap();
+        log.events();
+        let nop = |vcom| vec![Command::Nop | vcom, DUMMY_DATA];
+        assert_eq!(pair.service(0), Ok(true));
+        assert_eq!(log.writes(), [nop(Vcom::Lo)]);
+
+        // Only the lines of the first display are sent, the second keeps all of its dirty
+        pair.first_mut().set_pixel(0, 0, Color::On).unwrap();
+        let lines = pair.first_mut().dirty_line_count();
+        pair.flush_both().unwrap();
+        assert_eq!(log.writes().len(), lines * crate::Y_SCALE);
+        let all = crate::BUFFER_HEIGHT;
+        assert_eq!(pair.second_mut().dirty_line_count(), all);
+
+        // The first display keeps alternating on its own, and both are in phase once awake
+        assert_eq!(pair.service(1000), Ok(true));
+        assert_eq!(log.writes(), [nop(Vcom::Lo)]);
+        pair.second_mut().wake().unwrap();
+        log.events();
+        assert_eq!(pair.service(2000), Ok(true));
+        assert_eq!(log.writes(), [nop(Vcom::Hi), nop(Vcom::Hi)]);
+    }
+
+    #[test]
+    fn errors_name_the_display() {
+        let (log, first) = testing::display();
+        let (_, mut second) = testing::display();
+        let failing = testing::Log::default();
+        failing.reject_writes_over(0);
+        second.replace_spi(failing.spi());
+        let mut pair = DisplayPair::new(first, second);
+        pair.first_mut().set_pixel(0, 0, Color::On).unwrap();
+        pair.second_mut().set_pixel(0, 0, Color::On).unwrap();
+        assert_eq!(
+            pair.service(0),
+            Err(PairError::Second(Error::Spi(BusError)))
+        );
+        assert_eq!(log.writes().len(), 1);
+        assert_eq!(
+            pair.flush_both(),
+            Err(PairError::Second(Error::Spi(BusError)))
+        );
+        let (first, mut second) = pair.release();
+        assert!(second.is_dirty());
+
+        second.replace_spi(log.spi());
+        let mut pair = DisplayPair::new(second, first);
+        log.reject_writes_over(0);
+        assert_eq!(
+            pair.flush_both(),
+            Err(PairError::First(Error::Spi(BusError)))
+        );
+        assert!(pair.first_mut().is_dirty());
+    }
+}
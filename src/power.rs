@@ -0,0 +1,162 @@
+use crate::{Command, DisplaySpec, Error, FrameBuffer, MemoryDisplay, DUMMY_DATA};
+use core::borrow::BorrowMut;
+use hal::blocking::spi::Write;
+use hal::digital::v2::OutputPin;
+
+impl<SPI, CS, DISP, E, SPEC, BUF> MemoryDisplay<SPI, CS, DISP, SPEC, BUF>
+where
+    SPI: Write<u8, Error = E>,
+    CS: OutputPin,
+    DISP: OutputPin,
+    SPEC: DisplaySpec,
+    BUF: BorrowMut<FrameBuffer<SPEC>>,
+{
+    /// Power down the panel following Sharp's sequence: the display is turned off, its memory
+    /// cleared, and no further commands are sent, so VCOM stops alternating. Afterwards the
+    /// supply of the panel may be cut. With [`crate::VcomMode::External`], stop toggling
+    /// EXTCOMIN after this returns.
+    ///
+    /// The framebuffer is kept and every line marked dirty. Drawing continues to work, while
+    /// flushing returns [`Error::Asleep`] until [`MemoryDisplay::wake`]. Does nothing if the
+    /// display is asleep already.
+    pub fn sleep(&mut self) -> Result<(), Error<E, CS::Error, DISP::Error>> {
+        if self.asleep {
+            return Ok(());
+        }
+        self.disp.set_low().map_err(Error::Disp)?;
+        self.enabled = false;
+        self.toggle_vcom();
+        self.write_spi(&[Command::ClearMemory | self.vcom, DUMMY_DATA])
+            .map_err(Error::with_disp)?;
+        self.mark_all_dirty();
+        self.asleep = true;
+
+        debug!("sleep");
+        Ok(())
+    }
+
+    /// Power up the panel following Sharp's sequence, once its supply is back: its memory is
+    /// initialised with a clear, the framebuffer sent in full, see
+    /// [`MemoryDisplay::redraw_all`], and the display turned on, so it shows the content right
+    /// away. With [`crate::VcomMode::External`], start toggling EXTCOMIN before calling this.
+    ///
+    /// If this fails, calling it again repeats the whole sequence. Does nothing if the display
+    /// is awake and enabled.
+    pub fn wake(&mut self) -> Result<(), Error<E, CS::Error, DISP::Error>> {
+        if !self.asleep && self.enabled {
+            return Ok(());
+        }
+        self.asleep = false;
+        self.toggle_vcom();
+        self.write_spi(&[Command::ClearMemory | self.vcom, DUMMY_DATA])
+            .map_err(Error::with_disp)?;
+        self.redraw_all().map_err(Error::with_disp)?;
+        self.disp.set_high().map_err(Error::Disp)?;
+        self.enabled = true;
+
+        debug!("wake");
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{self, Event};
+    use std::cell::Cell;
+    use std::rc::Rc;
+    use std::vec;
+
+    #[test]
+    fn sleep_turns_off_and_clears_the_panel() {
+        let (log, mut display) = testing::display();
+        display.sleep().unwrap();
+        let clear = vec![Command::ClearMemory | display.vcom_phase(), DUMMY_DATA];
+        let expected = [
+            Event::Disp(false),
+            Event::Cs(true),
+            Event::Write(clear),
+            Event::Cs(false),
+        ];
+        assert_eq!(log.events(), expected);
+        assert!(display.is_asleep() && !display.is_enabled());
+        assert_eq!(display.flush_buffer(), Err(Error::Asleep));
+        display.sleep().unwrap();
+        assert_eq!(log.events(), []);
+    }
+
+    #[test]
+    fn wake_clears_and_redraws_before_turning_on() {
+        let (log, mut display) = testing::display();
+        testing::draw_diagonal(&mut display, [1, 5]);
+        display.sleep().unwrap();
+        log.events();
+        let vcom = !display.vcom_phase();
+        display.wake().unwrap();
+        let events = log.events();
+        let clear = vec![Command::ClearMemory | vcom, DUMMY_DATA];
+        assert_eq!(
+            events[..3],
+            [Event::Cs(true), Event::Write(clear), Event::Cs(false)]
+        );
+        assert_eq!(events.last(), Some(&Event::Disp(true)));
+        assert!(!display.is_asleep() && display.is_enabled());
+        assert_eq!(display.is_dirty(), cfg!(feature = "minimal-flush"));
+
+        // What is sent in between is every line, as after a clear
+        let (reference_log, mut reference) = testing::display();
+        testing::draw_diagonal(&mut reference, [1, 5]);
+        reference.set_vcom_phase(vcom);
+        reference.redraw_all().unwrap();
+        assert_eq!(
+            testing::written(&events[3..events.len() - 1]),
+            reference_log.bytes()
+        );
+        display.wake().unwrap();
+        assert_eq!(log.events(), []);
+    }
+
+    #[derive(Debug, PartialEq)]
+    struct DispFailed;
+
+    /// A display enable pin failing with an error of its own while `failing` is set.
+    struct FallibleDisp(Rc<Cell<bool>>);
+
+    impl OutputPin for FallibleDisp {
+        type Error = DispFailed;
+
+        fn set_low(&mut self) -> Result<(), DispFailed> {
+            if self.0.get() {
+                Err(DispFailed)
+            } else {
+                Ok(())
+            }
+        }
+
+        fn set_high(&mut self) -> Result<(), DispFailed> {
+            self.set_low()
+        }
+    }
+
+    #[test]
+    fn disp_errors_differing_from_chip_select() {
+        let log = testing::Log::default();
+        let failing = Rc::new(Cell::new(false));
+        let disp = FallibleDisp(failing.clone());
+        let mut display = MemoryDisplay::new(log.spi(), log.cs(), disp);
+        display.sleep().unwrap();
+        display.wake().unwrap();
+
+        failing.set(true);
+        assert_eq!(display.sleep(), Err(Error::Disp(DispFailed)));
+        assert!(!display.is_asleep());
+        failing.set(false);
+        display.sleep().unwrap();
+        failing.set(true);
+        assert_eq!(display.wake(), Err(Error::Disp(DispFailed)));
+        assert!(!display.is_enabled());
+        failing.set(false);
+        display.wake().unwrap();
+        assert!(display.is_enabled());
+    }
+}
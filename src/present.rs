@@ -0,0 +1,87 @@
+use crate::{DirtyLines, DisplaySpec, FrameBuffer, MemoryDisplay};
+use core::borrow::BorrowMut;
+
+impl<SPI, CS, DISP, SPEC: DisplaySpec, BUF: BorrowMut<FrameBuffer<SPEC>>>
+    MemoryDisplay<SPI, CS, DISP, SPEC, BUF>
+{
+    /// Start drawing a frame which is only shown once complete, e.g. when a flush from a timer
+    /// may run while the frame is half drawn:
+    ///
+    /// ```ignore
+    /// display.begin_frame();
+    /// draw_screen(&mut display);
+    /// display.end_frame();
+    /// ```
+    ///
+    /// Until [`MemoryDisplay::end_frame`] the lines drawn to are held back: flushing sends the
+    /// lines dirty from before the frame started, but none changed since. Lines which haven't
+    /// changed yet therefore may show the previous frame, while no line shows part of either.
+    /// [`MemoryDisplay::is_dirty`] and [`MemoryDisplay::dirty_line_count`] report the lines the
+    /// next flush sends likewise. Starting a frame while one is open does nothing.
+    ///
+    /// This applies to every way of flushing in this crate: [`MemoryDisplay::flush_buffer`] and
+    /// its variants such as [`MemoryDisplay::flush_lines`] and `MemoryDisplay::flush_region`,
+    /// [`MemoryDisplay::prepare_flush`], [`crate::DisplayPair::flush_both`] and
+    /// `AodHelper::low_power_tick`. With the `minimal-flush` feature every flush sends the whole
+    /// frame regardless.
+    pub fn begin_frame(&mut self) {
+        if self.committed.is_some() {
+            return;
+        }
+        let touched = &mut self.frame.borrow_mut().touched;
+        self.committed = Some(*touched);
+        touched.fill(false);
+    }
+
+    /// Finish the frame started with [`MemoryDisplay::begin_frame`], so the next flush sends
+    /// every line drawn to in it. Does nothing if no frame is open.
+    pub fn end_frame(&mut self) {
+        let Some(committed) = self.committed.take() else {
+            return;
+        };
+        let touched = &mut self.frame.borrow_mut().touched;
+        for y in committed.iter() {
+            touched.merge(y, &committed);
+        }
+    }
+
+    /// Whether a frame started with [`MemoryDisplay::begin_frame`] is open.
+    pub fn is_frame_open(&self) -> bool {
+        self.committed.is_some()
+    }
+
+    /// The lines the next flush sends: the dirty lines, except for those drawn to in an open
+    /// frame.
+    pub(crate) fn pending_lines(&self) -> DirtyLines<SPEC> {
+        let touched = self.frame.borrow().touched;
+        let Some(mut committed) = self.committed else {
+            return touched;
+        };
+        for y in touched.iter() {
+            committed.set(y, false);
+        }
+        committed
+    }
+
+    /// Run `flush` with only the [pending lines](Self::pending_lines) dirty, keeping the lines
+    /// of an open frame for [`MemoryDisplay::end_frame`]. Flushes nested in `flush` see the
+    /// same lines.
+    pub(crate) fn presenting<R>(&mut self, flush: impl FnOnce(&mut Self) -> R) -> R {
+        let pending = self.pending_lines();
+        let Some(committed) = self.committed.take() else {
+            return flush(self);
+        };
+        let touched = &mut self.frame.borrow_mut().touched;
+        let staging = core::mem::replace(touched, pending);
+
+        let result = flush(self);
+        // Lines held back keep what was committed for them, in addition to the frame
+        let touched = &mut self.frame.borrow_mut().touched;
+        let mut left = core::mem::replace(touched, staging);
+        for y in staging.iter() {
+            left.merge(y, &committed);
+        }
+        self.committed = Some(left);
+        result
+    }
+}
@@ -0,0 +1,89 @@
+//! Raspberry Pi support via [`rppal`], only available with the `rppal` feature.
+//!
+//! The display is driven through the hardware chip select of the SPI peripheral, configured as
+//! active high as the panels expect, so every frame is a single write to `spidev`.
+use crate::{spec, spidev_bufsiz, CsStrategy, DisplaySpec, FrameBuffer, MemoryDisplay};
+use core::convert::Infallible;
+use hal::digital::v2::OutputPin;
+use hal::spi::Phase;
+use rppal::gpio::{self, Gpio};
+use rppal::spi::{self, Bus, Mode, Polarity, SlaveSelect, Spi};
+
+/// A display connected to the SPI peripheral and GPIO of a Raspberry Pi.
+pub type RpiDisplay<SPEC = spec::Selected> = MemoryDisplay<Spi, HardwareCs, gpio::OutputPin, SPEC>;
+
+/// Stand-in for the chip select pin, which is driven by the SPI peripheral.
+pub struct HardwareCs;
+
+impl OutputPin for HardwareCs {
+    type Error = Infallible;
+
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+/// An error setting up the SPI peripheral or the DISP pin.
+#[derive(Debug)]
+pub enum RpiError {
+    Spi(spi::Error),
+    Gpio(gpio::Error),
+}
+
+impl From<spi::Error> for RpiError {
+    fn from(e: spi::Error) -> Self {
+        RpiError::Spi(e)
+    }
+}
+
+impl From<gpio::Error> for RpiError {
+    fn from(e: gpio::Error) -> Self {
+        RpiError::Gpio(e)
+    }
+}
+
+with_selected_model! {
+    /// Open the display on the given SPI bus and chip select, with DISP connected to the GPIO
+    /// `disp_pin` (BCM numbering). The bus is clocked at [`MAX_SCLK_HZ`](crate::MAX_SCLK_HZ).
+    ///
+    /// Frames larger than the `spidev` buffer are split into several transactions, see
+    /// [`MemoryDisplay::set_max_transfer_len`]. Increase `spidev.bufsiz` on the kernel command line
+    /// to send every frame in one go.
+    pub fn open(bus: Bus, slave_select: SlaveSelect, disp_pin: u8) -> Result<RpiDisplay, RpiError> {
+        open_spec(bus, slave_select, disp_pin)
+    }
+}
+
+/// Same as [`open`] for a display of the model `SPEC`, see [`MemoryDisplay::new_with_spec`].
+pub fn open_with_spec<SPEC: DisplaySpec>(
+    bus: Bus,
+    slave_select: SlaveSelect,
+    disp_pin: u8,
+    _spec: SPEC,
+) -> Result<RpiDisplay<SPEC>, RpiError> {
+    open_spec(bus, slave_select, disp_pin)
+}
+
+fn open_spec<SPEC: DisplaySpec>(
+    bus: Bus,
+    slave_select: SlaveSelect,
+    disp_pin: u8,
+) -> Result<RpiDisplay<SPEC>, RpiError> {
+    let mode = match SPEC::MODE.phase {
+        Phase::CaptureOnFirstTransition => Mode::Mode0,
+        Phase::CaptureOnSecondTransition => Mode::Mode1,
+    };
+    let spi = Spi::new(bus, slave_select, SPEC::MAX_SCLK_HZ, mode)?;
+    spi.set_ss_polarity(Polarity::ActiveHigh)?;
+    let disp = Gpio::new()?.get(disp_pin)?.into_output_low();
+
+    let mut display = MemoryDisplay::new_with_buffer(spi, HardwareCs, disp, FrameBuffer::new());
+    display.set_cs_strategy(CsStrategy::Hardware);
+    // The kernel default, in case the parameter cannot be read
+    let _ = display.set_max_transfer_len(Some(spidev_bufsiz().unwrap_or(4096)));
+    Ok(display)
+}
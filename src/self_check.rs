@@ -0,0 +1,180 @@
+use crate::{Command, DisplaySpec, FrameBuffer, MemoryDisplay, DUMMY_DATA};
+use core::borrow::BorrowMut;
+use hal::blocking::delay::DelayMs;
+use hal::blocking::spi::Write;
+use hal::digital::v2::OutputPin;
+
+/// Number of VCOM toggles performed by [`MemoryDisplay::self_check`].
+const VCOM_TOGGLES: usize = 4;
+
+/// A step of [`MemoryDisplay::self_check`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum BringUpStep {
+    /// Drive DISP high.
+    Enable,
+    /// Send the memory clear command.
+    ClearMemory,
+    /// Draw the test pattern into the framebuffer.
+    DrawPattern,
+    /// Send the test pattern.
+    Flush,
+    /// Toggle VCOM a few times, half a second apart.
+    ToggleVcom,
+}
+
+/// An error observed during [`MemoryDisplay::self_check`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum BringUpError<SpiE, CsE, DispE> {
+    Spi(SpiE),
+    Cs(CsE),
+    Disp(DispE),
+}
+
+/// The outcome of a single step of [`MemoryDisplay::self_check`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct StepReport<SpiE, CsE, DispE> {
+    pub step: BringUpStep,
+    /// Number of errors returned by the SPI bus or the pins during the step.
+    pub errors: usize,
+    /// The first of those errors.
+    pub first_error: Option<BringUpError<SpiE, CsE, DispE>>,
+    /// Duration of the step in microseconds, as measured by the supplied clock.
+    pub duration_us: u32,
+}
+
+/// What the MCU observed while running [`MemoryDisplay::self_check`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct BringUpReport<SpiE, CsE, DispE> {
+    pub steps: [StepReport<SpiE, CsE, DispE>; 5],
+}
+
+impl<SpiE, CsE, DispE> BringUpReport<SpiE, CsE, DispE> {
+    /// Whether every step completed without errors.
+    pub fn passed(&self) -> bool {
+        self.steps.iter().all(|step| step.errors == 0)
+    }
+}
+
+impl<SpiE, CsE, DispE> StepReport<SpiE, CsE, DispE> {
+    fn record<T>(&mut self, result: Result<T, BringUpError<SpiE, CsE, DispE>>) {
+        if let Err(e) = result {
+            self.errors += 1;
+            self.first_error.get_or_insert(e);
+        }
+    }
+}
+
+impl<SPI, CS, DISP, E, SPEC, BUF> MemoryDisplay<SPI, CS, DISP, SPEC, BUF>
+where
+    SPI: Write<u8, Error = E>,
+    CS: OutputPin,
+    DISP: OutputPin,
+    SPEC: DisplaySpec,
+    BUF: BorrowMut<FrameBuffer<SPEC>>,
+{
+    /// Run a scripted bring-up sequence for new boards and report what the MCU observed.
+    ///
+    /// The display is enabled and cleared, then a test pattern is drawn and sent: a one pixel
+    /// border around the panel and eight bars of increasing height from left to right, which
+    /// together show missing lines, mirrored axes and a wrong SPI mode at a glance. Finally
+    /// VCOM is toggled a few times using `delay`. Every error from the SPI bus and the pins is
+    /// collected instead of aborting, and each step is timed with `now_us`, a free-running
+    /// microsecond clock.
+    ///
+    /// The panel cannot be read back, so whether the pattern actually shows up has to be
+    /// checked by eye. Lines are sent in separate transactions, which works with every
+    /// [`CsStrategy`](crate::CsStrategy). The framebuffer is overwritten.
+    pub fn self_check<D: DelayMs<u16>>(
+        &mut self,
+        delay: &mut D,
+        mut now_us: impl FnMut() -> u32,
+    ) -> BringUpReport<E, CS::Error, DISP::Error> {
+        let steps = [
+            BringUpStep::Enable,
+            BringUpStep::ClearMemory,
+            BringUpStep::DrawPattern,
+            BringUpStep::Flush,
+            BringUpStep::ToggleVcom,
+        ];
+        let mut report = BringUpReport {
+            steps: steps.map(|step| StepReport {
+                step,
+                errors: 0,
+                first_error: None,
+                duration_us: 0,
+            }),
+        };
+
+        for step in report.steps.iter_mut() {
+            let start = now_us();
+            match step.step {
+                BringUpStep::Enable => {
+                    step.record(self.disp.set_high().map_err(BringUpError::Disp))
+                }
+                BringUpStep::ClearMemory => {
+                    self.toggle_vcom();
+                    self.checked_transaction(&[Command::ClearMemory | self.vcom, DUMMY_DATA], step);
+                }
+                BringUpStep::DrawPattern => self.draw_test_pattern(),
+                BringUpStep::Flush => {
+                    self.toggle_vcom();
+                    for y in 0..SPEC::BUFFER_HEIGHT {
+                        let line = self.line_to_send(y);
+                        for row in self.panel_lines(y) {
+                            let command = Command::WriteLine | self.vcom;
+                            let transaction = Self::line_frame(command, row, &line);
+                            self.checked_transaction(transaction.as_ref(), step);
+                        }
+                    }
+                    self.frame.borrow_mut().touched.fill(false);
+                }
+                BringUpStep::ToggleVcom => {
+                    for _ in 0..VCOM_TOGGLES {
+                        delay.delay_ms(500);
+                        self.toggle_vcom();
+                        self.checked_transaction(&[Command::Nop | self.vcom, DUMMY_DATA], step);
+                    }
+                }
+            }
+            step.duration_us = now_us().wrapping_sub(start);
+        }
+
+        report
+    }
+
+    fn checked_transaction(
+        &mut self,
+        data: &[u8],
+        step: &mut StepReport<E, CS::Error, DISP::Error>,
+    ) {
+        step.record(self.cs.set_high().map_err(BringUpError::Cs));
+        step.record(self.spi.write(data).map_err(BringUpError::Spi));
+        step.record(self.cs.set_low().map_err(BringUpError::Cs));
+    }
+
+    fn draw_test_pattern(&mut self) {
+        let (width, height) = (SPEC::BUFFER_WIDTH, SPEC::BUFFER_HEIGHT);
+
+        self.frame.borrow_mut().buffer.fill(true);
+        self.fill_black(0, 0, width, 1);
+        self.fill_black(0, height - 1, width, 1);
+        self.fill_black(0, 0, 1, height);
+        self.fill_black(width - 1, 0, 1, height);
+        for i in 0..8 {
+            let x = 4 + i * (width - 8) / 8;
+            self.fill_black(x, 4, (width - 8) / 16, (i + 1) * (height - 8) / 8);
+        }
+    }
+
+    fn fill_black(&mut self, x: usize, y: usize, width: usize, height: usize) {
+        // The pattern is drawn on the panel, whatever the rotation
+        self.frame
+            .borrow_mut()
+            .buffer
+            .fill_rect(x..x + width, y..y + height, false);
+    }
+}
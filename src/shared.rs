@@ -0,0 +1,62 @@
+//! Sharing a display between async tasks, built on [`embassy_sync::mutex::Mutex`].
+//! Only available with the `embassy` feature.
+use crate::{Error, MemoryDisplay};
+use embassy_sync::blocking_mutex::raw::RawMutex;
+use embassy_sync::mutex::{Mutex, MutexGuard, TryLockError};
+use hal::blocking::spi::Write;
+use hal::digital::v2::OutputPin;
+
+/// A display which can be shared between tasks, e.g. from a `static`.
+///
+/// Every access locks the display for its whole duration, including the flush, so tasks
+/// drawing to it never see each other's half-finished content.
+pub struct SharedDisplay<M: RawMutex, SPI, CS, DISP> {
+    display: Mutex<M, MemoryDisplay<SPI, CS, DISP>>,
+}
+
+impl<M, SPI, CS, DISP, E> SharedDisplay<M, SPI, CS, DISP>
+where
+    M: RawMutex,
+    SPI: Write<u8, Error = E>,
+    CS: OutputPin,
+    DISP: OutputPin,
+{
+    pub const fn new(display: MemoryDisplay<SPI, CS, DISP>) -> Self {
+        Self {
+            display: Mutex::new(display),
+        }
+    }
+
+    /// Wait for the display, run `f` to draw to it and flush the changes.
+    pub async fn draw<R>(
+        &self,
+        f: impl FnOnce(&mut MemoryDisplay<SPI, CS, DISP>) -> R,
+    ) -> Result<R, Error<E, CS::Error>> {
+        let mut display = self.display.lock().await;
+        let result = f(&mut display);
+        display.flush_buffer()?;
+        Ok(result)
+    }
+
+    /// Same as [`SharedDisplay::draw`], but fails instead of waiting if another task is
+    /// currently using the display. The inner result is that of the flush.
+    pub fn try_draw<R>(
+        &self,
+        f: impl FnOnce(&mut MemoryDisplay<SPI, CS, DISP>) -> R,
+    ) -> Result<Result<R, Error<E, CS::Error>>, TryLockError> {
+        let mut display = self.display.try_lock()?;
+        let result = f(&mut display);
+        Ok(display.flush_buffer().map(|_| result))
+    }
+
+    /// Toggle VCOM, see [`MemoryDisplay::maintain_vcom`]. Call this periodically from a task
+    /// at (at least) [`RECOMMENDED_VCOM_HZ`](crate::RECOMMENDED_VCOM_HZ).
+    pub async fn maintain_vcom(&self) -> Result<(), Error<E, CS::Error>> {
+        self.display.lock().await.maintain_vcom()
+    }
+
+    /// Wait for exclusive access to the display, e.g. to draw without flushing.
+    pub async fn lock(&self) -> MutexGuard<'_, M, MemoryDisplay<SPI, CS, DISP>> {
+        self.display.lock().await
+    }
+}
@@ -0,0 +1,35 @@
+use core::cell::RefCell;
+use hal::blocking::spi::Write;
+
+/// An SPI bus shared by several displays (or other devices) with separate chip select pins,
+/// e.g. two panels on one peripheral:
+///
+/// ```ignore
+/// let bus = RefCell::new(spi);
+/// let mut left = MemoryDisplay::new(SharedSpi::new(&bus), cs_left, disp_left);
+/// let mut right = MemoryDisplay::new(SharedSpi::new(&bus), cs_right, disp_right);
+/// ```
+///
+/// Every display keeps its own framebuffer, dirty lines and VCOM phase. The bus is borrowed
+/// for each write only, so the displays can be used in any order from the same context. A
+/// display only ever writes while its chip select is asserted, so the others ignore its
+/// traffic. For sharing between interrupt priorities or tasks, use a bus manager of the HAL
+/// or `embedded-hal-bus` with the `embedded-hal-1` feature instead, see `hal1`.
+pub struct SharedSpi<'a, SPI> {
+    bus: &'a RefCell<SPI>,
+}
+
+impl<'a, SPI> SharedSpi<'a, SPI> {
+    pub fn new(bus: &'a RefCell<SPI>) -> Self {
+        Self { bus }
+    }
+}
+
+impl<SPI: Write<u8>> Write<u8> for SharedSpi<'_, SPI> {
+    type Error = SPI::Error;
+
+    /// Panics if the bus is borrowed elsewhere at the same time.
+    fn write(&mut self, words: &[u8]) -> Result<(), Self::Error> {
+        self.bus.borrow_mut().write(words)
+    }
+}
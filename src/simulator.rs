@@ -0,0 +1,341 @@
+//! Host-side simulation of a memory display.
+//!
+//! A [`Simulator`] provides an SPI bus and pins which can be passed to
+//! [`MemoryDisplay::new`](crate::MemoryDisplay::new). Everything sent over them is decoded as the
+//! panel would interpret it, so tests can inspect the shown image and UIs can be previewed or
+//! recorded without hardware. Only available with the `simulator` feature.
+use crate::decode::{self, Panel, Record};
+use crate::spec::DisplaySpec;
+use core::convert::Infallible;
+use hal::blocking::spi::Write;
+use hal::digital::v2::OutputPin;
+use std::borrow::Cow;
+use std::cell::{Ref, RefCell};
+use std::fs::File;
+use std::io::{self, BufWriter};
+use std::path::Path;
+use std::rc::Rc;
+use std::time::Instant;
+use std::vec::Vec;
+
+/// A simulated display, see the [module documentation](self).
+#[derive(Clone)]
+pub struct Simulator {
+    state: Rc<RefCell<State>>,
+}
+
+struct State {
+    panel: Panel,
+    cs: bool,
+    disp: bool,
+    transaction: Vec<u8>,
+    // Bytes trailing every line and every frame
+    trailers: (usize, usize),
+    recording: Option<Recording>,
+}
+
+struct Recording {
+    file: File,
+    scale: u16,
+    max_frames: usize,
+    frame_rate: Option<u16>,
+    last: Panel,
+    frames: Vec<(Panel, Instant)>,
+}
+
+impl Simulator {
+    with_selected_model! {
+        /// Create a simulator of the display model selected via the feature flag.
+        pub fn new() -> Self {
+            Self::of::<crate::spec::Selected>()
+        }
+    }
+
+    /// Create a simulator of the display model `SPEC`, e.g. for a display created with
+    /// [`MemoryDisplay::new_with_spec`](crate::MemoryDisplay::new_with_spec).
+    pub fn new_with_spec<SPEC: DisplaySpec>(_spec: SPEC) -> Self {
+        Self::of::<SPEC>()
+    }
+
+    /// Create a simulator of a display with the given dimensions, taking a byte after every
+    /// line and every frame like the supported panels.
+    pub fn with_size(width: usize, height: usize) -> Self {
+        Self::with_trailers(width, height, (1, 1))
+    }
+
+    fn of<SPEC: DisplaySpec>() -> Self {
+        let trailers = (SPEC::LINE_TRAILER_BYTES, SPEC::FRAME_TRAILER_BYTES);
+        Self::with_trailers(SPEC::WIDTH, SPEC::HEIGHT, trailers)
+    }
+
+    fn with_trailers(width: usize, height: usize, trailers: (usize, usize)) -> Self {
+        Self {
+            state: Rc::new(RefCell::new(State {
+                panel: Panel::new(width, height),
+                cs: false,
+                disp: false,
+                transaction: Vec::new(),
+                trailers,
+                recording: None,
+            })),
+        }
+    }
+
+    /// The SPI bus connected to the simulated display.
+    pub fn spi(&self) -> SimulatorSpi {
+        SimulatorSpi {
+            state: self.state.clone(),
+        }
+    }
+
+    /// The chip select pin of the simulated display.
+    pub fn cs(&self) -> SimulatorPin {
+        SimulatorPin {
+            state: self.state.clone(),
+            cs: true,
+        }
+    }
+
+    /// The display enable pin of the simulated display.
+    pub fn disp(&self) -> SimulatorPin {
+        SimulatorPin {
+            state: self.state.clone(),
+            cs: false,
+        }
+    }
+
+    /// The current contents of the panel memory.
+    pub fn panel(&self) -> Ref<'_, Panel> {
+        Ref::map(self.state.borrow(), |state| &state.panel)
+    }
+
+    /// Whether the display is enabled via the DISP pin.
+    pub fn is_enabled(&self) -> bool {
+        self.state.borrow().disp
+    }
+
+    /// Start recording an animated GIF to `path`. Every transaction which changes the panel
+    /// contents becomes a frame, up to `max_frames` frames. Each pixel is drawn as a square of
+    /// `scale` pixels.
+    ///
+    /// Frames are shown for the wall-clock time between the transactions producing them, or
+    /// for a constant time if a `frame_rate` (in frames per second) is given.
+    pub fn start_recording(
+        &self,
+        path: impl AsRef<Path>,
+        scale: u16,
+        max_frames: usize,
+        frame_rate: Option<u16>,
+    ) -> io::Result<()> {
+        let file = File::create(path)?;
+        let mut state = self.state.borrow_mut();
+        state.recording = Some(Recording {
+            file,
+            scale: scale.max(1),
+            max_frames,
+            frame_rate,
+            last: state.panel.clone(),
+            frames: Vec::new(),
+        });
+        Ok(())
+    }
+
+    /// Stop recording and write the GIF, returning the number of frames recorded.
+    /// Does nothing if no recording is in progress.
+    pub fn stop_recording(&self) -> io::Result<usize> {
+        match self.state.borrow_mut().recording.take() {
+            Some(recording) => recording.finish(),
+            None => Ok(0),
+        }
+    }
+}
+
+with_selected_model! {
+    impl Default for Simulator {
+        fn default() -> Self {
+            Self::new()
+        }
+    }
+}
+
+impl State {
+    fn end_transaction(&mut self) {
+        let records = decode::decode_with_trailers(
+            &self.transaction,
+            self.panel.width(),
+            self.panel.height(),
+            self.trailers,
+        );
+        self.transaction.clear();
+
+        for record in &records {
+            self.panel.apply(record);
+        }
+        if let Some(recording) = &mut self.recording {
+            let drawn = records
+                .iter()
+                .any(|r| matches!(r, Record::WriteLines { .. } | Record::ClearMemory { .. }));
+            if drawn
+                && recording.last != self.panel
+                && recording.frames.len() < recording.max_frames
+            {
+                recording.last = self.panel.clone();
+                recording.frames.push((self.panel.clone(), Instant::now()));
+            }
+        }
+    }
+}
+
+impl Recording {
+    fn finish(self) -> io::Result<usize> {
+        let (width, height) = (self.last.width(), self.last.height());
+        let scale = self.scale as usize;
+        let (gif_width, gif_height) = (width * scale, height * scale);
+        let size_error = || io::Error::new(io::ErrorKind::InvalidInput, "recording too large");
+
+        let palette = [0xff, 0xff, 0xff, 0x00, 0x00, 0x00];
+        let mut encoder = gif::Encoder::new(
+            BufWriter::new(self.file),
+            gif_width.try_into().map_err(|_| size_error())?,
+            gif_height.try_into().map_err(|_| size_error())?,
+            &palette,
+        )
+        .map_err(io::Error::other)?;
+        encoder
+            .set_repeat(gif::Repeat::Infinite)
+            .map_err(io::Error::other)?;
+
+        let end = Instant::now();
+        for (i, (panel, start)) in self.frames.iter().enumerate() {
+            // GIF delays are given in hundredths of a second
+            let delay = match self.frame_rate {
+                Some(rate) => 100 / rate.max(1),
+                None => {
+                    let next = self.frames.get(i + 1).map_or(end, |(_, time)| *time);
+                    (next.duration_since(*start).as_millis() / 10).clamp(1, u16::MAX as u128) as u16
+                }
+            };
+
+            let mut pixels = Vec::with_capacity(gif_width * gif_height);
+            for y in 0..gif_height {
+                pixels.extend((0..gif_width).map(|x| !panel.pixel(x / scale, y / scale) as u8));
+            }
+            let frame = gif::Frame {
+                width: gif_width as u16,
+                height: gif_height as u16,
+                delay,
+                buffer: Cow::Owned(pixels),
+                ..gif::Frame::default()
+            };
+            encoder.write_frame(&frame).map_err(io::Error::other)?;
+        }
+
+        Ok(self.frames.len())
+    }
+}
+
+/// The SPI bus of a [`Simulator`].
+pub struct SimulatorSpi {
+    state: Rc<RefCell<State>>,
+}
+
+impl Write<u8> for SimulatorSpi {
+    type Error = Infallible;
+
+    fn write(&mut self, words: &[u8]) -> Result<(), Self::Error> {
+        let mut state = self.state.borrow_mut();
+        // The panel ignores data while it is not selected
+        if state.cs {
+            state.transaction.extend_from_slice(words);
+        }
+        Ok(())
+    }
+}
+
+/// A pin of a [`Simulator`].
+pub struct SimulatorPin {
+    state: Rc<RefCell<State>>,
+    cs: bool,
+}
+
+impl OutputPin for SimulatorPin {
+    type Error = Infallible;
+
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        let mut state = self.state.borrow_mut();
+        if !self.cs {
+            state.disp = false;
+        } else if state.cs {
+            // The panel latches the transaction on the falling edge of SCS
+            state.cs = false;
+            state.end_transaction();
+        }
+        Ok(())
+    }
+
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        let mut state = self.state.borrow_mut();
+        if self.cs {
+            state.cs = true;
+        } else {
+            state.disp = true;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Color, MemoryDisplay};
+
+    /// Record a frame for every pixel of `lines` turned on in turn, with two frames which
+    /// don't change the panel in between.
+    fn record(name: &str, max_frames: usize, lines: &[u32]) -> (usize, std::path::PathBuf) {
+        let path = std::env::temp_dir().join(std::format!("{name}-{}.gif", std::process::id()));
+        let simulator = Simulator::new();
+        let mut display = MemoryDisplay::new(simulator.spi(), simulator.cs(), simulator.disp());
+        display.enable().unwrap();
+        simulator
+            .start_recording(&path, 2, max_frames, Some(10))
+            .unwrap();
+        for &y in lines {
+            display.set_pixel(1, y, Color::On).unwrap();
+            display.flush_buffer().unwrap();
+            display.flush_buffer().unwrap();
+            display.display_mode().unwrap();
+        }
+        (simulator.stop_recording().unwrap(), path)
+    }
+
+    #[test]
+    fn records_a_frame_per_change() {
+        let (frames, path) = record("three-frames", 10, &[0, 1, 2]);
+        assert_eq!(frames, 3);
+
+        let mut options = gif::DecodeOptions::new();
+        options.set_color_output(gif::ColorOutput::Indexed);
+        let mut decoder = options.read_info(File::open(&path).unwrap()).unwrap();
+        let mut decoded = Vec::new();
+        while let Some(frame) = decoder.read_next_frame().unwrap() {
+            assert_eq!(
+                (frame.width as usize, frame.height as usize, frame.delay),
+                (2 * crate::WIDTH, 2 * crate::HEIGHT, 10)
+            );
+            // Pixels 0 to 2 of a line scaled by 2, palette index 0 being white: a line still
+            // cleared is white, one sent is black but for pixel 1
+            let row = |y: usize| &frame.buffer[y * frame.width as usize..][..6];
+            decoded.push([0, 1, 2].map(|y| row(2 * y) == [1, 1, 0, 0, 1, 1]));
+        }
+        std::fs::remove_file(path).unwrap();
+        let (lo, hi) = (false, true);
+        assert_eq!(decoded, [[hi, lo, lo], [hi, hi, lo], [hi, hi, hi]]);
+    }
+
+    #[test]
+    fn stops_at_max_frames() {
+        let (frames, path) = record("max-frames", 2, &[0, 1, 2]);
+        std::fs::remove_file(path).unwrap();
+        assert_eq!(frames, 2);
+    }
+}
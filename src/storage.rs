@@ -0,0 +1,634 @@
+//! Storage of the framebuffer. By default it is kept row-major, one array of bytes per line in
+//! the order the lines are sent. With the `column-major` feature it is kept one array of bytes
+//! per column instead, which makes vertical operations (scrolling plots, rotated text) byte
+//! operations, and every line is gathered from the columns while flushing.
+use crate::{DisplaySpec, Zeroed};
+use bitvec::prelude::*;
+use core::ops::Range;
+
+/// Pixel `(x, y)` is stored in bit `x % 8` of byte `x / 8` of line `y`.
+#[cfg(not(feature = "column-major"))]
+pub(crate) struct Storage<SPEC: DisplaySpec> {
+    pub(crate) lines: SPEC::PerLine<SPEC::Line>,
+}
+
+/// Pixel `(x, y)` is stored in bit `y % 8` of byte `y / 8` of column `x`.
+#[cfg(feature = "column-major")]
+pub(crate) struct Storage<SPEC: DisplaySpec> {
+    columns: SPEC::PerColumn<SPEC::LineBits>,
+}
+
+impl<SPEC: DisplaySpec> Clone for Storage<SPEC> {
+    fn clone(&self) -> Self {
+        Self {
+            #[cfg(not(feature = "column-major"))]
+            lines: self.lines,
+            #[cfg(feature = "column-major")]
+            columns: self.columns,
+        }
+    }
+}
+
+#[cfg(not(feature = "column-major"))]
+impl<SPEC: DisplaySpec> Storage<SPEC> {
+    pub(crate) const fn new() -> Self {
+        Self {
+            lines: Zeroed::ZERO,
+        }
+    }
+
+    fn rows(&self) -> &[SPEC::Line] {
+        self.lines.as_ref()
+    }
+
+    fn rows_mut(&mut self) -> &mut [SPEC::Line] {
+        self.lines.as_mut()
+    }
+
+    pub(crate) fn set(&mut self, x: usize, y: usize, on: bool) {
+        self.rows_mut()[y]
+            .as_mut()
+            .view_bits_mut::<Lsb0>()
+            .set(x, on);
+    }
+
+    pub(crate) fn get(&self, x: usize, y: usize) -> bool {
+        self.rows()[y].as_ref().view_bits::<Lsb0>()[x]
+    }
+
+    pub(crate) fn fill(&mut self, on: bool) {
+        let value = if on { 0xFF } else { 0x00 };
+        self.rows_mut()
+            .iter_mut()
+            .for_each(|line| line.as_mut().fill(value));
+    }
+
+    pub(crate) fn invert(&mut self) {
+        self.rows_mut()
+            .iter_mut()
+            .flat_map(|line| line.as_mut())
+            .for_each(|byte| *byte = !*byte);
+    }
+
+    /// Line `y` in the row-major layout.
+    pub(crate) fn line(&self, y: usize) -> SPEC::Line {
+        self.rows()[y]
+    }
+
+    /// Replace line `y` with `line` in the row-major layout.
+    pub(crate) fn set_line(&mut self, y: usize, line: &SPEC::Line) {
+        self.rows_mut()[y] = *line;
+    }
+
+    /// Copy the lines which differ from `src`, calling `changed` for each of them.
+    pub(crate) fn copy_changed(&mut self, src: &Storage<SPEC>, mut changed: impl FnMut(usize)) {
+        for (y, (dst, src)) in self.rows_mut().iter_mut().zip(src.rows()).enumerate() {
+            if dst != src {
+                *dst = *src;
+                changed(y);
+            }
+        }
+    }
+
+    /// Set the bits `mask` of byte `byte` of line `y` to `on`.
+    #[cfg(feature = "glyphs")]
+    pub(crate) fn set_bits(&mut self, y: usize, byte: usize, mask: u8, on: bool) {
+        let dst = &mut self.rows_mut()[y].as_mut()[byte];
+        *dst = if on { *dst | mask } else { *dst & !mask };
+    }
+
+    fn fill_span(&mut self, y: usize, x: Range<usize>, on: bool) {
+        let bytes = x.start / 8..(x.end - 1) / 8 + 1;
+        for (byte, dst) in bytes.clone().zip(&mut self.rows_mut()[y].as_mut()[bytes]) {
+            let mask = byte_mask(&x, byte);
+            *dst = if on { *dst | mask } else { *dst & !mask };
+        }
+    }
+
+    pub(crate) fn fill_rect(&mut self, x: Range<usize>, y: Range<usize>, on: bool) {
+        self.fill_rect_changed(x, y, on, |_| {});
+    }
+
+    /// Same as [`Storage::fill_rect`], calling `changed` for each line which differs afterwards.
+    pub(crate) fn fill_rect_changed(
+        &mut self,
+        x: Range<usize>,
+        y: Range<usize>,
+        on: bool,
+        mut changed: impl FnMut(usize),
+    ) {
+        for y in y {
+            let before = self.rows()[y];
+            self.fill_span(y, x.clone(), on);
+            if self.rows()[y] != before {
+                changed(y);
+            }
+        }
+    }
+
+    /// Same as [`Storage::fill`], calling `changed` for each line which had any pixel differ.
+    pub(crate) fn fill_changed(&mut self, on: bool, mut changed: impl FnMut(usize)) {
+        for (y, line) in self.rows().iter().enumerate() {
+            let pixels = &line.as_ref().view_bits::<Lsb0>()[..SPEC::BUFFER_WIDTH];
+            if if on { !pixels.all() } else { pixels.any() } {
+                changed(y);
+            }
+        }
+        self.fill(on);
+    }
+
+    /// Set the pixels `x` of line `y` to `pixels` in turn, returning whether any changed.
+    #[cfg(feature = "graphics")]
+    pub(crate) fn set_run(
+        &mut self,
+        x: Range<usize>,
+        y: usize,
+        pixels: impl Iterator<Item = bool>,
+    ) -> bool {
+        let line = &mut self.rows_mut()[y];
+        let before = *line;
+        let bits = &mut line.as_mut().view_bits_mut::<Lsb0>()[x];
+        for (mut bit, on) in bits.iter_mut().zip(pixels) {
+            *bit = on;
+        }
+        *line != before
+    }
+
+    /// Invert the pixels `x` of lines `y`.
+    #[cfg(feature = "graphics")]
+    pub(crate) fn invert_rect(&mut self, x: Range<usize>, y: Range<usize>) {
+        let bytes = x.start / 8..(x.end - 1) / 8 + 1;
+        for line in &mut self.rows_mut()[y] {
+            for (byte, dst) in bytes.clone().zip(&mut line.as_mut()[bytes.clone()]) {
+                *dst ^= byte_mask(&x, byte);
+            }
+        }
+    }
+
+    /// Move the pixels `x` of lines `y.start + n..y.end` up by `n` lines if `up`, otherwise
+    /// those of lines `y.start..y.end - n` down, filling the vacated lines with `fill`.
+    pub(crate) fn shift_vertically(
+        &mut self,
+        x: Range<usize>,
+        y: Range<usize>,
+        n: usize,
+        up: bool,
+        fill: bool,
+    ) {
+        // Only the bytes overlapping the region are touched, masked to the region's columns
+        let bytes = (x.start / 8)..(x.end - 1) / 8 + 1;
+        let fill = if fill { 0xFF } else { 0x00 };
+        let (top, bottom) = (y.start, y.end);
+
+        let copy_row = |lines: &mut [SPEC::Line], dst: usize, src: Option<usize>| {
+            let src = src.map(|src| lines[src]);
+            for byte in bytes.clone() {
+                let value = src.map_or(fill, |src| src.as_ref()[byte]);
+                let mask = byte_mask(&x, byte);
+                let dst = &mut lines[dst].as_mut()[byte];
+                *dst = (*dst & !mask) | (value & mask);
+            }
+        };
+
+        if up {
+            for y in top..bottom {
+                copy_row(self.rows_mut(), y, (y + n < bottom).then_some(y + n));
+            }
+        } else {
+            for y in (top..bottom).rev() {
+                copy_row(self.rows_mut(), y, (y >= top + n).then(|| y - n));
+            }
+        }
+    }
+
+    /// Move the pixels `x.start + n..x.end` of lines `y` left by `n` pixels if `left`,
+    /// otherwise `x.start..x.end - n` right, filling the vacated columns with `fill`.
+    #[cfg(feature = "graphics")]
+    pub(crate) fn shift_horizontally(
+        &mut self,
+        x: Range<usize>,
+        y: Range<usize>,
+        n: usize,
+        left: bool,
+        fill: bool,
+    ) {
+        let (x0, x1) = (x.start, x.end);
+        for line in &mut self.rows_mut()[y] {
+            let bits = line.as_mut().view_bits_mut::<Lsb0>();
+            if left {
+                bits.copy_within(x0 + n..x1, x0);
+                bits[x1 - n..x1].fill(fill);
+            } else {
+                bits.copy_within(x0..x1 - n, x0 + n);
+                bits[x0..x0 + n].fill(fill);
+            }
+        }
+    }
+}
+
+#[cfg(feature = "column-major")]
+impl<SPEC: DisplaySpec> Storage<SPEC> {
+    pub(crate) const fn new() -> Self {
+        Self {
+            columns: Zeroed::ZERO,
+        }
+    }
+
+    fn columns(&self) -> &[SPEC::LineBits] {
+        self.columns.as_ref()
+    }
+
+    fn columns_mut(&mut self) -> &mut [SPEC::LineBits] {
+        self.columns.as_mut()
+    }
+
+    pub(crate) fn set(&mut self, x: usize, y: usize, on: bool) {
+        self.columns_mut()[x]
+            .as_mut()
+            .view_bits_mut::<Lsb0>()
+            .set(y, on);
+    }
+
+    pub(crate) fn get(&self, x: usize, y: usize) -> bool {
+        self.columns()[x].as_ref().view_bits::<Lsb0>()[y]
+    }
+
+    pub(crate) fn fill(&mut self, on: bool) {
+        let value = if on { 0xFF } else { 0x00 };
+        self.columns_mut()
+            .iter_mut()
+            .for_each(|column| column.as_mut().fill(value));
+    }
+
+    pub(crate) fn invert(&mut self) {
+        self.columns_mut()
+            .iter_mut()
+            .flat_map(|column| column.as_mut())
+            .for_each(|byte| *byte = !*byte);
+    }
+
+    /// Line `y` in the row-major layout, gathered from the columns.
+    pub(crate) fn line(&self, y: usize) -> SPEC::Line {
+        let (byte, bit) = (y / 8, y % 8);
+        let mut line = SPEC::Line::ZERO;
+        for (dst, columns) in line.as_mut().iter_mut().zip(self.columns().chunks(8)) {
+            // One row of the transpose of the 8x8 block formed by the byte of every column: pick
+            // the bit of the line from each byte and multiply them into the top byte, column `i`
+            // ending up in bit `i`
+            let block = columns.iter().enumerate().fold(0u64, |block, (i, column)| {
+                block | (column.as_ref()[byte] as u64) << (8 * i)
+            });
+            let bits = (block >> bit) & 0x0101_0101_0101_0101;
+            *dst = (bits.wrapping_mul(0x0102_0408_1020_4080) >> 56) as u8;
+        }
+        line
+    }
+
+    /// Replace line `y` with `line` in the row-major layout, scattering it over the columns.
+    pub(crate) fn set_line(&mut self, y: usize, line: &SPEC::Line) {
+        let bits = line.as_ref().view_bits::<Lsb0>();
+        for (x, column) in self.columns_mut().iter_mut().enumerate() {
+            column.as_mut().view_bits_mut::<Lsb0>().set(y, bits[x]);
+        }
+    }
+
+    /// Copy the lines which differ from `src`, calling `changed` for each of them.
+    pub(crate) fn copy_changed(&mut self, src: &Storage<SPEC>, mut changed: impl FnMut(usize)) {
+        // Lines are spread over all columns, so collect the differing bits of every column and
+        // copy everything: unchanged lines stay the same anyway
+        let mut diff = SPEC::LineBits::ZERO;
+        for (dst, src) in self.columns().iter().zip(src.columns()) {
+            let bytes = dst.as_ref().iter().zip(src.as_ref());
+            for (diff, (dst, src)) in diff.as_mut().iter_mut().zip(bytes) {
+                *diff |= dst ^ src;
+            }
+        }
+        self.columns = src.columns;
+        for y in diff.as_ref().view_bits::<Lsb0>().iter_ones() {
+            if y < SPEC::BUFFER_HEIGHT {
+                changed(y);
+            }
+        }
+    }
+
+    /// Set the bits `mask` of byte `byte` of line `y` to `on`.
+    #[cfg(feature = "glyphs")]
+    pub(crate) fn set_bits(&mut self, y: usize, byte: usize, mask: u8, on: bool) {
+        for i in 0..8 {
+            if mask & (1 << i) != 0 {
+                self.set(byte * 8 + i, y, on);
+            }
+        }
+    }
+
+    pub(crate) fn fill_rect(&mut self, x: Range<usize>, y: Range<usize>, on: bool) {
+        for column in &mut self.columns_mut()[x] {
+            column.as_mut().view_bits_mut::<Lsb0>()[y.clone()].fill(on);
+        }
+    }
+
+    /// Same as [`Storage::fill_rect`], calling `changed` for each line which differs afterwards.
+    pub(crate) fn fill_rect_changed(
+        &mut self,
+        x: Range<usize>,
+        y: Range<usize>,
+        on: bool,
+        mut changed: impl FnMut(usize),
+    ) {
+        let mut diff = SPEC::LineBits::ZERO;
+        for column in &mut self.columns_mut()[x] {
+            let before = *column;
+            column.as_mut().view_bits_mut::<Lsb0>()[y.clone()].fill(on);
+            let bytes = before.as_ref().iter().zip(column.as_ref());
+            for (diff, (before, after)) in diff.as_mut().iter_mut().zip(bytes) {
+                *diff |= before ^ after;
+            }
+        }
+        diff.as_ref()
+            .view_bits::<Lsb0>()
+            .iter_ones()
+            .for_each(&mut changed);
+    }
+
+    /// Same as [`Storage::fill`], calling `changed` for each line which had any pixel differ.
+    pub(crate) fn fill_changed(&mut self, on: bool, mut changed: impl FnMut(usize)) {
+        let value = if on { 0xFF } else { 0x00 };
+        let mut diff = SPEC::LineBits::ZERO;
+        for column in self.columns() {
+            for (diff, byte) in diff.as_mut().iter_mut().zip(column.as_ref()) {
+                *diff |= byte ^ value;
+            }
+        }
+        self.fill(on);
+        for y in diff.as_ref().view_bits::<Lsb0>().iter_ones() {
+            if y < SPEC::BUFFER_HEIGHT {
+                changed(y);
+            }
+        }
+    }
+
+    /// Set the pixels `x` of line `y` to `pixels` in turn, returning whether any changed.
+    #[cfg(feature = "graphics")]
+    pub(crate) fn set_run(
+        &mut self,
+        x: Range<usize>,
+        y: usize,
+        pixels: impl Iterator<Item = bool>,
+    ) -> bool {
+        let mut changed = false;
+        for (x, on) in x.zip(pixels) {
+            changed |= self.get(x, y) != on;
+            self.set(x, y, on);
+        }
+        changed
+    }
+
+    /// Invert the pixels `x` of lines `y`.
+    #[cfg(feature = "graphics")]
+    pub(crate) fn invert_rect(&mut self, x: Range<usize>, y: Range<usize>) {
+        let bytes = y.start / 8..(y.end - 1) / 8 + 1;
+        for column in &mut self.columns_mut()[x] {
+            for (byte, dst) in bytes.clone().zip(&mut column.as_mut()[bytes.clone()]) {
+                *dst ^= byte_mask(&y, byte);
+            }
+        }
+    }
+
+    /// Move the pixels `x` of lines `y.start + n..y.end` up by `n` lines if `up`, otherwise
+    /// those of lines `y.start..y.end - n` down, filling the vacated lines with `fill`.
+    pub(crate) fn shift_vertically(
+        &mut self,
+        x: Range<usize>,
+        y: Range<usize>,
+        n: usize,
+        up: bool,
+        fill: bool,
+    ) {
+        let (top, bottom) = (y.start, y.end);
+        let (shifted, vacated) = match up {
+            true => (top..bottom - n, bottom - n..bottom),
+            false => (top + n..bottom, top..top + n),
+        };
+        let fill = if fill { 0xFF } else { 0x00 };
+        let (bytes, bits) = (n / 8, n % 8);
+
+        // Only the bytes overlapping the region are touched, the masks are the same for every
+        // column
+        let range = top / 8..(bottom - 1) / 8 + 1;
+        let (mut shifted_mask, mut vacated_mask) = (SPEC::LineBits::ZERO, SPEC::LineBits::ZERO);
+        for i in range.clone() {
+            shifted_mask.as_mut()[i] = byte_mask(&shifted, i);
+            vacated_mask.as_mut()[i] = byte_mask(&vacated, i);
+        }
+
+        for column in &mut self.columns_mut()[x] {
+            // The column is shifted as a little endian integer, by whole bytes and the remaining
+            // bits, reading zeros past either end
+            let src = *column;
+            let byte = |i: Option<usize>| i.and_then(|i| src.as_ref().get(i).copied()).unwrap_or(0);
+            for i in range.clone() {
+                let value = if up {
+                    let (lo, hi) = (byte(Some(i + bytes)), byte(Some(i + bytes + 1)));
+                    ((lo as u16 | (hi as u16) << 8) >> bits) as u8
+                } else {
+                    let (lo, hi) = (byte(i.checked_sub(bytes + 1)), byte(i.checked_sub(bytes)));
+                    ((lo as u16 | (hi as u16) << 8) << bits >> 8) as u8
+                };
+                let (shifted, vacated) = (shifted_mask.as_ref()[i], vacated_mask.as_ref()[i]);
+                let dst = &mut column.as_mut()[i];
+                *dst = (*dst & !(shifted | vacated)) | (value & shifted) | (fill & vacated);
+            }
+        }
+    }
+
+    /// Move the pixels `x.start + n..x.end` of lines `y` left by `n` pixels if `left`,
+    /// otherwise `x.start..x.end - n` right, filling the vacated columns with `fill`.
+    #[cfg(feature = "graphics")]
+    pub(crate) fn shift_horizontally(
+        &mut self,
+        x: Range<usize>,
+        y: Range<usize>,
+        n: usize,
+        left: bool,
+        fill: bool,
+    ) {
+        let copy_column = |columns: &mut [SPEC::LineBits], dst: usize, src: Option<usize>| {
+            let src = src.map(|src| columns[src]);
+            let bits = &mut columns[dst].as_mut().view_bits_mut::<Lsb0>()[y.clone()];
+            match src {
+                Some(src) => bits.copy_from_bitslice(&src.as_ref().view_bits::<Lsb0>()[y.clone()]),
+                None => bits.fill(fill),
+            }
+        };
+
+        if left {
+            for dst in x.clone() {
+                copy_column(
+                    self.columns_mut(),
+                    dst,
+                    (dst + n < x.end).then_some(dst + n),
+                );
+            }
+        } else {
+            for dst in x.clone().rev() {
+                copy_column(
+                    self.columns_mut(),
+                    dst,
+                    (dst >= x.start + n).then(|| dst - n),
+                );
+            }
+        }
+    }
+}
+
+/// Mask of the bits `bits` within byte `byte` of a line or column.
+fn byte_mask(bits: &Range<usize>, byte: usize) -> u8 {
+    let lo = bits.start.clamp(byte * 8, byte * 8 + 8) - byte * 8;
+    let hi = bits.end.clamp(byte * 8, byte * 8 + 8) - byte * 8;
+    if hi > lo {
+        ((1u16 << hi) - (1u16 << lo)) as u8
+    } else {
+        0
+    }
+}
+
+// Both layouts are checked against the same model, row by row
+#[cfg(test)]
+mod tests {
+    use crate::{BUFFER_HEIGHT, BUFFER_WIDTH, LINE_BYTES};
+    use std::{boxed::Box, vec, vec::Vec};
+
+    type Storage = super::Storage<crate::spec::Selected>;
+
+    type Model = Vec<Vec<bool>>;
+
+    /// Storage and model set to the same pseudo-random pixels.
+    fn noise(mut seed: u32) -> (Box<Storage>, Model) {
+        let mut storage = Box::new(Storage::new());
+        let mut model = vec![vec![false; BUFFER_WIDTH]; BUFFER_HEIGHT];
+        for (y, line) in model.iter_mut().enumerate() {
+            for (x, pixel) in line.iter_mut().enumerate() {
+                // xorshift32
+                seed ^= seed << 13;
+                seed ^= seed >> 17;
+                seed ^= seed << 5;
+                *pixel = seed & 1 != 0;
+                storage.set(x, y, *pixel);
+            }
+        }
+        (storage, model)
+    }
+
+    fn packed(line: &[bool]) -> [u8; LINE_BYTES] {
+        let mut bytes = [0; LINE_BYTES];
+        for (x, _) in line.iter().enumerate().filter(|(_, &on)| on) {
+            bytes[x / 8] |= 1 << (x % 8);
+        }
+        bytes
+    }
+
+    fn check(storage: &Storage, model: &Model) {
+        for (y, line) in model.iter().enumerate() {
+            assert_eq!(storage.line(y), packed(line), "line {y}");
+            for (x, &on) in line.iter().enumerate() {
+                assert_eq!(storage.get(x, y), on, "({x}, {y})");
+            }
+        }
+    }
+
+    #[test]
+    fn lines_are_gathered_in_the_wire_order() {
+        let (mut storage, mut model) = noise(1);
+        check(&storage, &model);
+
+        let (other, other_model) = noise(2);
+        for y in [0, 3, BUFFER_HEIGHT - 1] {
+            storage.set_line(y, &other.line(y));
+            model[y] = other_model[y].clone();
+        }
+        check(&storage, &model);
+
+        let mut changed = vec![];
+        storage.copy_changed(&other, |y| changed.push(y));
+        let rest = (0..BUFFER_HEIGHT).filter(|y| ![0, 3, BUFFER_HEIGHT - 1].contains(y));
+        assert_eq!(changed, rest.collect::<Vec<_>>());
+        check(&storage, &other_model);
+    }
+
+    #[test]
+    fn fills_change_only_their_rectangle() {
+        let (mut storage, mut model) = noise(3);
+        let (x, y) = (3..BUFFER_WIDTH.min(21), 5..BUFFER_HEIGHT.min(17));
+        for on in [true, false] {
+            let mut changed = vec![];
+            storage.fill_rect_changed(x.clone(), y.clone(), on, |y| changed.push(y));
+            let mut expected = vec![];
+            for line in y.clone() {
+                if model[line][x.clone()].iter().any(|&pixel| pixel != on) {
+                    expected.push(line);
+                }
+                model[line][x.clone()].fill(on);
+            }
+            assert_eq!(changed, expected);
+            check(&storage, &model);
+        }
+
+        let mut changed = vec![];
+        storage.fill_changed(true, |y| changed.push(y));
+        let expected = (0..BUFFER_HEIGHT).filter(|&y| model[y].contains(&false));
+        assert_eq!(changed, expected.collect::<Vec<_>>());
+        model.iter_mut().for_each(|line| line.fill(true));
+        check(&storage, &model);
+    }
+
+    #[test]
+    fn vertical_shifts_move_whole_columns() {
+        let (x, y) = (5..BUFFER_WIDTH.min(30), 2..BUFFER_HEIGHT.min(27));
+        for (n, up) in [(1, true), (7, false), (8, true), (9, false), (25, true)] {
+            let (mut storage, before) = noise(n as u32);
+            storage.shift_vertically(x.clone(), y.clone(), n, up, true);
+            let mut model = before.clone();
+            for row in y.clone() {
+                let src = if up {
+                    row.checked_add(n).filter(|&src| src < y.end)
+                } else {
+                    row.checked_sub(n).filter(|&src| src >= y.start)
+                };
+                for column in x.clone() {
+                    model[row][column] = src.is_none_or(|src| before[src][column]);
+                }
+            }
+            check(&storage, &model);
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "graphics")]
+    fn horizontal_shifts_and_inversion() {
+        let (x, y) = (5..BUFFER_WIDTH.min(30), 2..BUFFER_HEIGHT.min(27));
+        for (n, left) in [(1, true), (7, false), (8, true), (13, false)] {
+            let (mut storage, before) = noise(n as u32 + 10);
+            storage.shift_horizontally(x.clone(), y.clone(), n, left, false);
+            let mut model = before.clone();
+            for row in y.clone() {
+                for column in x.clone() {
+                    let src = if left {
+                        Some(column + n).filter(|&src| src < x.end)
+                    } else {
+                        column.checked_sub(n).filter(|&src| src >= x.start)
+                    };
+                    model[row][column] = src.is_some_and(|src| before[row][src]);
+                }
+            }
+            check(&storage, &model);
+
+            storage.invert_rect(x.clone(), y.clone());
+            for row in y.clone() {
+                model[row][x.clone()]
+                    .iter_mut()
+                    .for_each(|pixel| *pixel = !*pixel);
+            }
+            check(&storage, &model);
+        }
+    }
+}
@@ -0,0 +1,301 @@
+use embedded_graphics::draw_target::DrawTarget;
+use embedded_graphics::geometry::Dimensions;
+use embedded_graphics::primitives::Rectangle;
+use embedded_graphics::Pixel;
+
+/// Limits above which [`StrictDisplay`] panics in debug builds.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct StrictLimits {
+    /// Maximum number of pixels drawn outside the display per frame.
+    pub out_of_bounds: usize,
+    /// Maximum [overdraw factor](StrictDisplay::overdraw) per frame.
+    pub overdraw: f32,
+    /// Maximum number of pixels drawn after [`StrictDisplay::request_flush`] per frame.
+    pub draws_after_flush: usize,
+}
+
+/// A development aid which forwards all drawing to a display while flagging suspicious use.
+///
+/// Per frame, it counts pixels drawn outside the display (usually a layout bug), the total
+/// number of pixels drawn relative to the display area (a runaway redraw shows up as an
+/// overdraw factor far above 1) and pixels drawn after the frame was marked as finished with
+/// [`StrictDisplay::request_flush`]. Call [`StrictDisplay::end_frame`] after flushing to reset
+/// the counters. Only available with the `test-utils` feature.
+pub struct StrictDisplay<'a, D> {
+    target: &'a mut D,
+    limits: Option<StrictLimits>,
+    out_of_bounds: usize,
+    drawn: usize,
+    draws_after_flush: usize,
+    flush_requested: bool,
+}
+
+impl<'a, D> StrictDisplay<'a, D>
+where
+    D: DrawTarget,
+{
+    /// Wrap a display, only counting suspicious drawing.
+    pub fn new(target: &'a mut D) -> Self {
+        Self {
+            target,
+            limits: None,
+            out_of_bounds: 0,
+            drawn: 0,
+            draws_after_flush: 0,
+            flush_requested: false,
+        }
+    }
+
+    /// Wrap a display, panicking as soon as a counter exceeds its limit if
+    /// `debug_assertions` are enabled.
+    pub fn with_limits(target: &'a mut D, limits: StrictLimits) -> Self {
+        Self {
+            limits: Some(limits),
+            ..Self::new(target)
+        }
+    }
+
+    /// Mark the frame as finished. Anything drawn afterwards until [`StrictDisplay::end_frame`]
+    /// is counted as drawn after flush.
+    pub fn request_flush(&mut self) {
+        self.flush_requested = true;
+    }
+
+    /// Finish the frame after flushing, resetting all counters.
+    pub fn end_frame(&mut self) {
+        self.out_of_bounds = 0;
+        self.drawn = 0;
+        self.draws_after_flush = 0;
+        self.flush_requested = false;
+    }
+
+    /// Number of pixels drawn outside the display in this frame.
+    pub fn out_of_bounds(&self) -> usize {
+        self.out_of_bounds
+    }
+
+    /// Number of pixels drawn in this frame divided by the number of pixels of the display.
+    pub fn overdraw(&self) -> f32 {
+        let size = self.target.bounding_box().size;
+        self.drawn as f32 / (size.width * size.height).max(1) as f32
+    }
+
+    /// Number of pixels drawn after [`StrictDisplay::request_flush`] in this frame.
+    pub fn draws_after_flush(&self) -> usize {
+        self.draws_after_flush
+    }
+
+    /// The wrapped display.
+    pub fn inner(&mut self) -> &mut D {
+        self.target
+    }
+
+    fn count(&mut self, inside: usize, outside: usize) {
+        self.drawn += inside + outside;
+        self.out_of_bounds += outside;
+        if self.flush_requested {
+            self.draws_after_flush += inside + outside;
+        }
+
+        if let Some(limits) = self.limits {
+            debug_assert!(
+                self.out_of_bounds <= limits.out_of_bounds,
+                "{} pixels drawn outside the display in this frame",
+                self.out_of_bounds
+            );
+            debug_assert!(
+                self.overdraw() <= limits.overdraw,
+                "every pixel drawn {} times in this frame",
+                self.overdraw()
+            );
+            debug_assert!(
+                self.draws_after_flush <= limits.draws_after_flush,
+                "{} pixels drawn after a flush was requested",
+                self.draws_after_flush
+            );
+        }
+    }
+
+    fn count_area(&mut self, area: &Rectangle) {
+        let total = (area.size.width * area.size.height) as usize;
+        let visible = area.intersection(&self.target.bounding_box()).size;
+        let inside = (visible.width * visible.height) as usize;
+        self.count(inside, total - inside);
+    }
+}
+
+impl<D> Dimensions for StrictDisplay<'_, D>
+where
+    D: DrawTarget,
+{
+    fn bounding_box(&self) -> Rectangle {
+        self.target.bounding_box()
+    }
+}
+
+impl<D> DrawTarget for StrictDisplay<'_, D>
+where
+    D: DrawTarget,
+{
+    type Color = D::Color;
+    type Error = D::Error;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        let bounds = self.target.bounding_box();
+        let (mut inside, mut outside) = (0, 0);
+        let result = self
+            .target
+            .draw_iter(pixels.into_iter().inspect(|Pixel(point, _)| {
+                if bounds.contains(*point) {
+                    inside += 1;
+                } else {
+                    outside += 1;
+                }
+            }));
+        self.count(inside, outside);
+        result
+    }
+
+    fn fill_contiguous<I>(&mut self, area: &Rectangle, colors: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Self::Color>,
+    {
+        self.count_area(area);
+        self.target.fill_contiguous(area, colors)
+    }
+
+    fn fill_solid(&mut self, area: &Rectangle, color: Self::Color) -> Result<(), Self::Error> {
+        self.count_area(area);
+        self.target.fill_solid(area, color)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+    use embedded_graphics::pixelcolor::BinaryColor;
+    use embedded_graphics::prelude::*;
+
+    const LIMITS: StrictLimits = StrictLimits {
+        out_of_bounds: 0,
+        overdraw: 2.0,
+        draws_after_flush: 0,
+    };
+
+    #[test]
+    fn counts_pixels_outside_the_display() {
+        let (_, mut display) = testing::display();
+        let mut strict = StrictDisplay::new(&mut display);
+        let size = strict.bounding_box().size;
+        let pixels = [(0, 0), (-1, 0), (0, -3), (size.width as i32, 2), (5, 5)];
+        let pixels = pixels.map(|(x, y)| Pixel(Point::new(x, y), BinaryColor::On));
+        strict.draw_iter(pixels).unwrap();
+        assert_eq!(strict.out_of_bounds(), 3);
+
+        // A 4x3 rectangle with its left column outside
+        let area = Rectangle::new(Point::new(-1, 0), Size::new(4, 3));
+        strict.fill_solid(&area, BinaryColor::On).unwrap();
+        assert_eq!(strict.out_of_bounds(), 6);
+        let beyond = Rectangle::new(Point::new(0, size.height as i32), Size::new(2, 2));
+        let colors = [BinaryColor::Off; 4];
+        strict.fill_contiguous(&beyond, colors).unwrap();
+        assert_eq!(strict.out_of_bounds(), 10);
+        assert_eq!(strict.draws_after_flush(), 0);
+
+        strict.end_frame();
+        assert_eq!(strict.out_of_bounds(), 0);
+        // The pixels inside were drawn
+        assert_eq!(display.get_pixel(5, 5), Some(BinaryColor::On.into()));
+    }
+
+    #[test]
+    fn overdraw_is_relative_to_the_display_area() {
+        let (_, mut display) = testing::display();
+        let mut strict = StrictDisplay::new(&mut display);
+        let screen = strict.bounding_box();
+        assert_eq!(strict.overdraw(), 0.0);
+        strict.fill_solid(&screen, BinaryColor::On).unwrap();
+        assert_eq!(strict.overdraw(), 1.0);
+        // Half of the screen, pixel by pixel
+        let half = Rectangle::new(
+            screen.top_left,
+            Size::new(screen.size.width / 2, screen.size.height),
+        );
+        let pixels = half.points().map(|point| Pixel(point, BinaryColor::Off));
+        strict.draw_iter(pixels).unwrap();
+        assert_eq!(strict.overdraw(), 1.5);
+        strict.fill_solid(&screen, BinaryColor::On).unwrap();
+        assert_eq!(strict.overdraw(), 2.5);
+        strict.end_frame();
+        assert_eq!(strict.overdraw(), 0.0);
+    }
+
+    #[test]
+    fn counts_pixels_drawn_after_a_flush_request() {
+        let (_, mut display) = testing::display();
+        let mut strict = StrictDisplay::new(&mut display);
+        let area = Rectangle::new(Point::new(2, 2), Size::new(3, 3));
+        strict.fill_solid(&area, BinaryColor::On).unwrap();
+        strict.request_flush();
+        assert_eq!(strict.draws_after_flush(), 0);
+        strict.fill_solid(&area, BinaryColor::Off).unwrap();
+        strict
+            .draw_iter([Pixel(Point::new(-1, -1), BinaryColor::On)])
+            .unwrap();
+        assert_eq!(strict.draws_after_flush(), 10);
+
+        strict.end_frame();
+        assert_eq!(strict.draws_after_flush(), 0);
+        strict.fill_solid(&area, BinaryColor::On).unwrap();
+        assert_eq!(strict.draws_after_flush(), 0);
+    }
+
+    #[test]
+    fn drawing_within_the_limits_passes() {
+        let (_, mut display) = testing::display();
+        let mut strict = StrictDisplay::with_limits(&mut display, LIMITS);
+        let screen = strict.bounding_box();
+        strict.fill_solid(&screen, BinaryColor::On).unwrap();
+        strict.fill_solid(&screen, BinaryColor::Off).unwrap();
+        strict.request_flush();
+        strict.end_frame();
+        strict.fill_solid(&screen, BinaryColor::On).unwrap();
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "1 pixels drawn outside the display")]
+    fn panics_drawing_outside() {
+        let (_, mut display) = testing::display();
+        let mut strict = StrictDisplay::with_limits(&mut display, LIMITS);
+        let pixel = Pixel(Point::new(-1, 0), BinaryColor::On);
+        strict.draw_iter([pixel]).unwrap();
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "every pixel drawn 3 times")]
+    fn panics_overdrawing() {
+        let (_, mut display) = testing::display();
+        let mut strict = StrictDisplay::with_limits(&mut display, LIMITS);
+        let screen = strict.bounding_box();
+        for _ in 0..3 {
+            strict.fill_solid(&screen, BinaryColor::On).unwrap();
+        }
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "1 pixels drawn after a flush was requested")]
+    fn panics_drawing_after_a_flush_request() {
+        let (_, mut display) = testing::display();
+        let mut strict = StrictDisplay::with_limits(&mut display, LIMITS);
+        strict.request_flush();
+        let pixel = Pixel(Point::new(0, 0), BinaryColor::On);
+        strict.draw_iter([pixel]).unwrap();
+    }
+}
@@ -0,0 +1,256 @@
+//! Doubles of the bus and the pins for the unit tests, recording everything the driver does in
+//! one log, and the bytes of a frame built from the datasheets to compare the driver against.
+
+// Not every combination of features runs every test
+#![allow(dead_code)]
+
+use crate::spec::{self, DisplaySpec, Zeroed};
+use crate::{CsStrategy, MemoryDisplay, Vcom, PANEL_LINE_BYTES};
+use core::convert::Infallible;
+use hal::blocking::spi::Write;
+use hal::digital::v2::OutputPin;
+use std::{cell::RefCell, rc::Rc, vec, vec::Vec};
+
+/// Something the driver did, in the order it happened.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) enum Event {
+    Write(Vec<u8>),
+    /// Chip select driven high (`true`) or low.
+    Cs(bool),
+    /// DISP driven high (`true`) or low.
+    Disp(bool),
+}
+
+#[derive(Default)]
+struct State {
+    events: Vec<Event>,
+    // Longest write accepted
+    max_write: Option<usize>,
+}
+
+/// The failure of a write to a [`Spi`], see [`Log::reject_writes_over`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct BusError;
+
+/// The log shared by a [`Spi`] and its [`Pin`]s.
+#[derive(Clone, Default)]
+pub(crate) struct Log(Rc<RefCell<State>>);
+
+impl Log {
+    pub(crate) fn spi(&self) -> Spi {
+        Spi(self.clone())
+    }
+
+    pub(crate) fn cs(&self) -> Pin {
+        Pin(self.clone(), Event::Cs)
+    }
+
+    pub(crate) fn disp(&self) -> Pin {
+        Pin(self.clone(), Event::Disp)
+    }
+
+    /// Fail every write longer than `len` from now on, e.g. like Linux `spidev`.
+    pub(crate) fn reject_writes_over(&self, len: usize) {
+        self.0.borrow_mut().max_write = Some(len);
+    }
+
+    /// Take everything recorded so far.
+    pub(crate) fn events(&self) -> Vec<Event> {
+        core::mem::take(&mut self.0.borrow_mut().events)
+    }
+
+    /// Take the writes recorded so far, dropping the pin events.
+    pub(crate) fn writes(&self) -> Vec<Vec<u8>> {
+        self.events()
+            .into_iter()
+            .filter_map(|event| match event {
+                Event::Write(bytes) => Some(bytes),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Take the bytes written so far, however they were split into writes.
+    pub(crate) fn bytes(&self) -> Vec<u8> {
+        self.writes().concat()
+    }
+}
+
+/// The bytes written by `events`, however they were split into writes.
+pub(crate) fn written(events: &[Event]) -> Vec<u8> {
+    let writes = events.iter().filter_map(|event| match event {
+        Event::Write(bytes) => Some(bytes.as_slice()),
+        _ => None,
+    });
+    writes.collect::<Vec<_>>().concat()
+}
+
+/// A bus recording every write into its [`Log`].
+pub(crate) struct Spi(Log);
+
+impl Write<u8> for Spi {
+    type Error = BusError;
+
+    fn write(&mut self, words: &[u8]) -> Result<(), BusError> {
+        let mut state = self.0 .0.borrow_mut();
+        if state.max_write.is_some_and(|len| words.len() > len) {
+            return Err(BusError);
+        }
+        state.events.push(Event::Write(words.to_vec()));
+        Ok(())
+    }
+}
+
+/// A pin recording every level set into its [`Log`].
+pub(crate) struct Pin(Log, fn(bool) -> Event);
+
+impl OutputPin for Pin {
+    type Error = Infallible;
+
+    fn set_low(&mut self) -> Result<(), Infallible> {
+        self.0 .0.borrow_mut().events.push((self.1)(false));
+        Ok(())
+    }
+
+    fn set_high(&mut self) -> Result<(), Infallible> {
+        self.0 .0.borrow_mut().events.push((self.1)(true));
+        Ok(())
+    }
+}
+
+pub(crate) type TestDisplay = MemoryDisplay<Spi, Pin, Pin>;
+
+/// An enabled display with every pixel [`Color::Off`](crate::Color::Off), no dirty lines and
+/// nothing logged yet. Its first flush toggles VCOM to [`Vcom::Lo`].
+pub(crate) fn display() -> (Log, TestDisplay) {
+    let log = Log::default();
+    let mut display = MemoryDisplay::new(log.spi(), log.cs(), log.disp());
+    display.enable().unwrap();
+    log.events();
+    (log, display)
+}
+
+/// Same as [`display`] for a display of the model `S`.
+pub(crate) fn display_of<S: DisplaySpec>(spec: S) -> (Log, MemoryDisplay<Spi, Pin, Pin, S>) {
+    let log = Log::default();
+    let mut display = MemoryDisplay::new_with_spec(log.spi(), log.cs(), log.disp(), spec);
+    display.enable().unwrap();
+    log.events();
+    (log, display)
+}
+
+/// The bytes of a flush of a display of the model `S` with the first and the last pixel of its
+/// last line set.
+pub(crate) fn last_line_of<S: DisplaySpec>(spec: S) -> Vec<u8> {
+    let (log, mut display) = display_of(spec);
+    let last = S::BUFFER_HEIGHT as u32 - 1;
+    display.set_pixel(0, last, true).unwrap();
+    display.set_pixel(S::WIDTH as u32 - 1, last, true).unwrap();
+    display.flush_buffer().unwrap();
+    log.bytes()
+}
+
+/// Draw a single pixel on each of `lines`, pixel `y % BUFFER_WIDTH` of line `y`, so every line
+/// differs from the others. See [`diagonal`] for the pixels sent.
+pub(crate) fn draw_diagonal(display: &mut TestDisplay, lines: impl IntoIterator<Item = usize>) {
+    for y in lines {
+        let x = y % crate::BUFFER_WIDTH;
+        display
+            .set_pixel(x as u32, y as u32, crate::Color::On)
+            .unwrap();
+    }
+}
+
+/// The lines drawn by [`draw_diagonal`] as sent, for [`frame`].
+pub(crate) fn diagonal(
+    lines: impl IntoIterator<Item = usize>,
+) -> Vec<(usize, [u8; PANEL_LINE_BYTES])> {
+    let lines = lines.into_iter();
+    lines
+        .map(|y| (y, pixels(&[y % crate::BUFFER_WIDTH])))
+        .collect()
+}
+
+/// Every pixel of the drawable area, row by row, `true` being [`Color::On`](crate::Color::On).
+pub(crate) fn snapshot(display: &TestDisplay) -> Vec<Vec<bool>> {
+    let (width, height) = display.drawable_size();
+    let pixel = |x, y| display.get_pixel(x as u32, y as u32) == Some(crate::Color::On);
+    (0..height)
+        .map(|y| (0..width).map(|x| pixel(x, y)).collect())
+        .collect()
+}
+
+/// Set every pixel of the drawable area from a pseudo-random sequence starting at `seed`.
+pub(crate) fn draw_noise(display: &mut TestDisplay, mut seed: u32) {
+    let (width, height) = display.drawable_size();
+    for y in 0..height {
+        for x in 0..width {
+            // xorshift32
+            seed ^= seed << 13;
+            seed ^= seed >> 17;
+            seed ^= seed << 5;
+            display
+                .set_pixel(x as u32, y as u32, seed & 1 != 0)
+                .unwrap();
+        }
+    }
+}
+
+/// Split `bytes` into writes of the lengths `lens`, the last taking what is left.
+pub(crate) fn split(bytes: &[u8], lens: &[usize]) -> Vec<Vec<u8>> {
+    let mut rest = bytes;
+    let mut writes = Vec::new();
+    for &len in lens {
+        let (write, tail) = rest.split_at(len);
+        writes.push(write.to_vec());
+        rest = tail;
+    }
+    writes.push(rest.to_vec());
+    writes
+}
+
+/// Every way of framing chip select available with the features enabled.
+pub(crate) fn strategies() -> Vec<CsStrategy> {
+    let strategies = vec![CsStrategy::Software, CsStrategy::PerLine];
+    #[cfg(feature = "transaction-buffer")]
+    let strategies = [strategies, vec![CsStrategy::Hardware]].concat();
+    strategies
+}
+
+/// A line of the panel with the pixels `on` set and all others cleared, in the order sent:
+/// left to right, the leftmost pixel of every byte in its most significant bit.
+pub(crate) fn pixels(on: &[usize]) -> [u8; PANEL_LINE_BYTES] {
+    pixels_of::<spec::Selected>(on)
+}
+
+/// Same as [`pixels`] for a line of the panel of the model `S`.
+pub(crate) fn pixels_of<S: DisplaySpec>(on: &[usize]) -> S::PanelLine {
+    let mut line = S::PanelLine::ZERO;
+    for &x in on {
+        line.as_mut()[x / 8] |= 0x80 >> (x % 8);
+    }
+    line
+}
+
+/// The bytes of a frame writing the lines given by the row on the panel (counting from 0) and
+/// their pixels, see [`pixels`], as the datasheets describe it: the command with the M1 bit
+/// for `vcom`, every line as its address (least significant bit first), data and dummy bytes,
+/// and the dummy bytes ending the frame. Addresses wider than 8 bits send bits 8 and 9 in the
+/// last two bits of the byte before the address byte.
+pub(crate) fn frame(vcom: Vcom, lines: &[(usize, [u8; PANEL_LINE_BYTES])]) -> Vec<u8> {
+    frame_of::<spec::Selected>(vcom, lines)
+}
+
+/// Same as [`frame`] for a panel of the model `S`.
+pub(crate) fn frame_of<S: DisplaySpec>(vcom: Vcom, lines: &[(usize, S::PanelLine)]) -> Vec<u8> {
+    let mut bytes = vec![0x80 | vcom as u8];
+    for (row, data) in lines {
+        let address = row + 1;
+        *bytes.last_mut().unwrap() |= ((address >> 8 & 1) << 1 | address >> 9 & 1) as u8;
+        bytes.push((address as u8).reverse_bits());
+        bytes.extend_from_slice(data.as_ref());
+        bytes.extend(core::iter::repeat_n(0, S::LINE_TRAILER_BYTES));
+    }
+    bytes.extend(core::iter::repeat_n(0, S::FRAME_TRAILER_BYTES));
+    bytes
+}
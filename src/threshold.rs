@@ -0,0 +1,267 @@
+use crate::GetPixel;
+use core::marker::PhantomData;
+use embedded_graphics::draw_target::DrawTarget;
+use embedded_graphics::geometry::{Dimensions, Point};
+use embedded_graphics::pixelcolor::{BinaryColor, Gray8, GrayColor, PixelColor};
+use embedded_graphics::primitives::{PointsIter, Rectangle};
+use embedded_graphics::Pixel;
+
+/// Draw grayscale content onto a binary display by thresholding its luma, for crisp icons
+/// without the patterns of [`crate::Halftone`].
+///
+/// It accepts [`Gray8`] by default and any other color converting into it, e.g.
+/// `Thresholded<'_, D, Rgb888>` for RGB content. A pixel is drawn [`BinaryColor::On`] if its
+/// luma is at least the threshold (128 by default).
+///
+/// With a hysteresis band (see [`Thresholded::set_hysteresis`]) the current color of each pixel
+/// is read back from the target, which keeps anti-aliased edges close to the threshold from
+/// flickering between the frames of an animation.
+pub struct Thresholded<'a, D, C = Gray8> {
+    target: &'a mut D,
+    threshold: u8,
+    hysteresis: u8,
+    color: PhantomData<C>,
+}
+
+impl<'a, D, C> Thresholded<'a, D, C>
+where
+    D: DrawTarget<Color = BinaryColor> + GetPixel<Color = BinaryColor>,
+    C: PixelColor + Into<Gray8>,
+{
+    pub fn new(target: &'a mut D) -> Self {
+        Self {
+            target,
+            threshold: 128,
+            hysteresis: 0,
+            color: PhantomData,
+        }
+    }
+
+    /// Set the luma from which pixels are drawn [`BinaryColor::On`].
+    pub fn set_threshold(&mut self, threshold: u8) {
+        self.threshold = threshold;
+    }
+
+    pub fn threshold(&self) -> u8 {
+        self.threshold
+    }
+
+    /// Set the width of the hysteresis band around the threshold, 0 to disable it. A pixel
+    /// which is currently on is only turned off below `threshold - hysteresis`, one which is
+    /// off only turned on from `threshold + hysteresis`.
+    pub fn set_hysteresis(&mut self, hysteresis: u8) {
+        self.hysteresis = hysteresis;
+    }
+
+    pub fn hysteresis(&self) -> u8 {
+        self.hysteresis
+    }
+
+    /// The binary color a pixel of the given color is drawn with at a position.
+    pub fn map(&self, point: Point, color: C) -> BinaryColor {
+        let luma = color.into().luma();
+        let threshold = match self.hysteresis {
+            0 => self.threshold,
+            hysteresis => match self.target.pixel(point) {
+                Some(BinaryColor::On) => self.threshold.saturating_sub(hysteresis),
+                Some(BinaryColor::Off) => self.threshold.saturating_add(hysteresis),
+                None => self.threshold,
+            },
+        };
+        (luma >= threshold).into()
+    }
+}
+
+impl<D, C> Dimensions for Thresholded<'_, D, C>
+where
+    D: DrawTarget<Color = BinaryColor> + GetPixel<Color = BinaryColor>,
+{
+    fn bounding_box(&self) -> Rectangle {
+        self.target.bounding_box()
+    }
+}
+
+impl<D, C> DrawTarget for Thresholded<'_, D, C>
+where
+    D: DrawTarget<Color = BinaryColor> + GetPixel<Color = BinaryColor>,
+    C: PixelColor + Into<Gray8>,
+{
+    type Color = C;
+    type Error = D::Error;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        if self.hysteresis == 0 {
+            let threshold = self.threshold;
+            return self
+                .target
+                .draw_iter(pixels.into_iter().map(|Pixel(point, color)| {
+                    Pixel(point, (color.into().luma() >= threshold).into())
+                }));
+        }
+        // Mapping reads back the target, so pixels are drawn one at a time
+        for Pixel(point, color) in pixels {
+            let color = self.map(point, color);
+            self.target.draw_iter([Pixel(point, color)])?;
+        }
+        Ok(())
+    }
+
+    fn fill_contiguous<I>(&mut self, area: &Rectangle, colors: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Self::Color>,
+    {
+        if self.hysteresis == 0 {
+            // Mapping doesn't depend on the target, so the whole area can be passed through
+            let threshold = self.threshold;
+            return self.target.fill_contiguous(
+                area,
+                colors
+                    .into_iter()
+                    .map(|color| (color.into().luma() >= threshold).into()),
+            );
+        }
+        // Same as in draw_iter
+        for (point, color) in area.points().zip(colors) {
+            let color = self.map(point, color);
+            self.target.draw_iter([Pixel(point, color)])?;
+        }
+        Ok(())
+    }
+
+    fn fill_solid(&mut self, area: &Rectangle, color: Self::Color) -> Result<(), Self::Error> {
+        if self.hysteresis == 0 {
+            let color = (color.into().luma() >= self.threshold).into();
+            return self.target.fill_solid(area, color);
+        }
+        self.fill_contiguous(area, area.points().map(|_| color))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+    use embedded_graphics::pixelcolor::{Rgb888, RgbColor};
+    use embedded_graphics::prelude::Size;
+    use std::vec::Vec;
+
+    fn draw(target: &mut Thresholded<'_, testing::TestDisplay>, x: i32, luma: u8) {
+        target
+            .draw_iter([Pixel(Point::new(x, 0), Gray8::new(luma))])
+            .unwrap();
+    }
+
+    fn on(display: &testing::TestDisplay, x: i32) -> bool {
+        display.pixel(Point::new(x, 0)) == Some(BinaryColor::On)
+    }
+
+    #[test]
+    fn luma_from_the_threshold_is_on() {
+        let (_, mut display) = testing::display();
+        let mut target = Thresholded::new(&mut display);
+        assert_eq!(target.threshold(), 128);
+        draw(&mut target, 0, 127);
+        draw(&mut target, 1, 128);
+        target.set_threshold(200);
+        draw(&mut target, 2, 199);
+        draw(&mut target, 3, 200);
+        target.set_threshold(0);
+        draw(&mut target, 4, 0);
+        target.set_threshold(255);
+        draw(&mut target, 5, 254);
+        draw(&mut target, 6, 255);
+        let pixels: Vec<_> = (0..7).map(|x| on(&display, x)).collect();
+        assert_eq!(pixels, [false, true, false, true, true, false, true]);
+    }
+
+    #[test]
+    fn rgb_is_thresholded_by_its_luma() {
+        let (_, mut display) = testing::display();
+        let mut target = Thresholded::<_, Rgb888>::new(&mut display);
+        let colors = [
+            Rgb888::WHITE,
+            Rgb888::BLUE,
+            Rgb888::GREEN,
+            Rgb888::new(128, 128, 128),
+        ];
+        for (x, color) in colors.into_iter().enumerate() {
+            target
+                .draw_iter([Pixel(Point::new(x as i32, 0), color)])
+                .unwrap();
+        }
+        let pixels: Vec<_> = (0..4).map(|x| on(&display, x)).collect();
+        assert_eq!(pixels, [true, false, true, true]);
+    }
+
+    #[test]
+    fn hysteresis_keeps_the_current_color() {
+        let (_, mut display) = testing::display();
+        let mut target = Thresholded::new(&mut display);
+        target.set_hysteresis(10);
+        assert_eq!(target.hysteresis(), 10);
+        // Off pixels turn on from 138
+        for (x, luma) in [(0, 128), (1, 137), (2, 138)] {
+            draw(&mut target, x, luma);
+        }
+        assert_eq!(
+            [0, 1, 2].map(|x| on(target.target, x)),
+            [false, false, true]
+        );
+        // On pixels turn off below 118
+        for x in 0..3 {
+            draw(&mut target, x, 255);
+        }
+        for (x, luma) in [(0, 128), (1, 118), (2, 117)] {
+            draw(&mut target, x, luma);
+        }
+        assert_eq!([0, 1, 2].map(|x| on(target.target, x)), [true, true, false]);
+        // and the band saturates at the ends of the range
+        target.set_threshold(250);
+        draw(&mut target, 2, 255);
+        assert!(on(target.target, 2));
+        target.set_threshold(5);
+        draw(&mut target, 2, 0);
+        assert!(on(target.target, 2));
+        target.set_hysteresis(0);
+        draw(&mut target, 2, 4);
+        assert!(!on(target.target, 2));
+    }
+
+    #[test]
+    fn fills_match_drawing_pixel_by_pixel() {
+        let area = Rectangle::new(Point::new(3, 2), Size::new(21, 9));
+        let luma = |point: Point| (point.x * 13 + point.y * 29) as u8;
+        for hysteresis in [0, 20] {
+            let (_, mut expected) = testing::display();
+            testing::draw_noise(&mut expected, 246);
+            let (_, mut display) = testing::display();
+            testing::draw_noise(&mut display, 246);
+
+            let mut target = Thresholded::new(&mut expected);
+            target.set_hysteresis(hysteresis);
+            for point in area.points() {
+                target
+                    .draw_iter([Pixel(point, Gray8::new(luma(point)))])
+                    .unwrap();
+            }
+            let mut target = Thresholded::new(&mut display);
+            target.set_hysteresis(hysteresis);
+            let colors = area.points().map(|point| Gray8::new(luma(point)));
+            target.fill_contiguous(&area, colors).unwrap();
+            assert_eq!(testing::snapshot(&display), testing::snapshot(&expected));
+
+            let mut target = Thresholded::new(&mut expected);
+            target.set_hysteresis(hysteresis);
+            for point in area.points() {
+                target.draw_iter([Pixel(point, Gray8::new(130))]).unwrap();
+            }
+            let mut target = Thresholded::new(&mut display);
+            target.set_hysteresis(hysteresis);
+            target.fill_solid(&area, Gray8::new(130)).unwrap();
+            assert_eq!(testing::snapshot(&display), testing::snapshot(&expected));
+        }
+    }
+}
@@ -0,0 +1,190 @@
+//! Animated transitions between the framebuffer and a [`SharpFrame`].
+use crate::{spec, DisplaySpec, Error, FrameBuffer, MemoryDisplay, SharpFrame};
+use core::borrow::BorrowMut;
+use embedded_graphics::pixelcolor::BinaryColor;
+use hal::blocking::spi::Write;
+use hal::digital::v2::OutputPin;
+
+/// An effect for switching from the content of the framebuffer to a [`SharpFrame`].
+///
+/// Transitions proceed in steps (see [`TransitionRun::step`]), each of which only changes and
+/// flushes the lines affected since the previous one, so a transition is a handful of partial
+/// flushes:
+///
+/// ```ignore
+/// let mut run = Transition::WipeDown.start(&next_screen);
+/// for i in 1..=10 {
+///     run.step(&mut display, i as f32 / 10.0)?;
+///     delay.delay_ms(25u32);
+/// }
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Transition {
+    /// The new frame is revealed line by line from the top.
+    WipeDown,
+    /// The new frame slides in from the right, pushing the old one out to the left.
+    SlideLeft,
+    /// The new frame is revealed pixel by pixel in a fixed pseudo-random order.
+    Dissolve,
+}
+
+impl Transition {
+    /// Start a transition to `target` from whatever the framebuffer holds at the first step.
+    pub fn start<SPEC: DisplaySpec>(self, target: &SharpFrame<SPEC>) -> TransitionRun<'_, SPEC> {
+        TransitionRun {
+            transition: self,
+            target,
+            done: 0,
+            order: 0,
+        }
+    }
+}
+
+/// A transition in progress, created by [`Transition::start`].
+pub struct TransitionRun<'a, SPEC: DisplaySpec = spec::Selected> {
+    transition: Transition,
+    target: &'a SharpFrame<SPEC>,
+    done: usize,
+    // State of the generator of the order of Transition::Dissolve
+    order: usize,
+}
+
+impl<SPEC: DisplaySpec> TransitionRun<'_, SPEC> {
+    /// Number of pixels of the framebuffer, the length of the order in which
+    /// [`Transition::Dissolve`] reveals them.
+    const PIXELS: usize = SPEC::BUFFER_WIDTH * SPEC::BUFFER_HEIGHT;
+
+    /// Advance the transition to `progress` (from 0.0 to 1.0) and flush the lines which changed,
+    /// returning whether it is complete. Steps backwards are ignored.
+    ///
+    /// Once complete the framebuffer holds exactly the target frame.
+    pub fn step<SPI, CS, DISP, E, BUF>(
+        &mut self,
+        display: &mut MemoryDisplay<SPI, CS, DISP, SPEC, BUF>,
+        progress: f32,
+    ) -> Result<bool, Error<E, CS::Error>>
+    where
+        SPI: Write<u8, Error = E>,
+        CS: OutputPin,
+        DISP: OutputPin,
+        SPEC: DisplaySpec,
+        BUF: BorrowMut<FrameBuffer<SPEC>>,
+    {
+        let len = self.len();
+        let to = ((progress.clamp(0.0, 1.0) * len as f32) as usize).min(len);
+        if to > self.done {
+            match self.transition {
+                Transition::WipeDown => self.wipe_down(display, to),
+                Transition::SlideLeft => self.slide_left(display, to),
+                Transition::Dissolve => self.dissolve(display, to),
+            }
+            self.done = to;
+            display.flush_buffer()?;
+        }
+        Ok(self.is_complete())
+    }
+
+    /// Whether the framebuffer has reached the target frame.
+    pub fn is_complete(&self) -> bool {
+        self.done == self.len()
+    }
+
+    /// Number of units (lines, columns or pixels) the transition proceeds in.
+    fn len(&self) -> usize {
+        match self.transition {
+            Transition::WipeDown => SPEC::BUFFER_HEIGHT,
+            Transition::SlideLeft => SPEC::BUFFER_WIDTH,
+            Transition::Dissolve => Self::PIXELS,
+        }
+    }
+
+    fn wipe_down<SPI, CS, DISP, E, BUF>(
+        &self,
+        display: &mut MemoryDisplay<SPI, CS, DISP, SPEC, BUF>,
+        to: usize,
+    ) where
+        SPI: Write<u8, Error = E>,
+        CS: OutputPin,
+        DISP: OutputPin,
+        SPEC: DisplaySpec,
+        BUF: BorrowMut<FrameBuffer<SPEC>>,
+    {
+        for y in self.done..to {
+            let line = self.target.buffer.line(y);
+            if display.frame.borrow().buffer.line(y) != line {
+                let frame = display.frame.borrow_mut();
+                frame.buffer.set_line(y, &line);
+                frame.touched.set(y, true);
+            }
+        }
+    }
+
+    fn slide_left<SPI, CS, DISP, E, BUF>(
+        &self,
+        display: &mut MemoryDisplay<SPI, CS, DISP, SPEC, BUF>,
+        to: usize,
+    ) where
+        SPI: Write<u8, Error = E>,
+        CS: OutputPin,
+        DISP: OutputPin,
+        SPEC: DisplaySpec,
+        BUF: BorrowMut<FrameBuffer<SPEC>>,
+    {
+        // Shift what is shown so far and append the next columns of the target: after the
+        // transition has advanced by `to` columns, the first `to` columns of the target are
+        // shown at the right edge. Like the frames this works on the panel, whatever the
+        // rotation of the display.
+        let step = to - self.done;
+        display.shift_horizontally(
+            0..SPEC::BUFFER_WIDTH,
+            0..SPEC::BUFFER_HEIGHT,
+            step as u32,
+            true,
+            BinaryColor::Off,
+        );
+        for x in 0..step {
+            for y in 0..SPEC::BUFFER_HEIGHT {
+                let on = self.target.buffer.get(self.done + x, y);
+                display
+                    .frame
+                    .borrow_mut()
+                    .buffer
+                    .set(SPEC::BUFFER_WIDTH - step + x, y, on);
+            }
+        }
+    }
+
+    fn dissolve<SPI, CS, DISP, E, BUF>(
+        &mut self,
+        display: &mut MemoryDisplay<SPI, CS, DISP, SPEC, BUF>,
+        to: usize,
+    ) where
+        SPI: Write<u8, Error = E>,
+        CS: OutputPin,
+        DISP: OutputPin,
+        SPEC: DisplaySpec,
+        BUF: BorrowMut<FrameBuffer<SPEC>>,
+    {
+        // A linear congruential generator modulo a power of two visits every value below it
+        // exactly once per period given these constraints on its parameters (c odd, a - 1 a
+        // multiple of 4), so the pixels are visited in a fixed order skipping the values past
+        // the end of the framebuffer
+        let modulus = Self::PIXELS.next_power_of_two();
+        for _ in self.done..to {
+            let pixel = loop {
+                self.order =
+                    (self.order.wrapping_mul(1_103_515_245).wrapping_add(12_345)) & (modulus - 1);
+                if self.order < Self::PIXELS {
+                    break self.order;
+                }
+            };
+            let (x, y) = (pixel % SPEC::BUFFER_WIDTH, pixel / SPEC::BUFFER_WIDTH);
+            let on = self.target.buffer.get(x, y);
+            if display.frame.borrow().buffer.get(x, y) != on {
+                let frame = display.frame.borrow_mut();
+                frame.buffer.set(x, y, on);
+                frame.touched.set(y, true);
+            }
+        }
+    }
+}
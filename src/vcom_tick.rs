@@ -0,0 +1,163 @@
+use crate::{DisplaySpec, Error, FrameBuffer, MemoryDisplay, VcomMode};
+use core::borrow::BorrowMut;
+use core::sync::atomic::{AtomicBool, Ordering};
+use hal::blocking::spi::Write;
+use hal::digital::v2::OutputPin;
+
+/// A flag raised by a periodic timer interrupt whenever VCOM is due to alternate, serviced by
+/// [`MemoryDisplay::service_vcom`] in the main loop. This keeps VCOM toggling while the display
+/// is idle, without the interrupt handler having to touch the bus or own the display.
+///
+/// It is meant to be placed in a `static`:
+///
+/// ```ignore
+/// static VCOM: VcomTick = VcomTick::new();
+///
+/// // In the timer interrupt, at twice RECOMMENDED_VCOM_HZ
+/// VCOM.tick();
+///
+/// // In the main loop
+/// display.service_vcom(&VCOM)?;
+/// ```
+///
+/// Every command sent by the driver alternates VCOM already, so a flush racing with a tick
+/// satisfies it and the tick is dropped instead of toggling VCOM a second time. This
+/// guarantees at least one toggle per two ticks, hence ticking at twice the required
+/// frequency.
+///
+/// Like [`crate::FlushRequester`] only atomic loads and stores are used.
+#[derive(Debug, Default)]
+pub struct VcomTick {
+    pending: AtomicBool,
+}
+
+impl VcomTick {
+    pub const fn new() -> Self {
+        Self {
+            pending: AtomicBool::new(false),
+        }
+    }
+
+    /// Signal that VCOM is due to alternate. This never blocks and may be called from any
+    /// context.
+    pub fn tick(&self) {
+        self.pending.store(true, Ordering::Release);
+    }
+
+    /// Whether a tick has been raised and not yet serviced.
+    pub fn is_pending(&self) -> bool {
+        self.pending.load(Ordering::Acquire)
+    }
+}
+
+impl<SPI, CS, DISP, E, SPEC, BUF> MemoryDisplay<SPI, CS, DISP, SPEC, BUF>
+where
+    SPI: Write<u8, Error = E>,
+    CS: OutputPin,
+    DISP: OutputPin,
+    SPEC: DisplaySpec,
+    BUF: BorrowMut<FrameBuffer<SPEC>>,
+{
+    /// Service a tick raised through `tick`, returning whether a command was sent for it.
+    ///
+    /// If a command has been sent since the previous tick was serviced, VCOM has alternated
+    /// already and nothing is sent. Otherwise VCOM is alternated, see
+    /// [`MemoryDisplay::maintain_vcom`]. With [`VcomMode::External`] or while the display is
+    /// asleep the tick is only cleared.
+    pub fn service_vcom(&mut self, tick: &VcomTick) -> Result<bool, Error<E, CS::Error>> {
+        if !tick.is_pending() {
+            return Ok(false);
+        }
+        tick.pending.store(false, Ordering::Relaxed);
+        let sent = core::mem::replace(&mut self.vcom_sent, false);
+        if sent || self.vcom_mode == VcomMode::External || self.asleep {
+            return Ok(false);
+        }
+        self.maintain_vcom()?;
+        self.vcom_sent = false;
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{testing, Vcom};
+
+    #[test]
+    fn idle_ticks_alternate_vcom() {
+        let (log, mut display) = testing::display();
+        let tick = VcomTick::new();
+        assert_eq!(display.service_vcom(&tick), Ok(false));
+        assert_eq!(log.events(), []);
+
+        for phase in [Vcom::Lo, Vcom::Hi, Vcom::Lo] {
+            tick.tick();
+            assert!(tick.is_pending());
+            assert_eq!(display.service_vcom(&tick), Ok(true));
+            assert!(!tick.is_pending());
+            assert_eq!(display.vcom_phase(), phase);
+            assert_eq!(log.writes(), [[phase as u8, 0]]);
+        }
+    }
+
+    #[test]
+    fn ticks_between_commands_are_coalesced() {
+        let (log, mut display) = testing::display();
+        let tick = VcomTick::new();
+        // Both ticks are serviced by one command
+        tick.tick();
+        tick.tick();
+        assert_eq!(display.service_vcom(&tick), Ok(true));
+        assert_eq!(display.service_vcom(&tick), Ok(false));
+        assert_eq!(log.writes().len(), 1);
+        assert_eq!(display.vcom_phase(), Vcom::Lo);
+    }
+
+    #[test]
+    fn flush_racing_a_tick_toggles_vcom_once() {
+        let (log, mut display) = testing::display();
+        let tick = VcomTick::new();
+        tick.tick();
+        display.flush_buffer().unwrap();
+        let phase = display.vcom_phase();
+        assert_eq!(display.service_vcom(&tick), Ok(false));
+        assert_eq!(display.vcom_phase(), phase);
+        log.events();
+        assert_eq!(display.service_vcom(&tick), Ok(false));
+        assert_eq!(log.events(), []);
+
+        // Flushes between ticks leave at least one toggle per two ticks
+        let mut toggles = 0;
+        for i in 0..8 {
+            if i % 3 == 0 {
+                display.flush_buffer().unwrap();
+                log.events();
+                toggles += 1;
+            }
+            tick.tick();
+            let sent = display.service_vcom(&tick).unwrap();
+            assert_eq!(log.writes().len(), sent as usize);
+            toggles += sent as usize;
+        }
+        assert_eq!(toggles, 3 + 5);
+    }
+
+    #[test]
+    fn ticks_are_cleared_without_sending() {
+        let (log, mut display) = testing::display();
+        let tick = VcomTick::new();
+        display.set_vcom_mode(VcomMode::External);
+        tick.tick();
+        assert_eq!(display.service_vcom(&tick), Ok(false));
+        assert!(!tick.is_pending());
+
+        display.set_vcom_mode(VcomMode::Software);
+        display.sleep().unwrap();
+        log.events();
+        tick.tick();
+        assert_eq!(display.service_vcom(&tick), Ok(false));
+        assert!(!tick.is_pending());
+        assert!(log.writes().is_empty());
+    }
+}
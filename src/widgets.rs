@@ -0,0 +1,280 @@
+//! Ready-made widgets for dashboards. Only available with the `widgets` feature.
+use crate::{DisplaySpec, FrameBuffer, MemoryDisplay};
+use core::borrow::BorrowMut;
+use embedded_graphics::draw_target::DrawTarget;
+use embedded_graphics::pixelcolor::BinaryColor;
+use embedded_graphics::prelude::{Point, Size};
+use embedded_graphics::primitives::Rectangle;
+use hal::blocking::spi::Write;
+use hal::digital::v2::OutputPin;
+
+/// How the samples of a column of a [`StripChart`] are combined.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Aggregate {
+    /// Plot the mean of the samples.
+    Mean,
+    /// Plot a bar from the smallest to the largest sample, showing noise and spikes.
+    MinMax,
+}
+
+/// A scrolling plot of a live value, e.g. a sensor reading.
+///
+/// New columns appear at the right edge of the region while older ones scroll out to the left
+/// using [`MemoryDisplay::scroll_region_left`], so only the lines covered by the region are
+/// sent. Consecutive columns are joined into a continuous line. The last `N` columns are kept
+/// so the plot can be redrawn when the range changes, `N` should be at least the width of the
+/// region.
+pub struct StripChart<const N: usize> {
+    region: Rectangle,
+    range: (f32, f32),
+    samples_per_column: u32,
+    aggregate: Aggregate,
+    autoscale: bool,
+    clamp: bool,
+    grid: Option<u32>,
+    foreground: BinaryColor,
+    background: BinaryColor,
+    // Samples of the column not drawn yet: count, sum, min and max
+    pending: (u32, f32, f32, f32),
+    // Ring buffer of the (low, high) values of the drawn columns
+    history: [(f32, f32); N],
+    len: usize,
+    head: usize,
+    // Total number of columns drawn, to keep dotted grid lines in phase while scrolling
+    columns: u32,
+}
+
+impl<const N: usize> StripChart<N> {
+    /// Create a chart in `region` plotting values from `min` at the bottom to `max` at the top,
+    /// one sample per column, in black on white.
+    pub fn new(region: Rectangle, min: f32, max: f32) -> Self {
+        Self {
+            region,
+            range: (min, max),
+            samples_per_column: 1,
+            aggregate: Aggregate::Mean,
+            autoscale: false,
+            clamp: true,
+            grid: None,
+            foreground: BinaryColor::Off,
+            background: BinaryColor::On,
+            pending: (0, 0.0, 0.0, 0.0),
+            history: [(0.0, 0.0); N],
+            len: 0,
+            head: 0,
+            columns: 0,
+        }
+    }
+
+    /// Combine `samples` samples into every column using `aggregate`.
+    pub fn set_samples_per_column(&mut self, samples: u32, aggregate: Aggregate) {
+        self.samples_per_column = samples.max(1);
+        self.aggregate = aggregate;
+    }
+
+    /// Fit the range to the visible columns, redrawing the plot whenever it changes.
+    pub fn set_autoscale(&mut self, autoscale: bool) {
+        self.autoscale = autoscale;
+    }
+
+    /// Whether columns entirely out of range are drawn at the top or bottom edge (the default)
+    /// or left out.
+    pub fn set_clamp(&mut self, clamp: bool) {
+        self.clamp = clamp;
+    }
+
+    /// Draw dotted horizontal grid lines every `spacing` pixels from the bottom of the region.
+    pub fn set_grid(&mut self, spacing: Option<u32>) {
+        self.grid = spacing.filter(|&spacing| spacing > 0);
+    }
+
+    pub fn set_colors(&mut self, foreground: BinaryColor, background: BinaryColor) {
+        self.foreground = foreground;
+        self.background = background;
+    }
+
+    /// The range currently plotted as `(min, max)`.
+    pub fn range(&self) -> (f32, f32) {
+        self.range
+    }
+
+    /// Add a sample. Once enough samples for a column have been collected, the plot is
+    /// scrolled left by one pixel and the new column is drawn.
+    pub fn push<SPI, CS, DISP, E, SPEC, BUF>(
+        &mut self,
+        display: &mut MemoryDisplay<SPI, CS, DISP, SPEC, BUF>,
+        value: f32,
+    ) -> Result<(), E>
+    where
+        SPI: Write<u8, Error = E>,
+        CS: OutputPin,
+        DISP: OutputPin,
+        SPEC: DisplaySpec,
+        BUF: BorrowMut<FrameBuffer<SPEC>>,
+    {
+        let (count, sum, min, max) = self.pending;
+        self.pending = if count == 0 {
+            (1, value, value, value)
+        } else {
+            (count + 1, sum + value, min.min(value), max.max(value))
+        };
+        if self.pending.0 < self.samples_per_column {
+            return Ok(());
+        }
+
+        let (count, sum, min, max) = self.pending;
+        self.pending.0 = 0;
+        let column = match self.aggregate {
+            Aggregate::Mean => (sum / count as f32, sum / count as f32),
+            Aggregate::MinMax => (min, max),
+        };
+        let prev = self.last();
+        let capacity = N.min(self.region.size.width as usize);
+        if capacity == 0 {
+            return Ok(());
+        }
+        if self.len < capacity {
+            self.len += 1;
+        }
+        self.history[self.head] = column;
+        self.head = (self.head + 1) % capacity;
+        self.columns = self.columns.wrapping_add(1);
+
+        if self.autoscale && self.fit_range() {
+            return self.redraw(display);
+        }
+        display.scroll_region_left(self.region, 1, self.background);
+        let x = self.region.size.width as i32 - 1;
+        self.draw_column(display, x, self.columns, column, prev)
+    }
+
+    /// Clear the region and draw all stored columns.
+    pub fn redraw<SPI, CS, DISP, E, SPEC, BUF>(
+        &mut self,
+        display: &mut MemoryDisplay<SPI, CS, DISP, SPEC, BUF>,
+    ) -> Result<(), E>
+    where
+        SPI: Write<u8, Error = E>,
+        CS: OutputPin,
+        DISP: OutputPin,
+        SPEC: DisplaySpec,
+        BUF: BorrowMut<FrameBuffer<SPEC>>,
+    {
+        display.fill_solid(&self.region, self.background)?;
+        let width = self.region.size.width as i32;
+        let mut prev = None;
+        for i in 0..self.len {
+            let age = self.len - 1 - i;
+            let column = self.column(age);
+            let number = self.columns.wrapping_sub(age as u32);
+            self.draw_column(display, width - 1 - age as i32, number, column, prev)?;
+            prev = Some(column);
+        }
+        Ok(())
+    }
+
+    /// The column drawn `age` columns ago.
+    fn column(&self, age: usize) -> (f32, f32) {
+        let capacity = N.min(self.region.size.width as usize);
+        self.history[(self.head + capacity - 1 - age) % capacity]
+    }
+
+    fn last(&self) -> Option<(f32, f32)> {
+        (self.len > 0).then(|| self.column(0))
+    }
+
+    /// Set the range to the stored columns, returning whether it changed.
+    fn fit_range(&mut self) -> bool {
+        let (mut min, mut max) = self.column(0);
+        for age in 1..self.len {
+            let (lo, hi) = self.column(age);
+            min = min.min(lo);
+            max = max.max(hi);
+        }
+        let changed = (min, max) != self.range;
+        self.range = (min, max);
+        changed
+    }
+
+    /// Row of the region a value is plotted in, outside the region if it is out of range.
+    fn row(&self, value: f32) -> i32 {
+        let (min, max) = self.range;
+        let fraction = if max > min {
+            (max - value) / (max - min)
+        } else {
+            0.5
+        };
+        let row = fraction * (self.region.size.height as f32 - 1.0) + 0.5;
+        // Round down, also for rows above the region
+        if row < 0.0 {
+            row as i32 - 1
+        } else {
+            row as i32
+        }
+    }
+
+    /// Draw the column `(lo, hi)` at `x` of the region, joined to the previous column.
+    fn draw_column<SPI, CS, DISP, E, SPEC, BUF>(
+        &self,
+        display: &mut MemoryDisplay<SPI, CS, DISP, SPEC, BUF>,
+        x: i32,
+        number: u32,
+        (lo, hi): (f32, f32),
+        prev: Option<(f32, f32)>,
+    ) -> Result<(), E>
+    where
+        SPI: Write<u8, Error = E>,
+        CS: OutputPin,
+        DISP: OutputPin,
+        SPEC: DisplaySpec,
+        BUF: BorrowMut<FrameBuffer<SPEC>>,
+    {
+        let height = self.region.size.height;
+        if let Some(spacing) = self.grid.filter(|_| number.is_multiple_of(2)) {
+            for row in (0..height).rev().step_by(spacing as usize) {
+                self.fill(display, x, row as i32, row as i32, self.foreground)?;
+            }
+        }
+
+        // Extend the span towards the previous column so the plot is a continuous line
+        let (lo, hi) = match prev {
+            Some((_, prev_hi)) if prev_hi < lo => (prev_hi, hi),
+            Some((prev_lo, _)) if prev_lo > hi => (lo, prev_lo),
+            _ => (lo, hi),
+        };
+        let (top, bottom) = (self.row(hi), self.row(lo));
+        let last = height as i32 - 1;
+        if !self.clamp && (bottom < 0 || top > last) {
+            return Ok(());
+        }
+        self.fill(
+            display,
+            x,
+            top.clamp(0, last),
+            bottom.clamp(0, last),
+            self.foreground,
+        )
+    }
+
+    fn fill<SPI, CS, DISP, E, SPEC, BUF>(
+        &self,
+        display: &mut MemoryDisplay<SPI, CS, DISP, SPEC, BUF>,
+        x: i32,
+        top: i32,
+        bottom: i32,
+        color: BinaryColor,
+    ) -> Result<(), E>
+    where
+        SPI: Write<u8, Error = E>,
+        CS: OutputPin,
+        DISP: OutputPin,
+        SPEC: DisplaySpec,
+        BUF: BorrowMut<FrameBuffer<SPEC>>,
+    {
+        let area = Rectangle::new(
+            self.region.top_left + Point::new(x, top),
+            Size::new(1, (bottom - top + 1) as u32),
+        );
+        display.fill_solid(&area, color)
+    }
+}
@@ -0,0 +1,197 @@
+use crate::spec::{self, DisplaySpec};
+use crate::DUMMY_DATA;
+use core::marker::PhantomData;
+use hal::blocking::spi::Write;
+
+/// Words converted at a time by [`WordSpi`], i.e. per write to the bus.
+const CHUNK_WORDS: usize = 32;
+
+/// Which byte of a 16-bit word [`WordSpi`] sends first.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum WordOrder {
+    /// The first byte is the high byte of the word, for a bus sending every word most
+    /// significant bit first.
+    HighByteFirst,
+    /// The first byte is the low byte of the word, e.g. for a DMA engine swapping the bytes of
+    /// every word.
+    LowByteFirst,
+}
+
+/// An SPI bus in 16-bit frame mode, which many SoCs drive faster by DMA than in 8-bit mode:
+///
+/// ```ignore
+/// let spi = WordSpi::new(spi16, WordOrder::HighByteFirst);
+/// let mut display = MemoryDisplay::new(spi, cs, disp);
+/// ```
+///
+/// On the wire the bytes are sent the same as on an 8-bit bus, two per word. Every write of
+/// the driver is an even number of bytes long as long as a line of the panel is, which holds
+/// for every supported panel but the LPM009M360A, for which the display doesn't compile with
+/// this bus. Any other write of an odd length is completed with a dummy byte. Like a display
+/// the bus is built for a model, see [`WordSpi::new_with_spec`].
+pub struct WordSpi<SPI, SPEC: DisplaySpec = spec::Selected> {
+    spi: SPI,
+    order: WordOrder,
+    spec: PhantomData<SPEC>,
+}
+
+impl<SPI> WordSpi<SPI> {
+    pub fn new(spi: SPI, order: WordOrder) -> Self {
+        Self {
+            spi,
+            order,
+            spec: PhantomData,
+        }
+    }
+}
+
+impl<SPI, SPEC: DisplaySpec> WordSpi<SPI, SPEC> {
+    const EVEN_LINES: () = assert!(
+        SPEC::WRITE_BUFFER_SIZE.is_multiple_of(2),
+        "the lines of this panel are an odd number of bytes, which 16-bit words cannot carry"
+    );
+
+    /// Same as [`WordSpi::new`] for a display of the model `SPEC`, see
+    /// [`MemoryDisplay::new_with_spec`](crate::MemoryDisplay::new_with_spec).
+    pub fn new_with_spec(spi: SPI, order: WordOrder, _spec: SPEC) -> Self {
+        Self {
+            spi,
+            order,
+            spec: PhantomData,
+        }
+    }
+
+    /// Give back the bus.
+    pub fn release(self) -> SPI {
+        self.spi
+    }
+}
+
+impl<SPI: Write<u16>, SPEC: DisplaySpec> Write<u8> for WordSpi<SPI, SPEC> {
+    type Error = SPI::Error;
+
+    fn write(&mut self, words: &[u8]) -> Result<(), Self::Error> {
+        let () = Self::EVEN_LINES;
+        let mut buffer = [0u16; CHUNK_WORDS];
+        for chunk in words.chunks(2 * CHUNK_WORDS) {
+            let pairs = chunk.chunks(2);
+            let len = pairs.len();
+            for (word, pair) in buffer.iter_mut().zip(pairs) {
+                let (first, second) = (pair[0], pair.get(1).copied().unwrap_or(DUMMY_DATA));
+                *word = match self.order {
+                    WordOrder::HighByteFirst => u16::from_be_bytes([first, second]),
+                    WordOrder::LowByteFirst => u16::from_le_bytes([first, second]),
+                };
+            }
+            self.spi.write(&buffer[..len])?;
+        }
+        Ok(())
+    }
+}
+
+// The lines of the LPM009M360A are an odd number of bytes long
+#[cfg(all(test, not(feature = "lpm009m360a")))]
+mod tests {
+    use super::*;
+    use crate::{testing, CsStrategy, MemoryDisplay};
+    use core::convert::Infallible;
+    use std::{cell::RefCell, rc::Rc, vec, vec::Vec};
+
+    /// A 16-bit bus recording every write.
+    #[derive(Clone, Default)]
+    struct Words(Rc<RefCell<Vec<Vec<u16>>>>);
+
+    impl Write<u16> for Words {
+        type Error = Infallible;
+
+        fn write(&mut self, words: &[u16]) -> Result<(), Infallible> {
+            self.0.borrow_mut().push(words.to_vec());
+            Ok(())
+        }
+    }
+
+    impl Words {
+        /// The bytes of every write so far, sent in `order`.
+        fn bytes(&self, order: WordOrder) -> Vec<u8> {
+            let words = self.0.borrow();
+            let bytes = words.iter().flatten().flat_map(|&word| match order {
+                WordOrder::HighByteFirst => word.to_be_bytes(),
+                WordOrder::LowByteFirst => word.to_le_bytes(),
+            });
+            bytes.collect()
+        }
+    }
+
+    #[test]
+    fn words_carry_the_bytes_of_an_8_bit_bus() {
+        for order in [WordOrder::HighByteFirst, WordOrder::LowByteFirst] {
+            for strategy in testing::strategies() {
+                let (log, mut bytes) = testing::display();
+                let words = Words::default();
+                let word_log = testing::Log::default();
+                let spi = WordSpi::new(words.clone(), order);
+                let mut display = MemoryDisplay::new(spi, word_log.cs(), word_log.disp());
+                display.enable().unwrap();
+                word_log.events();
+                for display in [&mut bytes as &mut dyn Drawn, &mut display] {
+                    display.draw_and_send(strategy);
+                }
+                // The same bytes with the same chip select framing, no write padded
+                let events = log.events();
+                assert_eq!(
+                    words.bytes(order),
+                    testing::written(&events),
+                    "{order:?} {strategy:?}"
+                );
+                let cs = |events: Vec<testing::Event>| {
+                    let cs = events
+                        .into_iter()
+                        .filter(|event| matches!(event, testing::Event::Cs(_)));
+                    cs.collect::<Vec<_>>()
+                };
+                assert_eq!(cs(word_log.events()), cs(events));
+            }
+        }
+    }
+
+    /// Drawing the same pixels and sending them, on either bus.
+    trait Drawn {
+        fn draw_and_send(&mut self, strategy: CsStrategy);
+    }
+
+    impl<SPI: hal::blocking::spi::Write<u8>> Drawn for MemoryDisplay<SPI, testing::Pin, testing::Pin>
+    where
+        SPI::Error: core::fmt::Debug,
+    {
+        fn draw_and_send(&mut self, strategy: CsStrategy) {
+            self.set_cs_strategy(strategy);
+            for y in (0..crate::BUFFER_HEIGHT).step_by(3) {
+                let x = (y * 7) % crate::BUFFER_WIDTH;
+                self.set_pixel(x as u32, y as u32, crate::Color::On)
+                    .unwrap();
+            }
+            self.flush_buffer().unwrap();
+            self.clear().unwrap();
+            self.display_mode().unwrap();
+        }
+    }
+
+    #[test]
+    fn odd_writes_are_completed_and_long_ones_split() {
+        let words = Words::default();
+        let mut spi = WordSpi::new(words.clone(), WordOrder::HighByteFirst);
+        spi.write(&[1, 2, 3]).unwrap();
+        assert_eq!(
+            *words.0.borrow(),
+            [vec![0x0102, 0x0300 | DUMMY_DATA as u16]]
+        );
+
+        words.0.borrow_mut().clear();
+        let bytes: Vec<u8> = (0..2 * CHUNK_WORDS as u8 + 6).collect();
+        spi.write(&bytes).unwrap();
+        let lens: Vec<_> = words.0.borrow().iter().map(Vec::len).collect();
+        assert_eq!(lens, [CHUNK_WORDS, 3]);
+        assert_eq!(words.bytes(WordOrder::HighByteFirst), bytes);
+    }
+}